@@ -0,0 +1,108 @@
+//! Embeds what `iroha_wasm_pack version --verbose` reports about the build itself.
+//!
+//! Everything falls back to `unknown`, a tarball of the sources has no git history and a crate
+//! built as a dependency may come without its lockfile.
+
+use std::{
+    env, fs,
+    path::Path,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("set by cargo");
+    let manifest_dir = Path::new(&manifest_dir);
+
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .current_dir(manifest_dir)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|commit| commit.trim().to_owned())
+        .filter(|commit| !commit.is_empty());
+    println!(
+        "cargo:rustc-env=IROHA_WASM_PACK_GIT_COMMIT={}",
+        commit.as_deref().unwrap_or("unknown")
+    );
+    // Rebuilding on every commit only makes sense inside a checkout, a missing file would
+    // rerun the script on every build.
+    let head = manifest_dir.join(".git/HEAD");
+    if head.is_file() {
+        println!("cargo:rerun-if-changed={}", head.display());
+        if let Some(reference) = fs::read_to_string(&head)
+            .ok()
+            .and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_owned()))
+        {
+            let reference = manifest_dir.join(".git").join(reference);
+            if reference.is_file() {
+                println!("cargo:rerun-if-changed={}", reference.display());
+            }
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+
+    // Reproducible builds pin the date with `SOURCE_DATE_EPOCH`.
+    let seconds = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<u64>().ok())
+        .or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .ok()
+                .map(|elapsed| elapsed.as_secs())
+        });
+    println!(
+        "cargo:rustc-env=IROHA_WASM_PACK_BUILD_DATE={}",
+        seconds.map_or_else(|| "unknown".to_owned(), date)
+    );
+
+    let wasm_opt = fs::read_to_string(manifest_dir.join("Cargo.lock"))
+        .ok()
+        .and_then(|lock| locked_version(&lock, "wasm-opt"));
+    println!(
+        "cargo:rustc-env=IROHA_WASM_PACK_WASM_OPT_VERSION={}",
+        wasm_opt.as_deref().unwrap_or("unknown")
+    );
+
+    println!(
+        "cargo:rustc-env=IROHA_WASM_PACK_HOST={}",
+        env::var("TARGET").expect("set by cargo")
+    );
+}
+
+/// Version of `package` in the lockfile
+fn locked_version(lock: &str, package: &str) -> Option<String> {
+    let name = format!("name = \"{}\"", package);
+    let mut lines = lock.lines();
+    lines.find(|line| *line == name)?;
+    lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')
+        .map(ToOwned::to_owned)
+}
+
+/// `YYYY-MM-DD` of a Unix time, in UTC
+fn date(seconds: u64) -> String {
+    // Days to the civil date, from Howard Hinnant's `civil_from_days`.
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
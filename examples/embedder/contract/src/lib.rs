@@ -0,0 +1,11 @@
+//! Smallest contract `iroha_wasm_pack` accepts: a trigger entrypoint doing nothing.
+
+#![no_std]
+
+#[no_mangle]
+pub extern "C" fn _iroha_trigger_main() {}
+
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    core::arch::wasm32::unreachable()
+}
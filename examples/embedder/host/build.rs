@@ -0,0 +1,5 @@
+fn main() {
+    iroha_wasm_pack::build_script::compile("../contract")
+        .release()
+        .run();
+}
@@ -0,0 +1,8 @@
+//! Prints the size and the leading bytes of the embedded contract.
+
+const CONTRACT: &[u8] = include_bytes!(env!("CONTRACT_WASM"));
+
+fn main() {
+    println!("embedded contract: {} bytes", CONTRACT.len());
+    println!("magic: {:02x?}", &CONTRACT[..4]);
+}
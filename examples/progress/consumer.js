@@ -0,0 +1,66 @@
+#!/usr/bin/env node
+// Follows `iroha_wasm_pack --progress-format json build` the way an editor extension would:
+// a line per finished step, diagnostics as `file:line:column` and the outcome at the end.
+//
+//     node examples/progress/consumer.js [build options...]
+//
+// Events of an unknown kind and fields this script doesn't use are skipped, newer releases
+// may add them without bumping the schema version.
+
+const { spawn } = require("child_process");
+const readline = require("readline");
+
+const SUPPORTED_VERSION = 1;
+
+const child = spawn(
+  "iroha_wasm_pack",
+  ["--progress-format", "json", "build", ...process.argv.slice(2)],
+  { stdio: ["ignore", "inherit", "pipe"] }
+);
+
+const handlers = {
+  "step-started": (event) => {
+    process.stdout.write(`${event.package}: ${event.step}...`);
+  },
+  "step-finished": (event) => {
+    process.stdout.write(` ${event.millis} ms\n`);
+  },
+  diagnostic: (event) => {
+    const location = event.file ? `${event.file}:${event.line}:${event.column}: ` : "";
+    const code = event.code ? `[${event.code}] ` : "";
+    console.log(`\n${location}${event.level}: ${code}${event.message}`);
+  },
+  warning: (event) => {
+    console.log(`\nwarning: ${event.message}`);
+  },
+  finished: (event) => {
+    if (event.success) {
+      for (const artifact of event.report.artifacts) {
+        console.log(`${artifact.wasm} (${artifact.size} bytes, ${artifact.hash})`);
+      }
+      console.log(`Finished in ${event.millis} ms`);
+    } else {
+      console.log(`Failed with exit code ${event.exit_code}: ${event.error.message}`);
+    }
+  },
+};
+
+readline.createInterface({ input: child.stderr }).on("line", (line) => {
+  let event;
+  try {
+    event = JSON.parse(line);
+  } catch {
+    // Not an event, e.g. a panic message.
+    console.error(line);
+    return;
+  }
+  if (event.version > SUPPORTED_VERSION) {
+    console.error(`progress schema version ${event.version} is newer than this script`);
+  }
+  const handle = handlers[event.event];
+  if (handle) {
+    handle(event);
+  }
+});
+
+child.on("close", (code) => process.exit(code));
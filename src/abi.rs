@@ -0,0 +1,202 @@
+use super::build::{root, working_dir};
+use super::deploy::artifact;
+use super::new::walk_files;
+use super::wasm::{self, ENTRYPOINT_EXPORTS};
+use super::*;
+use serde_derive::Serialize;
+use std::{fs, path::PathBuf};
+use syn::visit::Visit;
+
+/// Version of the ABI document, bump it on any field change
+pub const ABI_VERSION: u32 = 1;
+
+/// Function names of the executor entrypoints, exported as `_iroha_executor_<name>`
+const EXECUTOR_ENTRYPOINTS: &[&str] = &[
+    "migrate",
+    "validate_transaction",
+    "validate_instruction",
+    "validate_query",
+];
+
+/// An entrypoint declared in the sources
+#[derive(Debug, Serialize)]
+pub struct Entrypoint {
+    pub name: String,
+    pub export: String,
+    pub kind: &'static str,
+    /// Parameters the host passes, from `params = "[...]"`
+    pub params: Vec<String>,
+    #[serde(skip)]
+    pub path: PathBuf,
+    #[serde(skip)]
+    pub line: usize,
+}
+
+impl Entrypoint {
+    fn new(name: String, params: Vec<String>, path: PathBuf, line: usize) -> Self {
+        let (kind, export) = if EXECUTOR_ENTRYPOINTS.contains(&name.as_str()) {
+            ("executor", format!("_iroha_executor_{}", name))
+        } else if params.iter().any(|param| param == "operation") {
+            ("validator", "_iroha_validator_main".to_owned())
+        } else if params.is_empty() {
+            ("smart_contract", "_iroha_smart_contract_main".to_owned())
+        } else {
+            ("trigger", "_iroha_trigger_main".to_owned())
+        };
+        Entrypoint {
+            name,
+            export,
+            kind,
+            params,
+            path,
+            line,
+        }
+    }
+}
+
+/// `params` of an `#[entrypoint(...)]` attribute, `None` if it isn't one
+fn entrypoint_params(attr: &syn::Attribute) -> Option<Vec<String>> {
+    let segment = attr.path().segments.last()?;
+    if segment.ident != "entrypoint" {
+        return None;
+    }
+    let mut params = Vec::new();
+    if let syn::Meta::List(_) = attr.meta {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("params") {
+                let list: syn::LitStr = meta.value()?.parse()?;
+                params = list
+                    .value()
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .split(',')
+                    .map(|param| param.trim().to_owned())
+                    .filter(|param| !param.is_empty())
+                    .collect();
+            }
+            Ok(())
+        });
+    }
+    Some(params)
+}
+
+struct Entrypoints<'a> {
+    path: &'a std::path::Path,
+    found: Vec<Entrypoint>,
+}
+
+impl<'ast> Visit<'ast> for Entrypoints<'_> {
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        if let Some(params) = item.attrs.iter().find_map(entrypoint_params) {
+            self.found.push(Entrypoint::new(
+                item.sig.ident.to_string(),
+                params,
+                self.path.to_owned(),
+                item.sig.ident.span().start().line,
+            ));
+        }
+        syn::visit::visit_item_fn(self, item);
+    }
+}
+
+/// Entrypoints declared in `src/` of the crate at `root`
+pub fn source_entrypoints(root: &std::path::Path) -> Result<Vec<Entrypoint>, Error> {
+    let mut files = walk_files(&root.join("src"))?;
+    files.sort();
+    let mut found = Vec::new();
+    for path in files
+        .iter()
+        .filter(|path| path.extension().map_or(false, |ext| ext == "rs"))
+    {
+        let source = fs::read_to_string(path).map_err(|err| {
+            Error::other(format!("read {} failed", path.display())).caused_by(err)
+        })?;
+        let file = syn::parse_file(&source).map_err(|err| {
+            Error::other(format!("{} doesn't parse", path.display())).caused_by(err)
+        })?;
+        let mut entrypoints = Entrypoints {
+            path: path.strip_prefix(root).unwrap_or(path),
+            found: Vec::new(),
+        };
+        entrypoints.visit_file(&file);
+        found.extend(entrypoints.found);
+    }
+    Ok(found)
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack abi` command.
+#[derive(Debug, StructOpt)]
+pub struct AbiArgs {
+    /// Wasm to cross-check, defaults to the optimized wasm of the current project
+    #[structopt(long = "wasm", parse(from_os_str))]
+    pub wasm: Option<PathBuf>,
+
+    /// Write the document to this file instead of stdout
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+}
+
+impl RunArgs for AbiArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let root = root(working_dir()?)?;
+        let entrypoints = source_entrypoints(&root)?;
+        let path = artifact(self.wasm.as_ref())?;
+        let bytes = fs::read(&path).map_err(|err| {
+            Error::other(format!("read {} failed", path.display())).caused_by(err)
+        })?;
+        let exports: Vec<String> = wasm::parse(&bytes)?
+            .exports
+            .into_iter()
+            .filter(|export| export.kind == "func")
+            .map(|export| export.name)
+            .collect();
+        let mut errors = Vec::new();
+        if entrypoints.is_empty() {
+            errors.push("no function in src/ has `#[entrypoint]`".to_owned());
+        }
+        for entrypoint in &entrypoints {
+            if !exports.contains(&entrypoint.export) {
+                errors.push(format!(
+                    "{}:{}: `{}` should be exported as `{}`, which {} lacks",
+                    entrypoint.path.display(),
+                    entrypoint.line,
+                    entrypoint.name,
+                    entrypoint.export,
+                    path.display()
+                ));
+            }
+        }
+        for export in exports
+            .iter()
+            .filter(|export| ENTRYPOINT_EXPORTS.contains(&export.as_str()))
+        {
+            if !entrypoints
+                .iter()
+                .any(|entrypoint| &entrypoint.export == export)
+            {
+                errors.push(format!(
+                    "{} exports `{}`, but no entrypoint in src/ declares it, is the wasm stale?",
+                    path.display(),
+                    export
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Error::wasm(format!(
+                "the sources and the wasm disagree:\n  {}",
+                errors.join("\n  ")
+            )));
+        }
+        let contract = entrypoints.first().map_or("", |entrypoint| entrypoint.kind);
+        let document = serde_json::json!({
+            "version": ABI_VERSION,
+            "contract": contract,
+            "entrypoints": entrypoints,
+        });
+        let document = serde_json::to_string_pretty(&document)?;
+        match &self.output {
+            Some(output) => fs::write(output, document + "\n")?,
+            None => println!("{}", document),
+        }
+        Ok(CommandReport::default())
+    }
+}
@@ -0,0 +1,158 @@
+use super::deploy::artifact;
+use super::run::{Harness, HostArgs};
+use super::*;
+use serde_derive::Serialize;
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use wasmtime::Config;
+
+/// Everything required to configure and run the `iroha_wasm_pack bench` command.
+#[derive(Debug, StructOpt)]
+pub struct BenchArgs {
+    /// Wasm to measure, defaults to the optimized wasm of the current project
+    #[structopt(parse(from_os_str))]
+    pub wasm: Option<PathBuf>,
+
+    /// Number of runs, each in a fresh instance
+    #[structopt(long = "iterations", default_value = "10")]
+    pub iterations: usize,
+
+    /// Measure this other wasm first and show the change to it
+    #[structopt(long = "compare", parse(from_os_str))]
+    pub compare: Option<PathBuf>,
+
+    /// With `--compare`, fail if the median fuel grew by more than this percentage
+    #[structopt(long = "threshold", requires = "compare")]
+    pub threshold: Option<f64>,
+
+    #[structopt(flatten)]
+    pub host: HostArgs,
+
+    /// Print the statistics as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+}
+
+/// Minimum, median and maximum of the runs
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub min: u64,
+    pub median: u64,
+    pub max: u64,
+}
+
+impl Stats {
+    fn of(mut samples: Vec<u64>) -> Self {
+        samples.sort_unstable();
+        Stats {
+            min: samples[0],
+            median: samples[samples.len() / 2],
+            max: samples[samples.len() - 1],
+        }
+    }
+}
+
+/// Cost of one wasm
+#[derive(Debug, Serialize)]
+pub struct Measurement {
+    pub wasm: PathBuf,
+    /// Fuel is deterministic, the number to compare implementations by
+    pub fuel: Stats,
+    pub nanos: Stats,
+}
+
+impl BenchArgs {
+    fn measure(&self, path: &Path) -> Result<Measurement, Error> {
+        let wasmtime_err =
+            |err: anyhow::Error| Error::wasm(path.display().to_string()).caused_by(err);
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let harness = Harness::new(&config, path, &self.host)?;
+        let (mut fuel, mut nanos) = (Vec::new(), Vec::new());
+        for _ in 0..self.iterations.max(1) {
+            let mut store = harness.store()?;
+            store.add_fuel(u64::MAX / 2).map_err(wasmtime_err)?;
+            let (_, main) = harness.instantiate(&mut store).map_err(wasmtime_err)?;
+            // Instantiation is excluded, only the entrypoint is measured.
+            let before = store.fuel_consumed().unwrap_or_default();
+            let started = Instant::now();
+            main.call(&mut store, ()).map_err(wasmtime_err)?;
+            nanos.push(started.elapsed().as_nanos() as u64);
+            fuel.push(store.fuel_consumed().unwrap_or_default() - before);
+        }
+        Ok(Measurement {
+            wasm: path.to_owned(),
+            fuel: Stats::of(fuel),
+            nanos: Stats::of(nanos),
+        })
+    }
+}
+
+fn print(measurement: &Measurement) {
+    let (fuel, nanos) = (&measurement.fuel, &measurement.nanos);
+    println!("{}", measurement.wasm.display());
+    println!(
+        "  fuel  min {:>12}  median {:>12}  max {:>12}",
+        fuel.min, fuel.median, fuel.max
+    );
+    println!(
+        "  time  min {:>10}µs  median {:>10}µs  max {:>10}µs",
+        nanos.min / 1000,
+        nanos.median / 1000,
+        nanos.max / 1000
+    );
+}
+
+/// Change from `old` to `new` in percent
+fn change(old: u64, new: u64) -> f64 {
+    if old == 0 {
+        return 0.0;
+    }
+    (new as f64 - old as f64) / old as f64 * 100.0
+}
+
+impl RunArgs for BenchArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let path = artifact(self.wasm.as_ref())?;
+        let baseline = match &self.compare {
+            Some(other) => Some(self.measure(other)?),
+            None => None,
+        };
+        let measurement = self.measure(&path)?;
+        let fuel_change = baseline
+            .as_ref()
+            .map(|baseline| change(baseline.fuel.median, measurement.fuel.median));
+        if self.json {
+            let json = serde_json::json!({
+                "iterations": self.iterations,
+                "measurement": measurement,
+                "baseline": baseline,
+                "fuel_change_percent": fuel_change,
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        } else {
+            if let Some(baseline) = &baseline {
+                print(baseline);
+            }
+            print(&measurement);
+            if let (Some(baseline), Some(fuel_change)) = (&baseline, fuel_change) {
+                println!(
+                    "median fuel {:+.2}%, median time {:+.2}%",
+                    fuel_change,
+                    change(baseline.nanos.median, measurement.nanos.median)
+                );
+            }
+        }
+        match (fuel_change, self.threshold) {
+            (Some(fuel_change), Some(threshold)) if fuel_change > threshold => {
+                Err(Error::other(format!(
+                    "median fuel grew by {:.2}%, more than the threshold of {}%",
+                    fuel_change, threshold
+                )))
+            }
+            _ => Ok(CommandReport::default()),
+        }
+    }
+}
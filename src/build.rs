@@ -0,0 +1,5468 @@
+use super::project_config::ProjectConfig;
+use super::runner::{CommandRunner, DuctRunner, Invocation};
+use super::*;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    cell::RefCell,
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, OnceLock},
+};
+use structopt::clap::AppSettings;
+
+/// Everything required to configure and run the `iroha_wasm_pack build` command.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    // Everything after `--` is forwarded to `cargo`.
+    setting = AppSettings::TrailingVarArg,
+)]
+pub struct BuildArgs {
+    /// Install missing toolchain components without asking
+    #[structopt(short = "y", long = "yes")]
+    pub yes: bool,
+
+    /// Non-interactive mode for CI, implies `--yes`
+    #[structopt(long = "ci")]
+    pub ci: bool,
+
+    /// Never install missing toolchain components, fail instead
+    #[structopt(long = "no-install", conflicts_with_all = &["yes", "ci"])]
+    pub no_install: bool,
+
+    /// Build with the precompiled std of the wasm32 target instead of `-Z build-std`
+    #[structopt(long = "no-build-std")]
+    pub no_build_std: bool,
+
+    /// Package of the workspace to build
+    #[structopt(short = "p", long = "package")]
+    pub package: Option<String>,
+
+    /// Cargo.toml of the project, instead of looking up from the current directory
+    #[structopt(long = "manifest-path", parse(from_os_str))]
+    pub manifest_path: Option<PathBuf>,
+
+    /// Directory for the build outputs instead of the `target/` of the project
+    #[structopt(long = "target-dir", parse(from_os_str))]
+    pub target_dir: Option<PathBuf>,
+
+    /// Build every member of the workspace
+    #[structopt(long = "workspace", conflicts_with = "package")]
+    pub workspace: bool,
+
+    /// Override the link-time optimization of the profile: off, thin or fat
+    #[structopt(long = "lto", possible_values = &["off", "thin", "fat"])]
+    pub lto: Option<Lto>,
+
+    /// Override the codegen units of the profile
+    #[structopt(long = "codegen-units", parse(try_from_str = parse_codegen_units))]
+    pub codegen_units: Option<u32>,
+
+    /// Override rustc's opt-level of the profile: 0, 1, 2, 3, s or z
+    #[structopt(long = "opt-level-rustc", parse(try_from_str = parse_opt_level))]
+    pub opt_level_rustc: Option<String>,
+
+    /// Build with debug info and write it to a `<name>.debug.wasm` companion file
+    #[structopt(long = "split-debug")]
+    pub split_debug: bool,
+
+    #[structopt(flatten)]
+    pub wasm_opt: WasmOptArgs,
+
+    /// Skip wasm-opt and use the wasm emitted by cargo as is
+    #[structopt(long = "skip-opt")]
+    pub skip_opt: bool,
+
+    /// Build the given example instead of the library
+    #[structopt(long = "example")]
+    pub example: Option<String>,
+
+    /// Build all examples instead of the library
+    #[structopt(long = "examples", conflicts_with = "example")]
+    pub examples: bool,
+
+    /// Only build the test harness wasm, skipping wasm-opt and the size check
+    #[structopt(long = "tests", conflicts_with_all = &["example", "examples"])]
+    pub tests: bool,
+
+    /// Fail the build if any warning was emitted, including cargo's
+    #[structopt(long = "fail-on-warnings")]
+    pub fail_on_warnings: bool,
+
+    /// Largest size of the optimized wasm in bytes, overriding the metadata and config file
+    #[structopt(long = "max-size")]
+    pub max_size: Option<u64>,
+
+    /// Warn if the embedded Iroha dependency version doesn't match, e.g. a tag or git revision
+    #[structopt(long = "expect-iroha-version")]
+    pub expect_iroha_version: Option<String>,
+
+    /// Warn if the node at this Torii URL runs another Iroha version than the one built against
+    #[structopt(long = "node-url")]
+    pub node_url: Option<String>,
+
+    /// [experimental] Also package the contract as a wasm component, needs `--wit`
+    #[structopt(long = "component", requires = "wit")]
+    pub component: bool,
+
+    /// [experimental] WIT file with the world of the component
+    #[structopt(long = "wit", parse(from_os_str))]
+    pub wit: Option<PathBuf>,
+
+    /// Fail immediately if another build holds the output directory lock
+    #[structopt(long = "no-wait")]
+    pub no_wait: bool,
+
+    /// Nightly toolchain to build with, overriding the toolchain file of the project
+    #[structopt(long = "toolchain")]
+    pub toolchain: Option<String>,
+
+    /// Print where the bytes of the optimized module go, like `iroha_wasm_pack size`
+    #[structopt(long = "analyze-size")]
+    pub analyze_size: bool,
+
+    /// List the packages and the steps that would run, without running them
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Leave out a step of the pipeline, `--dry-run` lists their names
+    #[structopt(long = "skip-step", number_of_values = 1)]
+    pub skip_steps: Vec<String>,
+
+    /// Print how long every step took
+    #[structopt(long = "timings")]
+    pub timings: bool,
+
+    /// Write the artifacts, warnings and step timings as JSON to this file
+    #[structopt(long = "report", parse(from_os_str))]
+    pub report: Option<PathBuf>,
+
+    /// Don't run the `[hooks]` of iroha_wasm_pack.toml
+    #[structopt(long = "no-hooks")]
+    pub no_hooks: bool,
+
+    /// Build with the release profile
+    #[structopt(long = "release")]
+    pub release: bool,
+
+    /// Pass an argument to cargo, e.g. `--cargo-arg=--locked`
+    #[structopt(long = "cargo-arg", number_of_values = 1, allow_hyphen_values = true)]
+    pub cargo_args: Vec<String>,
+
+    /// Compile and optimize in the pinned builder image, the checks after it run here
+    #[structopt(long = "docker")]
+    pub docker: bool,
+
+    /// Builder image to use with `--docker`
+    #[structopt(long = "docker-image", requires = "docker")]
+    pub docker_image: Option<String>,
+
+    /// Forward the arguments the tool doesn't know to cargo, as earlier releases did
+    #[structopt(long = "forward-unknown")]
+    pub forward_unknown: bool,
+
+    #[structopt(allow_hyphen_values = true)]
+    /// Extra options to pass to cargo, after `--`
+    pub extra_options: Vec<String>,
+}
+
+/// Options of wasm-opt, shared by `build` and `opt`.
+#[derive(Debug, StructOpt)]
+pub struct WasmOptArgs {
+    /// Comma separated wasm target features for codegen and wasm-opt, e.g. `-sign-ext,+simd128`
+    #[structopt(long = "target-features")]
+    pub target_features: Option<TargetFeatures>,
+
+    /// Optimization level of wasm-opt: 0, 1, 2, 3, 4, s or z
+    #[structopt(long = "opt-level", default_value = "s", parse(try_from_str = parse_wasm_opt_level))]
+    pub opt_level: String,
+
+    /// Keep the `name` section, so traps show function names
+    #[structopt(long = "keep-names")]
+    pub keep_names: bool,
+
+    /// Run this wasm-opt binary instead of the bundled one
+    #[structopt(long = "wasm-opt-path", parse(from_os_str))]
+    pub wasm_opt_path: Option<PathBuf>,
+
+    /// Abort wasm-opt if it runs longer than the given number of seconds
+    #[structopt(long = "wasm-opt-timeout")]
+    pub wasm_opt_timeout: Option<u64>,
+}
+
+fn parse_wasm_opt_level(s: &str) -> Result<String, String> {
+    match s {
+        "0" | "1" | "2" | "3" | "4" | "s" | "z" => Ok(s.to_owned()),
+        _ => Err(format!(
+            "invalid wasm-opt level `{}`, expected one of: 0, 1, 2, 3, 4, s, z",
+            s
+        )),
+    }
+}
+
+/// Wasm features known to both rustc and wasm-opt.
+const WASM_FEATURES: &[&str] = &[
+    "atomics",
+    "bulk-memory",
+    "exception-handling",
+    "extended-const",
+    "multivalue",
+    "mutable-globals",
+    "nontrapping-fptoint",
+    "reference-types",
+    "relaxed-simd",
+    "sign-ext",
+    "simd128",
+    "tail-call",
+];
+
+/// Validated list of `+feature`/`-feature` toggles for the wasm target.
+#[derive(Debug, Clone, Default)]
+pub struct TargetFeatures(Vec<(String, bool)>);
+
+impl FromStr for TargetFeatures {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut features: Vec<(String, bool)> = Vec::new();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (name, enabled) = match item.as_bytes()[0] {
+                b'+' => (&item[1..], true),
+                b'-' => (&item[1..], false),
+                _ => (item, true),
+            };
+            if !WASM_FEATURES.contains(&name) {
+                return Err(format!(
+                    "unknown wasm target feature `{}`, known features are: {}",
+                    name,
+                    WASM_FEATURES.join(", ")
+                ));
+            }
+            if let Some((_, other)) = features.iter().find(|(known, _)| known == name) {
+                if *other != enabled {
+                    return Err(format!(
+                        "wasm target feature `{}` is both enabled and disabled",
+                        name
+                    ));
+                }
+                continue;
+            }
+            features.push((name.to_owned(), enabled));
+        }
+        Ok(TargetFeatures(features))
+    }
+}
+
+/// The toggles as `--target-features` takes them
+impl std::fmt::Display for TargetFeatures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let list: Vec<String> = self
+            .0
+            .iter()
+            .map(|(name, enabled)| format!("{}{}", if *enabled { '+' } else { '-' }, name))
+            .collect();
+        f.write_str(&list.join(","))
+    }
+}
+
+impl TargetFeatures {
+    /// Value of rustc's `-C target-feature`
+    pub fn to_rustc_flag(&self) -> String {
+        format!("-Ctarget-feature={}", self)
+    }
+
+    /// Apply the same toggles to wasm-opt so codegen and post-processing agree
+    pub fn apply_to(&self, options: &mut wasm_opt::OptimizationOptions) {
+        use wasm_opt::Feature;
+        for (name, enabled) in &self.0 {
+            let feature = match name.as_str() {
+                "atomics" => Feature::Atomics,
+                "bulk-memory" => Feature::BulkMemory,
+                "exception-handling" => Feature::ExceptionHandling,
+                "extended-const" => Feature::ExtendedConst,
+                "multivalue" => Feature::Multivalue,
+                "mutable-globals" => Feature::MutableGlobals,
+                "nontrapping-fptoint" => Feature::TruncSat,
+                "reference-types" => Feature::ReferenceTypes,
+                "relaxed-simd" => Feature::RelaxedSimd,
+                "sign-ext" => Feature::SignExt,
+                "simd128" => Feature::Simd,
+                "tail-call" => Feature::TailCall,
+                _ => continue,
+            };
+            if *enabled {
+                options.enable_feature(feature);
+            } else {
+                options.disable_feature(feature);
+            }
+        }
+    }
+
+    /// The same toggles as flags of a wasm-opt binary
+    pub fn to_wasm_opt_flags(&self) -> Vec<String> {
+        self.0
+            .iter()
+            .map(|(name, enabled)| {
+                let name = match name.as_str() {
+                    "atomics" => "threads",
+                    "nontrapping-fptoint" => "nontrapping-float-to-int",
+                    "simd128" => "simd",
+                    name => name,
+                };
+                let toggle = if *enabled { "enable" } else { "disable" };
+                format!("--{}-{}", toggle, name)
+            })
+            .collect()
+    }
+}
+
+/// `CARGO_ENCODED_RUSTFLAGS` combining the user's flags with `extra` ones.
+///
+/// The encoded variable takes precedence over `RUSTFLAGS`, so flags from either
+/// are carried over instead of being silently dropped.
+fn encoded_rustflags(extra: &[String]) -> String {
+    use std::env::var;
+    let mut flags: Vec<String> = match var("CARGO_ENCODED_RUSTFLAGS") {
+        Ok(encoded) if !encoded.is_empty() => encoded.split('\x1f').map(str::to_owned).collect(),
+        _ => var("RUSTFLAGS")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_owned)
+            .collect(),
+    };
+    flags.extend(extra.iter().cloned());
+    flags.join("\x1f")
+}
+
+/// Link-time optimization setting of a cargo profile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lto {
+    Off,
+    Thin,
+    Fat,
+}
+
+impl FromStr for Lto {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Lto::Off),
+            "thin" => Ok(Lto::Thin),
+            "fat" => Ok(Lto::Fat),
+            _ => Err(format!(
+                "invalid lto `{}`, expected one of: off, thin, fat",
+                s
+            )),
+        }
+    }
+}
+
+impl Lto {
+    /// Value of `profile.<name>.lto` in cargo's config syntax
+    fn as_config_value(&self) -> &'static str {
+        match self {
+            Lto::Off => "\"off\"",
+            Lto::Thin => "\"thin\"",
+            Lto::Fat => "\"fat\"",
+        }
+    }
+}
+
+fn parse_codegen_units(s: &str) -> Result<u32, String> {
+    match s.parse::<u32>() {
+        Ok(units) if units > 0 => Ok(units),
+        _ => Err(format!(
+            "invalid codegen-units `{}`, expected a positive integer",
+            s
+        )),
+    }
+}
+
+fn parse_opt_level(s: &str) -> Result<String, String> {
+    match s {
+        "0" | "1" | "2" | "3" | "s" | "z" => Ok(s.to_owned()),
+        _ => Err(format!(
+            "invalid opt-level `{}`, expected one of: 0, 1, 2, 3, s, z",
+            s
+        )),
+    }
+}
+
+/// What to do when a required toolchain component is missing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallPolicy {
+    /// Ask the user on a TTY, install silently otherwise
+    Prompt,
+    /// Install without asking
+    Always,
+    /// Refuse to install and return an error
+    Never,
+}
+
+impl BuildArgs {
+    /// Whether `--release` is forwarded to cargo
+    pub fn is_release(&self) -> bool {
+        self.release || self.extra_options.iter().any(|x| x == "--release")
+    }
+
+    /// Reject flags that contradict each other.
+    ///
+    /// Checked after parsing, clap would count the default `--opt-level` as given.
+    pub fn check_conflicts(&self) -> Result<(), Error> {
+        let opt = &self.wasm_opt;
+        let wasm_opt_flags = [
+            ("--opt-level", opt.opt_level != "s"),
+            ("--keep-names", opt.keep_names),
+            ("--wasm-opt-path", opt.wasm_opt_path.is_some()),
+            ("--wasm-opt-timeout", opt.wasm_opt_timeout.is_some()),
+        ];
+        if let (true, Some((flag, _))) = (
+            self.skip_opt,
+            wasm_opt_flags.iter().find(|(_, given)| *given),
+        ) {
+            return Err(Error::usage(format!(
+                "`--skip-opt` can't be used with `{}`, wasm-opt doesn't run",
+                flag
+            )));
+        }
+        // The builder has neither the host's wasm-opt nor a way to run the tests.
+        let docker_flags = [
+            ("--tests", self.tests),
+            ("--wasm-opt-path", opt.wasm_opt_path.is_some()),
+        ];
+        match docker_flags.iter().find(|(_, given)| *given) {
+            Some((flag, _)) if self.docker => Err(Error::usage(format!(
+                "`--docker` can't be used with `{}`",
+                flag
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// `--config` overrides of the cargo profile in use
+    pub fn profile_overrides(&self) -> Vec<String> {
+        let profile = if self.is_release() { "release" } else { "dev" };
+        let mut overrides = Vec::new();
+        if let Some(lto) = self.lto {
+            overrides.push(format!("profile.{}.lto={}", profile, lto.as_config_value()));
+        }
+        if let Some(units) = self.codegen_units {
+            overrides.push(format!("profile.{}.codegen-units={}", profile, units));
+        }
+        if let Some(level) = &self.opt_level_rustc {
+            let value = match level.as_str() {
+                "s" | "z" => format!("\"{}\"", level),
+                _ => level.clone(),
+            };
+            overrides.push(format!("profile.{}.opt-level={}", profile, value));
+        }
+        overrides.extend(self.split_debug_overrides());
+        overrides
+    }
+
+    /// `--config` overrides keeping the debug info for `--split-debug`
+    fn split_debug_overrides(&self) -> Vec<String> {
+        if !self.split_debug {
+            return Vec::new();
+        }
+        let profile = if self.is_release() { "release" } else { "dev" };
+        vec![
+            format!("profile.{}.debug=2", profile),
+            format!("profile.{}.strip=\"none\"", profile),
+        ]
+    }
+
+    /// Installation policy selected by `--yes`, `--ci` and `--no-install`
+    pub fn install_policy(&self) -> InstallPolicy {
+        if self.no_install {
+            InstallPolicy::Never
+        } else if self.yes || self.ci {
+            InstallPolicy::Always
+        } else {
+            InstallPolicy::Prompt
+        }
+    }
+}
+
+/// Ask for confirmation before running an installation `command` for `what`.
+///
+/// Non-TTY stdin is treated as `--yes`, so scripted builds keep working.
+fn confirm_install(policy: InstallPolicy, what: &str, command: &str) -> Result<(), Error> {
+    use std::io::{stderr, stdin, IsTerminal, Write};
+    match policy {
+        InstallPolicy::Always => Ok(()),
+        InstallPolicy::Never => Err(Error::toolchain(format!(
+            "{} is missing and `--no-install` was given, install it with `{}`",
+            what, command
+        ))
+        .with_code("E0202")),
+        InstallPolicy::Prompt if !stdin().is_terminal() => Ok(()),
+        InstallPolicy::Prompt => {
+            eprint!(
+                "{} is missing, it can be installed by running:\n\n    {}\n\nRun it now? [y/N] ",
+                what, command
+            );
+            stderr().flush()?;
+            let mut answer = String::new();
+            stdin().read_line(&mut answer)?;
+            match answer.trim().to_lowercase().as_str() {
+                "y" | "yes" => Ok(()),
+                _ => Err(Error::toolchain(format!(
+                    "{} is required, install it with `{}` or pass `--yes`",
+                    what, command
+                ))
+                .with_code("E0202")),
+            }
+        }
+    }
+}
+
+/// Version of the `iroha_wasm_pack metadata` document, bump it on any field change
+pub const METADATA_VERSION: u32 = 1;
+
+/// Everything the steps of a build need to know about one package or example
+#[derive(Serialize)]
+pub struct BuildContext {
+    package_name: String,
+    crate_type: String,
+    max_size: u64,
+    max_memory_pages: u64,
+    allowed_imports: Vec<String>,
+    toolchain: Option<String>,
+    fail_on_warnings: bool,
+    #[serde(skip)]
+    settings: Vec<Setting>,
+    /// Directory of the package's Cargo.toml
+    crate_root: PathBuf,
+    #[serde(skip)]
+    warnings: RefCell<Vec<String>>,
+    /// Runs cargo, rustc and rustup for the steps
+    #[serde(skip)]
+    runner: Arc<dyn CommandRunner>,
+    cargo_package: Option<String>,
+    example: Option<String>,
+    lock_file: PathBuf,
+    profile: &'static str,
+    target_dir: PathBuf,
+    wasm_folder: PathBuf,
+    wasm_in: PathBuf,
+    wasm_out: PathBuf,
+}
+
+/// Where the value of a setting comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Default,
+    Flag,
+    Env,
+    Metadata,
+    /// `iroha_wasm_pack.toml`
+    Config,
+    /// A rust-toolchain file
+    File,
+}
+
+/// A resolved setting, as `iroha_wasm_pack config` lists it
+#[derive(Debug, Clone, Serialize)]
+pub struct Setting {
+    pub key: &'static str,
+    pub value: String,
+    pub source: Source,
+    /// Configuration file the value comes from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Environment variable the value comes from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub var: Option<String>,
+}
+
+impl Setting {
+    /// Where the value comes from, e.g. `config ./iroha_wasm_pack.toml`
+    pub fn origin(&self) -> String {
+        let source = format!("{:?}", self.source).to_lowercase();
+        match (&self.file, &self.var) {
+            (Some(file), _) => format!("{} {}", source, file.display()),
+            (None, Some(var)) => format!("{} {}", source, var),
+            (None, None) => source,
+        }
+    }
+}
+
+/// Settings of a package merged from the flags, environment, metadata, configuration files
+/// and defaults, in this order of precedence.
+///
+/// The only place they are merged, `build` and `config` can't disagree.
+pub struct Settings {
+    pub max_size: u64,
+    pub max_memory_pages: u64,
+    pub allowed_imports: Vec<String>,
+    pub fail_on_warnings: bool,
+    pub toolchain: Option<String>,
+    pub listed: Vec<Setting>,
+}
+
+impl Settings {
+    fn resolve(args: &BuildArgs, config: &CargoConfig) -> Result<Self, Error> {
+        use std::env::var;
+        let project = ProjectConfig::discover(&config.root)?;
+        let metadata = config.package.as_ref().map(|package| &package.metadata);
+        let name = config.package.as_ref().map(|package| package.name.as_str());
+        let mut listed = Vec::new();
+        let mut list = |key, value: String, source, file: Option<&Path>| {
+            listed.push(Setting {
+                key,
+                value,
+                source,
+                file: file.map(Path::to_path_buf),
+                var: None,
+            });
+        };
+        let package_budget = project.get(|file| {
+            name.and_then(|name| file.packages.get(name))
+                .and_then(|package| package.max_size)
+        });
+        let max_size = match (
+            args.max_size
+                .or_else(|| metadata.and_then(|metadata| metadata.max_size)),
+            package_budget.or_else(|| project.get(|file| file.build.max_size)),
+        ) {
+            (Some(max_size), _) if args.max_size.is_some() => {
+                list("max-size", max_size.to_string(), Source::Flag, None);
+                max_size
+            }
+            (Some(max_size), _) => {
+                list("max-size", max_size.to_string(), Source::Metadata, None);
+                max_size
+            }
+            (None, Some((max_size, file))) => {
+                list("max-size", max_size.to_string(), Source::Config, Some(file));
+                max_size
+            }
+            (None, None) => {
+                list("max-size", MAX_WASM_SIZE.to_string(), Source::Default, None);
+                MAX_WASM_SIZE
+            }
+        };
+        let configured = project.get(|file| file.build.fail_on_warnings);
+        let fail_on_warnings = if args.fail_on_warnings {
+            list("fail-on-warnings", "true".to_owned(), Source::Flag, None);
+            true
+        } else if metadata.map_or(false, |metadata| metadata.fail_on_warnings) {
+            list(
+                "fail-on-warnings",
+                "true".to_owned(),
+                Source::Metadata,
+                None,
+            );
+            true
+        } else if let Some((value, file)) = configured {
+            list(
+                "fail-on-warnings",
+                value.to_string(),
+                Source::Config,
+                Some(file),
+            );
+            value
+        } else {
+            list(
+                "fail-on-warnings",
+                "false".to_owned(),
+                Source::Default,
+                None,
+            );
+            false
+        };
+        // A toolchain file of the project wins over the configuration file, like with rustup.
+        let configured = if has_toolchain_file(&config.root) {
+            None
+        } else {
+            project.get(|file| file.build.toolchain.clone())
+        };
+        let requested = args
+            .toolchain
+            .clone()
+            .or_else(|| configured.as_ref().map(|(toolchain, _)| toolchain.clone()));
+        let toolchain = project_toolchain(&config.root, requested);
+        match (&args.toolchain, &toolchain, &configured) {
+            (Some(toolchain), _, _) => list("toolchain", toolchain.clone(), Source::Flag, None),
+            (None, None, _) => list(
+                "toolchain",
+                "from rust-toolchain file".to_owned(),
+                Source::File,
+                None,
+            ),
+            (None, Some(toolchain), Some((_, file))) => {
+                list("toolchain", toolchain.clone(), Source::Config, Some(file))
+            }
+            (None, Some(toolchain), None) => {
+                list("toolchain", toolchain.clone(), Source::Default, None)
+            }
+        }
+        let max_memory_pages = match project.get(|file| file.validation.max_memory_pages) {
+            Some((pages, file)) => {
+                list(
+                    "max-memory-pages",
+                    pages.to_string(),
+                    Source::Config,
+                    Some(file),
+                );
+                pages
+            }
+            None => {
+                list(
+                    "max-memory-pages",
+                    MAX_MEMORY_PAGES.to_string(),
+                    Source::Default,
+                    None,
+                );
+                MAX_MEMORY_PAGES
+            }
+        };
+        let allowed_imports = match project.get(|file| file.validation.allowed_imports.clone()) {
+            Some((imports, file)) => {
+                list(
+                    "allowed-imports",
+                    imports.join(", "),
+                    Source::Config,
+                    Some(file),
+                );
+                imports
+            }
+            None => {
+                list("allowed-imports", "none".to_owned(), Source::Default, None);
+                Vec::new()
+            }
+        };
+        let flag_or_default = |set: bool| if set { Source::Flag } else { Source::Default };
+        list(
+            "build-std",
+            (!args.no_build_std).to_string(),
+            flag_or_default(args.no_build_std),
+            None,
+        );
+        list(
+            "skip",
+            metadata.map_or(false, |metadata| metadata.skip).to_string(),
+            if metadata.map_or(false, |metadata| metadata.skip) {
+                Source::Metadata
+            } else {
+                Source::Default
+            },
+            None,
+        );
+        list(
+            "opt-level",
+            args.wasm_opt.opt_level.clone(),
+            flag_or_default(args.wasm_opt.opt_level != "s"),
+            None,
+        );
+        list(
+            "keep-names",
+            args.wasm_opt.keep_names.to_string(),
+            flag_or_default(args.wasm_opt.keep_names),
+            None,
+        );
+        list(
+            "target-features",
+            args.wasm_opt
+                .target_features
+                .as_ref()
+                .map_or("none".to_owned(), |features| features.to_rustc_flag()),
+            flag_or_default(args.wasm_opt.target_features.is_some()),
+            None,
+        );
+        for (key, value) in [
+            (
+                "lto",
+                args.lto
+                    .map(|lto| lto.as_config_value().trim_matches('"').to_owned()),
+            ),
+            (
+                "codegen-units",
+                args.codegen_units.map(|units| units.to_string()),
+            ),
+            ("opt-level-rustc", args.opt_level_rustc.clone()),
+        ] {
+            match value {
+                Some(value) => list(key, value, Source::Flag, None),
+                None => list(
+                    key,
+                    "from the cargo profile".to_owned(),
+                    Source::Default,
+                    None,
+                ),
+            }
+        }
+        for name in ["CARGO_ENCODED_RUSTFLAGS", "RUSTFLAGS"] {
+            if let Ok(value) = var(name) {
+                list("rustflags", value, Source::Env, None);
+                break;
+            }
+        }
+        // Flags set by `IROHA_WASM_PACK_*` variables look typed, tell them apart here.
+        for setting in listed
+            .iter_mut()
+            .filter(|setting| setting.source == Source::Flag)
+        {
+            let var = env_overrides::var_of(setting.key)
+                .or_else(|| env_overrides::var_of(&format!("no-{}", setting.key)));
+            if var.is_some() {
+                setting.source = Source::Env;
+                setting.var = var;
+            }
+        }
+        Ok(Settings {
+            max_size,
+            max_memory_pages,
+            allowed_imports,
+            fail_on_warnings,
+            toolchain,
+            listed,
+        })
+    }
+}
+
+/// Keys `[package.metadata.iroha_wasm_pack]` accepts
+const METADATA_KEYS: &[&str] = &["fail-on-warnings", "skip", "max-size"];
+
+/// Unknown keys and type errors in `[package.metadata.iroha_wasm_pack]` of the project at `root`
+pub fn metadata_issues(root: &Path) -> Result<Vec<String>, Error> {
+    let cargo_toml: toml::Value = toml::from_str(&fs::read_to_string(root.join("Cargo.toml"))?)?;
+    let table = match cargo_toml
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("iroha_wasm_pack"))
+    {
+        Some(table) => table.clone(),
+        None => return Ok(Vec::new()),
+    };
+    let mut issues: Vec<String> = table
+        .as_table()
+        .map(|table| {
+            table
+                .keys()
+                .filter(|key| !METADATA_KEYS.contains(&key.as_str()))
+                .map(|key| {
+                    format!(
+                        "unknown key `{}` in [package.metadata.iroha_wasm_pack], known keys are: {}",
+                        key,
+                        METADATA_KEYS.join(", ")
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if let Err(err) = table.try_into::<PackMetadata>() {
+        issues.push(format!("[package.metadata.iroha_wasm_pack]: {}", err));
+    }
+    Ok(issues)
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack config` command.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    setting = AppSettings::TrailingVarArg,
+)]
+pub struct ConfigArgs {
+    /// Print the settings as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    /// Options of `build` to resolve the settings with
+    #[structopt(flatten)]
+    pub build: BuildArgs,
+}
+
+impl RunArgs for ConfigArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let root = root(working_dir()?)?;
+        let issues = metadata_issues(&root)?;
+        if !issues.is_empty() {
+            return Err(Error::manifest(format!(
+                "{} configuration error(s):\n  {}",
+                issues.len(),
+                issues.join("\n  ")
+            ))
+            .with_code("E0103"));
+        }
+        let contexts = BuildContext::resolve(&self.build)?;
+        let project = ProjectConfig::discover(&root)?;
+        let packages: Vec<serde_json::Value> = contexts
+            .iter()
+            .map(|ctx| {
+                serde_json::json!({
+                    "package": ctx.cargo_package,
+                    "example": ctx.example,
+                    "settings": ctx.settings,
+                })
+            })
+            .collect();
+        let files: Vec<&Path> = project.files().collect();
+        let document = serde_json::json!({
+            "config_files": files,
+            "explicit_config": project.is_explicit(),
+            "packages": packages,
+        });
+        let mut summary = Vec::new();
+        if self.json {
+            summary.push(serde_json::to_string_pretty(&document)?);
+        } else {
+            for file in project.files() {
+                if project.is_explicit() {
+                    summary.push(format!("config file {} (--config)", file.display()));
+                } else {
+                    summary.push(format!("config file {}", file.display()));
+                }
+            }
+            for ctx in &contexts {
+                let name = ctx
+                    .example
+                    .as_ref()
+                    .or(ctx.cargo_package.as_ref())
+                    .map_or("(current package)".to_owned(), |name| name.clone());
+                summary.push(name);
+                for setting in &ctx.settings {
+                    summary.push(format!(
+                        "  {:<18} {:<32} ({})",
+                        setting.key,
+                        setting.value,
+                        setting.origin()
+                    ));
+                }
+            }
+        }
+        Ok(CommandReport {
+            summary,
+            data: Some(document),
+            ..CommandReport::default()
+        })
+    }
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack metadata` command.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    setting = AppSettings::TrailingVarArg,
+)]
+pub struct MetadataArgs {
+    /// Options of `build` to resolve the contexts with
+    #[structopt(flatten)]
+    pub build: BuildArgs,
+}
+
+/// The document `metadata` prints for the project at `root`
+fn metadata_document(root: &Path, contexts: &[BuildContext]) -> serde_json::Value {
+    serde_json::json!({
+        "version": METADATA_VERSION,
+        "root": root,
+        "packages": contexts,
+    })
+}
+
+impl RunArgs for MetadataArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let contexts = BuildContext::resolve(&self.build)?;
+        let document = metadata_document(&project_root(&self.build)?, &contexts);
+        Ok(CommandReport {
+            summary: vec![serde_json::to_string_pretty(&document)?],
+            data: Some(document),
+            ..CommandReport::default()
+        })
+    }
+}
+
+/// Root of the project of `--manifest-path`, or the one around the current directory
+fn project_root(args: &BuildArgs) -> Result<PathBuf, Error> {
+    let start = match args.manifest_path.as_deref().and_then(Path::parent) {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => working_dir()?,
+    };
+    root(start)
+}
+
+/// Toolchain to build the project at `root` with, `None` for the one of its toolchain file
+pub fn project_toolchain(root: &Path, requested: Option<String>) -> Option<String> {
+    // A toolchain file of the project is picked up by rustup itself.
+    if requested.is_some() {
+        requested
+    } else if has_toolchain_file(root) {
+        None
+    } else {
+        Some(DEFAULT_TOOLCHAIN.to_owned())
+    }
+}
+
+fn has_toolchain_file(root: &Path) -> bool {
+    ["rust-toolchain.toml", "rust-toolchain"]
+        .iter()
+        .any(|file| root.join(file).exists())
+}
+
+// Construct this context to reuse in multi build steps
+impl BuildContext {
+    /// Contexts of the library or the selected examples of a package
+    fn for_package(
+        args: &BuildArgs,
+        target_root: &Path,
+        config: CargoConfig,
+        cargo_package: Option<String>,
+    ) -> Result<Vec<Self>, Error> {
+        let profile = if args.is_release() {
+            "release"
+        } else {
+            "debug"
+        };
+        let target_dir = target_dir(args.target_dir.as_deref(), target_root);
+        let wasm_folder = target_dir.join("wasm32-unknown-unknown").join(profile);
+        let wasm_name = match &config.package {
+            Some(package) => &package.name,
+            None => {
+                return Err(
+                    Error::manifest("missing [package] section in Cargo.toml").with_code("E0102")
+                )
+            }
+        };
+        let settings = Settings::resolve(args, &config)?;
+        let (max_size, fail_on_warnings) = (settings.max_size, settings.fail_on_warnings);
+        let toolchain = settings.toolchain.clone();
+        if !args.examples && args.example.is_none() {
+            // Cargo names the artifact after `[lib] name`, or the package name with `_` for `-`.
+            let artifact = match &config.lib.name {
+                Some(name) => name.clone(),
+                None => wasm_name.replace('-', "_"),
+            };
+            let wasm_in = wasm_folder.join(format!("{}{}", artifact, ".wasm"));
+            let wasm_out = wasm_folder.join(format!("{}{}", artifact, "_optimized.wasm"));
+            let crate_type = config.lib.crate_type.first().cloned().unwrap_or_default();
+            return Ok(vec![BuildContext {
+                package_name: wasm_name.clone(),
+                crate_type: crate_type,
+                max_size: max_size,
+                max_memory_pages: settings.max_memory_pages,
+                allowed_imports: settings.allowed_imports.clone(),
+                toolchain: toolchain,
+                fail_on_warnings: fail_on_warnings,
+                settings: settings.listed,
+                crate_root: config.root.clone(),
+                warnings: RefCell::default(),
+                runner: Arc::new(DuctRunner),
+                cargo_package: cargo_package,
+                example: None,
+                lock_file: target_root.join("Cargo.lock"),
+                profile: profile,
+                target_dir: target_dir,
+                wasm_folder: wasm_folder,
+                wasm_in: wasm_in,
+                wasm_out: wasm_out,
+            }]);
+        }
+        let mut names = example_names(&config)?;
+        if let Some(example) = &args.example {
+            if !names.contains(example) {
+                return Err(Error::manifest(format!(
+                    "no example `{}` in package `{}`, examples are: {}",
+                    example,
+                    wasm_name,
+                    names.join(", ")
+                )));
+            }
+            names = vec![example.clone()];
+        }
+        let examples_folder = wasm_folder.join("examples");
+        Ok(names
+            .into_iter()
+            .map(|name| {
+                // Examples are binaries unless a `[[example]]` entry says otherwise.
+                let crate_type = config
+                    .examples
+                    .iter()
+                    .find(|example| example.name == name)
+                    .and_then(|example| example.crate_type.first().cloned())
+                    .unwrap_or_else(|| "bin".to_owned());
+                BuildContext {
+                    package_name: wasm_name.clone(),
+                    crate_type: crate_type,
+                    max_size: max_size,
+                    max_memory_pages: settings.max_memory_pages,
+                    allowed_imports: settings.allowed_imports.clone(),
+                    toolchain: toolchain.clone(),
+                    fail_on_warnings: fail_on_warnings,
+                    settings: settings.listed.clone(),
+                    crate_root: config.root.clone(),
+                    warnings: RefCell::default(),
+                    runner: Arc::new(DuctRunner),
+                    cargo_package: cargo_package.clone(),
+                    wasm_in: examples_folder.join(format!("{}{}", name, ".wasm")),
+                    wasm_out: examples_folder.join(format!("{}{}", name, "_optimized.wasm")),
+                    example: Some(name),
+                    lock_file: target_root.join("Cargo.lock"),
+                    profile: profile,
+                    target_dir: target_dir.clone(),
+                    wasm_folder: wasm_folder.clone(),
+                }
+            })
+            .collect())
+    }
+
+    /// Name of the package being built
+    pub fn package_name(&self) -> &str {
+        &self.package_name
+    }
+
+    /// Run the external programs of the steps with `runner`, e.g. a `ScriptedRunner`
+    pub fn with_runner(mut self, runner: Arc<dyn CommandRunner>) -> Self {
+        self.runner = runner;
+        self
+    }
+
+    pub fn runner(&self) -> &dyn CommandRunner {
+        self.runner.as_ref()
+    }
+
+    /// Example being built, if any
+    pub fn example(&self) -> Option<&str> {
+        self.example.as_deref()
+    }
+
+    /// Where the optimized wasm is written
+    pub fn wasm_out(&self) -> &Path {
+        &self.wasm_out
+    }
+
+    /// Print a warning and remember it for `--fail-on-warnings`
+    fn warn(&self, message: String) {
+        if progress::is_json() {
+            progress::emit(progress::Event::Warning { message: &message });
+        } else {
+            eprintln!("warning: {}", message);
+        }
+        self.warnings.borrow_mut().push(message);
+    }
+
+    /// Contexts of all packages selected from the current directory or `--manifest-path`
+    pub fn resolve(args: &BuildArgs) -> Result<Vec<Self>, Error> {
+        let root = project_root(args)?;
+        let config = pasre_cargo_config(&root)?;
+        let selecting = args.workspace || args.package.is_some();
+        if let (None, Some(name), Some(package)) =
+            (&config.workspace, &args.package, &config.package)
+        {
+            if name != &package.name {
+                return Err(Error::manifest(format!(
+                    "package `{}` doesn't match `{}` of {}, which isn't a workspace",
+                    name,
+                    package.name,
+                    root.join("Cargo.toml").display()
+                )));
+            }
+        }
+        if config.workspace.is_none() || (!selecting && config.package.is_some()) {
+            // Members of a workspace share the target directory of the workspace root.
+            let target_root = workspace_root(&root)?.unwrap_or_else(|| root.clone());
+            return BuildContext::for_package(args, &target_root, config, None);
+        }
+        let mut members = match &config.workspace {
+            Some(workspace) => workspace_members(&root, workspace)?,
+            None => Vec::new(),
+        };
+        members.retain(|member| {
+            member
+                .package
+                .as_ref()
+                .map_or(false, |package| !package.metadata.skip)
+        });
+        if config.package.is_some() {
+            members.insert(0, config);
+        }
+        let names: Vec<String> = members
+            .iter()
+            .filter_map(|member| member.package.as_ref())
+            .map(|package| package.name.clone())
+            .collect();
+        if !selecting {
+            return Err(Error::manifest(format!(
+                "{} is a virtual workspace manifest, there is no package to build here.\n\n\
+                cd into one of the members, or select them with `-p <name>` or `--workspace`:\n\n    {}",
+                root.join("Cargo.toml").display(),
+                names.join("\n    ")
+            )).with_code("E0102"));
+        }
+        if let Some(name) = &args.package {
+            if !names.contains(name) {
+                return Err(Error::manifest(format!(
+                    "package `{}` is not a member of the workspace, members are: {}",
+                    name,
+                    names.join(", ")
+                )));
+            }
+        }
+        let mut contexts = Vec::new();
+        for (member, name) in members.into_iter().zip(names) {
+            if args
+                .package
+                .as_ref()
+                .map_or(true, |package| package == &name)
+            {
+                contexts.extend(BuildContext::for_package(args, &root, member, Some(name))?);
+            }
+        }
+        Ok(contexts)
+    }
+}
+
+/// One named step of a build, run for every package
+pub trait Step {
+    /// Name `--skip-step` and the reports refer to the step by
+    fn name(&self) -> &'static str;
+    /// What the step does, as `--dry-run` lists it
+    fn description(&self) -> &'static str;
+    fn run(&self, args: &BuildArgs, ctx: &mut BuildContext) -> Result<(), Error>;
+}
+
+/// A step running one of the `step_*` functions
+#[derive(Clone, Copy)]
+pub struct FnStep {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub run: fn(&BuildArgs, &mut BuildContext) -> Result<(), Error>,
+}
+
+impl Step for FnStep {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn run(&self, args: &BuildArgs, ctx: &mut BuildContext) -> Result<(), Error> {
+        (self.run)(args, ctx)
+    }
+}
+
+/// Every step pipelines are made of, by name
+pub const STEPS: &[FnStep] = &[
+    FnStep {
+        name: "check-rustc-version",
+        description: "check that rustc is recent enough",
+        run: |args, ctx| step_check_rustc_version(args, ctx),
+    },
+    FnStep {
+        name: "check-crate-config",
+        description: "check the crate type and the package metadata",
+        run: |args, ctx| step_check_crate_config(args, ctx),
+    },
+    FnStep {
+        name: "check-wasm-target",
+        description: "check for the wasm32 target or rust-src, installing them if allowed",
+        run: |args, ctx| step_check_for_wasm_target(args, ctx),
+    },
+    FnStep {
+        name: "check-wasm",
+        description: "type-check the crate for the wasm target",
+        run: |args, ctx| step_check_wasm(args, ctx),
+    },
+    FnStep {
+        name: "build-wasm",
+        description: "compile the crate to wasm with cargo",
+        run: step_build_wasm,
+    },
+    FnStep {
+        name: "build-tests",
+        description: "compile the wasm test harnesses",
+        run: |args, ctx| step_build_tests(args, ctx),
+    },
+    FnStep {
+        name: "docker-build",
+        description: "compile and optimize the wasm in the builder image with `--docker`",
+        run: step_docker_build,
+    },
+    FnStep {
+        name: "wasm-opt",
+        description: "optimize the wasm with wasm-opt",
+        run: |args, ctx| step_wasm_opt(args, ctx),
+    },
+    FnStep {
+        name: "iroha-version",
+        description: "embed the Iroha version of Cargo.lock, compare it with `--expect-iroha-version` or `--node-url`",
+        run: |args, ctx| step_iroha_version(args, ctx),
+    },
+    FnStep {
+        name: "strip",
+        description: "strip the custom sections Iroha has no use for",
+        run: |args, ctx| step_strip(args, ctx),
+    },
+    FnStep {
+        name: "split-debug",
+        description: "tag the wasm and its `--split-debug` companion with a build id",
+        run: |args, ctx| step_split_debug(args, ctx),
+    },
+    FnStep {
+        name: "component",
+        description: "wrap the module as a component with `--component`",
+        run: |args, ctx| step_component(args, ctx),
+    },
+    FnStep {
+        name: "analyze-size",
+        description: "print where the bytes go with `--analyze-size`",
+        run: |args, ctx| step_analyze_size(args, ctx),
+    },
+    FnStep {
+        name: "verify-module",
+        description: "run the post-build checks on the optimized wasm",
+        run: |args, ctx| step_verify_module(args, ctx),
+    },
+    FnStep {
+        name: "record-size",
+        description: "append the size to the build history",
+        run: |args, ctx| step_record_size(args, ctx),
+    },
+];
+
+/// The registered step called `name`
+pub fn registered_step(name: &str) -> Result<&'static FnStep, Error> {
+    STEPS.iter().find(|step| step.name == name).ok_or_else(|| {
+        let names: Vec<&str> = STEPS.iter().map(|step| step.name).collect();
+        Error::usage(format!(
+            "no step `{}`, steps are: {}",
+            name,
+            names.join(", ")
+        ))
+    })
+}
+
+/// How long a step took for a package
+#[derive(Debug, Clone, Serialize)]
+pub struct StepTiming {
+    pub package: String,
+    pub step: String,
+    pub millis: u64,
+}
+
+/// The ordered steps a command runs for every package
+#[derive(Default)]
+pub struct Pipeline {
+    steps: Vec<Box<dyn Step>>,
+    /// Whether `[hooks]` run around the steps, only `build` turns them on
+    hooks: bool,
+}
+
+impl Pipeline {
+    /// The registered steps with these names, in this order
+    pub fn from_names(names: &[&str]) -> Result<Self, Error> {
+        let mut pipeline = Pipeline::default();
+        for name in names {
+            pipeline.push(*registered_step(name)?);
+        }
+        Ok(pipeline)
+    }
+
+    /// Steps of `iroha_wasm_pack build` with these options
+    pub fn for_build(args: &BuildArgs) -> Result<Self, Error> {
+        args.check_conflicts()?;
+        let names: &[&str] = if args.tests {
+            &["check-rustc-version", "check-wasm-target", "build-tests"]
+        } else {
+            &[
+                "check-rustc-version",
+                "check-crate-config",
+                "check-wasm-target",
+                "build-wasm",
+                "wasm-opt",
+                "iroha-version",
+                "strip",
+                "split-debug",
+                "component",
+                "analyze-size",
+                "verify-module",
+                "record-size",
+            ]
+        };
+        let mut names = names.to_vec();
+        if args.docker {
+            names.retain(|name| !DOCKER_STEPS.contains(name));
+            names.insert(1, "docker-build");
+        }
+        Ok(Pipeline::from_names(&names)?
+            .skipping(&args.skip_steps)?
+            .with_hooks(!args.no_hooks))
+    }
+
+    /// Run the `[hooks]` of the configuration files around the steps
+    pub fn with_hooks(mut self, enabled: bool) -> Self {
+        self.hooks = enabled;
+        self
+    }
+
+    /// Leave out the steps `--skip-step` names, which have to be registered
+    pub fn skipping(mut self, names: &[String]) -> Result<Self, Error> {
+        for name in names {
+            registered_step(name)?;
+            self.steps.retain(|step| step.name() != name);
+        }
+        Ok(self)
+    }
+
+    pub fn push(&mut self, step: impl Step + 'static) {
+        self.steps.push(Box::new(step));
+    }
+
+    /// Insert `step` right before the step called `name`
+    pub fn insert_before(&mut self, name: &str, step: impl Step + 'static) -> Result<(), Error> {
+        let index = self.position(name)?;
+        self.steps.insert(index, Box::new(step));
+        Ok(())
+    }
+
+    /// Insert `step` right after the step called `name`
+    pub fn insert_after(&mut self, name: &str, step: impl Step + 'static) -> Result<(), Error> {
+        let index = self.position(name)?;
+        self.steps.insert(index + 1, Box::new(step));
+        Ok(())
+    }
+
+    fn position(&self, name: &str) -> Result<usize, Error> {
+        self.steps
+            .iter()
+            .position(|step| step.name() == name)
+            .ok_or_else(|| Error::other(format!("no step `{}` in the pipeline", name)))
+    }
+
+    pub fn steps(&self) -> impl Iterator<Item = &dyn Step> {
+        self.steps.iter().map(|step| step.as_ref())
+    }
+
+    /// Run the steps in order, stopping at the first that fails.
+    ///
+    /// With hooks, `pre_build` runs first, then `pre_<step>`, the step and `post_<step>` for
+    /// every step, then `post_build`. After a failing step or hook nothing else runs but
+    /// `on_failure`, whose own failures are only reported.
+    pub fn run(&self, args: &BuildArgs, ctx: &mut BuildContext) -> Result<Vec<StepTiming>, Error> {
+        let project = if self.hooks {
+            Some(ProjectConfig::discover(&ctx.crate_root)?)
+        } else {
+            None
+        };
+        let result = self.run_steps(args, ctx, project.as_ref());
+        if let (Err(err), Some(project)) = (&result, &project) {
+            if let Err(hook_err) = run_hooks(project, "on_failure", None, ctx, Some(err)) {
+                ctx.warn(format!("on_failure hook failed: {}", hook_err));
+            }
+        }
+        result
+    }
+
+    fn run_steps(
+        &self,
+        args: &BuildArgs,
+        ctx: &mut BuildContext,
+        project: Option<&ProjectConfig>,
+    ) -> Result<Vec<StepTiming>, Error> {
+        let hooks = |key: &str, step: Option<&str>, ctx: &BuildContext| match project {
+            Some(project) => run_hooks(project, key, step, ctx, None),
+            None => Ok(()),
+        };
+        hooks("pre_build", None, ctx)?;
+        let mut timings = Vec::new();
+        for step in &self.steps {
+            let span = tracing::info_span!("step", step = step.name(), package = %ctx.package_name);
+            let _entered = span.enter();
+            let key = step.name().replace('-', "_");
+            hooks(&format!("pre_{}", key), Some(step.name()), ctx)?;
+            let started = std::time::Instant::now();
+            step.run(args, ctx)?;
+            timings.push(StepTiming {
+                package: ctx.package_name.clone(),
+                step: step.name().to_owned(),
+                millis: started.elapsed().as_millis() as u64,
+            });
+            hooks(&format!("post_{}", key), Some(step.name()), ctx)?;
+        }
+        hooks("post_build", None, ctx)?;
+        Ok(timings)
+    }
+}
+
+/// Run the commands `[hooks]` lists under `key`, in order, with the context in variables.
+///
+/// Their output is captured and printed with the hook and program in front of every line.
+fn run_hooks(
+    project: &ProjectConfig,
+    key: &str,
+    step: Option<&str>,
+    ctx: &BuildContext,
+    error: Option<&Error>,
+) -> Result<(), Error> {
+    let (hooks, file) = match project.get(|file| file.hooks.get(key).cloned()) {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+    let dir = file.parent().unwrap_or_else(|| Path::new("."));
+    for hook in hooks {
+        let argv = hook.argv();
+        let (program, hook_args) = match argv.split_first() {
+            Some((program, hook_args)) => (program, hook_args),
+            None => continue,
+        };
+        // A relative path to a script is relative to the file listing it, not the shell.
+        let program = if program.contains('/') || program.contains('\\') {
+            dir.join(program).display().to_string()
+        } else {
+            program.clone()
+        };
+        let mut invocation = Invocation::new(&program, hook_args)
+            .dir(dir)
+            .env("IROHA_WASM_PACK_HOOK_NAME", key)
+            .env("IROHA_WASM_PACK_HOOK_STEP", step.unwrap_or_default())
+            .env("IROHA_WASM_PACK_HOOK_PACKAGE", &ctx.package_name)
+            .env("IROHA_WASM_PACK_HOOK_PROFILE", ctx.profile)
+            .env(
+                "IROHA_WASM_PACK_HOOK_CRATE_ROOT",
+                ctx.crate_root.display().to_string(),
+            )
+            .env(
+                "IROHA_WASM_PACK_HOOK_TARGET_DIR",
+                ctx.target_dir.display().to_string(),
+            )
+            .env(
+                "IROHA_WASM_PACK_HOOK_WASM",
+                ctx.wasm_out.display().to_string(),
+            )
+            .capture_stderr();
+        if let Some(error) = error {
+            invocation = invocation.env("IROHA_WASM_PACK_HOOK_ERROR", error.to_string());
+        }
+        let failure = match ctx.runner().output(&invocation) {
+            Ok(output) => {
+                for line in output.stdout.lines().chain(output.stderr.lines()) {
+                    eprintln!("[{} {}] {}", key, program, line);
+                }
+                if output.is_success() {
+                    continue;
+                }
+                invocation.failed(&output)
+            }
+            Err(err) => err,
+        };
+        if !hook.allow_failure() {
+            return Err(failure);
+        }
+        ctx.warn(format!("{} hook failed: {}", key, failure));
+    }
+    Ok(())
+}
+
+/// What `build` produced
+#[derive(Debug, Serialize)]
+pub struct BuildReport {
+    /// Optimized wasm of every package and example, empty when building tests
+    pub artifacts: Vec<Artifact>,
+    /// Warnings printed while building
+    pub warnings: Vec<String>,
+    /// Every step that ran, in order
+    pub steps: Vec<StepTiming>,
+}
+
+/// One optimized wasm written by `build`
+#[derive(Debug, Serialize)]
+pub struct Artifact {
+    pub package: String,
+    pub example: Option<String>,
+    pub wasm: PathBuf,
+    pub size: u64,
+    /// Hash Iroha identifies the blob by, see `wasm::iroha_hash`
+    pub hash: String,
+    /// Iroha dependencies the module is built against, from its `iroha-version` section
+    pub iroha_versions: Vec<String>,
+}
+
+/// Run the steps of `iroha_wasm_pack build` for every selected package
+pub fn build(args: &BuildArgs) -> Result<BuildReport, Error> {
+    let pipeline = Pipeline::for_build(args)?;
+    let mut contexts = BuildContext::resolve(args)?;
+    let mut steps = Vec::new();
+    for ctx in &mut contexts {
+        let _lock = BuildLock::acquire(&ctx.wasm_folder, args.no_wait)?;
+        steps.extend(pipeline.run(args, ctx)?);
+    }
+    check_promoted_warnings(&contexts)?;
+    let mut artifacts = Vec::new();
+    if !args.tests {
+        for ctx in &contexts {
+            let bytes = fs::read(&ctx.wasm_out)?;
+            let iroha_versions = wasm::custom_section(&bytes, IROHA_VERSION_SECTION)?
+                .map(|versions| {
+                    String::from_utf8_lossy(versions)
+                        .lines()
+                        .map(ToOwned::to_owned)
+                        .collect()
+                })
+                .unwrap_or_default();
+            artifacts.push(Artifact {
+                package: ctx.package_name.clone(),
+                example: ctx.example.clone(),
+                wasm: ctx.wasm_out.clone(),
+                size: bytes.len() as u64,
+                hash: wasm::iroha_hash(&bytes),
+                iroha_versions,
+            });
+        }
+    }
+    Ok(BuildReport {
+        artifacts,
+        warnings: contexts
+            .iter()
+            .flat_map(|ctx| ctx.warnings.borrow().clone())
+            .collect(),
+        steps,
+    })
+}
+
+impl RunArgs for BuildArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let mut summary = Vec::new();
+        if self.dry_run {
+            let pipeline = Pipeline::for_build(&self)?;
+            for ctx in BuildContext::resolve(&self)? {
+                summary.push(match &ctx.example {
+                    Some(example) => format!("{} (example {})", ctx.package_name, example),
+                    None => ctx.package_name.clone(),
+                });
+            }
+            let overrides = env_overrides::applied_flags();
+            if !overrides.is_empty() {
+                summary.push("\nFrom the environment:".to_owned());
+                for (flag, var) in overrides {
+                    summary.push(format!("  --{:<20}{}", flag, var));
+                }
+            }
+            let profile = self.profile_overrides();
+            if !profile.is_empty() {
+                summary.push("\nProfile overrides:".to_owned());
+                for config in profile {
+                    summary.push(format!("  --config {}", config));
+                }
+            }
+            summary.push(String::new());
+            for step in pipeline.steps() {
+                summary.push(format!("  {:<22}{}", step.name(), step.description()));
+            }
+            return Ok(CommandReport::summary(summary));
+        }
+        let report = build(&self)?;
+        if self.timings {
+            for timing in logging::recorded_timings() {
+                summary.push(format!(
+                    "{:<24}{:<22}{:>8} ms",
+                    timing.package, timing.step, timing.millis
+                ));
+            }
+        }
+        if let Some(path) = &self.report {
+            fs::write(path, serde_json::to_string_pretty(&report)?)?;
+        }
+        for artifact in &report.artifacts {
+            if !artifact.iroha_versions.is_empty() {
+                summary.push(format!(
+                    "Built against {}",
+                    artifact.iroha_versions.join(", ")
+                ));
+            }
+        }
+        let examples: Vec<&str> = report
+            .artifacts
+            .iter()
+            .filter_map(|artifact| artifact.example.as_deref())
+            .collect();
+        if !examples.is_empty() {
+            summary.push(format!("Built examples: {}", examples.join(", ")));
+        }
+        Ok(CommandReport {
+            summary,
+            ..CommandReport::from(report)
+        })
+    }
+}
+
+/// Fail if a package with `fail-on-warnings` emitted any warning
+fn check_promoted_warnings(contexts: &[BuildContext]) -> Result<(), Error> {
+    let promoted: Vec<String> = contexts
+        .iter()
+        .filter(|ctx| ctx.fail_on_warnings)
+        .flat_map(|ctx| ctx.warnings.borrow().clone())
+        .collect();
+    if !promoted.is_empty() {
+        return Err(Error::other(format!(
+            "{} warning(s) emitted with `--fail-on-warnings`:\n  {}",
+            promoted.len(),
+            promoted.join("\n  ")
+        )));
+    }
+    Ok(())
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack check` command.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    setting = AppSettings::TrailingVarArg,
+)]
+pub struct CheckArgs {
+    /// Same options as `build`, those of wasm-opt and the size check are ignored
+    #[structopt(flatten)]
+    pub build: BuildArgs,
+}
+
+impl RunArgs for CheckArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let args = &self.build;
+        let names: &[&str] = if args.tests {
+            &["check-rustc-version", "check-wasm-target", "check-wasm"]
+        } else {
+            &[
+                "check-rustc-version",
+                "check-crate-config",
+                "check-wasm-target",
+                "check-wasm",
+            ]
+        };
+        let pipeline = Pipeline::from_names(names)?.skipping(&args.skip_steps)?;
+        let mut contexts = BuildContext::resolve(args)?;
+        let mut report = CommandReport::default();
+        for ctx in &mut contexts {
+            let _lock = BuildLock::acquire(&ctx.wasm_folder, args.no_wait)?;
+            report.steps.extend(pipeline.run(args, ctx)?);
+        }
+        check_promoted_warnings(&contexts)?;
+        report.warnings = contexts
+            .iter()
+            .flat_map(|ctx| ctx.warnings.borrow().clone())
+            .collect();
+        Ok(report)
+    }
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack lint` command.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    setting = AppSettings::TrailingVarArg,
+)]
+pub struct LintArgs {
+    /// Fail if clippy or the Iroha lints report anything
+    #[structopt(long = "deny-warnings")]
+    pub deny_warnings: bool,
+
+    /// Print clippy's JSON messages followed by the Iroha lints, one JSON object per line
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    /// Same options as `build`, those of wasm-opt and the size check are ignored
+    #[structopt(flatten)]
+    pub build: BuildArgs,
+}
+
+impl LintArgs {
+    /// Clippy with the flags of the real build, so lints see the same cfg.
+    ///
+    /// With `--json` its messages are returned instead of rendered.
+    fn clippy(&self, ctx: &BuildContext) -> Result<Vec<serde_json::Value>, Error> {
+        let args = &self.build;
+        if !self.json {
+            let invocation = cargo_command(
+                "clippy",
+                args,
+                ctx,
+                &["--message-format=json-diagnostic-rendered-ansi"],
+            );
+            return run_cargo_json("clippy", &invocation, ctx).map(|_| Vec::new());
+        }
+        let invocation = cargo_command("clippy", args, ctx, &["--message-format=json"]);
+        let output = ctx.runner().output(&invocation)?;
+        let mut messages = Vec::new();
+        for line in output.stdout.lines() {
+            if let Ok(message) = serde_json::from_str::<serde_json::Value>(line) {
+                if message["reason"] == "compiler-message"
+                    && message["message"]["level"] == "warning"
+                {
+                    let text = message["message"]["message"].as_str().unwrap_or_default();
+                    ctx.warnings.borrow_mut().push(text.to_owned());
+                }
+                messages.push(message);
+            }
+        }
+        if !output.is_success() {
+            return Err(Error::command("cargo", &["clippy"], output.status, ""));
+        }
+        Ok(messages)
+    }
+}
+
+impl RunArgs for LintArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let args = &self.build;
+        let pipeline = Pipeline::from_names(&["check-rustc-version", "check-wasm-target"])?
+            .skipping(&args.skip_steps)?;
+        let mut contexts = BuildContext::resolve(args)?;
+        let (mut messages, mut findings) = (Vec::new(), Vec::new());
+        for ctx in &mut contexts {
+            let _lock = BuildLock::acquire(&ctx.wasm_folder, args.no_wait)?;
+            pipeline.run(args, ctx)?;
+            messages.extend(self.clippy(ctx)?);
+            // Examples are binaries, only libraries need an entrypoint.
+            let contract = ctx.example.is_none() && ctx.crate_type == "cdylib";
+            findings.extend(lints::lint_crate(&ctx.crate_root, contract)?);
+        }
+        let warnings: Vec<String> = contexts
+            .iter()
+            .flat_map(|ctx| ctx.warnings.borrow().clone())
+            .collect();
+        if self.deny_warnings && warnings.len() + findings.len() > 0 {
+            return Err(Error::other(format!(
+                "{} clippy warning(s) and {} Iroha lint finding(s) with `--deny-warnings`",
+                warnings.len(),
+                findings.len()
+            )));
+        }
+        // `--json` keeps printing one message or finding per line.
+        let summary = if self.json {
+            let mut lines = Vec::new();
+            for message in &messages {
+                lines.push(serde_json::to_string(message)?);
+            }
+            for finding in &findings {
+                lines.push(serde_json::to_string(finding)?);
+            }
+            lines
+        } else {
+            findings.iter().map(ToString::to_string).collect()
+        };
+        Ok(CommandReport {
+            summary,
+            warnings,
+            data: Some(serde_json::json!({
+                "clippy": messages,
+                "findings": findings,
+            })),
+            ..CommandReport::default()
+        })
+    }
+}
+
+/// Type-check with `cargo check`, flags match the real build so errors do too
+pub fn step_check_wasm(args: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    let mut extra = vec!["--message-format=json-diagnostic-rendered-ansi"];
+    if args.tests {
+        extra.push("--tests");
+    }
+    let invocation = cargo_command("check", args, ctx, &extra);
+    run_cargo_json("check", &invocation, ctx).map(|_| ())
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack expand` command.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    setting = AppSettings::TrailingVarArg,
+)]
+pub struct ExpandArgs {
+    /// Only print this item, e.g. `my_trigger` or `utils::parse`
+    #[structopt(long = "item")]
+    pub item: Option<String>,
+
+    /// Same options as `build`, those of wasm-opt and the size check are ignored
+    #[structopt(flatten)]
+    pub build: BuildArgs,
+}
+
+/// Whether the rustc cargo builds the crate with is a nightly, `-Z` flags need one
+fn builds_with_nightly(args: &BuildArgs, ctx: &BuildContext) -> bool {
+    let mut rustc_args = Vec::new();
+    // Without build-std cargo runs with the toolchain rustup picks for the crate.
+    if let (false, Some(toolchain)) = (args.no_build_std, &ctx.toolchain) {
+        rustc_args.push(format!("+{}", toolchain));
+    }
+    rustc_args.push("-vV".to_owned());
+    let invocation = Invocation::new("rustc", rustc_args).dir(&ctx.crate_root);
+    match ctx.runner().read(&invocation) {
+        Ok(version) => version
+            .lines()
+            .filter_map(|line| line.strip_prefix("release: "))
+            .any(|release| release.contains("-nightly") || release.contains("-dev")),
+        Err(_) => false,
+    }
+}
+
+/// Lines of the item at `path` in `file`, 1-based and inclusive
+fn item_lines(items: &[syn::Item], path: &[&str]) -> Option<(usize, usize)> {
+    use syn::spanned::Spanned;
+    let (first, rest) = path.split_first()?;
+    for item in items {
+        let ident = match item {
+            syn::Item::Fn(item) => &item.sig.ident,
+            syn::Item::Mod(item) => &item.ident,
+            syn::Item::Struct(item) => &item.ident,
+            syn::Item::Enum(item) => &item.ident,
+            syn::Item::Const(item) => &item.ident,
+            syn::Item::Static(item) => &item.ident,
+            syn::Item::Trait(item) => &item.ident,
+            syn::Item::Type(item) => &item.ident,
+            _ => continue,
+        };
+        if ident != first {
+            continue;
+        }
+        if rest.is_empty() {
+            let span = item.span();
+            return Some((span.start().line, span.end().line));
+        }
+        if let syn::Item::Mod(syn::ItemMod {
+            content: Some((_, items)),
+            ..
+        }) = item
+        {
+            return item_lines(items, rest);
+        }
+    }
+    None
+}
+
+/// Lines of the shim functions the entrypoint macro generates
+fn shim_lines(file: &syn::File) -> Vec<(usize, usize)> {
+    use syn::spanned::Spanned;
+    file.items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Fn(function)
+                if wasm::ENTRYPOINT_EXPORTS.contains(&function.sig.ident.to_string().as_str()) =>
+            {
+                Some((item.span().start().line, item.span().end().line))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+impl RunArgs for ExpandArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        use std::io::{stdout, IsTerminal};
+        let args = &self.build;
+        let mut contexts = BuildContext::resolve(args)?;
+        let ctx = contexts
+            .first_mut()
+            .ok_or_else(|| Error::other("no package selected to expand"))?;
+        Pipeline::from_names(&["check-rustc-version", "check-wasm-target"])?
+            .skipping(&args.skip_steps)?
+            .run(args, ctx)?;
+        let target = if ctx.example.is_some() {
+            &[][..]
+        } else {
+            &["--lib"][..]
+        };
+        let mut extra = vec!["--profile=check"];
+        extra.extend_from_slice(target);
+        // The compiler errors go to the terminal as they are when the crate doesn't compile.
+        let invocation = cargo_rustc_command("rustc", args, ctx, &extra, &["-Zunpretty=expanded"]);
+        let output = ctx.runner().output(&invocation)?;
+        if !output.is_success() {
+            if !builds_with_nightly(args, ctx) {
+                return Err(Error::other(
+                    "expand failed, expanding needs a nightly toolchain, check the errors above",
+                ));
+            }
+            return Err(Error::other(
+                "expand failed, the crate doesn't compile, see the errors above",
+            ));
+        }
+        let mut source = output.stdout;
+        // Pretty print when rustfmt is around, the raw expansion is still valid otherwise.
+        let rustfmt = Invocation::new("rustfmt", ["--edition", "2021", "--emit", "stdout"])
+            .stdin(source.clone())
+            .capture_stderr();
+        if let Ok(formatted) = ctx.runner().read(&rustfmt) {
+            source = formatted;
+        }
+        let file = syn::parse_file(&source).ok();
+        let lines: Vec<&str> = source.lines().collect();
+        let (first, last) = match (&self.item, &file) {
+            (Some(item), Some(file)) => {
+                let path: Vec<&str> = item.split("::").collect();
+                item_lines(&file.items, &path).ok_or_else(|| {
+                    Error::other(format!("no item `{}` in the expanded crate", item))
+                })?
+            }
+            (Some(_), None) => {
+                return Err(Error::other(
+                    "the expanded source doesn't parse, `--item` can't be used",
+                ))
+            }
+            (None, _) => (1, lines.len()),
+        };
+        let shims = file.as_ref().map(shim_lines).unwrap_or_default();
+        let color = stdout().is_terminal();
+        let mut summary = Vec::new();
+        for (number, line) in lines.iter().enumerate().take(last).skip(first - 1) {
+            let shim = shims
+                .iter()
+                .any(|(start, end)| (*start..=*end).contains(&(number + 1)));
+            summary.push(match (shim, color) {
+                (true, true) => format!("\x1b[1;33m{}\x1b[0m", line),
+                (true, false) => format!("{} // generated by #[entrypoint]", line),
+                _ => line.to_string(),
+            });
+        }
+        let data = serde_json::json!({
+            "package": ctx.package_name,
+            "example": ctx.example,
+            "first_line": first,
+            "source": lines[first - 1..last].join("\n"),
+            "shims": shims,
+        });
+        Ok(CommandReport {
+            summary,
+            data: Some(data),
+            ..CommandReport::default()
+        })
+    }
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack clean` command.
+#[derive(Debug, StructOpt)]
+pub struct CleanArgs {
+    /// Only remove the outputs of this profile, like `debug` or `release`
+    #[structopt(long = "profile")]
+    pub profile: Option<String>,
+
+    /// Also clear the cache directory of the tool
+    #[structopt(long = "all")]
+    pub all: bool,
+
+    /// List what would be removed without removing it
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Directory of the build outputs, like the `--target-dir` of `build`
+    #[structopt(long = "target-dir", parse(from_os_str))]
+    pub target_dir: Option<PathBuf>,
+}
+
+/// Cache directory of the tool, `$IROHA_WASM_PACK_CACHE_DIR` or `$XDG_CACHE_HOME/iroha_wasm_pack`
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("IROHA_WASM_PACK_CACHE_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    let cache = match std::env::var_os("XDG_CACHE_HOME") {
+        Some(cache) => PathBuf::from(cache),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(cache.join("iroha_wasm_pack"))
+}
+
+/// Files and bytes below `path`, symlinks are counted but not followed
+fn disk_usage(path: &Path) -> (u64, u64) {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return (0, 0),
+    };
+    if !metadata.is_dir() {
+        return (1, metadata.len());
+    }
+    fs::read_dir(path)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| disk_usage(&entry.path()))
+        .fold((0, 0), |(files, bytes), (more_files, more_bytes)| {
+            (files + more_files, bytes + more_bytes)
+        })
+}
+
+impl RunArgs for CleanArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let root = root(working_dir()?)?;
+        let target_root = workspace_root(&root)?.unwrap_or(root);
+        let target_dir = target_dir(self.target_dir.as_deref(), &target_root);
+        let wasm_target = target_dir.join("wasm32-unknown-unknown");
+        let mut removals = vec![(
+            match self.profile.as_deref() {
+                Some("dev") => wasm_target.join("debug"),
+                Some(profile) => wasm_target.join(profile),
+                None => wasm_target.clone(),
+            },
+            target_dir,
+        )];
+        if self.all {
+            if let Some(cache) = cache_dir() {
+                removals.push((cache.clone(), cache));
+            }
+        }
+        let (mut files, mut bytes) = (0, 0);
+        let (mut summary, mut removed) = (Vec::new(), Vec::new());
+        for (path, scope) in removals {
+            if fs::symlink_metadata(&path).is_err() {
+                continue;
+            }
+            // A symlink must not take the removal outside of the directory it belongs to.
+            let inside = match (fs::canonicalize(&path), fs::canonicalize(&scope)) {
+                (Ok(path), Ok(scope)) => path.starts_with(scope),
+                _ => false,
+            };
+            if !inside {
+                return Err(Error::other(format!(
+                    "refusing to remove {}, it resolves outside of {}",
+                    path.display(),
+                    scope.display()
+                )));
+            }
+            let (path_files, path_bytes) = disk_usage(&path);
+            files += path_files;
+            bytes += path_bytes;
+            if self.dry_run {
+                summary.push(format!("Would remove {}", path.display()));
+                removed.push(path);
+                continue;
+            }
+            info!("Removing {}", path.display());
+            if let Err(err) = fs::remove_dir_all(&path) {
+                return Err(
+                    Error::other(format!("remove {} failed", path.display())).caused_by(err)
+                );
+            }
+            removed.push(path);
+        }
+        summary.push(format!(
+            "{} {} files, {:.1} MiB",
+            if self.dry_run {
+                "Would remove"
+            } else {
+                "Removed"
+            },
+            files,
+            bytes as f64 / (1024.0 * 1024.0)
+        ));
+        Ok(CommandReport {
+            summary,
+            data: Some(serde_json::json!({
+                "dry_run": self.dry_run,
+                "removed": removed,
+                "files": files,
+                "bytes": bytes,
+            })),
+            ..CommandReport::default()
+        })
+    }
+}
+
+/// Advisory lock on the profile output directory, released when dropped.
+///
+/// The OS drops the lock together with the file handle, so it is also
+/// released on panic or when the process is killed by Ctrl-C.
+pub struct BuildLock {
+    _file: fs::File,
+}
+
+impl BuildLock {
+    /// Lock `dir`, waiting for other processes unless `no_wait` is set
+    pub fn acquire(dir: &Path, no_wait: bool) -> Result<Self, Error> {
+        use fs2::FileExt;
+        fs::create_dir_all(dir)?;
+        let path = dir.join(".iroha_wasm_pack-lock");
+        let file = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(&path)?;
+        if file.try_lock_exclusive().is_err() {
+            if no_wait {
+                return Err(Error::other(format!(
+                    "{} is locked by another iroha_wasm_pack process",
+                    dir.display()
+                ))
+                .with_code("E0106"));
+            }
+            // Shown without `RUST_LOG`, the build would seem to hang otherwise.
+            tracing::warn!(
+                "Blocking waiting for the iroha_wasm_pack process building to {} to finish",
+                dir.display()
+            );
+            file.lock_exclusive()?;
+        }
+        Ok(BuildLock { _file: file })
+    }
+}
+
+/// Find the project root directory.
+///
+/// The start directory is canonicalized so the computed `target/` path matches the one
+/// cargo writes to, even when the project is reached through a symlink.
+pub fn root(start: PathBuf) -> Result<PathBuf, Error> {
+    let mut cur = match fs::canonicalize(&start) {
+        Ok(path) => path,
+        Err(err) => {
+            return Err(
+                Error::other(format!("resolve directory {} failed", start.display()))
+                    .caused_by(err),
+            )
+        }
+    };
+    while !cur.join("Cargo.toml").exists() {
+        if !cur.pop() {
+            return Err(Error::manifest(format!("No Cargo.toml found from {} or parent, you should init a project by `iroha_wasm_pack new` first", start.display())).with_code("E0101"));
+        }
+    }
+    Ok(cur)
+}
+
+/// Current working directory, with a readable error if it no longer exists.
+pub fn working_dir() -> Result<PathBuf, Error> {
+    match current_dir() {
+        Ok(dir) => Ok(dir),
+        Err(err) => Err(Error::other(
+            "current directory is not accessible, it may have been deleted",
+        )
+        .caused_by(err)),
+    }
+}
+
+/// Fetch rustc version by command
+pub fn rustc_minor_version(runner: &dyn CommandRunner) -> Result<u32, Error> {
+    let stdout = runner.read(&Invocation::new("rustc", ["--version"]))?;
+    info!("Checked rustc version {}", stdout);
+    let mut pieces = stdout.split('.');
+    if pieces.next() == Some("rustc 1") {
+        if let Some(version) = pieces.next() {
+            return Ok(version.parse()?);
+        }
+    }
+    Err(Error::toolchain("We can't figure out what your Rust version is- which means you might not have Rust installed. Please install Rust version 1.30.0 or higher.").with_code("E0201"))
+}
+
+pub fn step_check_rustc_version(_: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    // Ensure that `rustc` is present and that it is >= 1.30.0
+    let local_minor_version = rustc_minor_version(ctx.runner())?;
+    if local_minor_version < 30 {
+        return Err(Error::toolchain(format!("Your version of Rust, '1.{}', is not supported. Please install Rust version 1.30.0 or higher.", local_minor_version.to_string())).with_code("E0201"));
+    }
+    Ok(())
+}
+
+/// Cargo.toml Deserialize
+struct Package {
+    name: String,
+    metadata: PackMetadata,
+}
+
+/// `[package.metadata.iroha_wasm_pack]`
+#[derive(Deserialize, Default)]
+struct PackMetadata {
+    #[serde(default, rename = "fail-on-warnings")]
+    fail_on_warnings: bool,
+    /// Not a contract, like a library shared by the members of a workspace
+    #[serde(default)]
+    skip: bool,
+    #[serde(rename = "max-size")]
+    max_size: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+struct Metadata {
+    #[serde(default)]
+    iroha_wasm_pack: PackMetadata,
+}
+
+/// A manifest value that may be inherited with `{ workspace = true }`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MaybeInherited<T> {
+    Local(T),
+    Inherited { workspace: bool },
+}
+
+#[derive(Deserialize)]
+struct RawPackage {
+    name: MaybeInherited<String>,
+    #[serde(default)]
+    metadata: Metadata,
+}
+
+#[derive(Deserialize, Default)]
+struct Lib {
+    name: Option<String>,
+    #[serde(default, alias = "crate-type")]
+    crate_type: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Example {
+    name: String,
+    #[serde(default, alias = "crate-type")]
+    crate_type: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Workspace {
+    #[serde(default)]
+    members: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    package: toml::value::Table,
+}
+
+#[derive(Deserialize)]
+struct RawCargoConfig {
+    package: Option<RawPackage>,
+    #[serde(default)]
+    lib: Lib,
+    #[serde(default)]
+    example: Vec<Example>,
+    workspace: Option<Workspace>,
+}
+
+struct CargoConfig {
+    root: PathBuf,
+    package: Option<Package>,
+    lib: Lib,
+    examples: Vec<Example>,
+    workspace: Option<Workspace>,
+}
+
+/// Parse the cargo toml without resolving inherited fields
+fn read_cargo_config(root: &Path) -> Result<RawCargoConfig, Error> {
+    let path = root.join("Cargo.toml");
+    let cargo_xml = match fs::read_to_string(&path) {
+        Ok(cargo_xml) => cargo_xml,
+        Err(err) => {
+            return Err(Error::manifest(format!("read {} failed", path.display())).caused_by(err))
+        }
+    };
+    match toml::from_str(&cargo_xml) {
+        Ok(config) => Ok(config),
+        Err(err) => Err(Error::manifest("parse cargo toml failed").caused_by(err)),
+    }
+}
+
+/// Parse the cargo toml
+fn pasre_cargo_config(root: &PathBuf) -> Result<CargoConfig, Error> {
+    let raw = read_cargo_config(root)?;
+    let package = match raw.package {
+        Some(package) => Some(Package {
+            name: resolve_inherited(root, "name", package.name)?,
+            metadata: package.metadata.iroha_wasm_pack,
+        }),
+        None => None,
+    };
+    Ok(CargoConfig {
+        root: root.clone(),
+        package: package,
+        lib: raw.lib,
+        examples: raw.example,
+        workspace: raw.workspace,
+    })
+}
+
+/// Names of the `[[example]]` targets and the ones auto-discovered in `examples/`
+fn example_names(config: &CargoConfig) -> Result<Vec<String>, Error> {
+    let mut names: Vec<String> = config.examples.iter().map(|x| x.name.clone()).collect();
+    let dir = config.root.join("examples");
+    if dir.is_dir() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            let is_example =
+                path.extension().map_or(false, |ext| ext == "rs") || path.join("main.rs").exists();
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned());
+            match name {
+                Some(name) if is_example && !names.contains(&name) => names.push(name),
+                _ => {}
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Find the root of the workspace `root` belongs to, which may be `root` itself
+pub fn workspace_root(root: &Path) -> Result<Option<PathBuf>, Error> {
+    for dir in root.ancestors() {
+        if dir.join("Cargo.toml").exists() && read_cargo_config(dir)?.workspace.is_some() {
+            return Ok(Some(dir.to_path_buf()));
+        }
+    }
+    Ok(None)
+}
+
+/// Target directory of the workspace at `target_root`, picked like cargo does:
+/// `--target-dir`, `CARGO_TARGET_DIR`, `build.target-dir` of a cargo config, `target/`
+pub fn target_dir(explicit: Option<&Path>, target_root: &Path) -> PathBuf {
+    if let Some(dir) = explicit {
+        return dir.to_path_buf();
+    }
+    if let Some(dir) = std::env::var_os("CARGO_TARGET_DIR").filter(|dir| !dir.is_empty()) {
+        return PathBuf::from(dir);
+    }
+    configured_target_dir(target_root).unwrap_or_else(|| target_root.join("target"))
+}
+
+/// `build.target-dir` of the nearest cargo config above `start` or in `CARGO_HOME`,
+/// relative paths are taken from the directory holding `.cargo/`
+fn configured_target_dir(start: &Path) -> Option<PathBuf> {
+    let mut configs: Vec<PathBuf> = start.ancestors().map(|dir| dir.join(".cargo")).collect();
+    match std::env::var_os("CARGO_HOME") {
+        Some(home) => configs.push(PathBuf::from(home)),
+        None => {
+            if let Some(home) = std::env::var_os("HOME") {
+                configs.push(Path::new(&home).join(".cargo"));
+            }
+        }
+    }
+    configs.iter().find_map(|dir| {
+        let config = [dir.join("config.toml"), dir.join("config")]
+            .iter()
+            .find_map(|path| fs::read_to_string(path).ok())?
+            .parse::<toml::Value>()
+            .ok()?;
+        let target_dir = config.get("build")?.get("target-dir")?.as_str()?;
+        Some(dir.parent().unwrap_or(dir).join(target_dir))
+    })
+}
+
+/// Take a manifest value from `[workspace.package]` if it is inherited
+fn resolve_inherited<T: serde::de::DeserializeOwned>(
+    root: &Path,
+    key: &str,
+    value: MaybeInherited<T>,
+) -> Result<T, Error> {
+    match value {
+        MaybeInherited::Local(value) => Ok(value),
+        MaybeInherited::Inherited { workspace: false } => Err(Error::manifest(format!(
+            "`package.{}` of {} must be a value or `{{ workspace = true }}`",
+            key,
+            root.join("Cargo.toml").display()
+        ))),
+        MaybeInherited::Inherited { workspace: true } => {
+            let workspace_root = match workspace_root(root)? {
+                Some(workspace_root) => workspace_root,
+                None => {
+                    return Err(Error::manifest(format!(
+                        "`package.{}` of {} is inherited, but no workspace root was found",
+                        key,
+                        root.join("Cargo.toml").display()
+                    )))
+                }
+            };
+            let inherited = read_cargo_config(&workspace_root)?
+                .workspace
+                .and_then(|workspace| workspace.package.get(key).cloned());
+            match inherited {
+                Some(value) => Ok(value.try_into()?),
+                None => Err(Error::manifest(format!(
+                    "`package.{}` is inherited, but `workspace.package.{}` is missing in {}",
+                    key,
+                    key,
+                    workspace_root.join("Cargo.toml").display()
+                ))),
+            }
+        }
+    }
+}
+
+/// Parse the manifests of all workspace members.
+///
+/// Members are globs like cargo takes them, a directory matched without a Cargo.toml is
+/// left out and so is everything below an `exclude` path.
+fn workspace_members(root: &Path, workspace: &Workspace) -> Result<Vec<CargoConfig>, Error> {
+    let prefix = glob::Pattern::escape(&root.display().to_string());
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for member in &workspace.members {
+        let member = member.trim_start_matches("./");
+        if !member.contains(['*', '?', '[']) {
+            dirs.push(root.join(member));
+            continue;
+        }
+        let mut found = Vec::new();
+        for dir in glob::glob(&format!("{}/{}", prefix, member))? {
+            let dir = dir?;
+            if dir.join("Cargo.toml").is_file() {
+                found.push(dir);
+            }
+        }
+        found.sort();
+        dirs.extend(found);
+    }
+    let excluded: Vec<PathBuf> = workspace
+        .exclude
+        .iter()
+        .map(|path| root.join(path.trim_start_matches("./")))
+        .collect();
+    dirs.retain(|dir| !excluded.iter().any(|excluded| dir.starts_with(excluded)));
+    let mut unique = Vec::new();
+    for dir in dirs {
+        if !unique.contains(&dir) {
+            unique.push(dir);
+        }
+    }
+    unique.iter().map(pasre_cargo_config).collect()
+}
+
+/// Check crate-type
+pub fn step_check_crate_config(_: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    if ctx.crate_type == "cdylib" {
+        Ok(())
+    } else if let Some(example) = &ctx.example {
+        Err(Error::manifest(format!(
+            "example `{}` must be a cdylib to compile to a wasm entrypoint. Add the following to your \
+            Cargo.toml file:\n\n\
+            [[example]]\n\
+            name = \"{}\"\n\
+            crate-type = [\"cdylib\"]",
+            example, example
+        )).with_code("E0104"))
+    } else {
+        let msg = format!("crate-type must be cdylib to compile to wasm32-unknown-unknown. Add the following to your \
+            Cargo.toml file:\n\n\
+            [lib]\n\
+            crate-type = [\"cdylib\"]");
+        Err(Error::manifest(msg).with_code("E0104"))
+    }
+}
+
+/// Get rustc's sysroot as a PathBuf
+pub fn get_rustc_sysroot(
+    runner: &dyn CommandRunner,
+    toolchain: Option<&str>,
+) -> Result<PathBuf, Error> {
+    let mut args = vec!["--print".to_owned(), "sysroot".to_owned()];
+    if let Some(toolchain) = toolchain {
+        args.insert(0, format!("+{}", toolchain));
+    }
+    let invocation = Invocation::new("rustc", &args).capture_stderr();
+    let stdout = runner.read(&invocation).map_err(|err| {
+        Error::toolchain("Getting rustc's sysroot wasn't successful").caused_by(err)
+    })?;
+    info!("Rustc sysroot: {}", stdout);
+    Ok(PathBuf::from_str(&stdout).unwrap())
+}
+
+/// Checks if the wasm32-unknown-unknown is present in rustc's sysroot.
+pub fn is_wasm32_target_in_sysroot(sysroot: &Path) -> bool {
+    let wasm32_target = "wasm32-unknown-unknown";
+
+    let rustlib_path = sysroot.join("lib/rustlib");
+
+    info!("Looking for {} in {:?}", wasm32_target, rustlib_path);
+
+    if rustlib_path.join(wasm32_target).exists() {
+        info!("Found {} in {:?}", wasm32_target, rustlib_path);
+        true
+    } else {
+        info!("Failed to find {} in {:?}", wasm32_target, rustlib_path);
+        false
+    }
+}
+
+/// Add wasm32-unknown-unknown using `rustup`.
+pub fn rustup_add_wasm_target(
+    runner: &dyn CommandRunner,
+    policy: InstallPolicy,
+) -> Result<(), Error> {
+    confirm_install(
+        policy,
+        "The wasm32-unknown-unknown target",
+        "rustup target add wasm32-unknown-unknown",
+    )?;
+    let result = runner.run(&Invocation::new(
+        "rustup",
+        ["target", "add", "wasm32-unknown-unknown"],
+    ));
+    if let Err(err) = result {
+        return Err(Error::toolchain(
+            "Adding the wasm32-unknown-unknown target with rustup failed",
+        )
+        .caused_by(err));
+    }
+    Ok(())
+}
+
+/// Checks if the rust-src component is present in rustc's sysroot.
+pub fn is_rust_src_in_sysroot(sysroot: &Path) -> bool {
+    let src_path = sysroot.join("lib/rustlib/src/rust/library");
+    info!("Looking for rust-src in {:?}", src_path);
+    src_path.exists()
+}
+
+/// Add rust-src to the toolchain using `rustup`, the active one if `toolchain` is `None`.
+pub fn rustup_add_rust_src(
+    runner: &dyn CommandRunner,
+    policy: InstallPolicy,
+    toolchain: Option<&str>,
+) -> Result<(), Error> {
+    let mut rustup_args = vec!["component", "add", "rust-src"];
+    if let Some(toolchain) = toolchain {
+        rustup_args.push("--toolchain");
+        rustup_args.push(toolchain);
+    }
+    confirm_install(
+        policy,
+        "The rust-src component of the nightly toolchain",
+        &format!("rustup {}", rustup_args.join(" ")),
+    )?;
+    let result = runner.run(&Invocation::new("rustup", rustup_args));
+    if let Err(err) = result {
+        return Err(
+            Error::toolchain("Adding the rust-src component with rustup failed").caused_by(err),
+        );
+    }
+    Ok(())
+}
+
+pub fn step_check_for_wasm_target(args: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    // `-Z build-std` compiles std from source, the precompiled target isn't needed.
+    if !args.no_build_std {
+        info!("Building std from source, checking for rust-src instead of the precompiled wasm32 target");
+        let toolchain = ctx.toolchain.as_deref();
+        let sysroot = get_rustc_sysroot(ctx.runner(), toolchain)?;
+        if is_rust_src_in_sysroot(&sysroot) {
+            return Ok(());
+        }
+        return rustup_add_rust_src(ctx.runner(), args.install_policy(), toolchain);
+    }
+    info!("Building with `--no-build-std`, the precompiled wasm32 target is required");
+    let sysroot = get_rustc_sysroot(ctx.runner(), None)?;
+
+    // If wasm32-unknown-unknown already exists we're ok.
+    if is_wasm32_target_in_sysroot(&sysroot) {
+        Ok(())
+    // If it doesn't exist, then we need to check if we're using rustup.
+    } else {
+        // If sysroot contains "rustup", then we can assume we're using rustup
+        // and use rustup to add the wasm32-unknown-unknown target.
+        if sysroot.to_string_lossy().contains("rustup") {
+            rustup_add_wasm_target(ctx.runner(), args.install_policy())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// `cargo build` for the wasm target with `extra` arguments before the forwarded ones
+fn cargo_build_command(args: &BuildArgs, ctx: &BuildContext, extra: &[&str]) -> Invocation {
+    cargo_command("build", args, ctx, extra)
+}
+
+/// Cargo `subcommand` for the wasm target, with the flags of the real build
+fn cargo_command(
+    subcommand: &str,
+    args: &BuildArgs,
+    ctx: &BuildContext,
+    extra: &[&str],
+) -> Invocation {
+    cargo_rustc_command(subcommand, args, ctx, extra, &[])
+}
+
+/// `cargo_command` passing `rustc_args` after `--` to the compiler of the crate itself
+fn cargo_rustc_command(
+    subcommand: &str,
+    args: &BuildArgs,
+    ctx: &BuildContext,
+    extra: &[&str],
+    rustc_args: &[&str],
+) -> Invocation {
+    let toolchain = ctx
+        .toolchain
+        .as_ref()
+        .map(|toolchain| format!("+{}", toolchain));
+    let mut cargo_args = Vec::new();
+    if let (false, Some(toolchain)) = (args.no_build_std, &toolchain) {
+        cargo_args.push(toolchain.as_str());
+    }
+    cargo_args.extend(if args.no_build_std {
+        vec![subcommand, "--target", "wasm32-unknown-unknown"]
+    } else {
+        vec![
+            subcommand,
+            "-Z",
+            "build-std",
+            "-Z",
+            "build-std-features=panic_immediate_abort",
+            "--target",
+            "wasm32-unknown-unknown",
+        ]
+    });
+    cargo_args.extend_from_slice(extra);
+    let manifest = ctx.crate_root.join("Cargo.toml").display().to_string();
+    if args.manifest_path.is_some() {
+        cargo_args.push("--manifest-path");
+        cargo_args.push(&manifest);
+    }
+    let target_dir = ctx.target_dir.display().to_string();
+    if args.target_dir.is_some() {
+        cargo_args.push("--target-dir");
+        cargo_args.push(&target_dir);
+    }
+    if let Some(package) = &ctx.cargo_package {
+        cargo_args.push("-p");
+        cargo_args.push(package);
+    }
+    if let Some(example) = &ctx.example {
+        cargo_args.push("--example");
+        cargo_args.push(example);
+    }
+    let overrides = args.profile_overrides();
+    for value in &overrides {
+        info!("Overriding cargo profile with --config {}", value);
+        cargo_args.push("--config");
+        cargo_args.push(value);
+    }
+    if args.release {
+        cargo_args.push("--release");
+    }
+    args.cargo_args.iter().for_each(|x| cargo_args.push(x));
+    args.extra_options.iter().for_each(|x| cargo_args.push(x));
+    if !rustc_args.is_empty() {
+        cargo_args.push("--");
+        cargo_args.extend_from_slice(rustc_args);
+    }
+    let mut rustflags = Vec::new();
+    if let Some(features) = &args.wasm_opt.target_features {
+        info!("Building with {}", features.to_rustc_flag());
+        rustflags.push(features.to_rustc_flag());
+    }
+    let mut invocation = Invocation::new("cargo", cargo_args);
+    if !rustflags.is_empty() {
+        invocation = invocation.env("CARGO_ENCODED_RUSTFLAGS", encoded_rustflags(&rustflags));
+    }
+    invocation
+}
+
+/// Run a cargo command emitting JSON messages, printing and recording its diagnostics.
+///
+/// Returns the remaining non-diagnostic messages.
+fn run_cargo_json(
+    subcommand: &str,
+    invocation: &Invocation,
+    ctx: &BuildContext,
+) -> Result<Vec<serde_json::Value>, Error> {
+    let output = ctx.runner().output(invocation)?;
+    let mut messages = Vec::new();
+    let mut errors = Vec::new();
+    for line in output.stdout.lines() {
+        let message: serde_json::Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        if message["reason"] != "compiler-message" {
+            messages.push(message);
+            continue;
+        }
+        let diagnostic = &message["message"];
+        if progress::is_json() {
+            progress::emit_compiler_message(&message);
+        } else if let Some(rendered) = diagnostic["rendered"].as_str() {
+            eprint!("{}", rendered);
+        }
+        let text = diagnostic["message"].as_str().unwrap_or_default();
+        let target = message["target"]["name"].as_str().unwrap_or_default();
+        if diagnostic["level"] == "warning" {
+            ctx.warnings
+                .borrow_mut()
+                .push(format!("{}: {}", target, text));
+        } else if diagnostic["level"] == "error" {
+            errors.push(format!("error: {}", text));
+        }
+    }
+    if !output.is_success() {
+        // The rendered diagnostics were printed already, the summary is enough here.
+        return Err(Error::command(
+            "cargo",
+            &[subcommand],
+            output.status,
+            &errors.join("\n"),
+        ));
+    }
+    Ok(messages)
+}
+
+pub fn step_build_wasm(args: &BuildArgs, ctx: &mut BuildContext) -> Result<(), Error> {
+    let invocation = cargo_build_command(
+        args,
+        ctx,
+        &["--message-format=json-diagnostic-rendered-ansi"],
+    );
+    let messages = run_cargo_json("build", &invocation, ctx)?;
+    // Cargo knows where the wasm really went, e.g. with `build.target-dir` in its config.
+    let file_name = ctx.wasm_in.file_name().map(ToOwned::to_owned);
+    let produced = messages
+        .iter()
+        .filter(|message| message["reason"] == "compiler-artifact")
+        .flat_map(|message| message["filenames"].as_array().cloned().unwrap_or_default())
+        .filter_map(|filename| filename.as_str().map(PathBuf::from))
+        .find(|path| path.file_name() == file_name.as_deref());
+    if let Some(path) = produced {
+        ctx.wasm_in = path;
+    }
+    Ok(())
+}
+
+/// Builder image of `--docker`, tagged with the version of the crate
+pub const DOCKER_IMAGE: &str = "ghcr.io/suiwenfeng/iroha_wasm_pack-builder";
+
+/// Named volume keeping cargo's registry between `--docker` builds
+pub const CARGO_CACHE_VOLUME: &str = "iroha_wasm_pack-cargo-cache";
+
+/// Where the workspace is mounted in the builder
+const DOCKER_PROJECT: &str = "/project";
+
+/// Steps the builder runs with `--docker`, the host runs the others
+const DOCKER_STEPS: &[&str] = &[
+    "check-rustc-version",
+    "check-wasm-target",
+    "build-wasm",
+    "wasm-opt",
+];
+
+impl BuildContext {
+    /// Root of the workspace, where Cargo.lock is
+    fn workspace_root(&self) -> &Path {
+        self.lock_file.parent().unwrap_or(&self.crate_root)
+    }
+
+    /// Target directory the builder writes to, inside the mounted workspace
+    fn docker_target_dir(&self) -> PathBuf {
+        self.workspace_root().join("target").join("docker")
+    }
+}
+
+/// `path` relative to the workspace as seen in the builder
+fn in_builder(relative: &Path) -> String {
+    relative
+        .components()
+        .fold(DOCKER_PROJECT.to_owned(), |path, component| {
+            format!("{}/{}", path, component.as_os_str().to_string_lossy())
+        })
+}
+
+/// `docker run` compiling and optimizing `ctx` in the builder, as `user` (uid, gid) if given
+pub fn docker_run_invocation(
+    args: &BuildArgs,
+    ctx: &BuildContext,
+    user: Option<(u32, u32)>,
+) -> Result<Invocation, Error> {
+    let root = ctx.workspace_root();
+    let workdir = ctx.crate_root.strip_prefix(root).map_err(|_| {
+        Error::other(format!(
+            "{} is outside of the workspace at {}",
+            ctx.crate_root.display(),
+            root.display()
+        ))
+    })?;
+    let image = match &args.docker_image {
+        Some(image) => image.clone(),
+        None => format!("{}:{}", DOCKER_IMAGE, env!("CARGO_PKG_VERSION")),
+    };
+    let mut docker_args = vec![
+        "run".to_owned(),
+        "--rm".to_owned(),
+        "--volume".to_owned(),
+        format!("{}:{}", root.display(), DOCKER_PROJECT),
+        "--volume".to_owned(),
+        format!("{}:/usr/local/cargo/registry", CARGO_CACHE_VOLUME),
+        "--workdir".to_owned(),
+        in_builder(workdir),
+    ];
+    if let Some((uid, gid)) = user {
+        // Otherwise the files written to the mount belong to root.
+        docker_args.extend([
+            "--user".to_owned(),
+            format!("{}:{}", uid, gid),
+            "--env".to_owned(),
+            "HOME=/tmp".to_owned(),
+        ]);
+    }
+    docker_args.push(image);
+    docker_args.extend(
+        [
+            "iroha_wasm_pack",
+            "build",
+            "--ci",
+            "--no-hooks",
+            "--target-dir",
+        ]
+        .map(str::to_owned),
+    );
+    docker_args.push(in_builder(Path::new("target/docker")));
+    for step in STEPS
+        .iter()
+        .filter(|step| !DOCKER_STEPS.contains(&step.name))
+    {
+        docker_args.extend(["--skip-step".to_owned(), step.name.to_owned()]);
+    }
+    for step in args
+        .skip_steps
+        .iter()
+        .filter(|step| DOCKER_STEPS.contains(&step.as_str()))
+    {
+        docker_args.extend(["--skip-step".to_owned(), step.clone()]);
+    }
+    if let Some(package) = &ctx.cargo_package {
+        docker_args.extend(["--package".to_owned(), package.clone()]);
+    }
+    if let Some(example) = &ctx.example {
+        docker_args.extend(["--example".to_owned(), example.clone()]);
+    }
+    if let Some(toolchain) = &args.toolchain {
+        docker_args.extend(["--toolchain".to_owned(), toolchain.clone()]);
+    }
+    // A `--release` after `--` reaches the builder with the other extra options.
+    for (set, flag) in [
+        (args.release, "--release"),
+        (args.no_build_std, "--no-build-std"),
+        (args.skip_opt, "--skip-opt"),
+        (args.wasm_opt.keep_names, "--keep-names"),
+        (args.split_debug, "--split-debug"),
+    ] {
+        if set {
+            docker_args.push(flag.to_owned());
+        }
+    }
+    docker_args.extend(["--opt-level".to_owned(), args.wasm_opt.opt_level.clone()]);
+    if let Some(timeout) = args.wasm_opt.wasm_opt_timeout {
+        docker_args.extend(["--wasm-opt-timeout".to_owned(), timeout.to_string()]);
+    }
+    if let Some(features) = &args.wasm_opt.target_features {
+        let flag = features.to_rustc_flag();
+        docker_args.extend([
+            "--target-features".to_owned(),
+            flag.trim_start_matches("-Ctarget-feature=").to_owned(),
+        ]);
+    }
+    // The builder adds the overrides of `--split-debug` itself.
+    let split_debug = args.split_debug_overrides();
+    for value in args
+        .profile_overrides()
+        .into_iter()
+        .filter(|value| !split_debug.contains(value))
+    {
+        docker_args.extend([
+            "--cargo-arg=--config".to_owned(),
+            format!("--cargo-arg={}", value),
+        ]);
+    }
+    for arg in &args.cargo_args {
+        docker_args.push(format!("--cargo-arg={}", arg));
+    }
+    if !args.extra_options.is_empty() {
+        docker_args.push("--".to_owned());
+        docker_args.extend(args.extra_options.iter().cloned());
+    }
+    Ok(Invocation::new("docker", docker_args))
+}
+
+/// Copy the wasm the builder wrote for `ctx` to where the steps on the host expect it
+pub fn copy_back_from_docker(args: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    let docker_target = ctx.docker_target_dir();
+    let companion = debug_companion(ctx);
+    for (path, required) in [
+        (&ctx.wasm_out, true),
+        (&ctx.wasm_in, false),
+        (&companion, args.split_debug),
+    ] {
+        let relative = path.strip_prefix(&ctx.target_dir).map_err(|_| {
+            Error::other(format!(
+                "{} is outside of the target directory",
+                path.display()
+            ))
+        })?;
+        let built = docker_target.join(relative);
+        if !built.is_file() {
+            if required {
+                return Err(Error::other(format!(
+                    "the builder didn't write {}",
+                    built.display()
+                )));
+            }
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&built, path)?;
+    }
+    Ok(())
+}
+
+/// Owner of the project, the builder writes as them
+#[cfg(unix)]
+fn docker_user(root: &Path) -> Option<(u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(root)
+        .ok()
+        .map(|metadata| (metadata.uid(), metadata.gid()))
+}
+
+/// Docker Desktop maps the owner of mounted files itself
+#[cfg(not(unix))]
+fn docker_user(_root: &Path) -> Option<(u32, u32)> {
+    None
+}
+
+/// Compile and optimize in the builder, then copy the wasm out
+pub fn step_docker_build(args: &BuildArgs, ctx: &mut BuildContext) -> Result<(), Error> {
+    let info =
+        Invocation::new("docker", ["info", "--format", "{{.ServerVersion}}"]).capture_stderr();
+    let reason = match ctx.runner().output(&info) {
+        Ok(output) if output.is_success() => None,
+        Ok(output) => Some(output.stderr.trim().to_owned()),
+        Err(err) => Some(err.to_string()),
+    };
+    if let Some(reason) = reason {
+        return Err(Error::toolchain(format!(
+            "`--docker` needs a running docker daemon, `docker info` failed: {}",
+            reason
+        )));
+    }
+    let invocation = docker_run_invocation(args, ctx, docker_user(ctx.workspace_root()))?;
+    info!("Building {} in the builder image", ctx.package_name);
+    ctx.runner().run(&invocation)?;
+    copy_back_from_docker(args, ctx)
+}
+
+/// Build the test harnesses and copy them to `<profile>/tests/` without running them
+pub fn step_build_tests(args: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    for path in build_test_harnesses(args, ctx)? {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// Build the test harnesses of every selected package, returning their paths
+pub fn build_tests(args: &BuildArgs) -> Result<Vec<PathBuf>, Error> {
+    let mut harnesses = Vec::new();
+    let pipeline = Pipeline::from_names(&["check-rustc-version", "check-wasm-target"])?;
+    for mut ctx in BuildContext::resolve(args)? {
+        let _lock = BuildLock::acquire(&ctx.wasm_folder, args.no_wait)?;
+        pipeline.run(args, &mut ctx)?;
+        harnesses.extend(build_test_harnesses(args, &ctx)?);
+    }
+    Ok(harnesses)
+}
+
+fn build_test_harnesses(args: &BuildArgs, ctx: &BuildContext) -> Result<Vec<PathBuf>, Error> {
+    let invocation = cargo_build_command(
+        args,
+        ctx,
+        &["--tests", "--message-format=json-diagnostic-rendered-ansi"],
+    );
+    let messages = run_cargo_json("build", &invocation, ctx)?;
+    let tests_folder = ctx.wasm_folder.join("tests");
+    fs::create_dir_all(&tests_folder)?;
+    let mut produced = Vec::new();
+    for message in messages {
+        if message["reason"] != "compiler-artifact" || message["profile"]["test"] != true {
+            continue;
+        }
+        let filenames = message["filenames"].as_array().cloned().unwrap_or_default();
+        for filename in filenames.iter().filter_map(|x| x.as_str()) {
+            let path = Path::new(filename);
+            if path.extension().map_or(true, |ext| ext != "wasm") {
+                continue;
+            }
+            let target = tests_folder.join(path.file_name().unwrap());
+            fs::copy(path, &target)?;
+            produced.push(target);
+        }
+    }
+    if produced.is_empty() {
+        return Err(Error::other("cargo didn't produce any wasm test harness"));
+    }
+    Ok(produced)
+}
+
+/// Optimize the wasm keeping its custom sections, the strip step removes them.
+///
+/// With `--split-debug` the companion comes from a second pass keeping the debug info,
+/// binaryen optimizes less with it, so the deployed module is the one of a normal build.
+pub fn step_wasm_opt(args: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    if args.skip_opt {
+        if args.split_debug {
+            fs::copy(&ctx.wasm_in, debug_companion(ctx))?;
+        }
+        fs::copy(&ctx.wasm_in, &ctx.wasm_out)?;
+        return Ok(());
+    }
+    if args.split_debug {
+        optimize(
+            ctx.runner(),
+            &args.wasm_opt,
+            true,
+            &ctx.wasm_in,
+            &debug_companion(ctx),
+        )?;
+    }
+    optimize(
+        ctx.runner(),
+        &args.wasm_opt,
+        args.wasm_opt.keep_names,
+        &ctx.wasm_in,
+        &ctx.wasm_out,
+    )
+}
+
+/// Strip the custom sections Iroha has no use for from the optimized wasm.
+///
+/// Without wasm-opt the module is left alone, unless `--split-debug` compiled the debug info
+/// into it.
+pub fn step_strip(args: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    if args.skip_opt && !args.split_debug {
+        return Ok(());
+    }
+    let module = fs::read(&ctx.wasm_out)?;
+    let stripped = wasm::strip_custom_sections(&module, &kept_sections(&args.wasm_opt))?;
+    fs::write(&ctx.wasm_out, stripped)?;
+    Ok(())
+}
+
+/// `<name>.debug.wasm` of `--split-debug`
+fn debug_companion(ctx: &BuildContext) -> PathBuf {
+    ctx.wasm_in.with_extension("debug.wasm")
+}
+
+/// Custom section with the Iroha dependency versions the module is built against
+pub const IROHA_VERSION_SECTION: &str = "iroha-version";
+
+/// Custom sections left in an optimized module
+fn kept_sections(opt: &WasmOptArgs) -> Vec<&'static str> {
+    let mut keep = vec![IROHA_VERSION_SECTION];
+    if opt.keep_names {
+        keep.push("name");
+    }
+    keep
+}
+
+/// Optimize `input` into `output`, then strip the custom sections Iroha has no use for
+pub fn run_wasm_opt(
+    runner: &dyn CommandRunner,
+    opt: &WasmOptArgs,
+    input: &Path,
+    output: &Path,
+) -> Result<(), Error> {
+    optimize(runner, opt, opt.keep_names, input, output)?;
+    let module = fs::read(output)?;
+    fs::write(
+        output,
+        wasm::strip_custom_sections(&module, &kept_sections(opt))?,
+    )?;
+    Ok(())
+}
+
+/// First argument of the binary started as the process optimizing one module
+pub const WASM_OPT_WORKER: &str = "__wasm-opt-worker";
+
+static WASM_OPT_WORKER_EXE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Run the bundled wasm-opt in child processes of `exe`, which has to hand the arguments
+/// after `WASM_OPT_WORKER` to `wasm_opt_worker`.
+///
+/// Without it the bundled wasm-opt runs in-process, where a timeout can't stop it.
+pub fn use_wasm_opt_worker(exe: PathBuf) {
+    let _ = WASM_OPT_WORKER_EXE.set(exe);
+}
+
+/// Optimize one module as the child process started by `optimize`, `args` are
+/// `<input> <output> <opt-level> <debug-info> [<target-features>]`
+pub fn wasm_opt_worker(args: &[String]) -> Result<(), Error> {
+    let (input, output, opt_level, debug_info, features) = match args {
+        [input, output, opt_level, debug_info, features @ ..] if features.len() <= 1 => {
+            (input, output, opt_level, debug_info, features.first())
+        }
+        _ => {
+            return Err(Error::usage(format!(
+                "expected `{} <input> <output> <opt-level> <debug-info> [<target-features>]`",
+                WASM_OPT_WORKER
+            )))
+        }
+    };
+    let features = match features {
+        Some(features) => Some(features.parse::<TargetFeatures>().map_err(Error::usage)?),
+        None => None,
+    };
+    optimization_options(opt_level, debug_info == "true", features.as_ref())
+        .run(input, output)
+        .map_err(|err| {
+            Error::other("wasm-opt failed")
+                .caused_by(err.to_string())
+                .with_code("E0401")
+        })
+}
+
+/// Options of the bundled wasm-opt
+fn optimization_options(
+    opt_level: &str,
+    debug_info: bool,
+    features: Option<&TargetFeatures>,
+) -> wasm_opt::OptimizationOptions {
+    use wasm_opt::OptimizationOptions;
+    let mut options = match opt_level {
+        "0" => OptimizationOptions::new_opt_level_0(),
+        "1" => OptimizationOptions::new_opt_level_1(),
+        "2" => OptimizationOptions::new_opt_level_2(),
+        "3" => OptimizationOptions::new_opt_level_3(),
+        "4" => OptimizationOptions::new_opt_level_4(),
+        "z" => OptimizationOptions::new_optimize_for_size_aggressively(),
+        _ => OptimizationOptions::new_optimize_for_size(),
+    };
+    options.debug_info(debug_info);
+    if let Some(features) = features {
+        features.apply_to(&mut options);
+    }
+    options
+}
+
+/// Optimize `input` into `output` keeping every custom section, with the debug info if
+/// `debug_info` is set.
+///
+/// wasm-opt writes to a temporary file renamed on success, a failed or stopped run leaves
+/// nothing at `output`.
+fn optimize(
+    runner: &dyn CommandRunner,
+    opt: &WasmOptArgs,
+    debug_info: bool,
+    input: &Path,
+    output: &Path,
+) -> Result<(), Error> {
+    let partial = output.with_extension("wasm-opt.partial");
+    let result = optimize_into(runner, opt, debug_info, input, &partial)
+        .and_then(|()| Ok(fs::rename(&partial, output)?));
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+fn optimize_into(
+    runner: &dyn CommandRunner,
+    opt: &WasmOptArgs,
+    debug_info: bool,
+    input: &Path,
+    output: &Path,
+) -> Result<(), Error> {
+    use std::{thread, time::Duration, time::Instant};
+    let (input, output) = (input.display().to_string(), output.display().to_string());
+    let invocation = match (&opt.wasm_opt_path, WASM_OPT_WORKER_EXE.get()) {
+        (Some(wasm_opt), _) => {
+            let mut flags = vec![
+                input,
+                "-o".to_owned(),
+                output,
+                format!("-O{}", opt.opt_level),
+            ];
+            if debug_info {
+                flags.push("--debuginfo".to_owned());
+            }
+            if let Some(features) = &opt.target_features {
+                flags.extend(features.to_wasm_opt_flags());
+            }
+            Invocation::new(&wasm_opt.display().to_string(), flags)
+        }
+        (None, Some(exe)) => {
+            let mut args = vec![
+                WASM_OPT_WORKER.to_owned(),
+                input,
+                output,
+                opt.opt_level.clone(),
+                debug_info.to_string(),
+            ];
+            if let Some(features) = &opt.target_features {
+                args.push(features.to_string());
+            }
+            Invocation::new(&exe.display().to_string(), args)
+        }
+        (None, None) if opt.wasm_opt_timeout.is_some() => {
+            return Err(Error::usage(
+                "`--wasm-opt-timeout` needs `--wasm-opt-path` here, the bundled wasm-opt runs \
+                in this process and can't be stopped",
+            ))
+        }
+        (None, None) => {
+            let options =
+                optimization_options(&opt.opt_level, debug_info, opt.target_features.as_ref());
+            return options.run(&input, &output).map_err(|err| {
+                Error::other("wasm-opt failed")
+                    .caused_by(err.to_string())
+                    .with_code("E0401")
+            });
+        }
+    };
+    let invocation = invocation.capture_stderr();
+    let mut child = runner.spawn(&invocation)?;
+    let started = Instant::now();
+    let deadline = opt.wasm_opt_timeout.map(Duration::from_secs);
+    let progress = Duration::from_secs(30);
+    let mut next_progress = progress;
+    loop {
+        if let Some(result) = child.try_wait()? {
+            if result.is_success() {
+                return Ok(());
+            }
+            return Err(Error::other("wasm-opt failed")
+                .caused_by(invocation.failed(&result))
+                .with_code("E0401"));
+        }
+        let elapsed = started.elapsed();
+        if deadline.map_or(false, |deadline| elapsed >= deadline) {
+            child.kill()?;
+            return Err(Error::other(format!(
+                "wasm-opt didn't finish within {}s. This usually means the module is \
+                huge, e.g. because of heavily monomorphized generics. Try `--skip-opt` \
+                or reduce the code size first",
+                elapsed.as_secs()
+            ))
+            .with_code("E0402"));
+        }
+        if elapsed >= next_progress {
+            eprintln!("wasm-opt still running, {}s elapsed", elapsed.as_secs());
+            next_progress += progress;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack opt` command.
+#[derive(Debug, StructOpt)]
+pub struct OptArgs {
+    /// Wasm file to optimize
+    #[structopt(parse(from_os_str))]
+    pub input: PathBuf,
+
+    /// Output file, defaults to `<stem>_optimized.wasm` next to the input
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    /// Largest accepted module in bytes, 4 MiB by default
+    #[structopt(long = "max-size")]
+    pub max_size: Option<u64>,
+
+    #[structopt(flatten)]
+    pub wasm_opt: WasmOptArgs,
+}
+
+impl RunArgs for OptArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let output = match &self.output {
+            Some(output) => output.clone(),
+            None => {
+                let stem = self
+                    .input
+                    .file_stem()
+                    .ok_or_else(|| Error::other("the input is not a file"))?;
+                self.input
+                    .with_file_name(format!("{}_optimized.wasm", stem.to_string_lossy()))
+            }
+        };
+        // Written next to the output and renamed, so a failed check leaves no partial file.
+        let partial = output.with_extension("wasm.partial");
+        let result =
+            run_wasm_opt(&DuctRunner, &self.wasm_opt, &self.input, &partial).and_then(|()| {
+                let module = fs::read(&partial)?;
+                let check = wasm::check_size(&module, self.max_size.unwrap_or(MAX_WASM_SIZE));
+                match check.outcome {
+                    wasm::Outcome::Pass => {}
+                    wasm::Outcome::Warn => eprintln!("warning: {}", check.details.join(", ")),
+                    wasm::Outcome::Fail => return Err(Error::wasm(check.details.join(", "))),
+                }
+                fs::rename(&partial, &output)?;
+                Ok(module.len())
+            });
+        match result {
+            Ok(len) => Ok(CommandReport {
+                summary: vec![format!("Wrote {} ({} bytes)", output.display(), len)],
+                files: vec![output],
+                ..CommandReport::default()
+            }),
+            Err(err) => {
+                let _ = fs::remove_file(&partial);
+                Err(err)
+            }
+        }
+    }
+}
+
+/// Append a custom section `name` with `payload` to the end of a wasm module
+fn append_custom_section(module: &mut Vec<u8>, name: &str, payload: &[u8]) {
+    fn leb128(mut value: usize, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+    let mut section = Vec::new();
+    leb128(name.len(), &mut section);
+    section.extend_from_slice(name.as_bytes());
+    section.extend_from_slice(payload);
+    module.push(0);
+    leb128(section.len(), module);
+    module.extend_from_slice(&section);
+}
+
+/// Cargo.lock Deserialize
+#[derive(Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CargoLock {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+/// Iroha crates whose version is embedded into the artifact
+const IROHA_CRATES: &[&str] = &["iroha_wasm", "iroha_data_model"];
+
+impl LockedPackage {
+    /// `iroha_wasm 2.0.0 (git+https://...#rev)`, as the section embeds it
+    fn describe(&self) -> String {
+        match &self.source {
+            Some(source) => format!("{} {} ({})", self.name, self.version, source),
+            None => format!("{} {}", self.name, self.version),
+        }
+    }
+
+    /// Value of `key` in the query of a git source, like the tag of `git+https://...?tag=v1#rev`
+    fn git_query(&self, key: &str) -> Option<&str> {
+        let source = self.source.as_deref()?.strip_prefix("git+")?;
+        let (_, query) = source.split('#').next()?.split_once('?')?;
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+    }
+
+    /// Commit a git source is locked to
+    fn git_commit(&self) -> Option<&str> {
+        let source = self.source.as_deref()?.strip_prefix("git+")?;
+        source.split_once('#').map(|(_, commit)| commit)
+    }
+
+    /// Whether the package is the `expected` version, tag, branch or commit.
+    ///
+    /// Only a commit may be abbreviated, to no less than 7 characters, so `rc.1` is not
+    /// `rc.11`.
+    fn is(&self, expected: &str) -> bool {
+        let version = expected.trim_start_matches('v');
+        self.version == version
+            || ["tag", "branch", "rev"]
+                .iter()
+                .filter_map(|key| self.git_query(key))
+                .any(|value| value == expected || value.trim_start_matches('v') == version)
+            || self.git_commit().map_or(false, |commit| {
+                expected.len() >= 7 && commit.starts_with(expected)
+            })
+    }
+}
+
+/// The Iroha dependencies resolved in the lockfile
+fn locked_iroha_packages(lock_file: &Path) -> Result<Vec<LockedPackage>, Error> {
+    let lock = match fs::read_to_string(lock_file) {
+        Ok(lock) => lock,
+        Err(err) => {
+            return Err(
+                Error::manifest(format!("read {} failed", lock_file.display())).caused_by(err),
+            )
+        }
+    };
+    let lock: CargoLock = toml::from_str(&lock)?;
+    Ok(lock
+        .package
+        .into_iter()
+        .filter(|package| IROHA_CRATES.contains(&package.name.as_str()))
+        .collect())
+}
+
+/// Embed the Iroha dependency versions as an `iroha-version` custom section
+pub fn step_iroha_version(args: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    let packages = locked_iroha_packages(&ctx.lock_file)?;
+    if packages.is_empty() {
+        ctx.warn(format!(
+            "no Iroha dependency found in {}, the iroha-version section is not embedded",
+            ctx.lock_file.display()
+        ));
+        return Ok(());
+    }
+    let versions: Vec<String> = packages.iter().map(LockedPackage::describe).collect();
+    let mut module = fs::read(&ctx.wasm_out)?;
+    append_custom_section(
+        &mut module,
+        IROHA_VERSION_SECTION,
+        versions.join("\n").as_bytes(),
+    );
+    fs::write(&ctx.wasm_out, module)?;
+    if let Some(expected) = &args.expect_iroha_version {
+        for package in packages.iter().filter(|package| !package.is(expected)) {
+            ctx.warn(format!(
+                "expected Iroha version `{}`, but built against {}",
+                expected,
+                package.describe()
+            ));
+        }
+    }
+    if let Some(url) = &args.node_url {
+        let running = compat::node_version(url)?;
+        for package in packages.iter().filter(|package| !package.is(&running)) {
+            ctx.warn(format!(
+                "the node at {} runs Iroha {}, but the contract is built against {}",
+                url,
+                running,
+                package.describe()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Link the optimized module and its `<name>.debug.wasm` companion written by the wasm-opt
+/// step by a build id
+pub fn step_split_debug(args: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    use blake2::{digest::consts::U32, Blake2b, Digest};
+    if !args.split_debug {
+        return Ok(());
+    }
+    let debug_path = debug_companion(ctx);
+    let mut optimized = fs::read(&ctx.wasm_out)?;
+    let mut debug = fs::read(&debug_path)?;
+    let build_id = hex::encode(Blake2b::<U32>::digest(&optimized));
+    append_custom_section(&mut optimized, "build_id", build_id.as_bytes());
+    append_custom_section(&mut debug, "build_id", build_id.as_bytes());
+    fs::write(&ctx.wasm_out, optimized)?;
+    fs::write(&debug_path, debug)?;
+    info!(
+        "Wrote debug info of build {} to {}",
+        build_id,
+        debug_path.display()
+    );
+    Ok(())
+}
+
+/// Wrap the optimized module into a `<name>.component.wasm` component of the `--wit` world
+pub fn step_component(args: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    use wit_component::{embed_component_metadata, ComponentEncoder, StringEncoding};
+    use wit_parser::{Resolve, UnresolvedPackage};
+    let wit = match (&args.component, &args.wit) {
+        (true, Some(wit)) => wit,
+        _ => return Ok(()),
+    };
+    let component_err = |err: anyhow::Error| {
+        Error::wasm(format!(
+            "package {} as a component failed",
+            ctx.wasm_out.display()
+        ))
+        .caused_by(err)
+    };
+    let mut resolve = Resolve::default();
+    let package = UnresolvedPackage::parse_file(wit)
+        .and_then(|package| resolve.push(package))
+        .map_err(component_err)?;
+    let world = resolve.select_world(package, None).map_err(component_err)?;
+    let mut module = fs::read(&ctx.wasm_out)?;
+    embed_component_metadata(&mut module, &resolve, world, StringEncoding::UTF8)
+        .map_err(component_err)?;
+    // Validation checks both the component structure and that the world covers the imports.
+    let component = ComponentEncoder::default()
+        .validate(true)
+        .module(&module)
+        .and_then(|encoder| encoder.encode())
+        .map_err(component_err)?;
+    let path = ctx.wasm_in.with_extension("component.wasm");
+    fs::write(&path, component)?;
+    info!("Wrote experimental component to {}", path.display());
+    Ok(())
+}
+
+/// Print the size breakdown of the optimized module for `--analyze-size`
+pub fn step_analyze_size(args: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    if args.analyze_size {
+        wasm::analyze_size(&fs::read(&ctx.wasm_out)?)?
+            .truncated(10)
+            .print();
+    }
+    Ok(())
+}
+
+/// Run the checks of `iroha_wasm_pack verify` on the optimized module
+pub fn step_verify_module(_: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    let module = fs::read(&ctx.wasm_out)?;
+    let policy = wasm::CheckPolicy {
+        max_size: ctx.max_size,
+        max_memory_pages: ctx.max_memory_pages,
+        allowed_imports: ctx.allowed_imports.clone(),
+        // Examples are binaries, only libraries have to look like a contract.
+        contract: ctx.crate_type == "cdylib",
+    };
+    let mut failed = Vec::new();
+    let mut code = None;
+    let mut too_large = false;
+    for check in wasm::run_checks(&module, &policy) {
+        match check.outcome {
+            wasm::Outcome::Pass => {}
+            wasm::Outcome::Warn => ctx.warn(check.details.join(", ")),
+            wasm::Outcome::Fail => {
+                code = code.or(Some(explain::check_code(check.name)));
+                too_large |= check.name == "size";
+                failed.push(check.details.join(", "))
+            }
+        }
+    }
+    if let (Some(code), true, 1) = (code, too_large, failed.len()) {
+        let error = Error::SizeLimit {
+            path: ctx.wasm_out.clone(),
+            size: module.len() as u64,
+            limit: ctx.max_size,
+        };
+        return Err(error.with_code(code));
+    }
+    if let Some(code) = code {
+        return Err(Error::wasm(format!(
+            "{} failed the post-build checks:\n  {}",
+            ctx.wasm_out.display(),
+            failed.join("\n  ")
+        ))
+        .with_code(code));
+    }
+    Ok(())
+}
+
+/// Size history entries kept per target directory, the oldest are dropped first
+const HISTORY_LIMIT: usize = 1000;
+
+/// A build as `iroha_wasm_pack history` lists it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SizeRecord {
+    /// Seconds since the unix epoch
+    pub timestamp: u64,
+    pub commit: Option<String>,
+    pub artifact: String,
+    pub profile: String,
+    pub size: u64,
+}
+
+/// One JSON record per line, shared by the packages and profiles of a target directory
+///
+/// It sits beside the build outputs rather than among them, `clean` keeps it.
+fn history_file(ctx: &BuildContext) -> PathBuf {
+    ctx.target_dir.join("iroha_wasm_pack-history.jsonl")
+}
+
+/// Records of history file contents, lines that don't parse are skipped
+fn parse_history(contents: &str) -> Vec<SizeRecord> {
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Records of the history file
+fn read_history(path: &Path) -> Vec<SizeRecord> {
+    parse_history(&fs::read_to_string(path).unwrap_or_default())
+}
+
+/// Add `record` to the history file, dropping the oldest records past the limit
+fn append_history(path: &Path, record: &SizeRecord) -> Result<(), Error> {
+    use fs2::FileExt;
+    use std::io::{Read, Seek, SeekFrom, Write};
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(path)?;
+    // Builds of the other profile write the same file without holding our build lock.
+    file.lock_exclusive()?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let history = parse_history(&contents);
+    if history.len() >= HISTORY_LIMIT {
+        let mut lines = Vec::with_capacity(HISTORY_LIMIT);
+        for kept in &history[history.len() + 1 - HISTORY_LIMIT..] {
+            lines.push(serde_json::to_string(kept)?);
+        }
+        lines.push(serde_json::to_string(record)?);
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all((lines.join("\n") + "\n").as_bytes())?;
+    } else {
+        file.seek(SeekFrom::End(0))?;
+        writeln!(file, "{}", serde_json::to_string(record)?)?;
+    }
+    // The lock goes with the file handle.
+    Ok(())
+}
+
+/// Append the optimized size to the history `iroha_wasm_pack history` shows
+pub fn step_record_size(_: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
+    use std::time::SystemTime;
+    let git = Invocation::new("git", ["rev-parse", "--short", "HEAD"])
+        .dir(&ctx.crate_root)
+        .capture_stderr();
+    let commit = ctx.runner().read(&git).ok();
+    let record = SizeRecord {
+        timestamp: SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs()),
+        commit,
+        artifact: artifact_name(ctx),
+        profile: ctx.profile.to_owned(),
+        size: fs::metadata(&ctx.wasm_out)?.len(),
+    };
+    append_history(&history_file(ctx), &record)
+}
+
+/// Name the history knows an artifact by, the file name of its wasm
+fn artifact_name(ctx: &BuildContext) -> String {
+    ctx.wasm_out
+        .file_name()
+        .map_or(String::new(), |name| name.to_string_lossy().into_owned())
+}
+
+/// `YYYY-MM-DD HH:MM:SS` of a Unix time, in UTC
+fn utc_date_time(seconds: u64) -> String {
+    // Days to the civil date, from Howard Hinnant's `civil_from_days`.
+    let days = (seconds / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    let time = seconds % 86400;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack history` command.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    setting = AppSettings::TrailingVarArg,
+)]
+pub struct HistoryArgs {
+    /// Show at most this many of the latest builds
+    #[structopt(long = "limit", default_value = "20")]
+    pub limit: usize,
+
+    /// Only show builds of this profile, `debug` or `release`
+    #[structopt(long = "profile", possible_values = &["debug", "release"])]
+    pub profile: Option<String>,
+
+    /// Print the builds as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    /// Draw the sizes as a sparkline
+    #[structopt(long = "graph")]
+    pub graph: bool,
+
+    /// Options of `build` selecting the package
+    #[structopt(flatten)]
+    pub build: BuildArgs,
+}
+
+impl RunArgs for HistoryArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let (mut summary, mut histories) = (Vec::new(), Vec::new());
+        for ctx in BuildContext::resolve(&self.build)? {
+            let artifact = artifact_name(&ctx);
+            let mut records: Vec<SizeRecord> = read_history(&history_file(&ctx))
+                .into_iter()
+                .filter(|record| record.artifact == artifact)
+                .filter(|record| {
+                    self.profile
+                        .as_ref()
+                        .map_or(true, |profile| &record.profile == profile)
+                })
+                .collect();
+            records.drain(..records.len().saturating_sub(self.limit));
+            let history = serde_json::json!({ "artifact": artifact, "builds": records });
+            if self.json {
+                summary.push(serde_json::to_string_pretty(&history)?);
+            } else {
+                summary.extend(history_lines(&artifact, &records, self.graph));
+            }
+            histories.push(history);
+        }
+        Ok(CommandReport {
+            summary,
+            data: Some(serde_json::Value::Array(histories)),
+            ..CommandReport::default()
+        })
+    }
+}
+
+/// Lines `history` shows for the `records` of `artifact`, with a sparkline if `graph` is set
+fn history_lines(artifact: &str, records: &[SizeRecord], graph: bool) -> Vec<String> {
+    let mut lines = vec![artifact.to_owned()];
+    if records.is_empty() {
+        lines.push("  no builds recorded yet".to_owned());
+        return lines;
+    }
+    let mut previous: Option<u64> = None;
+    for record in records {
+        let delta = previous.map_or(String::new(), |previous| {
+            format!("{:+}", record.size as i64 - previous as i64)
+        });
+        lines.push(format!(
+            "  {}  {:<9}  {:<7}  {:>10}  {:>8}",
+            utc_date_time(record.timestamp),
+            record.commit.as_deref().unwrap_or("-"),
+            record.profile,
+            record.size,
+            delta
+        ));
+        previous = Some(record.size);
+    }
+    if graph {
+        const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+        let min = records.iter().map(|record| record.size).min().unwrap_or(0);
+        let max = records.iter().map(|record| record.size).max().unwrap_or(0);
+        let line: String = records
+            .iter()
+            .map(|record| {
+                let level = (record.size - min) * 7 / (max - min).max(1);
+                BARS[level as usize]
+            })
+            .collect();
+        lines.push(format!("  {}  {} .. {} bytes", line, min, max));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::{CommandOutput, ScriptedRunner};
+    use crate::test_util;
+
+    fn build_args(flags: &[&str]) -> BuildArgs {
+        BuildArgs::from_iter_safe(std::iter::once("build").chain(flags.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn install_policy_follows_the_flags() {
+        assert_eq!(build_args(&[]).install_policy(), InstallPolicy::Prompt);
+        assert_eq!(
+            build_args(&["--yes"]).install_policy(),
+            InstallPolicy::Always
+        );
+        assert_eq!(build_args(&["-y"]).install_policy(), InstallPolicy::Always);
+        assert_eq!(
+            build_args(&["--ci"]).install_policy(),
+            InstallPolicy::Always
+        );
+        assert_eq!(
+            build_args(&["--no-install"]).install_policy(),
+            InstallPolicy::Never
+        );
+        assert!(BuildArgs::from_iter_safe(["build", "--yes", "--no-install"]).is_err());
+        assert!(BuildArgs::from_iter_safe(["build", "--ci", "--no-install"]).is_err());
+    }
+
+    #[test]
+    fn never_installing_names_the_command() {
+        let command = "rustup component add rust-src";
+        let err = confirm_install(InstallPolicy::Never, "rust-src", command).unwrap_err();
+        assert_eq!(err.code(), Some("E0202"));
+        assert!(err.to_string().contains(command));
+        assert!(confirm_install(InstallPolicy::Always, "rust-src", command).is_ok());
+    }
+
+    #[test]
+    fn refused_install_runs_nothing() {
+        let runner = ScriptedRunner::default();
+        let err = rustup_add_wasm_target(&runner, InstallPolicy::Never).unwrap_err();
+        assert_eq!(err.code(), Some("E0202"));
+        assert!(runner.invocations().is_empty());
+    }
+
+    #[test]
+    fn confirmed_install_runs_rustup() {
+        let runner = ScriptedRunner::default().respond("rustup", CommandOutput::success(""));
+        rustup_add_wasm_target(&runner, InstallPolicy::Always).unwrap();
+        assert_eq!(
+            runner.invocations()[0].args,
+            ["target", "add", "wasm32-unknown-unknown"]
+        );
+    }
+
+    /// Args of `build --manifest-path <dir>/Cargo.toml <flags>` and the context they select
+    fn context(
+        dir: &Path,
+        flags: &[&str],
+        runner: Arc<dyn CommandRunner>,
+    ) -> (BuildArgs, BuildContext) {
+        let manifest = dir.join("Cargo.toml");
+        let mut argv = vec!["--manifest-path", manifest.to_str().unwrap()];
+        argv.extend(flags);
+        let args = build_args(&argv);
+        let ctx = BuildContext::resolve(&args)
+            .unwrap()
+            .remove(0)
+            .with_runner(runner);
+        (args, ctx)
+    }
+
+    #[test]
+    fn build_std_checks_rust_src_instead_of_the_wasm32_target() {
+        let dir = test_util::contract("build-std");
+        let sysroot = dir.join("sysroot");
+        fs::create_dir_all(sysroot.join("lib/rustlib/src/rust/library")).unwrap();
+        let runner = Arc::new(
+            ScriptedRunner::default()
+                .respond("rustc", CommandOutput::success(sysroot.to_str().unwrap())),
+        );
+        let (args, ctx) = context(&dir, &[], runner.clone());
+        step_check_for_wasm_target(&args, &ctx).unwrap();
+        let invocations = runner.invocations();
+        assert_eq!(invocations.len(), 1);
+        assert_eq!(
+            invocations[0].args,
+            [
+                format!("+{}", DEFAULT_TOOLCHAIN),
+                "--print".to_owned(),
+                "sysroot".to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn build_std_installs_rust_src_for_the_toolchain() {
+        let dir = test_util::contract("build-std-install");
+        let sysroot = dir.join("rustup/toolchains/nightly");
+        fs::create_dir_all(sysroot.join("lib/rustlib/wasm32-unknown-unknown")).unwrap();
+        let runner = Arc::new(
+            ScriptedRunner::default()
+                .respond("rustc", CommandOutput::success(sysroot.to_str().unwrap()))
+                .respond("rustup", CommandOutput::success("")),
+        );
+        let (args, ctx) = context(&dir, &["--yes"], runner.clone());
+        step_check_for_wasm_target(&args, &ctx).unwrap();
+        assert_eq!(
+            runner.invocations()[1].args,
+            [
+                "component",
+                "add",
+                "rust-src",
+                "--toolchain",
+                DEFAULT_TOOLCHAIN
+            ]
+        );
+    }
+
+    #[test]
+    fn no_build_std_requires_the_wasm32_target() {
+        let dir = test_util::contract("no-build-std");
+        let sysroot = dir.join("rustup/toolchains/nightly");
+        fs::create_dir_all(sysroot.join("lib/rustlib/src/rust/library")).unwrap();
+        let runner = Arc::new(
+            ScriptedRunner::default()
+                .respond("rustc", CommandOutput::success(sysroot.to_str().unwrap())),
+        );
+        let (args, ctx) = context(&dir, &["--no-build-std", "--no-install"], runner.clone());
+        let err = step_check_for_wasm_target(&args, &ctx).unwrap_err();
+        assert_eq!(err.code(), Some("E0202"));
+        assert!(err
+            .to_string()
+            .contains("rustup target add wasm32-unknown-unknown"));
+        assert_eq!(runner.invocations()[0].args, ["--print", "sysroot"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_project_resolves_to_the_real_directory() {
+        let dir = test_util::contract("symlinked");
+        let link = test_util::temp_dir("symlinked-link").join("contract");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        let real = fs::canonicalize(&dir).unwrap();
+        assert_eq!(root(link.join("src")).unwrap(), real);
+        let (_, ctx) = context(&link, &[], Arc::new(ScriptedRunner::default()));
+        assert_eq!(ctx.crate_root, real);
+        assert!(ctx.wasm_out.starts_with(real.join("target")));
+    }
+
+    #[test]
+    fn missing_start_directory_is_an_error() {
+        let dir = test_util::temp_dir("deleted").join("gone");
+        let err = root(dir).unwrap_err();
+        assert!(err.to_string().contains("gone"));
+    }
+
+    /// A virtual workspace with the contracts `a` and `b` and a `shared` library it skips
+    fn workspace_fixture(name: &str) -> PathBuf {
+        let dir = test_util::temp_dir(name);
+        let member = |name: &str| {
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[lib]\ncrate-type = [\"cdylib\"]\n",
+                name
+            )
+        };
+        test_util::write_files(
+            &dir,
+            &[
+                (
+                    "Cargo.toml",
+                    "[workspace]\nmembers = [\"contracts/*\", \"shared\"]\n",
+                ),
+                ("contracts/a/Cargo.toml", &member("a")),
+                ("contracts/b/Cargo.toml", &member("b")),
+                (
+                    "shared/Cargo.toml",
+                    "[package]\nname = \"shared\"\nversion = \"0.1.0\"\n\n\
+                    [package.metadata.iroha_wasm_pack]\nskip = true\n",
+                ),
+            ],
+        );
+        dir
+    }
+
+    fn resolve(dir: &Path, flags: &[&str]) -> Result<Vec<BuildContext>, Error> {
+        let manifest = dir.join("Cargo.toml");
+        let mut argv = vec!["--manifest-path", manifest.to_str().unwrap()];
+        argv.extend(flags);
+        BuildContext::resolve(&build_args(&argv))
+    }
+
+    /// Toolchain a context builds with and the setting `config` lists for it
+    fn toolchain_of(ctx: &BuildContext) -> (Option<&str>, &Setting) {
+        let setting = ctx
+            .settings
+            .iter()
+            .find(|setting| setting.key == "toolchain")
+            .unwrap();
+        (ctx.toolchain.as_deref(), setting)
+    }
+
+    #[test]
+    fn a_toolchain_file_wins_over_the_configured_toolchain() {
+        let dir = test_util::contract("toolchain-precedence");
+        test_util::write_files(
+            &dir,
+            &[(
+                "iroha_wasm_pack.toml",
+                "[build]\ntoolchain = \"nightly-2023-01-01\"\n",
+            )],
+        );
+        let contexts = resolve(&dir, &[]).unwrap();
+        let (toolchain, setting) = toolchain_of(&contexts[0]);
+        assert_eq!(toolchain, Some("nightly-2023-01-01"));
+        assert_eq!(setting.source, Source::Config);
+        assert!(setting
+            .file
+            .as_ref()
+            .unwrap()
+            .ends_with("iroha_wasm_pack.toml"));
+
+        test_util::write_files(
+            &dir,
+            &[(
+                "rust-toolchain.toml",
+                "[toolchain]\nchannel = \"nightly-2023-06-01\"\n",
+            )],
+        );
+        let contexts = resolve(&dir, &[]).unwrap();
+        let (toolchain, setting) = toolchain_of(&contexts[0]);
+        assert_eq!(toolchain, None);
+        assert_eq!(setting.source, Source::File);
+        assert_eq!(setting.file, None);
+
+        let contexts = resolve(&dir, &["--toolchain", "stable"]).unwrap();
+        let (toolchain, setting) = toolchain_of(&contexts[0]);
+        assert_eq!(toolchain, Some("stable"));
+        assert_eq!(setting.source, Source::Flag);
+    }
+
+    fn names(contexts: &[BuildContext]) -> Vec<&str> {
+        contexts.iter().map(BuildContext::package_name).collect()
+    }
+
+    #[test]
+    fn virtual_workspace_needs_a_selection() {
+        let dir = workspace_fixture("virtual");
+        let err = resolve(&dir, &[]).err().unwrap();
+        assert_eq!(err.code(), Some("E0102"));
+        let message = err.to_string();
+        assert!(message.contains("virtual workspace manifest"));
+        assert!(message.contains("\n    a\n    b"));
+        assert!(!message.contains("shared"));
+    }
+
+    #[test]
+    fn workspace_selects_every_contract() {
+        let dir = workspace_fixture("workspace");
+        let contexts = resolve(&dir, &["--workspace"]).unwrap();
+        assert_eq!(names(&contexts), ["a", "b"]);
+        let target = target_dir(None, &fs::canonicalize(&dir).unwrap());
+        assert!(contexts.iter().all(|ctx| ctx.target_dir == target));
+    }
+
+    #[test]
+    fn package_selects_one_member() {
+        let dir = workspace_fixture("package");
+        let contexts = resolve(&dir, &["-p", "b"]).unwrap();
+        assert_eq!(names(&contexts), ["b"]);
+        assert_eq!(contexts[0].cargo_package.as_deref(), Some("b"));
+        let err = resolve(&dir, &["-p", "c"]).err().unwrap();
+        assert!(err.to_string().contains("members are: a, b"));
+    }
+
+    #[test]
+    fn package_outside_a_workspace_has_to_match() {
+        let dir = test_util::contract("package-outside");
+        let contexts = resolve(&dir, &["-p", "contract"]).unwrap();
+        assert_eq!(names(&contexts), ["contract"]);
+        let err = resolve(&dir, &["-p", "other"]).err().unwrap();
+        assert_eq!(err.class(), ErrorClass::Environment);
+        assert!(
+            err.to_string()
+                .starts_with("package `other` doesn't match `contract`"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn workspace_members_follow_globs_and_exclude() {
+        let dir = workspace_fixture("workspace-globs");
+        let member = |name: &str| {
+            format!(
+                "[package]\nname = \"{}\"\nversion = \"0.1.0\"\n\n[lib]\ncrate-type = [\"cdylib\"]\n",
+                name
+            )
+        };
+        test_util::write_files(
+            &dir,
+            &[
+                (
+                    "Cargo.toml",
+                    "[workspace]\n\
+                    members = [\"contracts/*\", \"nested/*/c?\", \"shared\", \"./contracts/a\"]\n\
+                    exclude = [\"contracts/b\"]\n",
+                ),
+                ("contracts/notes/README.md", "not a package"),
+                ("nested/x/c1/Cargo.toml", &member("c1")),
+                ("nested/x/d1/Cargo.toml", &member("d1")),
+            ],
+        );
+        let contexts = resolve(&dir, &["--workspace"]).unwrap();
+        assert_eq!(names(&contexts), ["a", "c1"]);
+    }
+
+    #[test]
+    fn member_builds_into_the_workspace_target() {
+        let dir = workspace_fixture("member");
+        let contexts = resolve(&dir.join("contracts/a"), &[]).unwrap();
+        assert_eq!(names(&contexts), ["a"]);
+        assert_eq!(
+            contexts[0].target_dir,
+            target_dir(None, &fs::canonicalize(&dir).unwrap())
+        );
+    }
+
+    /// A workspace whose `[workspace.package]` is `shared` and a member with `package`
+    fn inheriting_fixture(name: &str, shared: &str, package: &str) -> PathBuf {
+        let dir = test_util::temp_dir(name);
+        test_util::write_files(
+            &dir,
+            &[
+                (
+                    "Cargo.toml",
+                    &format!(
+                        "[workspace]\nmembers = [\"member\"]\n\n[workspace.package]\n{}",
+                        shared
+                    ),
+                ),
+                (
+                    "member/Cargo.toml",
+                    &format!("[package]\n{}\n[lib]\ncrate-type = [\"cdylib\"]\n", package),
+                ),
+            ],
+        );
+        dir.join("member")
+    }
+
+    #[test]
+    fn inherited_keys_other_than_the_name_are_accepted() {
+        let member = inheriting_fixture(
+            "inherit-version",
+            "version = \"1.2.3\"\nedition = \"2021\"\nauthors = [\"Alice\"]\n",
+            "name = \"a\"\nversion.workspace = true\nedition.workspace = true\n\
+            authors = { workspace = true }\n",
+        );
+        assert_eq!(names(&resolve(&member, &[]).unwrap()), ["a"]);
+    }
+
+    #[test]
+    fn inherited_name_comes_from_the_workspace() {
+        let member = inheriting_fixture(
+            "inherit-name",
+            "name = \"inherited\"\nversion = \"1.2.3\"\n",
+            "name.workspace = true\nversion.workspace = true\n",
+        );
+        assert_eq!(names(&resolve(&member, &[]).unwrap()), ["inherited"]);
+    }
+
+    #[test]
+    fn inherited_name_missing_from_the_workspace() {
+        let member = inheriting_fixture(
+            "inherit-missing",
+            "version = \"1.2.3\"\n",
+            "name.workspace = true\n",
+        );
+        let err = resolve(&member, &[]).err().unwrap();
+        assert!(err
+            .to_string()
+            .contains("`workspace.package.name` is missing"));
+    }
+
+    #[test]
+    fn name_not_inherited_must_be_a_value() {
+        let member = inheriting_fixture(
+            "inherit-false",
+            "name = \"inherited\"\n",
+            "name = { workspace = false }\n",
+        );
+        let err = resolve(&member, &[]).err().unwrap();
+        assert!(err
+            .to_string()
+            .contains("must be a value or `{ workspace = true }`"));
+    }
+
+    #[test]
+    fn failed_wasm_opt_leaves_no_output() {
+        let dir = test_util::temp_dir("wasm-opt-failed");
+        let runner = ScriptedRunner::default().respond(
+            "wasm-opt",
+            CommandOutput::failure(1, "[wasm-validator error]"),
+        );
+        let opt = WasmOptArgs::from_iter_safe(["opt", "--wasm-opt-path", "wasm-opt"]).unwrap();
+        let output = dir.join("contract_optimized.wasm");
+        let err = run_wasm_opt(&runner, &opt, &dir.join("contract.wasm"), &output).unwrap_err();
+        assert_eq!(err.code(), Some("E0401"));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        let args = &runner.invocations()[0].args;
+        assert_eq!(args[1], "-o");
+        assert!(args[2].ends_with("contract_optimized.wasm-opt.partial"));
+        assert_eq!(args[3], "-Os");
+    }
+
+    #[test]
+    fn iroha_version_is_compared_exactly() {
+        let package = LockedPackage {
+            name: "iroha_wasm".to_owned(),
+            version: "2.0.0-pre-rc.11".to_owned(),
+            source: Some(
+                "git+https://github.com/hyperledger/iroha?tag=v2.0.0-pre-rc.11#0123456789abcdef"
+                    .to_owned(),
+            ),
+        };
+        assert!(package.is("2.0.0-pre-rc.11"));
+        assert!(package.is("v2.0.0-pre-rc.11"));
+        assert!(package.is("0123456789abcdef"));
+        assert!(package.is("0123456"));
+        assert!(!package.is("2.0.0-pre-rc.1"));
+        assert!(!package.is("v2.0.0-pre-rc.1"));
+        assert!(!package.is("rc.11"));
+        assert!(!package.is("012345"));
+    }
+
+    #[test]
+    fn stripping_keeps_the_iroha_version_section() {
+        let mut module = b"\0asm\x01\0\0\0".to_vec();
+        append_custom_section(&mut module, IROHA_VERSION_SECTION, b"iroha_wasm 2.0.0");
+        append_custom_section(&mut module, "producers", b"");
+        append_custom_section(&mut module, "name", b"");
+        let opt = WasmOptArgs::from_iter_safe(["opt"]).unwrap();
+        let stripped = wasm::strip_custom_sections(&module, &kept_sections(&opt)).unwrap();
+        let names: Vec<String> = wasm::parse(&stripped)
+            .unwrap()
+            .custom_sections
+            .into_iter()
+            .map(|section| section.name)
+            .collect();
+        assert_eq!(names, [IROHA_VERSION_SECTION]);
+    }
+
+    /// Args and context of a `contract` fixture whose optimized wasm is `wat`
+    fn built(name: &str, wat: &str, flags: &[&str]) -> (BuildArgs, BuildContext) {
+        let dir = test_util::contract(name);
+        let (args, ctx) = context(&dir, flags, Arc::new(ScriptedRunner::default()));
+        fs::create_dir_all(&ctx.wasm_folder).unwrap();
+        fs::write(&ctx.wasm_out, wat::parse_str(wat).unwrap()).unwrap();
+        (args, ctx)
+    }
+
+    /// A WIT world exporting `run: func()`
+    fn world(name: &str) -> PathBuf {
+        let dir = test_util::temp_dir(name);
+        test_util::write_files(
+            &dir,
+            &[(
+                "contract.wit",
+                "package test:contract\n\nworld contract {\n  export run: func()\n}\n",
+            )],
+        );
+        dir.join("contract.wit")
+    }
+
+    #[test]
+    fn component_wraps_the_module() {
+        let wit = world("component-wit");
+        let (args, ctx) = built(
+            "component",
+            r#"(module (func (export "run")))"#,
+            &["--component", "--wit", wit.to_str().unwrap()],
+        );
+        step_component(&args, &ctx).unwrap();
+        let component = fs::read(ctx.wasm_in.with_extension("component.wasm")).unwrap();
+        let features = wasmparser::WasmFeatures {
+            component_model: true,
+            ..wasmparser::WasmFeatures::default()
+        };
+        wasmparser::Validator::new_with_features(features)
+            .validate_all(&component)
+            .unwrap();
+    }
+
+    #[test]
+    fn component_rejects_imports_outside_the_world() {
+        let wit = world("component-imports-wit");
+        let (args, ctx) = built(
+            "component-imports",
+            r#"(module (import "iroha" "log" (func)) (func (export "run")))"#,
+            &["--component", "--wit", wit.to_str().unwrap()],
+        );
+        let err = step_component(&args, &ctx).unwrap_err();
+        assert_eq!(err.class(), ErrorClass::Validation);
+        assert!(!ctx.wasm_in.with_extension("component.wasm").exists());
+    }
+
+    #[test]
+    fn component_needs_the_flag() {
+        let (args, ctx) = built("component-off", "(module)", &[]);
+        step_component(&args, &ctx).unwrap();
+        assert!(!ctx.wasm_in.with_extension("component.wasm").exists());
+    }
+
+    #[test]
+    fn target_dir_comes_from_the_nearest_cargo_config() {
+        let dir = test_util::temp_dir("target-dir");
+        test_util::write_files(
+            &dir,
+            &[
+                (".cargo/config.toml", "[build]\ntarget-dir = \"outer\"\n"),
+                (
+                    "project/.cargo/config",
+                    "[build]\ntarget-dir = \"../build\"\n",
+                ),
+                ("project/contract/Cargo.toml", ""),
+                ("other/Cargo.toml", ""),
+            ],
+        );
+        assert_eq!(
+            configured_target_dir(&dir.join("project/contract")),
+            Some(dir.join("project/../build"))
+        );
+        assert_eq!(
+            configured_target_dir(&dir.join("other")),
+            Some(dir.join("outer"))
+        );
+        let absolute = dir.join("absolute");
+        let config = format!(
+            "[build]\ntarget-dir = {:?}\n",
+            absolute.display().to_string()
+        );
+        test_util::write_files(&dir, &[("other/.cargo/config.toml", config.as_str())]);
+        assert_eq!(configured_target_dir(&dir.join("other")), Some(absolute));
+    }
+
+    #[test]
+    fn explicit_target_dir_wins() {
+        let dir = test_util::temp_dir("explicit-target-dir");
+        test_util::write_files(
+            &dir,
+            &[(".cargo/config.toml", "[build]\ntarget-dir = \"x\"\n")],
+        );
+        assert_eq!(
+            target_dir(Some(Path::new("/out")), &dir),
+            PathBuf::from("/out")
+        );
+    }
+
+    #[test]
+    fn clean_takes_a_target_dir() {
+        let args = CleanArgs::from_iter_safe(["clean", "--target-dir", "out"]).unwrap();
+        assert_eq!(args.target_dir, Some(PathBuf::from("out")));
+    }
+
+    #[test]
+    fn metadata_document_matches_the_snapshot() {
+        let dir = test_util::contract("metadata").canonicalize().unwrap();
+        let target = dir.join("target");
+        let contexts = resolve(&dir, &["--target-dir", target.to_str().unwrap()]).unwrap();
+        let wasm = target.join("wasm32-unknown-unknown").join("debug");
+        assert_eq!(
+            metadata_document(&dir, &contexts),
+            serde_json::json!({
+                "version": 1,
+                "root": dir,
+                "packages": [{
+                    "package_name": "contract",
+                    "crate_type": "cdylib",
+                    "max_size": MAX_WASM_SIZE,
+                    "max_memory_pages": MAX_MEMORY_PAGES,
+                    "allowed_imports": [],
+                    "toolchain": DEFAULT_TOOLCHAIN,
+                    "fail_on_warnings": false,
+                    "crate_root": dir,
+                    "cargo_package": null,
+                    "example": null,
+                    "lock_file": dir.join("Cargo.lock"),
+                    "profile": "debug",
+                    "target_dir": target,
+                    "wasm_folder": wasm,
+                    "wasm_in": wasm.join("contract.wasm"),
+                    "wasm_out": wasm.join("contract_optimized.wasm"),
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn nightly_is_read_from_the_rustc_release() {
+        let dir = test_util::contract("nightly");
+        let version = |release: &str| {
+            Arc::new(ScriptedRunner::default().respond(
+                "rustc",
+                CommandOutput::success(&format!(
+                    "rustc {}\nbinary: rustc\nrelease: {}\nLLVM version: 15.0.6",
+                    release, release
+                )),
+            ))
+        };
+        let runner = version("1.68.0-nightly");
+        let (args, ctx) = context(&dir, &[], runner.clone());
+        assert!(builds_with_nightly(&args, &ctx));
+        assert_eq!(
+            runner.invocations()[0].args,
+            [format!("+{}", DEFAULT_TOOLCHAIN), "-vV".to_owned()]
+        );
+        let (args, ctx) = context(&dir, &[], version("1.66.0"));
+        assert!(!builds_with_nightly(&args, &ctx));
+        // Without build-std the toolchain of the project is used, as cargo would.
+        let runner = version("1.68.0-dev");
+        let (args, ctx) = context(&dir, &["--no-build-std"], runner.clone());
+        assert!(builds_with_nightly(&args, &ctx));
+        assert_eq!(runner.invocations()[0].args, ["-vV"]);
+        let runner = Arc::new(
+            ScriptedRunner::default().respond("rustc", CommandOutput::failure(1, "no rustc")),
+        );
+        let (args, ctx) = context(&dir, &[], runner);
+        assert!(!builds_with_nightly(&args, &ctx));
+    }
+
+    #[test]
+    fn history_dates_are_utc() {
+        assert_eq!(utc_date_time(0), "1970-01-01 00:00:00");
+        assert_eq!(utc_date_time(951_782_400), "2000-02-29 00:00:00");
+        assert_eq!(utc_date_time(1_700_000_000), "2023-11-14 22:13:20");
+    }
+
+    #[test]
+    fn history_survives_clean_and_keeps_the_latest_records() {
+        let dir = test_util::contract("history");
+        let ctx = resolve(&dir, &[]).unwrap().remove(0);
+        let path = history_file(&ctx);
+        assert!(!path.starts_with(ctx.target_dir.join("wasm32-unknown-unknown")));
+        let record = |timestamp| SizeRecord {
+            timestamp,
+            commit: None,
+            artifact: "contract_optimized.wasm".to_owned(),
+            profile: "debug".to_owned(),
+            size: 100,
+        };
+        for timestamp in 0..HISTORY_LIMIT as u64 + 2 {
+            append_history(&path, &record(timestamp)).unwrap();
+        }
+        let history = read_history(&path);
+        assert_eq!(history.len(), HISTORY_LIMIT);
+        assert_eq!(history[0].timestamp, 2);
+        assert_eq!(
+            history[HISTORY_LIMIT - 1].timestamp,
+            HISTORY_LIMIT as u64 + 1
+        );
+    }
+
+    #[test]
+    fn unknown_step_is_a_usage_error() {
+        assert!(registered_step("check-wasm-target").is_ok());
+        let err = registered_step("chek-wasm-target").err().unwrap();
+        assert_eq!(err.class(), ErrorClass::Usage);
+        assert!(err.to_string().contains("check-wasm-target"));
+    }
+
+    /// A step only running `step <name>`, so the runner records it among the hooks
+    struct FakeStep {
+        name: &'static str,
+        fail: bool,
+    }
+
+    impl Step for FakeStep {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn description(&self) -> &'static str {
+            "a step of the tests"
+        }
+
+        fn run(&self, _: &BuildArgs, ctx: &mut BuildContext) -> Result<(), Error> {
+            ctx.runner().read(&Invocation::new("step", [self.name]))?;
+            if self.fail {
+                return Err(Error::other(format!("{} failed", self.name)));
+            }
+            Ok(())
+        }
+    }
+
+    fn fake(name: &'static str) -> FakeStep {
+        FakeStep { name, fail: false }
+    }
+
+    /// `program args` of every invocation so far
+    fn ran(runner: &ScriptedRunner) -> Vec<String> {
+        runner
+            .invocations()
+            .iter()
+            .map(|invocation| format!("{} {}", invocation.program, invocation.args.join(" ")))
+            .collect()
+    }
+
+    const HOOKS: &str = r#"version = 1
+
+[hooks]
+pre_build = ["hook pre_build"]
+pre_strip = ["hook pre_strip"]
+post_strip = ["hook post_strip"]
+pre_component = ["hook pre_component"]
+post_build = ["hook post_build"]
+on_failure = ["hook on_failure"]
+"#;
+
+    /// A runner answering every program in `programs` with success, in order
+    fn runner_for(programs: &[&str]) -> Arc<ScriptedRunner> {
+        Arc::new(
+            programs
+                .iter()
+                .fold(ScriptedRunner::default(), |runner, program| {
+                    runner.respond(program, CommandOutput::success(""))
+                }),
+        )
+    }
+
+    #[test]
+    fn pipeline_runs_steps_in_order_between_their_hooks() {
+        let dir = test_util::contract("pipeline");
+        test_util::write_files(&dir, &[("iroha_wasm_pack.toml", HOOKS)]);
+        let mut pipeline = Pipeline::default().with_hooks(true);
+        pipeline.push(fake("strip"));
+        pipeline.push(fake("component"));
+        pipeline.push(fake("analyze-size"));
+        pipeline
+            .insert_before("analyze-size", fake("wasm-opt"))
+            .unwrap();
+        let pipeline = pipeline.skipping(&["component".to_owned()]).unwrap();
+        let runner = runner_for(&["hook", "hook", "step", "hook", "step", "step", "hook"]);
+        let (args, mut ctx) = context(&dir, &[], runner.clone());
+        let timings = pipeline.run(&args, &mut ctx).unwrap();
+        assert_eq!(
+            timings
+                .iter()
+                .map(|timing| timing.step.as_str())
+                .collect::<Vec<_>>(),
+            ["strip", "wasm-opt", "analyze-size"]
+        );
+        assert_eq!(
+            ran(&runner),
+            [
+                "hook pre_build",
+                "hook pre_strip",
+                "step strip",
+                "hook post_strip",
+                "step wasm-opt",
+                "step analyze-size",
+                "hook post_build",
+            ]
+        );
+    }
+
+    #[test]
+    fn pipeline_without_hooks_only_runs_the_steps() {
+        let dir = test_util::contract("pipeline-no-hooks");
+        test_util::write_files(&dir, &[("iroha_wasm_pack.toml", HOOKS)]);
+        let mut pipeline = Pipeline::default();
+        pipeline.push(fake("strip"));
+        pipeline.insert_after("strip", fake("component")).unwrap();
+        let runner = runner_for(&["step", "step"]);
+        let (args, mut ctx) = context(&dir, &[], runner.clone());
+        pipeline.run(&args, &mut ctx).unwrap();
+        assert_eq!(ran(&runner), ["step strip", "step component"]);
+    }
+
+    #[test]
+    fn pipeline_stops_at_a_failing_step_and_runs_on_failure() {
+        let dir = test_util::contract("pipeline-failure");
+        test_util::write_files(&dir, &[("iroha_wasm_pack.toml", HOOKS)]);
+        let mut pipeline = Pipeline::default().with_hooks(true);
+        pipeline.push(FakeStep {
+            name: "wasm-opt",
+            fail: true,
+        });
+        pipeline.push(fake("strip"));
+        let runner = runner_for(&["hook", "step", "hook"]);
+        let (args, mut ctx) = context(&dir, &[], runner.clone());
+        let err = pipeline.run(&args, &mut ctx).err().unwrap();
+        assert_eq!(err.to_string(), "wasm-opt failed");
+        assert_eq!(
+            ran(&runner),
+            ["hook pre_build", "step wasm-opt", "hook on_failure"]
+        );
+    }
+
+    #[test]
+    fn skipping_an_unknown_step_fails() {
+        let err = Pipeline::default()
+            .skipping(&["optimise".to_owned()])
+            .err()
+            .unwrap();
+        assert_eq!(err.class(), ErrorClass::Usage);
+    }
+
+    /// Value of the variable `key` the invocation was given
+    fn env_of<'a>(invocation: &'a Invocation, key: &str) -> Option<&'a str> {
+        invocation
+            .env
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn hooks_run_relative_to_their_file_with_the_context_in_variables() {
+        let dir = test_util::contract("hook-env");
+        test_util::write_files(
+            &dir,
+            &[(
+                "iroha_wasm_pack.toml",
+                "version = 1\n\n[hooks]\npost_strip = [{ command = [\"scripts/size check.sh\", \"--max\", \"10 KiB\"] }]\n",
+            )],
+        );
+        let runner = runner_for(&[]);
+        let (_, ctx) = context(&dir, &[], runner.clone());
+        let runner = Arc::new(
+            ScriptedRunner::default().respond(
+                &ctx.crate_root
+                    .join("scripts/size check.sh")
+                    .display()
+                    .to_string(),
+                CommandOutput::success("ok"),
+            ),
+        );
+        let ctx = ctx.with_runner(runner.clone());
+        let project = ProjectConfig::discover(&ctx.crate_root).unwrap();
+        run_hooks(&project, "post_strip", Some("strip"), &ctx, None).unwrap();
+        let invocation = &runner.invocations()[0];
+        assert_eq!(invocation.args, ["--max", "10 KiB"]);
+        assert_eq!(invocation.dir.as_deref(), Some(ctx.crate_root.as_path()));
+        assert_eq!(
+            env_of(invocation, "IROHA_WASM_PACK_HOOK_NAME"),
+            Some("post_strip")
+        );
+        assert_eq!(
+            env_of(invocation, "IROHA_WASM_PACK_HOOK_STEP"),
+            Some("strip")
+        );
+        assert_eq!(
+            env_of(invocation, "IROHA_WASM_PACK_HOOK_PACKAGE"),
+            Some("contract")
+        );
+        assert_eq!(
+            env_of(invocation, "IROHA_WASM_PACK_HOOK_PROFILE"),
+            Some("debug")
+        );
+        assert_eq!(
+            env_of(invocation, "IROHA_WASM_PACK_HOOK_WASM"),
+            Some(ctx.wasm_out.display().to_string().as_str())
+        );
+        assert_eq!(env_of(invocation, "IROHA_WASM_PACK_HOOK_ERROR"), None);
+        assert!(invocation.capture_stderr);
+    }
+
+    #[test]
+    fn failing_hooks_stop_unless_allowed_to_fail() {
+        let dir = test_util::contract("hook-failure");
+        test_util::write_files(
+            &dir,
+            &[(
+                "iroha_wasm_pack.toml",
+                "version = 1\n\n[hooks]\npre_build = [{ command = [\"lint\"], allow-failure = true }, \"first\", \"second\"]\n",
+            )],
+        );
+        let runner = Arc::new(
+            ScriptedRunner::default()
+                .respond("lint", CommandOutput::failure(1, "lint failed"))
+                .respond("first", CommandOutput::failure(2, "first failed")),
+        );
+        let (_, ctx) = context(&dir, &[], runner.clone());
+        let project = ProjectConfig::discover(&ctx.crate_root).unwrap();
+        let err = run_hooks(&project, "pre_build", None, &ctx, None)
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("`first` failed"), "{}", err);
+        let programs: Vec<String> = runner
+            .invocations()
+            .into_iter()
+            .map(|invocation| invocation.program)
+            .collect();
+        assert_eq!(programs, ["lint", "first"]);
+        let warnings = ctx.warnings.borrow();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("pre_build hook failed: `lint` failed"));
+    }
+
+    #[test]
+    fn on_failure_gets_the_error_and_its_own_failure_is_a_warning() {
+        let dir = test_util::contract("hook-on-failure");
+        test_util::write_files(
+            &dir,
+            &[(
+                "iroha_wasm_pack.toml",
+                "version = 1\n\n[hooks]\non_failure = [\"notify\"]\n",
+            )],
+        );
+        let mut pipeline = Pipeline::default().with_hooks(true);
+        pipeline.push(FakeStep {
+            name: "wasm-opt",
+            fail: true,
+        });
+        let runner = Arc::new(
+            ScriptedRunner::default()
+                .respond("step", CommandOutput::success(""))
+                .respond("notify", CommandOutput::failure(1, "")),
+        );
+        let (args, mut ctx) = context(&dir, &[], runner.clone());
+        let err = pipeline.run(&args, &mut ctx).err().unwrap();
+        assert_eq!(err.to_string(), "wasm-opt failed");
+        let notify = &runner.invocations()[1];
+        assert_eq!(
+            env_of(notify, "IROHA_WASM_PACK_HOOK_NAME"),
+            Some("on_failure")
+        );
+        assert_eq!(
+            env_of(notify, "IROHA_WASM_PACK_HOOK_ERROR"),
+            Some("wasm-opt failed")
+        );
+        assert!(ctx.warnings.borrow()[0].starts_with("on_failure hook failed"));
+    }
+
+    #[test]
+    fn no_hooks_turns_the_hooks_off() {
+        assert!(Pipeline::for_build(&build_args(&[])).unwrap().hooks);
+        assert!(
+            !Pipeline::for_build(&build_args(&["--no-hooks"]))
+                .unwrap()
+                .hooks
+        );
+    }
+
+    #[test]
+    fn unknown_hooks_are_rejected() {
+        let dir = test_util::contract("hook-unknown");
+        test_util::write_files(
+            &dir,
+            &[(
+                "iroha_wasm_pack.toml",
+                "version = 1\n\n[hooks]\npre_optimise = [\"true\"]\n",
+            )],
+        );
+        let err = ProjectConfig::discover(&dir).err().unwrap();
+        assert!(
+            err.to_string().contains("unknown hook `pre_optimise`"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn rustc_minor_version_reads_the_version_line() {
+        let runner = ScriptedRunner::default().respond(
+            "rustc",
+            CommandOutput::success("rustc 1.68.0-nightly (afaf3e07a 2022-12-14)\n"),
+        );
+        assert_eq!(rustc_minor_version(&runner).unwrap(), 68);
+        assert_eq!(runner.invocations()[0].args, ["--version"]);
+        let runner =
+            ScriptedRunner::default().respond("rustc", CommandOutput::success("rustup 1.25.1"));
+        assert_eq!(
+            rustc_minor_version(&runner).err().unwrap().code(),
+            Some("E0201")
+        );
+        let runner = ScriptedRunner::default()
+            .respond("rustc", CommandOutput::failure(1, "no default toolchain"));
+        assert!(rustc_minor_version(&runner).is_err());
+    }
+
+    #[test]
+    fn old_rustc_is_rejected() {
+        let dir = test_util::contract("old-rustc");
+        let runner = Arc::new(
+            ScriptedRunner::default().respond("rustc", CommandOutput::success("rustc 1.29.2")),
+        );
+        let (args, ctx) = context(&dir, &[], runner);
+        let err = step_check_rustc_version(&args, &ctx).err().unwrap();
+        assert_eq!(err.code(), Some("E0201"));
+    }
+
+    #[test]
+    fn sysroot_comes_from_the_toolchain() {
+        let runner = ScriptedRunner::default()
+            .respond("rustc", CommandOutput::success("/rust/sysroot\n"))
+            .respond("rustc", CommandOutput::success("/rust/nightly"));
+        assert_eq!(
+            get_rustc_sysroot(&runner, None).unwrap(),
+            PathBuf::from("/rust/sysroot")
+        );
+        assert_eq!(
+            get_rustc_sysroot(&runner, Some("nightly")).unwrap(),
+            PathBuf::from("/rust/nightly")
+        );
+        let invocations = runner.invocations();
+        assert_eq!(invocations[0].args, ["--print", "sysroot"]);
+        assert_eq!(invocations[1].args, ["+nightly", "--print", "sysroot"]);
+        let runner =
+            ScriptedRunner::default().respond("rustc", CommandOutput::failure(1, "not found"));
+        let err = get_rustc_sysroot(&runner, None).err().unwrap();
+        assert_eq!(err.class(), ErrorClass::Environment);
+    }
+
+    /// `step_check_for_wasm_target` with `--no-build-std` and a sysroot at `sysroot` below
+    /// the project, returning the programs it ran
+    fn check_precompiled_target(
+        name: &str,
+        sysroot: &str,
+        with_target: bool,
+        flags: &[&str],
+    ) -> (Result<(), Error>, Vec<Invocation>) {
+        let dir = test_util::contract(name);
+        let sysroot = dir.join(sysroot);
+        fs::create_dir_all(sysroot.join("lib/rustlib")).unwrap();
+        if with_target {
+            fs::create_dir_all(sysroot.join("lib/rustlib/wasm32-unknown-unknown")).unwrap();
+        }
+        let runner = Arc::new(
+            ScriptedRunner::default()
+                .respond("rustc", CommandOutput::success(sysroot.to_str().unwrap()))
+                .respond("rustup", CommandOutput::success("")),
+        );
+        let mut all = vec!["--no-build-std"];
+        all.extend(flags);
+        let (args, ctx) = context(&dir, &all, runner.clone());
+        (
+            step_check_for_wasm_target(&args, &ctx),
+            runner.invocations(),
+        )
+    }
+
+    #[test]
+    fn installed_wasm32_target_needs_no_rustup() {
+        let (result, invocations) =
+            check_precompiled_target("target-installed", "rustup/toolchains/stable", true, &[]);
+        result.unwrap();
+        assert_eq!(invocations.len(), 1);
+        assert_eq!(invocations[0].args, ["--print", "sysroot"]);
+    }
+
+    #[test]
+    fn missing_wasm32_target_is_added_with_rustup() {
+        let (result, invocations) = check_precompiled_target(
+            "target-missing",
+            "rustup/toolchains/stable",
+            false,
+            &["--yes"],
+        );
+        result.unwrap();
+        assert_eq!(invocations[1].program, "rustup");
+        assert_eq!(
+            invocations[1].args,
+            ["target", "add", "wasm32-unknown-unknown"]
+        );
+    }
+
+    #[test]
+    fn missing_wasm32_target_without_rustup_is_left_to_cargo() {
+        let (result, invocations) =
+            check_precompiled_target("target-elsewhere", "usr/lib/rust", false, &["--yes"]);
+        result.unwrap();
+        assert_eq!(invocations.len(), 1);
+    }
+
+    #[test]
+    fn missing_wasm32_target_with_no_install_fails() {
+        let (result, invocations) = check_precompiled_target(
+            "target-no-install",
+            "rustup/toolchains/stable",
+            false,
+            &["--no-install"],
+        );
+        assert_eq!(result.err().unwrap().code(), Some("E0202"));
+        assert_eq!(invocations.len(), 1);
+    }
+
+    #[test]
+    fn record_size_asks_git_for_the_commit() {
+        let dir = test_util::contract("record-size");
+        let runner =
+            Arc::new(ScriptedRunner::default().respond("git", CommandOutput::success("1a2b3c4\n")));
+        let (args, ctx) = context(&dir, &[], runner.clone());
+        fs::create_dir_all(&ctx.wasm_folder).unwrap();
+        fs::write(&ctx.wasm_out, [0; 42]).unwrap();
+        step_record_size(&args, &ctx).unwrap();
+        let invocation = &runner.invocations()[0];
+        assert_eq!(invocation.args, ["rev-parse", "--short", "HEAD"]);
+        assert_eq!(invocation.dir.as_deref(), Some(ctx.crate_root.as_path()));
+        let history = read_history(&history_file(&ctx));
+        assert_eq!(history[0].commit.as_deref(), Some("1a2b3c4"));
+        assert_eq!(history[0].size, 42);
+    }
+
+    #[test]
+    fn build_wasm_runs_cargo_for_the_wasm_target() {
+        let dir = test_util::contract("build-wasm");
+        let moved = dir.join("elsewhere/contract.wasm");
+        let artifact = serde_json::json!({
+            "reason": "compiler-artifact",
+            "filenames": [moved],
+        });
+        let warning = serde_json::json!({
+            "reason": "compiler-message",
+            "target": { "name": "contract" },
+            "message": { "level": "warning", "message": "unused variable", "rendered": "" },
+        });
+        let runner = Arc::new(ScriptedRunner::default().respond(
+            "cargo",
+            CommandOutput::success(&format!("{}\n{}\n", warning, artifact)),
+        ));
+        let (args, mut ctx) = context(&dir, &["--release"], runner.clone());
+        step_build_wasm(&args, &mut ctx).unwrap();
+        let manifest = ctx.crate_root.join("Cargo.toml").display().to_string();
+        assert_eq!(
+            runner.invocations()[0].args,
+            [
+                format!("+{}", DEFAULT_TOOLCHAIN).as_str(),
+                "build",
+                "-Z",
+                "build-std",
+                "-Z",
+                "build-std-features=panic_immediate_abort",
+                "--target",
+                "wasm32-unknown-unknown",
+                "--message-format=json-diagnostic-rendered-ansi",
+                "--manifest-path",
+                manifest.as_str(),
+                "--release",
+            ]
+        );
+        assert_eq!(ctx.wasm_in, moved);
+        assert_eq!(*ctx.warnings.borrow(), ["contract: unused variable"]);
+    }
+
+    #[test]
+    fn build_wasm_without_build_std_uses_the_default_toolchain() {
+        let dir = test_util::contract("build-wasm-no-build-std");
+        let runner =
+            Arc::new(ScriptedRunner::default().respond("cargo", CommandOutput::success("")));
+        let (args, mut ctx) = context(
+            &dir,
+            &["--no-build-std", "--cargo-arg=--locked", "--", "--offline"],
+            runner.clone(),
+        );
+        let wasm_in = ctx.wasm_in.clone();
+        step_build_wasm(&args, &mut ctx).unwrap();
+        let args = &runner.invocations()[0].args;
+        assert_eq!(args[..3], ["build", "--target", "wasm32-unknown-unknown"]);
+        assert_eq!(args[args.len() - 2..], ["--locked", "--offline"]);
+        assert_eq!(ctx.wasm_in, wasm_in);
+    }
+
+    #[test]
+    fn failed_cargo_build_lists_the_errors() {
+        let dir = test_util::contract("build-wasm-failure");
+        let error = serde_json::json!({
+            "reason": "compiler-message",
+            "target": { "name": "contract" },
+            "message": { "level": "error", "message": "cannot find value `x`", "rendered": "" },
+        });
+        let runner = Arc::new(ScriptedRunner::default().respond(
+            "cargo",
+            CommandOutput {
+                code: Some(101),
+                stdout: error.to_string(),
+                ..CommandOutput::default()
+            },
+        ));
+        let (args, mut ctx) = context(&dir, &[], runner);
+        let err = step_build_wasm(&args, &mut ctx).err().unwrap();
+        assert_eq!(err.class(), ErrorClass::Build);
+        assert!(
+            err.to_string().contains("error: cannot find value `x`"),
+            "{}",
+            err
+        );
+    }
+
+    /// The clap error of `build <flags>`
+    fn rejected(flags: &[&str]) -> structopt::clap::Error {
+        BuildArgs::from_iter_safe(std::iter::once("build").chain(flags.iter().copied()))
+            .err()
+            .unwrap()
+    }
+
+    #[test]
+    fn unknown_flags_suggest_the_nearest_one() {
+        let err = rejected(&["--relase"]);
+        assert_eq!(err.kind, structopt::clap::ErrorKind::UnknownArgument);
+        assert!(err.message.contains("--release"), "{}", err.message);
+        let err = rejected(&["--max-sise", "10"]);
+        assert!(err.message.contains("--max-size"), "{}", err.message);
+        // Flags of cargo aren't forwarded unless after `--`.
+        assert_eq!(
+            rejected(&["--features", "debug"]).kind,
+            structopt::clap::ErrorKind::UnknownArgument
+        );
+    }
+
+    #[test]
+    fn cargo_gets_what_follows_the_separator_and_cargo_arg() {
+        let args = build_args(&[
+            "--release",
+            "--cargo-arg=--locked",
+            "--",
+            "--features",
+            "debug",
+        ]);
+        assert!(args.release);
+        assert!(args.is_release());
+        assert_eq!(args.cargo_args, ["--locked"]);
+        assert_eq!(args.extra_options, ["--features", "debug"]);
+        assert!(build_args(&["--", "--release"]).is_release());
+    }
+
+    #[test]
+    fn conflicting_flags_are_rejected() {
+        for flags in [
+            &["--workspace", "-p", "contract"][..],
+            &["--no-install", "--yes"],
+            &["--no-install", "--ci"],
+            &["--examples", "--example", "demo"],
+            &["--tests", "--examples"],
+        ] {
+            assert_eq!(
+                rejected(flags).kind,
+                structopt::clap::ErrorKind::ArgumentConflict,
+                "{:?}",
+                flags
+            );
+        }
+        assert_eq!(
+            rejected(&["--component"]).kind,
+            structopt::clap::ErrorKind::MissingRequiredArgument
+        );
+    }
+
+    #[test]
+    fn skip_opt_conflicts_with_the_wasm_opt_flags() {
+        build_args(&["--skip-opt"]).check_conflicts().unwrap();
+        build_args(&["--keep-names"]).check_conflicts().unwrap();
+        for flags in [
+            &["--skip-opt", "--keep-names"][..],
+            &["--skip-opt", "--opt-level", "z"],
+            &["--skip-opt", "--wasm-opt-timeout", "10"],
+        ] {
+            let err = build_args(flags).check_conflicts().err().unwrap();
+            assert_eq!(err.class(), ErrorClass::Usage);
+            assert!(err.to_string().contains(flags[1]), "{}", err);
+        }
+        assert!(Pipeline::for_build(&build_args(&["--skip-opt", "--keep-names"])).is_err());
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn docker_run_forwards_the_builder_flags() {
+        let dir = test_util::contract("docker-run");
+        let flags = [
+            "--docker",
+            "--docker-image",
+            "builder:dev",
+            "--release",
+            "--skip-step",
+            "wasm-opt",
+            "--skip-step",
+            "strip",
+            "--wasm-opt-timeout",
+            "30",
+            "--split-debug",
+            "--codegen-units",
+            "1",
+            "--cargo-arg=--locked",
+            "--",
+            "--features",
+            "debug",
+        ];
+        let (args, ctx) = context(&dir, &flags, runner_for(&[]));
+        let invocation = docker_run_invocation(&args, &ctx, Some((1000, 1001))).unwrap();
+        assert_eq!(invocation.program, "docker");
+        let mut expected = strings(&[
+            "run",
+            "--rm",
+            "--volume",
+            &format!("{}:/project", ctx.workspace_root().display()),
+            "--volume",
+            "iroha_wasm_pack-cargo-cache:/usr/local/cargo/registry",
+            "--workdir",
+            "/project",
+            "--user",
+            "1000:1001",
+            "--env",
+            "HOME=/tmp",
+            "builder:dev",
+            "iroha_wasm_pack",
+            "build",
+            "--ci",
+            "--no-hooks",
+            "--target-dir",
+            "/project/target/docker",
+        ]);
+        for step in STEPS
+            .iter()
+            .filter(|step| !DOCKER_STEPS.contains(&step.name))
+        {
+            expected.extend(strings(&["--skip-step", step.name]));
+        }
+        // Only the skipped steps the builder runs are passed on, the builder adds the profile
+        // overrides of `--split-debug` itself.
+        expected.extend(strings(&[
+            "--skip-step",
+            "wasm-opt",
+            "--release",
+            "--split-debug",
+            "--opt-level",
+            "s",
+            "--wasm-opt-timeout",
+            "30",
+            "--cargo-arg=--config",
+            "--cargo-arg=profile.release.codegen-units=1",
+            "--cargo-arg=--locked",
+            "--",
+            "--features",
+            "debug",
+        ]));
+        assert_eq!(invocation.args, expected);
+    }
+
+    #[test]
+    fn docker_run_passes_a_trailing_release_once() {
+        let dir = test_util::contract("docker-run-release");
+        let (args, ctx) = context(&dir, &["--docker", "--", "--release"], runner_for(&[]));
+        let invocation = docker_run_invocation(&args, &ctx, None).unwrap();
+        let releases = invocation
+            .args
+            .iter()
+            .filter(|arg| *arg == "--release")
+            .count();
+        assert_eq!(releases, 1);
+        assert_eq!(
+            invocation.args[invocation.args.len() - 2..],
+            ["--", "--release"]
+        );
+        assert!(!invocation.args.contains(&"--user".to_owned()));
+    }
+
+    #[test]
+    fn docker_build_copies_the_wasm_back() {
+        let dir = test_util::contract("docker-build");
+        let runner = runner_for(&["docker", "docker"]);
+        let (args, mut ctx) = context(&dir, &["--docker"], runner.clone());
+        let relative = ctx
+            .wasm_out
+            .strip_prefix(&ctx.target_dir)
+            .unwrap()
+            .to_owned();
+        let built = ctx.docker_target_dir().join(relative);
+        fs::create_dir_all(built.parent().unwrap()).unwrap();
+        fs::write(&built, b"\0asm\x01\0\0\0").unwrap();
+        step_docker_build(&args, &mut ctx).unwrap();
+        assert_eq!(fs::read(&ctx.wasm_out).unwrap(), b"\0asm\x01\0\0\0");
+        // The unoptimized module is optional.
+        assert!(!ctx.wasm_in.exists());
+        let invocations = runner.invocations();
+        assert_eq!(
+            invocations[0].args,
+            ["info", "--format", "{{.ServerVersion}}"]
+        );
+        assert_eq!(invocations[1].args[0], "run");
+    }
+
+    #[test]
+    fn docker_build_without_the_optimized_wasm_fails() {
+        let dir = test_util::contract("docker-build-missing");
+        let (args, ctx) = context(&dir, &["--docker"], runner_for(&[]));
+        let err = copy_back_from_docker(&args, &ctx).err().unwrap();
+        assert!(
+            err.to_string().starts_with("the builder didn't write"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn docker_build_copies_the_debug_companion_back() {
+        let dir = test_util::contract("docker-split-debug");
+        let (args, ctx) = context(&dir, &["--docker", "--split-debug"], runner_for(&[]));
+        let in_docker = |path: &Path| {
+            let built = ctx
+                .docker_target_dir()
+                .join(path.strip_prefix(&ctx.target_dir).unwrap());
+            fs::create_dir_all(built.parent().unwrap()).unwrap();
+            built
+        };
+        fs::write(in_docker(&ctx.wasm_out), b"optimized").unwrap();
+        let err = copy_back_from_docker(&args, &ctx).err().unwrap();
+        assert!(err.to_string().contains("contract.debug.wasm"), "{}", err);
+        fs::write(in_docker(&debug_companion(&ctx)), b"debug").unwrap();
+        copy_back_from_docker(&args, &ctx).unwrap();
+        assert_eq!(fs::read(debug_companion(&ctx)).unwrap(), b"debug");
+    }
+
+    #[test]
+    fn docker_build_needs_the_daemon() {
+        let dir = test_util::contract("docker-daemon");
+        let runner = Arc::new(ScriptedRunner::default().respond(
+            "docker",
+            CommandOutput::failure(1, "Cannot connect to the Docker daemon"),
+        ));
+        let (args, mut ctx) = context(&dir, &["--docker"], runner.clone());
+        let err = step_docker_build(&args, &mut ctx).err().unwrap();
+        assert_eq!(err.class(), ErrorClass::Environment);
+        assert!(err
+            .to_string()
+            .contains("Cannot connect to the Docker daemon"));
+        assert_eq!(runner.invocations().len(), 1);
+    }
+
+    /// Module with named functions and locals, the debug info binaryen keeps with `-g`
+    const NAMED: &str = r#"(module
+            (func $double (export "double") (param $x i32) (result i32)
+                local.get $x
+                local.get $x
+                i32.add))"#;
+
+    /// Context of a `contract` built from `NAMED` with `flags`, up to the split-debug step
+    fn optimized(name: &str, flags: &[&str]) -> (BuildArgs, BuildContext) {
+        let dir = test_util::contract(name);
+        let (args, ctx) = context(&dir, flags, runner_for(&[]));
+        fs::create_dir_all(&ctx.wasm_folder).unwrap();
+        fs::write(&ctx.wasm_in, wat::parse_str(NAMED).unwrap()).unwrap();
+        for step in [step_wasm_opt, step_strip, step_split_debug] {
+            step(&args, &ctx).unwrap();
+        }
+        (args, ctx)
+    }
+
+    #[test]
+    fn split_debug_deploys_the_module_of_a_normal_build() {
+        use blake2::{digest::consts::U32, Blake2b, Digest};
+        let (_, normal) = optimized("split-debug-off", &[]);
+        let (args, split) = optimized("split-debug-on", &["--split-debug"]);
+        let normal = fs::read(&normal.wasm_out).unwrap();
+        let deployed = fs::read(&split.wasm_out).unwrap();
+        let companion = fs::read(debug_companion(&split)).unwrap();
+        assert!(split.wasm_folder.join("contract.debug.wasm").is_file());
+
+        let build_id = wasm::custom_section(&deployed, "build_id")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            build_id,
+            hex::encode(Blake2b::<U32>::digest(&normal)).as_bytes()
+        );
+        assert_eq!(
+            wasm::custom_section(&companion, "build_id").unwrap(),
+            Some(build_id)
+        );
+        // Apart from the build id the deployed module is byte for byte the normal one.
+        let without_id =
+            wasm::strip_custom_sections(&deployed, &kept_sections(&args.wasm_opt)).unwrap();
+        assert_eq!(without_id, normal);
+        assert!(wasm::custom_section(&deployed, "name").unwrap().is_none());
+        assert!(wasm::custom_section(&companion, "name").unwrap().is_some());
+    }
+
+    #[test]
+    fn split_debug_without_wasm_opt_strips_the_deployed_module() {
+        let (_, ctx) = optimized("split-debug-skip-opt", &["--split-debug", "--skip-opt"]);
+        let compiled = wat::parse_str(NAMED).unwrap();
+        let deployed = fs::read(&ctx.wasm_out).unwrap();
+        let companion = fs::read(debug_companion(&ctx)).unwrap();
+        assert!(wasm::custom_section(&deployed, "name").unwrap().is_none());
+        assert!(wasm::custom_section(&companion, "name").unwrap().is_some());
+        assert_eq!(
+            wasm::retain_custom_sections(&companion, |name| name != "build_id").unwrap(),
+            compiled
+        );
+    }
+
+    #[test]
+    fn without_split_debug_no_companion_is_written() {
+        let (_, ctx) = optimized("split-debug-none", &[]);
+        assert!(!debug_companion(&ctx).exists());
+        assert!(
+            wasm::custom_section(&fs::read(&ctx.wasm_out).unwrap(), "build_id")
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn no_wait_fails_fast_while_the_lock_is_held() {
+        let dir = test_util::temp_dir("build-lock");
+        let held = BuildLock::acquire(&dir, true).unwrap();
+        let err = BuildLock::acquire(&dir, true).err().unwrap();
+        assert_eq!(err.code(), Some("E0106"));
+        assert_eq!(err.class(), ErrorClass::Environment);
+        assert!(err.to_string().contains("locked by another"), "{}", err);
+        drop(held);
+        // Dropping the lock releases it.
+        BuildLock::acquire(&dir, true).unwrap();
+    }
+
+    #[test]
+    fn the_lock_waits_for_another_handle_of_the_lock_file() {
+        use fs2::FileExt;
+        use std::{sync::mpsc, thread, time::Duration};
+        let dir = test_util::temp_dir("build-lock-wait");
+        // Another process holds the lock through its own handle, flock and LockFileEx both
+        // lock per handle, so a second handle in this process stands in for it.
+        let other = fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open(dir.join(".iroha_wasm_pack-lock"))
+            .unwrap();
+        other.lock_exclusive().unwrap();
+        assert_eq!(
+            BuildLock::acquire(&dir, true).err().unwrap().code(),
+            Some("E0106")
+        );
+        let (acquired, waited) = mpsc::channel();
+        let waiting = dir.clone();
+        let waiter = thread::spawn(move || {
+            let lock = BuildLock::acquire(&waiting, false);
+            acquired.send(lock.is_ok()).unwrap();
+        });
+        assert!(waited.recv_timeout(Duration::from_millis(300)).is_err());
+        other.unlock().unwrap();
+        assert!(waited.recv_timeout(Duration::from_secs(10)).unwrap());
+        waiter.join().unwrap();
+    }
+
+    #[test]
+    fn metadata_reports_its_document() {
+        let dir = test_util::contract("metadata-report")
+            .canonicalize()
+            .unwrap();
+        let manifest = dir.join("Cargo.toml");
+        let report = MetadataArgs::from_iter_safe([
+            "metadata",
+            "--manifest-path",
+            manifest.to_str().unwrap(),
+        ])
+        .unwrap()
+        .run()
+        .unwrap();
+        let contexts = resolve(&dir, &[]).unwrap();
+        let document = metadata_document(&dir, &contexts);
+        assert_eq!(report.data.as_ref(), Some(&document));
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&report.summary.join("\n")).unwrap(),
+            document
+        );
+    }
+
+    #[test]
+    fn history_reports_the_builds_and_the_human_lines() {
+        let dir = test_util::contract("history-report");
+        let manifest = dir.join("Cargo.toml");
+        let ctx = resolve(&dir, &[]).unwrap().remove(0);
+        for (timestamp, size) in [(1_700_000_000, 100), (1_700_000_060, 150)] {
+            let record = SizeRecord {
+                timestamp,
+                commit: Some("1a2b3c4".to_owned()),
+                artifact: "contract_optimized.wasm".to_owned(),
+                profile: "debug".to_owned(),
+                size,
+            };
+            append_history(&history_file(&ctx), &record).unwrap();
+        }
+        let history = |flags: &[&str]| {
+            let mut argv = vec!["history", "--manifest-path", manifest.to_str().unwrap()];
+            argv.extend(flags);
+            HistoryArgs::from_iter_safe(argv).unwrap().run().unwrap()
+        };
+        let report = history(&[]);
+        let data = report.data.unwrap();
+        assert_eq!(data[0]["artifact"], "contract_optimized.wasm");
+        assert_eq!(data[0]["builds"][1]["size"], 150);
+        assert_eq!(report.summary.len(), 3);
+        assert_eq!(report.summary[0], "contract_optimized.wasm");
+        assert!(report.summary[1].starts_with("  2023-11-14 22:13:20  1a2b3c4"));
+        assert!(report.summary[2].ends_with("+50"));
+
+        let report = history(&["--json"]);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&report.summary[0]).unwrap(),
+            report.data.unwrap()[0]
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! Build a contract from the `build.rs` of a crate embedding it, so the wasm is always fresh.
+//!
+//! ```no_run
+//! // build.rs of the host crate, with iroha_wasm_pack in [build-dependencies]
+//! fn main() {
+//!     iroha_wasm_pack::build_script::compile("../contract").release().run();
+//! }
+//! ```
+//!
+//! The host then embeds the optimized wasm with `include_bytes!(env!("CONTRACT_WASM"))`. The
+//! contract builds into `OUT_DIR`, outside the paths cargo watches for the host, so building it
+//! never makes the host's build script run again.
+
+use super::*;
+use std::{
+    env,
+    path::{Path, PathBuf},
+};
+
+/// Variables cargo sets for the host's build script that would leak into the contract's build
+const HOST_ENV: &[&str] = &[
+    "CARGO_ENCODED_RUSTFLAGS",
+    "CARGO_TARGET_DIR",
+    "RUSTC",
+    "RUSTC_WRAPPER",
+    "RUSTC_WORKSPACE_WRAPPER",
+    "RUSTDOC",
+    "RUSTUP_TOOLCHAIN",
+];
+
+/// Files of a project whose changes need a rebuild, besides `src/`
+const WATCHED_FILES: &[&str] = &[
+    "Cargo.toml",
+    "Cargo.lock",
+    "iroha_wasm_pack.toml",
+    "rust-toolchain",
+    "rust-toolchain.toml",
+];
+
+/// Build of the contract at `path`, relative to the host crate
+pub fn compile(path: impl AsRef<Path>) -> Compile {
+    Compile {
+        path: path.as_ref().to_path_buf(),
+        release: false,
+        skip_opt: false,
+        package: None,
+        env_name: "CONTRACT_WASM".to_owned(),
+        target_dir: None,
+    }
+}
+
+/// Options of a contract build started from a build script
+#[derive(Debug, Clone)]
+pub struct Compile {
+    path: PathBuf,
+    release: bool,
+    skip_opt: bool,
+    package: Option<String>,
+    env_name: String,
+    target_dir: Option<PathBuf>,
+}
+
+impl Compile {
+    pub fn release(mut self) -> Self {
+        self.release = true;
+        self
+    }
+
+    pub fn skip_opt(mut self, skip: bool) -> Self {
+        self.skip_opt = skip;
+        self
+    }
+
+    /// Member of a contract workspace to build
+    pub fn package(mut self, package: &str) -> Self {
+        self.package = Some(package.to_owned());
+        self
+    }
+
+    /// Variable pointing at the wasm, `CONTRACT_WASM` by default
+    pub fn env(mut self, name: &str) -> Self {
+        self.env_name = name.to_owned();
+        self
+    }
+
+    /// Build outputs of the contract, `$OUT_DIR/contract-target` by default
+    pub fn target_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.target_dir = Some(dir.into());
+        self
+    }
+
+    /// Build the contract and return its optimized wasm, panicking with the error if it fails
+    pub fn run(self) -> PathBuf {
+        match self.try_run() {
+            Ok(wasm) => wasm,
+            Err(err) => {
+                let mut message = format!("building the contract failed: {}", err);
+                let mut cause = std::error::Error::source(err.uncoded());
+                while let Some(err) = cause {
+                    message.push_str(&format!("\n  caused by: {}", err));
+                    cause = err.source();
+                }
+                if let Some(code) = err.code() {
+                    message.push_str(&format!(
+                        "\n\nFor more information about this error, try `iroha_wasm_pack explain {}`.",
+                        code
+                    ));
+                }
+                panic!("{}", message)
+            }
+        }
+    }
+
+    /// `run` returning the error
+    pub fn try_run(self) -> Result<PathBuf, Error> {
+        let host = env::var("CARGO_MANIFEST_DIR")
+            .map_err(|_| Error::usage("build_script::compile only works in a build script"))?;
+        let contract = Path::new(&host).join(&self.path);
+        println!("cargo:rerun-if-changed={}", contract.join("src").display());
+        for file in WATCHED_FILES {
+            if contract.join(file).exists() {
+                println!("cargo:rerun-if-changed={}", contract.join(file).display());
+            }
+        }
+        for name in HOST_ENV {
+            env::remove_var(name);
+        }
+        let target_dir = match self.target_dir {
+            Some(dir) => dir,
+            None => PathBuf::from(env::var("OUT_DIR")?).join("contract-target"),
+        };
+        let mut argv = vec![
+            "build".to_owned(),
+            "--ci".to_owned(),
+            // Hooks are for builds run by hand or CI, not for every build of the host.
+            "--no-hooks".to_owned(),
+            "--manifest-path".to_owned(),
+            contract.join("Cargo.toml").display().to_string(),
+            "--target-dir".to_owned(),
+            target_dir.display().to_string(),
+        ];
+        if self.skip_opt {
+            argv.push("--skip-opt".to_owned());
+        }
+        if let Some(package) = &self.package {
+            argv.extend(["--package".to_owned(), package.clone()]);
+        }
+        if self.release {
+            argv.push("--release".to_owned());
+        }
+        let options = BuildOptions::from_iter_safe(argv)?;
+        let report = build(&options)?;
+        let artifact = report
+            .artifacts
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::other("the build produced no wasm"))?;
+        println!(
+            "cargo:rustc-env={}={}",
+            self.env_name,
+            artifact.wasm.display()
+        );
+        Ok(artifact.wasm)
+    }
+}
@@ -0,0 +1,328 @@
+use super::build::cache_dir;
+use super::*;
+use serde_derive::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// Caches below the cache directory: name, subdirectory and what it holds
+const CACHES: &[(&str, &str, &str)] = &[
+    ("opt", "wasm-opt", "wasm-opt outputs by content hash"),
+    ("env", "env", "results of the toolchain and target checks"),
+    (
+        "fingerprints",
+        "fingerprints",
+        "fingerprints of the inputs of past builds",
+    ),
+];
+
+/// Everything required to configure and run the `iroha_wasm_pack cache` command.
+#[derive(Debug, StructOpt)]
+pub struct CacheArgs {
+    /// Print the result as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    #[structopt(subcommand)]
+    pub command: CacheCommand,
+}
+
+#[derive(Debug, StructOpt)]
+pub enum CacheCommand {
+    /// Location, entries and size of each cache
+    #[structopt(name = "info")]
+    Info,
+
+    /// Remove the selected caches
+    #[structopt(name = "clear")]
+    Clear {
+        /// The wasm-opt output cache
+        #[structopt(long = "opt")]
+        opt: bool,
+
+        /// The environment check cache
+        #[structopt(long = "env")]
+        env: bool,
+
+        /// The build fingerprints
+        #[structopt(long = "fingerprints")]
+        fingerprints: bool,
+
+        /// Every cache
+        #[structopt(long = "all")]
+        all: bool,
+    },
+
+    /// Remove the least recently used entries until all caches fit in `--max-size` bytes
+    #[structopt(name = "gc")]
+    Gc {
+        #[structopt(long = "max-size")]
+        max_size: u64,
+    },
+}
+
+/// A file in a cache
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    used: SystemTime,
+}
+
+/// Files below `dir`, symlinks are entries themselves and never followed
+fn entries(dir: &Path) -> Vec<Entry> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let metadata = match fs::symlink_metadata(entry.path()) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir() {
+            entries.extend(self::entries(&entry.path()));
+            continue;
+        }
+        entries.push(Entry {
+            path: entry.path(),
+            size: metadata.len(),
+            used: metadata
+                .accessed()
+                .or_else(|_| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH),
+        });
+    }
+    entries
+}
+
+#[derive(Debug, Serialize)]
+pub struct CacheInfo {
+    pub name: &'static str,
+    pub path: PathBuf,
+    pub description: &'static str,
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+/// Remove `path` without leaving `root`, a symlink is removed rather than followed
+fn remove(path: &Path, root: &Path) -> Result<(), Error> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(_) => return Ok(()),
+    };
+    let parent = path
+        .parent()
+        .and_then(|parent| fs::canonicalize(parent).ok());
+    let inside = match (parent, fs::canonicalize(root)) {
+        (Some(parent), Ok(root)) => parent.starts_with(root),
+        _ => false,
+    };
+    if !inside {
+        return Err(Error::other(format!(
+            "refusing to remove {}, it resolves outside of {}",
+            path.display(),
+            root.display()
+        )));
+    }
+    let removed = if metadata.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    removed.map_err(|err| Error::other(format!("remove {} failed", path.display())).caused_by(err))
+}
+
+/// Entries to evict, least recently used first, for the rest to fit in `max_size` bytes
+fn least_recently_used(mut entries: Vec<Entry>, max_size: u64) -> Vec<Entry> {
+    entries.sort_by_key(|entry| entry.used);
+    let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+    entries
+        .into_iter()
+        .take_while(|entry| {
+            let over = total > max_size;
+            total -= entry.size;
+            over
+        })
+        .collect()
+}
+
+/// What `cache gc` did
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Collected {
+    evicted: usize,
+    freed: u64,
+    remaining: u64,
+}
+
+/// Evict the least recently used entries of the caches below `root` down to `max_size` bytes
+fn gc(root: &Path, max_size: u64) -> Result<Collected, Error> {
+    let entries: Vec<Entry> = CACHES
+        .iter()
+        .flat_map(|(_, dir, _)| entries(&root.join(dir)))
+        .collect();
+    let mut collected = Collected {
+        remaining: entries.iter().map(|entry| entry.size).sum(),
+        ..Collected::default()
+    };
+    for entry in least_recently_used(entries, max_size) {
+        remove(&entry.path, root)?;
+        collected.remaining -= entry.size;
+        collected.freed += entry.size;
+        collected.evicted += 1;
+    }
+    Ok(collected)
+}
+
+impl RunArgs for CacheArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let root = cache_dir().ok_or_else(|| {
+            Error::other("no cache directory, set IROHA_WASM_PACK_CACHE_DIR or HOME")
+        })?;
+        let caches: Vec<CacheInfo> = CACHES
+            .iter()
+            .map(|&(name, dir, description)| {
+                let entries = entries(&root.join(dir));
+                CacheInfo {
+                    name,
+                    path: root.join(dir),
+                    description,
+                    entries: entries.len(),
+                    bytes: entries.iter().map(|entry| entry.size).sum(),
+                }
+            })
+            .collect();
+        match self.command {
+            CacheCommand::Info => {
+                if self.json {
+                    let json = serde_json::json!({ "root": root, "caches": caches });
+                    println!("{}", serde_json::to_string_pretty(&json)?);
+                    return Ok(CommandReport::default());
+                }
+                println!("{}", root.display());
+                for cache in &caches {
+                    println!(
+                        "  {:<14} {:>6} entries {:>10.1} KiB  {}",
+                        cache.name,
+                        cache.entries,
+                        cache.bytes as f64 / 1024.0,
+                        cache.description
+                    );
+                }
+            }
+            CacheCommand::Clear {
+                opt,
+                env,
+                fingerprints,
+                all,
+            } => {
+                if !(opt || env || fingerprints || all) {
+                    return Err(Error::other(
+                        "select the caches to clear: --opt, --env, --fingerprints or --all",
+                    ));
+                }
+                let selected = [opt, env, fingerprints];
+                let mut cleared = Vec::new();
+                for (cache, selected) in caches.iter().zip(selected) {
+                    if selected || all {
+                        remove(&cache.path, &root)?;
+                        cleared.push(cache.name);
+                    }
+                }
+                if self.json {
+                    println!("{}", serde_json::json!({ "cleared": cleared }));
+                } else {
+                    println!("Cleared {}", cleared.join(", "));
+                }
+            }
+            CacheCommand::Gc { max_size } => {
+                let collected = gc(&root, max_size)?;
+                if self.json {
+                    let json = serde_json::json!({
+                        "evicted": collected.evicted,
+                        "freed_bytes": collected.freed,
+                        "remaining_bytes": collected.remaining,
+                    });
+                    println!("{}", json);
+                } else {
+                    println!(
+                        "Evicted {} entries, freed {} bytes, {} bytes remain",
+                        collected.evicted, collected.freed, collected.remaining
+                    );
+                }
+            }
+        }
+        Ok(CommandReport::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use std::time::Duration;
+
+    fn entry(name: &str, size: u64, used: u64) -> Entry {
+        Entry {
+            path: PathBuf::from(name),
+            size,
+            used: SystemTime::UNIX_EPOCH + Duration::from_secs(used),
+        }
+    }
+
+    fn paths(entries: &[Entry]) -> Vec<&Path> {
+        entries.iter().map(|entry| entry.path.as_path()).collect()
+    }
+
+    #[test]
+    fn evicts_least_recently_used_until_it_fits() {
+        let entries = || {
+            vec![
+                entry("new", 10, 3),
+                entry("old", 10, 1),
+                entry("mid", 10, 2),
+            ]
+        };
+        assert_eq!(
+            paths(&least_recently_used(entries(), 10)),
+            [Path::new("old"), Path::new("mid")]
+        );
+        assert_eq!(
+            paths(&least_recently_used(entries(), 25)),
+            [Path::new("old")]
+        );
+        assert!(least_recently_used(entries(), 30).is_empty());
+        assert_eq!(least_recently_used(entries(), 0).len(), 3);
+    }
+
+    #[test]
+    fn gc_leaves_at_most_max_size() {
+        let root = test_util::temp_dir("cache-gc");
+        test_util::write_files(
+            &root,
+            &[
+                ("wasm-opt/a", "a".repeat(100).as_str()),
+                ("wasm-opt/nested/b", "b".repeat(200).as_str()),
+                ("env/c", "c".repeat(300).as_str()),
+                ("fingerprints/d", "d".repeat(400).as_str()),
+                ("other/e", "e".repeat(500).as_str()),
+            ],
+        );
+        let collected = gc(&root, 600).unwrap();
+        assert!(collected.remaining <= 600);
+        assert_eq!(collected.freed + collected.remaining, 1000);
+        let left: u64 = CACHES
+            .iter()
+            .flat_map(|(_, dir, _)| entries(&root.join(dir)))
+            .map(|entry| entry.size)
+            .sum();
+        assert_eq!(left, collected.remaining);
+        // Files outside of the caches are never counted nor removed.
+        assert!(root.join("other/e").exists());
+        assert_eq!(
+            gc(&root, 600).unwrap(),
+            Collected {
+                remaining: collected.remaining,
+                ..Collected::default()
+            }
+        );
+    }
+}
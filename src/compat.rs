@@ -0,0 +1,211 @@
+use super::wasm::HOST_MODULE;
+use super::*;
+use serde_derive::Deserialize;
+use std::path::PathBuf;
+use wasmtime::{Engine, ExternType, Module};
+
+/// Host functions of each supported Iroha release, oldest first.
+///
+/// To support a new release append its manifest, one `[[function]]` per host function.
+const MANIFESTS: &[&str] = &[
+    r#"
+release = "2.0.0-pre-rc.10"
+
+[[function]]
+name = "execute_instruction"
+params = ["i32", "i32"]
+
+[[function]]
+name = "execute_query"
+params = ["i32", "i32"]
+results = ["i32"]
+
+[[function]]
+name = "get_trigger_payload"
+results = ["i32"]
+
+[[function]]
+name = "dbg"
+params = ["i32", "i32"]
+"#,
+    r#"
+release = "2.0.0-pre-rc.11"
+
+[[function]]
+name = "execute_instruction"
+params = ["i32", "i32"]
+
+[[function]]
+name = "execute_query"
+params = ["i32", "i32"]
+results = ["i32"]
+
+[[function]]
+name = "get_trigger_payload"
+results = ["i32"]
+
+[[function]]
+name = "get_smart_contract_payload"
+results = ["i32"]
+
+[[function]]
+name = "dbg"
+params = ["i32", "i32"]
+"#,
+];
+
+/// A host function as a manifest lists it
+#[derive(Debug, Deserialize)]
+struct HostFunction {
+    #[serde(default = "host_module")]
+    module: String,
+    name: String,
+    #[serde(default)]
+    params: Vec<String>,
+    #[serde(default)]
+    results: Vec<String>,
+}
+
+fn host_module() -> String {
+    HOST_MODULE.to_owned()
+}
+
+/// Host ABI of one release
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    release: String,
+    #[serde(rename = "function")]
+    functions: Vec<HostFunction>,
+}
+
+impl Manifest {
+    fn find(&self, module: &str, name: &str) -> Option<&HostFunction> {
+        self.functions
+            .iter()
+            .find(|function| function.module == module && function.name == name)
+    }
+}
+
+fn manifests() -> Result<Vec<Manifest>, Error> {
+    MANIFESTS
+        .iter()
+        .map(|manifest| Ok(toml::from_str(manifest)?))
+        .collect()
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack compat` command.
+#[derive(Debug, StructOpt)]
+pub struct CompatArgs {
+    /// Wasm file to check
+    #[structopt(parse(from_os_str))]
+    pub wasm: PathBuf,
+
+    /// Iroha release to check against, e.g. `2.0.0-pre-rc.11`
+    #[structopt(long = "iroha", required_unless = "node-url")]
+    pub iroha: Option<String>,
+
+    /// Ask the node at this Torii URL for its version instead
+    #[structopt(long = "node-url", conflicts_with = "iroha")]
+    pub node_url: Option<String>,
+}
+
+/// Iroha version the node at the Torii `url` reports
+pub fn node_version(url: &str) -> Result<String, Error> {
+    let url = format!("{}/api_version", url.trim_end_matches('/'));
+    let version = ureq::get(&url)
+        .call()
+        .map_err(|err| Error::other(format!("GET {} failed", url)).caused_by(err))?
+        .into_string()?;
+    Ok(version.trim().trim_matches('"').to_owned())
+}
+
+impl CompatArgs {
+    /// `--iroha`, or the version the node reports
+    fn release(&self) -> Result<String, Error> {
+        match (&self.iroha, &self.node_url) {
+            (Some(release), _) => Ok(release.clone()),
+            (None, Some(url)) => node_version(url),
+            (None, None) => Err(Error::usage("either `--iroha` or `--node-url` is required")),
+        }
+    }
+}
+
+impl RunArgs for CompatArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let manifests = manifests()?;
+        let release = self.release()?;
+        let position = manifests
+            .iter()
+            .position(|manifest| manifest.release == release.trim_start_matches('v'))
+            .ok_or_else(|| {
+                let known: Vec<&str> = manifests
+                    .iter()
+                    .map(|manifest| manifest.release.as_str())
+                    .collect();
+                Error::wasm(format!(
+                    "no host ABI manifest for Iroha {}, known releases are: {}",
+                    release,
+                    known.join(", ")
+                ))
+            })?;
+        let manifest = &manifests[position];
+        let engine = Engine::default();
+        let module = Module::from_file(&engine, &self.wasm).map_err(|err| {
+            Error::wasm(format!("load {} failed", self.wasm.display())).caused_by(err)
+        })?;
+        let mut problems = Vec::new();
+        for import in module.imports() {
+            let ty = match import.ty() {
+                ExternType::Func(ty) => ty,
+                _ => continue,
+            };
+            let params: Vec<String> = ty.params().map(|param| param.to_string()).collect();
+            let results: Vec<String> = ty.results().map(|result| result.to_string()).collect();
+            let signature = |params: &[String], results: &[String]| {
+                format!("({}) -> ({})", params.join(", "), results.join(", "))
+            };
+            let name = format!("{}::{}", import.module(), import.name());
+            match manifest.find(import.module(), import.name()) {
+                Some(function) if function.params != params || function.results != results => {
+                    problems.push(format!(
+                        "`{}` is imported as {}, but Iroha {} has {}",
+                        name,
+                        signature(&params, &results),
+                        manifest.release,
+                        signature(&function.params, &function.results)
+                    ))
+                }
+                Some(_) => {}
+                None => {
+                    let newer = manifests[position + 1..]
+                        .iter()
+                        .find(|newer| newer.find(import.module(), import.name()).is_some());
+                    problems.push(match newer {
+                        Some(newer) => format!(
+                            "`{}` only exists since Iroha {}, not in {}",
+                            name, newer.release, manifest.release
+                        ),
+                        None => format!(
+                            "`{}` is not a host function of Iroha {}",
+                            name, manifest.release
+                        ),
+                    });
+                }
+            }
+        }
+        if !problems.is_empty() {
+            return Err(Error::wasm(format!(
+                "{} is incompatible with Iroha {}:\n  {}",
+                self.wasm.display(),
+                manifest.release,
+                problems.join("\n  ")
+            )));
+        }
+        println!(
+            "{} is compatible with Iroha {}",
+            self.wasm.display(),
+            manifest.release
+        );
+        Ok(CommandReport::default())
+    }
+}
@@ -0,0 +1,104 @@
+use super::wasm;
+use super::*;
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+/// Sections larger than this are summarized unless `--full` is given
+const DUMP_LIMIT: usize = 4096;
+
+/// Custom sections written by `build`, shown as text
+const TEXT_SECTIONS: &[&str] = &["iroha-version", "build_id"];
+
+/// Everything required to configure and run the `iroha_wasm_pack decode` command.
+#[derive(Debug, StructOpt)]
+pub struct DecodeArgs {
+    /// Wasm file to read
+    #[structopt(parse(from_os_str))]
+    pub path: PathBuf,
+
+    /// Show the contents of this custom section instead of listing them
+    #[structopt(long = "section")]
+    pub section: Option<String>,
+
+    /// Write the exact bytes of `--section`
+    #[structopt(long = "raw", requires = "section")]
+    pub raw: bool,
+
+    /// Write `--raw` bytes to this file instead of stdout
+    #[structopt(short = "o", long = "output", parse(from_os_str), requires = "raw")]
+    pub output: Option<PathBuf>,
+
+    /// Dump binary sections of any size
+    #[structopt(long = "full")]
+    pub full: bool,
+}
+
+/// Hex dump with offsets and the printable characters, 16 bytes a line
+fn hex_dump(bytes: &[u8]) {
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let text: String = chunk
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        println!("{:08x}  {:<47}  {}", line * 16, hex.join(" "), text);
+    }
+}
+
+impl RunArgs for DecodeArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let module = fs::read(&self.path).map_err(|err| {
+            Error::wasm(format!("read {} failed", self.path.display())).caused_by(err)
+        })?;
+        let name = match &self.section {
+            Some(name) => name,
+            None => {
+                let sections = wasm::parse(&module)?.custom_sections;
+                if sections.is_empty() {
+                    println!("{} has no custom sections", self.path.display());
+                }
+                for section in sections {
+                    println!("{:<24} {:>10} bytes", section.name, section.size);
+                }
+                return Ok(CommandReport::default());
+            }
+        };
+        let data = wasm::custom_section(&module, name)?.ok_or_else(|| {
+            Error::wasm(format!(
+                "{} has no `{}` custom section",
+                self.path.display(),
+                name
+            ))
+        })?;
+        if self.raw {
+            match &self.output {
+                Some(output) => fs::write(output, data)?,
+                None => io::stdout().lock().write_all(data)?,
+            }
+            return Ok(CommandReport::default());
+        }
+        if let Ok(json) = serde_json::from_slice::<serde_json::Value>(data) {
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        } else if TEXT_SECTIONS.contains(&name.as_str()) {
+            println!("{}", String::from_utf8_lossy(data));
+        } else if data.len() > DUMP_LIMIT && !self.full {
+            hex_dump(&data[..256]);
+            println!(
+                "... {} bytes in total, pass `--full` to dump them all or `--raw -o <file>` to save them",
+                data.len()
+            );
+        } else {
+            hex_dump(data);
+        }
+        Ok(CommandReport::default())
+    }
+}
@@ -0,0 +1,301 @@
+use super::build::{root, working_dir};
+use super::inspect::latest_artifact;
+use super::new::walk_files;
+use super::project_config::ProjectConfig;
+use super::wasm;
+use super::*;
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
+
+/// When a trigger runs, from `--filter` or the `kind` of a `--filter-file`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum FilterSpec {
+    /// Every block, before it is committed
+    PreCommit,
+    /// From `start_ms` since the unix epoch, every `period_ms` if given
+    Time {
+        start_ms: u64,
+        period_ms: Option<u64>,
+    },
+    /// Whenever an `ExecuteTrigger` instruction calls it
+    Call,
+    /// Every data event
+    Data,
+}
+
+impl FromStr for FilterSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let ms = |value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("`{}` is not a number of milliseconds", value))
+        };
+        let mut parts = s.split(':');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some("pre-commit"), None, _, _) => Ok(FilterSpec::PreCommit),
+            (Some("call"), None, _, _) => Ok(FilterSpec::Call),
+            (Some("data"), None, _, _) => Ok(FilterSpec::Data),
+            (Some("time"), Some(start), period, None) => Ok(FilterSpec::Time {
+                start_ms: ms(start)?,
+                period_ms: period.map(ms).transpose()?,
+            }),
+            _ => Err(format!(
+                "invalid filter `{}`, expected pre-commit, call, data or time:<start_ms>[:<period_ms>]",
+                s
+            )),
+        }
+    }
+}
+
+/// The trigger registered for a contract, shared by `deploy` and `genesis`
+#[derive(Debug, StructOpt)]
+pub struct TriggerArgs {
+    /// Id of the trigger, e.g. `my_trigger`
+    #[structopt(long = "trigger-id")]
+    pub trigger_id: String,
+
+    /// Account the trigger runs as, e.g. `alice@wonderland`
+    #[structopt(long = "authority")]
+    pub authority: Option<String>,
+
+    /// When the trigger runs: pre-commit, call, data or time:<start_ms>[:<period_ms>]
+    #[structopt(long = "filter", default_value = "pre-commit")]
+    pub filter: FilterSpec,
+
+    /// TOML file with the filter instead, e.g. `kind = "time"` and `start_ms = 0`
+    #[structopt(long = "filter-file", parse(from_os_str))]
+    pub filter_file: Option<PathBuf>,
+
+    /// Run the trigger this many times only, instead of indefinitely
+    #[structopt(long = "repeats")]
+    pub repeats: Option<u32>,
+}
+
+impl TriggerArgs {
+    fn filter_json(&self, authority: &str) -> Result<Value, Error> {
+        let filter = match &self.filter_file {
+            Some(path) => toml::from_str(&fs::read_to_string(path)?).map_err(|err| {
+                Error::other(format!("parse {} failed", path.display())).caused_by(err)
+            })?,
+            None => self.filter.clone(),
+        };
+        let duration = |ms: u64| json!({ "secs": ms / 1000, "nanos": (ms % 1000) * 1_000_000 });
+        Ok(match filter {
+            FilterSpec::PreCommit => json!({ "Time": "PreCommit" }),
+            FilterSpec::Time {
+                start_ms,
+                period_ms,
+            } => json!({ "Time": { "Schedule": {
+                "start": duration(start_ms),
+                "period": period_ms.map(duration),
+            } } }),
+            FilterSpec::Call => json!({ "ExecuteTrigger": {
+                "trigger_id": self.trigger_id,
+                "authority": authority,
+            } }),
+            FilterSpec::Data => json!({ "Data": "AcceptAll" }),
+        })
+    }
+
+    /// `Register<Trigger>` instruction as JSON, running `executable` as `authority`
+    pub fn register_json(&self, executable: Value, authority: &str) -> Result<Value, Error> {
+        let repeats = match self.repeats {
+            Some(times) => json!({ "Exactly": times }),
+            None => json!("Indefinitely"),
+        };
+        Ok(json!({ "Register": { "Trigger": {
+            "id": self.trigger_id,
+            "action": {
+                "executable": executable,
+                "repeats": repeats,
+                "technical_account": authority,
+                "filter": self.filter_json(authority)?,
+                "metadata": {}
+            }
+        } } }))
+    }
+}
+
+/// The wasm inline, as Iroha's JSON encodes `WasmSmartContract`
+pub fn inline_executable(module: &[u8]) -> Value {
+    use base64::Engine;
+    json!({ "Wasm": base64::engine::general_purpose::STANDARD.encode(module) })
+}
+
+/// Newest modification time of the sources of the project at `root`
+fn sources_modified(root: &Path) -> Option<SystemTime> {
+    let mut paths = vec![root.join("Cargo.toml"), root.join("Cargo.lock")];
+    paths.extend(walk_files(&root.join("src")).unwrap_or_default());
+    paths
+        .iter()
+        .filter_map(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+        .max()
+}
+
+/// The given wasm, or the optimized artifact of the current project, rebuilt if stale
+pub fn artifact(path: Option<&PathBuf>) -> Result<PathBuf, Error> {
+    if let Some(path) = path {
+        return Ok(path.clone());
+    }
+    let root = root(working_dir()?)?;
+    let stale = match latest_artifact() {
+        Ok(artifact) => {
+            let built = fs::metadata(&artifact)?.modified()?;
+            sources_modified(&root).map_or(false, |modified| modified > built)
+        }
+        Err(_) => true,
+    };
+    if stale {
+        info!("The wasm is older than the sources, building it first");
+        BuildArgs::from_iter_safe(["build", "--release"])?.run()?;
+    }
+    latest_artifact()
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack deploy` command.
+#[derive(Debug, StructOpt)]
+pub struct DeployArgs {
+    /// Wasm to deploy, defaults to the optimized wasm of the current project
+    #[structopt(parse(from_os_str))]
+    pub wasm: Option<PathBuf>,
+
+    /// Client config with the account and key pair to submit with
+    #[structopt(
+        long = "client-config",
+        parse(from_os_str),
+        default_value = "local/client.json"
+    )]
+    pub client_config: PathBuf,
+
+    /// Torii URL of the node, overriding `TORII_API_URL` of the client config
+    #[structopt(long = "node-url")]
+    pub node_url: Option<String>,
+
+    /// Node and client config of `[deploy.<env>]` in iroha_wasm_pack.toml, flags win
+    #[structopt(long = "env")]
+    pub env: Option<String>,
+
+    #[structopt(flatten)]
+    pub trigger: TriggerArgs,
+
+    /// Print the instruction instead of submitting it
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+}
+
+impl DeployArgs {
+    /// Fill the node and client config the flags leave out from `[deploy.<env>]`
+    fn apply_env(&mut self) -> Result<(), Error> {
+        let env = match &self.env {
+            Some(env) => env.clone(),
+            None => return Ok(()),
+        };
+        let project = ProjectConfig::discover(&working_dir()?)?;
+        let (target, file) = project
+            .get(|file| file.deploy.get(&env).cloned())
+            .ok_or_else(|| {
+                Error::other(format!("no [deploy.{}] in any iroha_wasm_pack.toml", env))
+            })?;
+        if self.node_url.is_none() {
+            self.node_url = target.node_url;
+        }
+        // The default client config gives way, an explicit `--client-config` doesn't.
+        let default = self.client_config == Path::new("local/client.json");
+        if let (Some(client_config), true) = (target.client_config, default) {
+            let dir = file.parent().unwrap_or_else(|| Path::new("."));
+            self.client_config = dir.join(client_config);
+        }
+        Ok(())
+    }
+
+    fn client_config(&self) -> Result<Value, Error> {
+        let mut config: Value = match fs::read_to_string(&self.client_config) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(err) => {
+                return Err(Error::other(format!(
+                    "read client config {} failed",
+                    self.client_config.display()
+                ))
+                .caused_by(err))
+            }
+        };
+        if let Some(url) = &self.node_url {
+            config["TORII_API_URL"] = json!(url);
+        }
+        Ok(config)
+    }
+}
+
+/// Error of a command that needs the Iroha crates, left out without the `iroha` feature
+pub fn without_iroha(what: &str) -> Error {
+    Error::usage(format!(
+        "{} needs iroha_wasm_pack built with the `iroha` feature",
+        what
+    ))
+}
+
+/// Submit the registration with the client `config`, waiting for the commit
+#[cfg(feature = "iroha")]
+fn submit(register: Value, config: Value) -> Result<CommandReport, Error> {
+    use iroha_client::client::Client;
+    use iroha_data_model::isi::InstructionBox;
+    let instruction: InstructionBox = serde_json::from_value(register)?;
+    let config = serde_json::from_value(config)?;
+    let client =
+        Client::new(&config).map_err(|err| Error::other("create client failed").caused_by(err))?;
+    // Rejections of the node, e.g. for the size limit or by the executor, are shown as is.
+    match client.submit_blocking(instruction) {
+        Ok(hash) => Ok(CommandReport::summary([format!(
+            "Committed transaction {}",
+            hash
+        )])),
+        Err(err) => Err(Error::other("transaction rejected").caused_by(err)),
+    }
+}
+
+#[cfg(not(feature = "iroha"))]
+fn submit(_register: Value, _config: Value) -> Result<CommandReport, Error> {
+    Err(without_iroha("submitting to a peer"))
+}
+
+impl RunArgs for DeployArgs {
+    fn run(mut self) -> Result<CommandReport, Error> {
+        self.apply_env()?;
+        let path = artifact(self.wasm.as_ref())?;
+        let module = fs::read(&path)?;
+        let config = self.client_config()?;
+        let authority = match (&self.trigger.authority, config["ACCOUNT_ID"].as_str()) {
+            (Some(authority), _) => authority.clone(),
+            (None, Some(account)) => account.to_owned(),
+            (None, None) => {
+                return Err(Error::other(
+                    "pass `--authority` or set ACCOUNT_ID in the client config",
+                ))
+            }
+        };
+        let register = self
+            .trigger
+            .register_json(inline_executable(&module), &authority)?;
+        println!(
+            "Registering trigger {} with {} ({} bytes, hash {})",
+            self.trigger.trigger_id,
+            path.display(),
+            module.len(),
+            wasm::iroha_hash(&module)
+        );
+        if self.dry_run {
+            println!("{}", serde_json::to_string_pretty(&register)?);
+            return Ok(CommandReport::default());
+        }
+        submit(register, config)
+    }
+}
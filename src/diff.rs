@@ -0,0 +1,214 @@
+use super::size::deltas;
+use super::wasm::{self, ModuleInfo};
+use super::*;
+use serde_derive::Serialize;
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+/// Everything required to configure and run the `iroha_wasm_pack diff` command.
+///
+/// Exits with 0 for identical modules and 1 for different ones, errors with the code of their class.
+#[derive(Debug, StructOpt)]
+pub struct DiffArgs {
+    /// The wasm currently in use, e.g. the deployed blob
+    #[structopt(parse(from_os_str))]
+    pub old: PathBuf,
+
+    /// The wasm replacing it
+    #[structopt(parse(from_os_str))]
+    pub new: PathBuf,
+
+    /// Print a single line per kind of change
+    #[structopt(long = "summary", conflicts_with = "json")]
+    pub summary: bool,
+
+    /// Print the differences as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+}
+
+/// Items only in the old module, only in the new one, or in both but different
+#[derive(Debug, Default, Serialize)]
+pub struct Changes {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl Changes {
+    /// Compare items by key, they changed if their values differ
+    fn between<V: PartialEq>(old: &BTreeMap<String, V>, new: &BTreeMap<String, V>) -> Self {
+        let mut changes = Changes::default();
+        for (key, value) in new {
+            match old.get(key) {
+                None => changes.added.push(key.clone()),
+                Some(old) if old != value => changes.changed.push(key.clone()),
+                Some(_) => {}
+            }
+        }
+        changes.removed = old
+            .keys()
+            .filter(|key| !new.contains_key(*key))
+            .cloned()
+            .collect();
+        changes
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "+{} -{} ~{}",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )
+    }
+
+    fn print(&self, title: &str) {
+        if self.is_empty() {
+            return;
+        }
+        println!("{}:", title);
+        for (sign, items) in [
+            ("+", &self.added),
+            ("-", &self.removed),
+            ("~", &self.changed),
+        ] {
+            for item in items {
+                println!("  {} {}", sign, item);
+            }
+        }
+    }
+}
+
+/// How two modules differ structurally
+#[derive(Debug, Serialize)]
+pub struct ModuleDiff {
+    pub identical: bool,
+    pub old_hash: String,
+    pub new_hash: String,
+    /// Whether functions are compared by name, rather than by index
+    pub named_functions: bool,
+    pub sections: BTreeMap<String, i64>,
+    pub imports: Changes,
+    pub exports: Changes,
+    pub functions: Changes,
+    pub data_segments: Changes,
+}
+
+fn read(path: &PathBuf) -> Result<Vec<u8>, Error> {
+    fs::read(path)
+        .map_err(|err| Error::other(format!("read {} failed", path.display())).caused_by(err))
+}
+
+fn imports(info: &ModuleInfo) -> BTreeMap<String, &'static str> {
+    info.imports
+        .iter()
+        .map(|import| (format!("{}::{}", import.module, import.name), import.kind))
+        .collect()
+}
+
+fn exports(info: &ModuleInfo) -> BTreeMap<String, &'static str> {
+    info.exports
+        .iter()
+        .map(|export| (export.name.clone(), export.kind))
+        .collect()
+}
+
+/// Compare the structure of two modules, without looking at instructions
+pub fn compare(old: &[u8], new: &[u8]) -> Result<ModuleDiff, Error> {
+    let (old_info, new_info) = (wasm::parse(old)?, wasm::parse(new)?);
+    let (old_size, new_size) = (wasm::analyze_size(old)?, wasm::analyze_size(new)?);
+    let sections = deltas(
+        old_size
+            .sections
+            .iter()
+            .map(|section| (section.name.as_str(), section.size)),
+        new_size
+            .sections
+            .iter()
+            .map(|section| (section.name.as_str(), section.size)),
+    );
+    let functions = |report: &wasm::SizeReport| -> BTreeMap<String, usize> {
+        report
+            .functions
+            .iter()
+            .map(|function| (function.name.clone(), function.size))
+            .collect()
+    };
+    let segments = |module: &[u8]| -> Result<BTreeMap<String, Vec<u8>>, Error> {
+        Ok(wasm::data_segments(module)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, data)| (format!("data[{}]", index), data.to_vec()))
+            .collect())
+    };
+    let (old_hash, new_hash) = (wasm::iroha_hash(old), wasm::iroha_hash(new));
+    Ok(ModuleDiff {
+        identical: old_hash == new_hash,
+        old_hash,
+        new_hash,
+        named_functions: old_size.has_names && new_size.has_names,
+        sections: sections
+            .into_iter()
+            .map(|(name, delta)| (name.to_owned(), delta))
+            .collect(),
+        imports: Changes::between(&imports(&old_info), &imports(&new_info)),
+        exports: Changes::between(&exports(&old_info), &exports(&new_info)),
+        functions: Changes::between(&functions(&old_size), &functions(&new_size)),
+        data_segments: Changes::between(&segments(old)?, &segments(new)?),
+    })
+}
+
+impl DiffArgs {
+    fn print(&self, diff: &ModuleDiff) -> Result<(), Error> {
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(diff)?);
+            return Ok(());
+        }
+        if diff.identical {
+            println!("identical, hash {}", diff.new_hash);
+            return Ok(());
+        }
+        if self.summary {
+            println!(
+                "different: {} section(s) resized, imports {}, exports {}, functions {}, data {}",
+                diff.sections.len(),
+                diff.imports.summary(),
+                diff.exports.summary(),
+                diff.functions.summary(),
+                diff.data_segments.summary()
+            );
+            return Ok(());
+        }
+        println!("{}  {}", diff.old_hash, self.old.display());
+        println!("{}  {}", diff.new_hash, self.new.display());
+        if !diff.sections.is_empty() {
+            println!("Sections:");
+            for (name, delta) in &diff.sections {
+                println!("  {:<24} {:>+9} B", name, delta);
+            }
+        }
+        diff.imports.print("Imports");
+        diff.exports.print("Exports");
+        if !diff.named_functions {
+            println!("No name section in both modules, functions are compared by index");
+        }
+        diff.functions.print("Functions");
+        diff.data_segments.print("Data segments");
+        Ok(())
+    }
+}
+
+impl RunArgs for DiffArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let diff = compare(&read(&self.old)?, &read(&self.new)?)?;
+        self.print(&diff)?;
+        Ok(CommandReport {
+            exit_code: if diff.identical { 0 } else { 1 },
+            ..CommandReport::default()
+        })
+    }
+}
@@ -0,0 +1,285 @@
+use super::build::{
+    get_rustc_sysroot, is_rust_src_in_sysroot, is_wasm32_target_in_sysroot, project_toolchain,
+    root, rustc_minor_version, rustup_add_rust_src, rustup_add_wasm_target, working_dir,
+    InstallPolicy,
+};
+use super::runner::DuctRunner;
+use super::*;
+use duct::cmd;
+use serde_derive::Serialize;
+use std::{env, fs};
+
+/// Everything required to configure and run the `iroha_wasm_pack doctor` command.
+#[derive(Debug, StructOpt)]
+pub struct DoctorArgs {
+    /// Print the probes as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    /// Install the missing components and targets found
+    #[structopt(long = "fix")]
+    pub fix: bool,
+
+    /// Don't ask before installing with `--fix`
+    #[structopt(short = "y", long = "yes", requires = "fix")]
+    pub yes: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Ok,
+    Warn,
+    Error,
+}
+
+/// A safe remediation `--fix` can apply
+#[derive(Debug, Clone)]
+enum Fix {
+    RustSrc(Option<String>),
+    WasmTarget,
+}
+
+/// Outcome of one probe of the environment
+#[derive(Debug, Serialize)]
+pub struct Probe {
+    pub name: &'static str,
+    pub status: Status,
+    pub details: String,
+    /// Command fixing the problem
+    pub remedy: Option<String>,
+    #[serde(skip)]
+    fix: Option<Fix>,
+}
+
+impl Probe {
+    fn ok(name: &'static str, details: String) -> Self {
+        Probe {
+            name,
+            status: Status::Ok,
+            details,
+            remedy: None,
+            fix: None,
+        }
+    }
+
+    fn failed(name: &'static str, status: Status, details: String, remedy: String) -> Self {
+        Probe {
+            name,
+            status,
+            details,
+            remedy: Some(remedy),
+            fix: None,
+        }
+    }
+}
+
+fn probe_cargo() -> Probe {
+    match cmd!("cargo", "--version").read() {
+        Ok(version) => Probe::ok("cargo", version),
+        Err(err) => Probe::failed(
+            "cargo",
+            Status::Error,
+            format!("cargo can't be run: {}", err),
+            "curl https://sh.rustup.rs -sSf | sh".to_owned(),
+        ),
+    }
+}
+
+/// Same requirement as the `build` step checking rustc
+fn probe_rustc() -> Probe {
+    match rustc_minor_version(&DuctRunner) {
+        Ok(minor) if minor >= 30 => Probe::ok("rustc", format!("1.{}", minor)),
+        Ok(minor) => Probe::failed(
+            "rustc",
+            Status::Error,
+            format!("Rust 1.{} is too old, 1.30.0 or higher is required", minor),
+            "rustup update".to_owned(),
+        ),
+        Err(err) => Probe::failed(
+            "rustc",
+            Status::Error,
+            err.to_string(),
+            "curl https://sh.rustup.rs -sSf | sh".to_owned(),
+        ),
+    }
+}
+
+fn probe_rustup() -> Probe {
+    match cmd!("rustup", "--version").stderr_null().read() {
+        Ok(version) => Probe::ok("rustup", version),
+        Err(_) => Probe::failed(
+            "rustup",
+            Status::Warn,
+            "rustup is missing, toolchains and components have to be installed by hand".to_owned(),
+            "curl https://sh.rustup.rs -sSf | sh".to_owned(),
+        ),
+    }
+}
+
+/// The nightly toolchain `build` uses for `-Z build-std` and its rust-src component
+fn probe_toolchain(toolchain: Option<&str>) -> Vec<Probe> {
+    let name = toolchain.unwrap_or("from the toolchain file");
+    let sysroot = match get_rustc_sysroot(&DuctRunner, toolchain) {
+        Ok(sysroot) => sysroot,
+        Err(_) => {
+            let install = toolchain.unwrap_or("<channel of the toolchain file>");
+            return vec![Probe::failed(
+                "toolchain",
+                Status::Error,
+                format!("the build toolchain ({}) is not installed", name),
+                format!("rustup toolchain install {} --component rust-src", install),
+            )];
+        }
+    };
+    let mut probes = vec![Probe::ok("toolchain", name.to_owned())];
+    if is_rust_src_in_sysroot(&sysroot) {
+        probes.push(Probe::ok("rust-src", sysroot.display().to_string()));
+    } else {
+        let mut remedy = "rustup component add rust-src".to_owned();
+        if let Some(toolchain) = toolchain {
+            remedy.push_str(&format!(" --toolchain {}", toolchain));
+        }
+        let mut probe = Probe::failed(
+            "rust-src",
+            Status::Error,
+            "rust-src is missing, `-Z build-std` can't build std".to_owned(),
+            remedy,
+        );
+        probe.fix = Some(Fix::RustSrc(toolchain.map(str::to_owned)));
+        probes.push(probe);
+    }
+    probes
+}
+
+/// The precompiled target of the default toolchain, only needed for `--no-build-std`
+fn probe_wasm_target() -> Probe {
+    match get_rustc_sysroot(&DuctRunner, None) {
+        Ok(sysroot) if is_wasm32_target_in_sysroot(&sysroot) => {
+            Probe::ok("wasm32 target", "installed".to_owned())
+        }
+        _ => {
+            let mut probe = Probe::failed(
+                "wasm32 target",
+                Status::Warn,
+                "wasm32-unknown-unknown is missing, builds with `--no-build-std` fail".to_owned(),
+                "rustup target add wasm32-unknown-unknown".to_owned(),
+            );
+            probe.fix = Some(Fix::WasmTarget);
+            probe
+        }
+    }
+}
+
+/// The bundled wasm-opt always works, a binary on PATH matters for `--wasm-opt-path`
+fn probe_wasm_opt() -> Probe {
+    match cmd!("wasm-opt", "--version").stderr_null().read() {
+        Ok(version) => Probe::ok("wasm-opt", format!("bundled, and {} on PATH", version)),
+        Err(_) => Probe::ok("wasm-opt", "bundled".to_owned()),
+    }
+}
+
+/// Several `rustc` on PATH make it unclear which one cargo runs
+fn probe_path() -> Probe {
+    let found: Vec<String> = env::var_os("PATH")
+        .map(|path| env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|dir| dir.join(format!("rustc{}", env::consts::EXE_SUFFIX)))
+        .filter(|rustc| rustc.is_file())
+        .map(|rustc| rustc.display().to_string())
+        .collect();
+    match found.len() {
+        0 => Probe::failed(
+            "PATH",
+            Status::Error,
+            "no rustc on PATH".to_owned(),
+            "source \"$HOME/.cargo/env\"".to_owned(),
+        ),
+        1 => Probe::ok("PATH", found[0].clone()),
+        _ => Probe::failed(
+            "PATH",
+            Status::Warn,
+            format!(
+                "several rustc on PATH, the first one wins: {}",
+                found.join(", ")
+            ),
+            "remove the ones not managed by rustup from PATH".to_owned(),
+        ),
+    }
+}
+
+/// The target directory of the current project can be written
+fn probe_target_dir() -> Option<Probe> {
+    let root = root(working_dir().ok()?).ok()?;
+    let target = root.join("target");
+    let marker = target.join(".iroha_wasm_pack-doctor");
+    let writable = fs::create_dir_all(&target)
+        .and_then(|()| fs::write(&marker, b""))
+        .and_then(|()| fs::remove_file(&marker));
+    Some(match writable {
+        Ok(()) => Probe::ok("target dir", target.display().to_string()),
+        Err(err) => Probe::failed(
+            "target dir",
+            Status::Error,
+            format!("{} is not writable: {}", target.display(), err),
+            format!("check the permissions of {}", target.display()),
+        ),
+    })
+}
+
+impl RunArgs for DoctorArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let toolchain = match root(working_dir()?) {
+            Ok(root) => project_toolchain(&root, None),
+            Err(_) => Some(DEFAULT_TOOLCHAIN.to_owned()),
+        };
+        let mut probes = vec![probe_cargo(), probe_rustc(), probe_rustup(), probe_path()];
+        probes.extend(probe_toolchain(toolchain.as_deref()));
+        probes.push(probe_wasm_target());
+        probes.push(probe_wasm_opt());
+        probes.extend(probe_target_dir());
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&probes)?);
+        } else {
+            for probe in &probes {
+                let icon = match probe.status {
+                    Status::Ok => "✅",
+                    Status::Warn => "⚠️ ",
+                    Status::Error => "❌",
+                };
+                println!("{} {}: {}", icon, probe.name, probe.details);
+                if let Some(remedy) = &probe.remedy {
+                    println!("     fix: {}", remedy);
+                }
+            }
+        }
+        let policy = if self.yes {
+            InstallPolicy::Always
+        } else {
+            InstallPolicy::Prompt
+        };
+        let mut errors = 0;
+        for probe in &probes {
+            let fixed = match (&probe.fix, self.fix) {
+                (Some(Fix::RustSrc(toolchain)), true) => {
+                    rustup_add_rust_src(&DuctRunner, policy, toolchain.as_deref()).is_ok()
+                }
+                (Some(Fix::WasmTarget), true) => {
+                    rustup_add_wasm_target(&DuctRunner, policy).is_ok()
+                }
+                _ => false,
+            };
+            if probe.status == Status::Error && !fixed {
+                errors += 1;
+            }
+        }
+        if errors > 0 {
+            return Err(Error::toolchain(format!(
+                "{} required check(s) failed",
+                errors
+            )));
+        }
+        Ok(CommandReport::default())
+    }
+}
@@ -0,0 +1,230 @@
+use super::*;
+use std::{
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// Everything required to configure and run the `iroha_wasm_pack encode` command.
+#[derive(Debug, StructOpt)]
+pub struct EncodeArgs {
+    /// Wasm file to encode
+    #[structopt(parse(from_os_str))]
+    pub path: PathBuf,
+
+    /// Encoding: base64 for genesis files, hex, or scale for a `WasmSmartContract` value
+    #[structopt(long = "format", possible_values = &["base64", "hex", "scale"])]
+    pub format: Format,
+
+    /// Write to this file instead of stdout
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    /// Break base64 and hex output into lines of this many characters, 0 doesn't
+    #[structopt(long = "wrap", default_value = "0")]
+    pub wrap: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Base64,
+    Hex,
+    Scale,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "base64" => Ok(Format::Base64),
+            "hex" => Ok(Format::Hex),
+            "scale" => Ok(Format::Scale),
+            _ => Err(format!(
+                "unknown format `{}`, expected base64, hex or scale",
+                s
+            )),
+        }
+    }
+}
+
+/// Writer breaking the text passing through into lines of `width` characters
+struct Wrap<W: Write> {
+    inner: W,
+    width: usize,
+    column: usize,
+}
+
+impl<W: Write> Write for Wrap<W> {
+    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize> {
+        let written = buf.len();
+        if self.width == 0 {
+            self.inner.write_all(buf)?;
+            return Ok(written);
+        }
+        while !buf.is_empty() {
+            let take = buf.len().min(self.width - self.column);
+            self.inner.write_all(&buf[..take])?;
+            self.column += take;
+            buf = &buf[take..];
+            if self.column == self.width {
+                self.inner.write_all(b"\n")?;
+                self.column = 0;
+            }
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Pass `input` to `write` in chunks, the file is never held in memory whole
+fn copy_chunks(
+    input: &mut impl Read,
+    mut write: impl FnMut(&[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut chunk = vec![0; 64 * 1024];
+    loop {
+        match input.read(&mut chunk)? {
+            0 => return Ok(()),
+            read => write(&chunk[..read])?,
+        }
+    }
+}
+
+/// Write the `len` bytes of `input` to `output` in `format`, text broken every `wrap` characters
+fn encode(
+    format: Format,
+    wrap: usize,
+    len: u64,
+    input: &mut impl Read,
+    output: &mut impl Write,
+) -> io::Result<()> {
+    use parity_scale_codec::{Compact, Encode};
+    match format {
+        Format::Scale => {
+            // `WasmSmartContract` is its bytes behind a compact length prefix.
+            output.write_all(&Compact(len).encode())?;
+            io::copy(input, output)?;
+        }
+        Format::Hex => {
+            let mut wrap = Wrap {
+                inner: output,
+                width: wrap,
+                column: 0,
+            };
+            copy_chunks(input, |chunk| wrap.write_all(hex::encode(chunk).as_bytes()))?;
+            if wrap.column != 0 || wrap.width == 0 {
+                wrap.inner.write_all(b"\n")?;
+            }
+        }
+        Format::Base64 => {
+            let mut wrap = Wrap {
+                inner: output,
+                width: wrap,
+                column: 0,
+            };
+            let mut encoder = base64::write::EncoderWriter::new(
+                &mut wrap,
+                &base64::engine::general_purpose::STANDARD,
+            );
+            io::copy(input, &mut encoder)?;
+            encoder.finish()?;
+            drop(encoder);
+            if wrap.column != 0 || wrap.width == 0 {
+                wrap.inner.write_all(b"\n")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl RunArgs for EncodeArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let open_err =
+            |err| Error::other(format!("read {} failed", self.path.display())).caused_by(err);
+        let mut input = File::open(&self.path).map_err(open_err)?;
+        let len = input.metadata().map_err(open_err)?.len();
+        // The length of a `WasmSmartContract` is a `u32`.
+        if self.format == Format::Scale && u32::try_from(len).is_err() {
+            return Err(Error::other(format!(
+                "{} is too large",
+                self.path.display()
+            )));
+        }
+        let output: Box<dyn Write> = match &self.output {
+            Some(output) => Box::new(File::create(output)?),
+            None => Box::new(io::stdout().lock()),
+        };
+        let mut output = BufWriter::new(output);
+        encode(self.format, self.wrap, len, &mut input, &mut output)?;
+        output.flush()?;
+        Ok(CommandReport::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(format: Format, wrap: usize, bytes: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        encode(format, wrap, bytes.len() as u64, &mut &*bytes, &mut output).unwrap();
+        output
+    }
+
+    fn text(format: Format, wrap: usize, bytes: &[u8]) -> String {
+        String::from_utf8(encoded(format, wrap, bytes)).unwrap()
+    }
+
+    #[test]
+    fn hex_is_lowercase_on_one_line() {
+        assert_eq!(text(Format::Hex, 0, b"\x00\xab\x10"), "00ab10\n");
+        assert_eq!(text(Format::Hex, 0, b""), "\n");
+    }
+
+    #[test]
+    fn base64_is_padded_standard_alphabet() {
+        assert_eq!(text(Format::Base64, 0, b"hello"), "aGVsbG8=\n");
+        assert_eq!(text(Format::Base64, 0, &[0xfb, 0xff]), "+/8=\n");
+    }
+
+    #[test]
+    fn wrap_breaks_lines_without_a_trailing_empty_one() {
+        assert_eq!(text(Format::Hex, 4, &[0, 1, 2]), "0001\n02\n");
+        assert_eq!(text(Format::Hex, 4, &[0, 1, 2, 3]), "0001\n0203\n");
+        assert_eq!(text(Format::Base64, 4, b"hello"), "aGVs\nbG8=\n");
+    }
+
+    #[test]
+    fn hex_spans_chunks() {
+        let bytes: Vec<u8> = (0..64 * 1024 + 3).map(|i| i as u8).collect();
+        let expected = hex::encode(&bytes)
+            .as_bytes()
+            .chunks(76)
+            .map(|line| format!("{}\n", std::str::from_utf8(line).unwrap()))
+            .collect::<String>();
+        assert_eq!(text(Format::Hex, 76, &bytes), expected);
+    }
+
+    #[test]
+    fn scale_prefixes_the_compact_length() {
+        assert_eq!(encoded(Format::Scale, 0, &[7, 8, 9]), [0x0c, 7, 8, 9]);
+        // From 64 bytes on the length takes two bytes.
+        let bytes = [1; 64];
+        let scale = encoded(Format::Scale, 0, &bytes);
+        assert_eq!(scale[..2], [0x01, 0x01]);
+        assert_eq!(scale[2..], bytes);
+    }
+
+    #[test]
+    fn scale_decodes_as_bytes() {
+        use parity_scale_codec::Decode;
+        let bytes: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        let scale = encoded(Format::Scale, 0, &bytes);
+        assert_eq!(Vec::<u8>::decode(&mut &*scale).unwrap(), bytes);
+    }
+}
@@ -0,0 +1,337 @@
+//! `IROHA_WASM_PACK_*` environment variables standing in for the flags of a command.
+//!
+//! Every long flag `--foo-bar` of a subcommand can be set with `IROHA_WASM_PACK_FOO_BAR`, the
+//! flags are read from the command's own help so new ones get a variable without any listing.
+//! The values are turned into flags before parsing, clap validates them like typed ones, and a
+//! flag given on the command line wins over its variable.
+
+use super::*;
+use std::{
+    env,
+    ffi::OsString,
+    sync::{Mutex, OnceLock},
+};
+use structopt::clap::App;
+
+/// Prefix of the variables
+pub const ENV_PREFIX: &str = "IROHA_WASM_PACK_";
+
+/// A flag of a command that a variable can set
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvOption {
+    pub long: String,
+    pub short: Option<String>,
+    /// `false` for switches, set with `true`/`false`, `1`/`0` or `yes`/`no`
+    pub takes_value: bool,
+}
+
+impl EnvOption {
+    /// The variable setting the flag, e.g. `IROHA_WASM_PACK_MAX_SIZE` for `--max-size`
+    pub fn var(&self) -> String {
+        format!(
+            "{}{}",
+            ENV_PREFIX,
+            self.long.to_uppercase().replace('-', "_")
+        )
+    }
+
+    /// Whether the flag is on the command line `args` of a subcommand with `options`.
+    ///
+    /// Values of other flags and everything after `--` don't count, in `-vp` the `p` is a
+    /// flag but in `-ofile` it is the value of `-o`.
+    fn given_in(&self, args: &[OsString], options: &[EnvOption]) -> bool {
+        let long = format!("--{}", self.long);
+        let mut value_next = false;
+        for arg in args.iter().skip(2).filter_map(|arg| arg.to_str()) {
+            if std::mem::take(&mut value_next) {
+                continue;
+            }
+            if arg == "--" {
+                return false;
+            }
+            if let Some(flag) = arg.strip_prefix("--") {
+                if arg == long || arg.starts_with(&format!("{}=", long)) {
+                    return true;
+                }
+                value_next = !flag.contains('=')
+                    && options
+                        .iter()
+                        .any(|option| option.long == flag && option.takes_value);
+                continue;
+            }
+            let cluster = match arg.strip_prefix('-') {
+                Some(cluster) => cluster,
+                None => continue,
+            };
+            for (at, c) in cluster.char_indices() {
+                let short = format!("-{}", c);
+                if self.short.as_deref() == Some(short.as_str()) {
+                    return true;
+                }
+                let takes_value = options.iter().any(|option| {
+                    option.short.as_deref() == Some(short.as_str()) && option.takes_value
+                });
+                if takes_value {
+                    // The rest of the cluster is the value, or the next argument is.
+                    value_next = at + c.len_utf8() == cluster.len();
+                    break;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Flags that were set from a variable, with it
+fn applied() -> &'static Mutex<Vec<(String, String)>> {
+    static APPLIED: OnceLock<Mutex<Vec<(String, String)>>> = OnceLock::new();
+    APPLIED.get_or_init(Mutex::default)
+}
+
+/// The flags `apply` set, with their variables
+pub fn applied_flags() -> Vec<(String, String)> {
+    applied().lock().unwrap().clone()
+}
+
+/// The variable `--long` was set from, if it was
+pub fn var_of(long: &str) -> Option<String> {
+    applied_flags()
+        .into_iter()
+        .find(|(flag, _)| flag == long)
+        .map(|(_, var)| var)
+}
+
+/// The flags of `subcommand`, from the arguments clap knows it by
+pub fn options(app: &App, subcommand: &str) -> Vec<EnvOption> {
+    let command = app.p.subcommands.iter().find(|command| {
+        command.p.meta.name == subcommand
+            || command
+                .p
+                .meta
+                .aliases
+                .iter()
+                .flatten()
+                .any(|(alias, _)| *alias == subcommand)
+    });
+    let command = match command {
+        Some(command) => command,
+        None => return Vec::new(),
+    };
+    let switches = command.p.flags.iter().map(|flag| (&flag.s, false));
+    let options = command.p.opts.iter().map(|opt| (&opt.s, true));
+    switches
+        .chain(options)
+        .filter_map(|(switched, takes_value)| match switched.long? {
+            "help" | "version" => None,
+            long => Some(EnvOption {
+                long: long.to_owned(),
+                short: switched.short.map(|short| format!("-{}", short)),
+                takes_value,
+            }),
+        })
+        .collect()
+}
+
+/// `args` with the flags set by variables added right after the subcommand
+pub fn apply(app: &App, args: Vec<OsString>) -> Result<Vec<OsString>, Error> {
+    let (args, injected) = inject(app, args, |var| env::var(var).ok())?;
+    applied().lock().unwrap().extend(injected);
+    Ok(args)
+}
+
+/// `args` with the flags of the variables `lookup` finds, and the flags with their variables
+fn inject(
+    app: &App,
+    args: Vec<OsString>,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<(Vec<OsString>, Vec<(String, String)>), Error> {
+    let subcommand = match args.get(1).and_then(|arg| arg.to_str()) {
+        Some(subcommand) if !subcommand.starts_with('-') => subcommand.to_owned(),
+        _ => return Ok((args, Vec::new())),
+    };
+    let options = options(app, &subcommand);
+    let mut injected = Vec::new();
+    for option in &options {
+        let var = option.var();
+        let value = match lookup(&var) {
+            Some(value) if !option.given_in(&args, &options) => value,
+            _ => continue,
+        };
+        let flag = if option.takes_value {
+            format!("--{}={}", option.long, value)
+        } else {
+            match value.to_lowercase().as_str() {
+                "true" | "1" | "yes" => format!("--{}", option.long),
+                "false" | "0" | "no" | "" => continue,
+                _ => {
+                    return Err(Error::usage(format!(
+                        "{}={} is not a boolean, use true or false",
+                        var, value
+                    )))
+                }
+            }
+        };
+        // Parse with this flag alone, so a bad value is blamed on its variable.
+        let mut alone = args.clone();
+        alone.insert(2, flag.clone().into());
+        if let Err(err) = app.clone().get_matches_from_safe(&alone) {
+            if app.clone().get_matches_from_safe(&args).is_ok() {
+                let reason = err.message.lines().next().unwrap_or_default().to_owned();
+                return Err(Error::usage(format!("{}: {}", var, reason)));
+            }
+        }
+        injected.push((option.long.clone(), var, flag));
+    }
+    let mut args = args;
+    let mut applied = Vec::new();
+    for (long, var, flag) in injected {
+        args.insert(2, flag.into());
+        applied.push((long, var));
+    }
+    Ok((args, applied))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(StructOpt)]
+    enum Cli {
+        #[structopt(name = "build")]
+        Build(build::BuildArgs),
+    }
+
+    /// The command line `iroha_wasm_pack <args>` with the flags of `vars`
+    fn injected(args: &[&str], vars: &[(&str, &str)]) -> Result<Vec<String>, Error> {
+        let args = std::iter::once("iroha_wasm_pack")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect();
+        let lookup = |var: &str| {
+            vars.iter()
+                .find(|(name, _)| *name == var)
+                .map(|(_, value)| value.to_string())
+        };
+        let (args, _) = inject(&Cli::clap(), args, lookup)?;
+        Ok(args
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect())
+    }
+
+    fn given(long: &str, args: &[&str]) -> bool {
+        let options = options(&Cli::clap(), "build");
+        let option = options.iter().find(|option| option.long == long).unwrap();
+        let args: Vec<OsString> = std::iter::once("iroha_wasm_pack")
+            .chain(std::iter::once("build"))
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect();
+        option.given_in(&args, &options)
+    }
+
+    #[test]
+    fn options_come_from_the_clap_arguments() {
+        let options = options(&Cli::clap(), "build");
+        let find = |long: &str| options.iter().find(|option| option.long == long);
+        assert_eq!(
+            find("yes"),
+            Some(&EnvOption {
+                long: "yes".to_owned(),
+                short: Some("-y".to_owned()),
+                takes_value: false,
+            })
+        );
+        assert_eq!(find("package").map(|option| option.takes_value), Some(true));
+        assert_eq!(find("max-size").unwrap().var(), "IROHA_WASM_PACK_MAX_SIZE");
+        assert!(find("help").is_none());
+        assert!(options(&Cli::clap(), "no-such-command").is_empty());
+    }
+
+    #[test]
+    fn boolean_variables() {
+        let set = |value| injected(&["build"], &[("IROHA_WASM_PACK_RELEASE", value)]);
+        for value in ["true", "1", "YES"] {
+            assert_eq!(
+                set(value).unwrap(),
+                ["iroha_wasm_pack", "build", "--release"]
+            );
+        }
+        for value in ["false", "0", "no", ""] {
+            assert_eq!(set(value).unwrap(), ["iroha_wasm_pack", "build"]);
+        }
+        let err = set("maybe").err().unwrap();
+        assert_eq!(err.class(), ErrorClass::Usage);
+        assert!(err.to_string().starts_with("IROHA_WASM_PACK_RELEASE=maybe"));
+    }
+
+    #[test]
+    fn path_variables() {
+        assert_eq!(
+            injected(
+                &["build"],
+                &[("IROHA_WASM_PACK_MANIFEST_PATH", "contracts/a b/Cargo.toml")]
+            )
+            .unwrap(),
+            [
+                "iroha_wasm_pack",
+                "build",
+                "--manifest-path=contracts/a b/Cargo.toml"
+            ]
+        );
+    }
+
+    #[test]
+    fn enum_variables() {
+        assert_eq!(
+            injected(&["build"], &[("IROHA_WASM_PACK_LTO", "thin")]).unwrap(),
+            ["iroha_wasm_pack", "build", "--lto=thin"]
+        );
+        let err = injected(&["build"], &[("IROHA_WASM_PACK_LTO", "medium")])
+            .err()
+            .unwrap();
+        assert_eq!(err.class(), ErrorClass::Usage);
+        assert!(err.to_string().starts_with("IROHA_WASM_PACK_LTO: "));
+    }
+
+    #[test]
+    fn size_variables() {
+        assert_eq!(
+            injected(&["build"], &[("IROHA_WASM_PACK_MAX_SIZE", "1048576")]).unwrap(),
+            ["iroha_wasm_pack", "build", "--max-size=1048576"]
+        );
+        let err = injected(&["build"], &[("IROHA_WASM_PACK_MAX_SIZE", "1MiB")])
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("IROHA_WASM_PACK_MAX_SIZE: "));
+    }
+
+    #[test]
+    fn flags_on_the_command_line_win() {
+        let vars = [("IROHA_WASM_PACK_MAX_SIZE", "10")];
+        assert_eq!(
+            injected(&["build", "--max-size", "20"], &vars).unwrap(),
+            ["iroha_wasm_pack", "build", "--max-size", "20"]
+        );
+        assert_eq!(
+            injected(&["build", "--max-size=20"], &vars).unwrap(),
+            ["iroha_wasm_pack", "build", "--max-size=20"]
+        );
+    }
+
+    #[test]
+    fn given_in_reads_flags_like_clap() {
+        assert!(given("yes", &["-y"]));
+        assert!(given("yes", &["--yes"]));
+        // Short flags in a cluster, until one taking a value.
+        assert!(!given("yes", &["-py", "y"]));
+        assert!(given("yes", &["-yp", "name"]));
+        assert!(!given("yes", &["-pyes"]));
+        assert!(given("package", &["-pname"]));
+        // Values of other flags and arguments after `--` aren't flags.
+        assert!(!given("yes", &["--toolchain", "-y"]));
+        assert!(!given("yes", &["--", "-y"]));
+        assert!(!given("release", &["--", "--release"]));
+        assert!(!given("release", &["--release-notes"]));
+    }
+}
@@ -0,0 +1,448 @@
+use super::explain;
+use serde_derive::Serialize;
+use std::{fmt, path::PathBuf, process::ExitStatus};
+
+/// Underlying cause of an error, kept so callers can walk the chain
+pub type Source = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Lines of stderr an `Error::Command` keeps
+const STDERR_EXCERPT_LINES: usize = 20;
+
+/// Everything that can go wrong in `iroha_wasm_pack`
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// The command line or a variable standing in for it is wrong
+    #[error("{message}")]
+    Usage { message: String },
+    /// `Cargo.toml`, its metadata or the layout of the project is wrong
+    #[error("{message}")]
+    Manifest {
+        message: String,
+        #[source]
+        source: Option<Source>,
+    },
+    /// The Rust toolchain misses a component or is too old
+    #[error("{message}")]
+    Toolchain {
+        message: String,
+        #[source]
+        source: Option<Source>,
+    },
+    /// An external program failed, `status` is `None` if it didn't exit normally
+    #[error(
+        "`{}` failed{}{}",
+        command_line(program, args),
+        exit_suffix(status),
+        stderr_suffix(stderr)
+    )]
+    Command {
+        program: String,
+        args: Vec<String>,
+        status: Option<ExitStatus>,
+        /// Last lines of what the program printed on stderr
+        stderr: String,
+    },
+    /// A wasm module is malformed or breaks the rules of Iroha
+    #[error("{message}")]
+    Wasm {
+        message: String,
+        #[source]
+        source: Option<Source>,
+    },
+    /// A wasm module is larger than the limit of the package
+    #[error("{} is {size} bytes, over the limit of {limit} bytes", path.display())]
+    SizeLimit {
+        path: PathBuf,
+        size: u64,
+        limit: u64,
+    },
+    /// Reading or writing a file failed
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// A library the tool uses failed, e.g. parsing JSON or TOML
+    #[error(transparent)]
+    External(Source),
+    /// Anything else, e.g. a node rejecting a transaction
+    #[error("{message}")]
+    Other {
+        message: String,
+        #[source]
+        source: Option<Source>,
+    },
+    /// An error with a stable code that `iroha_wasm_pack explain` describes
+    #[error(transparent)]
+    Coded(Coded),
+}
+
+/// `error` with a stable `code`, it reads like `error` and has the same causes
+#[derive(Debug)]
+pub struct Coded {
+    pub code: &'static str,
+    pub error: Box<Error>,
+}
+
+impl fmt::Display for Coded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for Coded {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+impl Error {
+    pub fn usage(message: impl Into<String>) -> Self {
+        Error::Usage {
+            message: message.into(),
+        }
+    }
+
+    pub fn manifest(message: impl Into<String>) -> Self {
+        Error::Manifest {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn toolchain(message: impl Into<String>) -> Self {
+        Error::Toolchain {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn wasm(message: impl Into<String>) -> Self {
+        Error::Wasm {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    pub fn other(message: impl Into<String>) -> Self {
+        Error::Other {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// `program` exiting with `status`, only the tail of `stderr` is kept
+    pub fn command(
+        program: impl Into<String>,
+        args: &[&str],
+        status: Option<ExitStatus>,
+        stderr: &str,
+    ) -> Self {
+        let lines: Vec<&str> = stderr.trim_end().lines().collect();
+        let excerpt = lines[lines.len().saturating_sub(STDERR_EXCERPT_LINES)..].join("\n");
+        Error::Command {
+            program: program.into(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            status,
+            stderr: excerpt,
+        }
+    }
+
+    /// Record what caused the error, a no-op for variants without a source
+    pub fn caused_by(mut self, cause: impl Into<Source>) -> Self {
+        match &mut self {
+            Error::Manifest { source, .. }
+            | Error::Toolchain { source, .. }
+            | Error::Wasm { source, .. }
+            | Error::Other { source, .. } => *source = Some(cause.into()),
+            _ => {}
+        }
+        self
+    }
+
+    /// Attach a stable `code`, which has to have an entry in `explain::EXPLANATIONS`
+    pub fn with_code(self, code: &'static str) -> Self {
+        debug_assert!(
+            explain::explanation(code).is_some(),
+            "no explanation for {}",
+            code
+        );
+        Error::Coded(Coded {
+            code,
+            error: Box::new(self),
+        })
+    }
+
+    /// Code of the error, if it has one
+    pub fn code(&self) -> Option<&'static str> {
+        match self {
+            Error::Coded(coded) => Some(coded.code),
+            _ => None,
+        }
+    }
+
+    /// The error without its code
+    pub fn uncoded(&self) -> &Error {
+        match self {
+            Error::Coded(coded) => coded.error.uncoded(),
+            error => error,
+        }
+    }
+
+    /// Kind of the failure, a code's explanation decides it over the variant
+    pub fn class(&self) -> ErrorClass {
+        if let Some(explanation) = self.code().and_then(explain::explanation) {
+            return explanation.class;
+        }
+        match self.uncoded() {
+            Error::Usage { .. } => ErrorClass::Usage,
+            Error::Manifest { .. } | Error::Toolchain { .. } | Error::Io(_) => {
+                ErrorClass::Environment
+            }
+            Error::Command { .. } => ErrorClass::Build,
+            Error::Wasm { .. } | Error::SizeLimit { .. } => ErrorClass::Validation,
+            _ => ErrorClass::Other,
+        }
+    }
+
+    /// The error as `--error-format json` prints it
+    pub fn diagnostic(&self) -> Diagnostic {
+        let class = self.class();
+        let code = self.code().unwrap_or_else(|| class.code());
+        let mut causes = Vec::new();
+        let mut cause = std::error::Error::source(self.uncoded());
+        while let Some(err) = cause {
+            causes.push(err.to_string());
+            cause = err.source();
+        }
+        Diagnostic {
+            code,
+            severity: "error",
+            message: self.to_string(),
+            file: match self.uncoded() {
+                Error::SizeLimit { path, .. } => Some(path.clone()),
+                _ => None,
+            },
+            causes,
+            hint: explain::explanation(code)
+                .and_then(|explanation| explanation.fixes.first().copied()),
+            exit_code: class.exit_code(),
+        }
+    }
+}
+
+/// Kind of a failure, it decides the exit code of the process
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorClass {
+    /// Wrong flags, arguments or `IROHA_WASM_PACK_*` variables, exit code 1
+    Usage,
+    /// The project, its configuration, the toolchain or the file system, exit code 2
+    Environment,
+    /// cargo, wasm-opt or another step of a build failed, exit code 3
+    Build,
+    /// A wasm module broke a rule or a limit, exit code 4
+    Validation,
+    /// Anything else, e.g. a node rejecting a transaction, exit code 5
+    Other,
+}
+
+impl ErrorClass {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorClass::Usage => 1,
+            ErrorClass::Environment => 2,
+            ErrorClass::Build => 3,
+            ErrorClass::Validation => 4,
+            ErrorClass::Other => 5,
+        }
+    }
+
+    /// Code of the errors of the class that have no more specific one
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorClass::Usage => "E0001",
+            ErrorClass::Environment => "E0002",
+            ErrorClass::Build => "E0003",
+            ErrorClass::Validation => "E0004",
+            ErrorClass::Other => "E0005",
+        }
+    }
+}
+
+/// One error for tools wrapping the binary, printed as a JSON object on stderr
+#[derive(Debug, Serialize)]
+pub struct Diagnostic {
+    pub code: &'static str,
+    pub severity: &'static str,
+    pub message: String,
+    /// File the error is about, if it names one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Messages of the underlying errors, the outermost first
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub causes: Vec<String>,
+    /// First fix `iroha_wasm_pack explain` suggests for the code
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<&'static str>,
+    pub exit_code: i32,
+}
+
+fn command_line(program: &str, args: &[String]) -> String {
+    std::iter::once(program)
+        .chain(args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn exit_suffix(status: &Option<ExitStatus>) -> String {
+    status.map_or_else(String::new, |status| format!(" with {}", status))
+}
+
+fn stderr_suffix(stderr: &str) -> String {
+    if stderr.is_empty() {
+        String::new()
+    } else {
+        format!(":\n{}", stderr)
+    }
+}
+
+macro_rules! external {
+    ($($error:ty),* $(,)?) => {$(
+        impl From<$error> for Error {
+            fn from(err: $error) -> Self {
+                Error::External(err.into())
+            }
+        }
+    )*};
+}
+
+external!(
+    anyhow::Error,
+    base64::DecodeError,
+    ctrlc::Error,
+    glob::GlobError,
+    glob::PatternError,
+    hex::FromHexError,
+    notify::Error,
+    parity_scale_codec::Error,
+    regex::Error,
+    serde_json::Error,
+    std::env::VarError,
+    std::num::ParseIntError,
+    std::str::Utf8Error,
+    std::string::FromUtf8Error,
+    std::time::SystemTimeError,
+    structopt::clap::Error,
+    syn::Error,
+    toml::de::Error,
+    toml::ser::Error,
+    toml_edit::TomlError,
+    ureq::Error,
+    wasmparser::BinaryReaderError,
+    wat::Error,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn classes_decide_codes_and_exit_codes() {
+        let cases = [
+            (Error::usage("bad flag"), "E0001", 1),
+            (Error::manifest("bad manifest"), "E0002", 2),
+            (Error::toolchain("no rustc"), "E0002", 2),
+            (
+                Error::Io(std::io::Error::from(std::io::ErrorKind::NotFound)),
+                "E0002",
+                2,
+            ),
+            (
+                Error::Command {
+                    program: "cargo".to_owned(),
+                    args: vec!["build".to_owned()],
+                    status: None,
+                    stderr: String::new(),
+                },
+                "E0003",
+                3,
+            ),
+            (Error::wasm("bad module"), "E0004", 4),
+            (Error::other("rejected"), "E0005", 5),
+        ];
+        for (err, code, exit_code) in cases {
+            let diagnostic = err.diagnostic();
+            assert_eq!(diagnostic.code, code, "{}", err);
+            assert_eq!(diagnostic.exit_code, exit_code, "{}", err);
+            assert_eq!(err.code(), None);
+        }
+    }
+
+    #[test]
+    fn a_code_overrides_the_class_of_the_variant() {
+        // A missing manifest is reported by `Error::manifest`, its code keeps it there.
+        let err = Error::other("no Cargo.toml").with_code("E0101");
+        assert_eq!(err.code(), Some("E0101"));
+        assert_eq!(err.class(), ErrorClass::Environment);
+        assert_eq!(err.to_string(), "no Cargo.toml");
+        assert!(matches!(err.uncoded(), Error::Other { .. }));
+    }
+
+    #[test]
+    fn a_code_adds_no_link_to_the_chain() {
+        let err = Error::other("no Cargo.toml")
+            .caused_by(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "not found",
+            ))
+            .with_code("E0101");
+        let mut chain = vec![err.to_string()];
+        let mut cause = std::error::Error::source(&err);
+        while let Some(err) = cause {
+            chain.push(err.to_string());
+            cause = err.source();
+        }
+        assert_eq!(chain, ["no Cargo.toml", "not found"]);
+        assert_eq!(err.diagnostic().causes, ["not found"]);
+    }
+
+    #[test]
+    fn json_diagnostic_shape() {
+        let err = Error::other("node rejected the transaction").caused_by(std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            "connection refused",
+        ));
+        assert_eq!(
+            serde_json::to_value(err.diagnostic()).unwrap(),
+            json!({
+                "code": "E0005",
+                "severity": "error",
+                "message": "node rejected the transaction",
+                "causes": ["connection refused"],
+                "hint": explain::explanation("E0005").unwrap().fixes[0],
+                "exit_code": 5,
+            })
+        );
+    }
+
+    #[test]
+    fn json_diagnostic_names_the_file_and_leaves_out_empty_fields() {
+        let err = Error::SizeLimit {
+            path: PathBuf::from("target/contract.wasm"),
+            size: 200,
+            limit: 100,
+        }
+        .with_code("E0203");
+        assert_eq!(
+            serde_json::to_value(err.diagnostic()).unwrap(),
+            json!({
+                "code": "E0203",
+                "severity": "error",
+                "message": "target/contract.wasm is 200 bytes, over the limit of 100 bytes",
+                "file": "target/contract.wasm",
+                "hint": explain::explanation("E0203").unwrap().fixes[0],
+                "exit_code": 4,
+            })
+        );
+    }
+}
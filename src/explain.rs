@@ -0,0 +1,354 @@
+use super::*;
+use error::ErrorClass;
+
+/// Code of the first of the failed post-build checks
+pub fn check_code(check: &str) -> &'static str {
+    match check {
+        "size" => "E0203",
+        "memory" => "E0204",
+        "imports" => "E0302",
+        "entrypoint" => "E0303",
+        "determinism" => "E0304",
+        _ => "E0301",
+    }
+}
+
+/// What `iroha_wasm_pack explain` prints for a code
+pub struct Explanation {
+    pub code: &'static str,
+    pub name: &'static str,
+    /// Decides the exit code of errors with the code
+    pub class: ErrorClass,
+    pub description: &'static str,
+    pub causes: &'static [&'static str],
+    pub fixes: &'static [&'static str],
+}
+
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "E0001",
+        name: "usage-error",
+        class: ErrorClass::Usage,
+        description: "The command line, or an `IROHA_WASM_PACK_*` variable standing in for a flag, is wrong. Exit code 1.",
+        causes: &["a misspelled flag or subcommand", "a value of the wrong type, e.g. a word where a number is expected", "a variable like IROHA_WASM_PACK_MAX_SIZE set to a bad value"],
+        fixes: &["iroha_wasm_pack <command> --help  # to see the flags and their values", "unset the variable named in the error"],
+    },
+    Explanation {
+        code: "E0002",
+        name: "environment-error",
+        class: ErrorClass::Environment,
+        description: "The project, its configuration, the toolchain or the file system keeps the command from running. Exit code 2.",
+        causes: &["a missing or unreadable file", "a broken Cargo.toml or iroha_wasm_pack.toml", "a missing tool"],
+        fixes: &["iroha_wasm_pack doctor  # to check the toolchain and environment", "iroha_wasm_pack config  # to see how the settings resolve"],
+    },
+    Explanation {
+        code: "E0003",
+        name: "build-error",
+        class: ErrorClass::Build,
+        description: "cargo, wasm-opt or another program the build runs failed. Exit code 3.",
+        causes: &["a compile error in the contract", "a dependency that doesn't build for wasm32-unknown-unknown"],
+        fixes: &["fix the errors cargo printed above", "iroha_wasm_pack check  # for a faster type-check"],
+    },
+    Explanation {
+        code: "E0004",
+        name: "validation-error",
+        class: ErrorClass::Validation,
+        description: "A wasm module breaks a rule or a limit of Iroha. Exit code 4.",
+        causes: &["a module too large, importing unknown functions or missing its entrypoint"],
+        fixes: &["iroha_wasm_pack verify <wasm>  # for the details of every check"],
+    },
+    Explanation {
+        code: "E0005",
+        name: "other-error",
+        class: ErrorClass::Other,
+        description: "Anything else, e.g. a node rejecting a transaction or a registry refusing a push. Exit code 5.",
+        causes: &["the node or registry the command talks to", "a network problem"],
+        fixes: &["run again with RUST_LOG=debug for more details"],
+    },
+    Explanation {
+        code: "E0101",
+        name: "manifest-missing",
+        class: ErrorClass::Environment,
+        description: "No Cargo.toml was found in the current directory or any of its parents.",
+        causes: &["running the tool outside of a project", "a typo in the path passed to `cd`"],
+        fixes: &[
+            "cd into the project directory",
+            "iroha_wasm_pack new <name>  # to create a project",
+            "iroha_wasm_pack init  # to turn the current directory into one",
+        ],
+    },
+    Explanation {
+        code: "E0102",
+        name: "package-missing",
+        class: ErrorClass::Environment,
+        description: "The Cargo.toml has no [package] to build, it is a virtual workspace manifest or incomplete.",
+        causes: &[
+            "running the tool in the root of a virtual workspace",
+            "a Cargo.toml without a [package] section",
+        ],
+        fixes: &[
+            "iroha_wasm_pack build -p <member>  # to build one member",
+            "iroha_wasm_pack build --workspace  # to build them all",
+        ],
+    },
+    Explanation {
+        code: "E0103",
+        name: "metadata-invalid",
+        class: ErrorClass::Environment,
+        description: "[package.metadata.iroha_wasm_pack] has an unknown key or a value of the wrong type.",
+        causes: &["a typo in a key", "a string where a number or boolean is expected"],
+        fixes: &[
+            "use only the keys fail-on-warnings (bool), skip (bool) and max-size (bytes)",
+            "iroha_wasm_pack config  # to see how the settings resolve",
+        ],
+    },
+    Explanation {
+        code: "E0104",
+        name: "crate-type",
+        class: ErrorClass::Environment,
+        description: "Only a cdylib compiles to a wasm module with an entrypoint Iroha can call.",
+        causes: &["a library without `crate-type`", "an example built as a binary"],
+        fixes: &["add `[lib] crate-type = [\"cdylib\"]` to Cargo.toml, or the same to the `[[example]]`"],
+    },
+    Explanation {
+        code: "E0105",
+        name: "config-file-invalid",
+        class: ErrorClass::Environment,
+        description: "An iroha_wasm_pack.toml has an unknown key, a value of the wrong type or an unsupported version.",
+        causes: &[
+            "a typo in a key, the error names the file and the line",
+            "a missing `version = 1` at the top of the file",
+            "a file written for a newer iroha_wasm_pack",
+        ],
+        fixes: &[
+            "fix the key named in the error, sections are build, packages, validation, deploy and new",
+            "iroha_wasm_pack config  # to see which files are read and how the settings resolve",
+        ],
+    },
+    Explanation {
+        code: "E0106",
+        name: "build-locked",
+        class: ErrorClass::Environment,
+        description: "Another iroha_wasm_pack process builds to the same output directory and `--no-wait` was given.",
+        causes: &[
+            "a build still running in another terminal or an editor",
+            "two CI jobs sharing a target directory",
+        ],
+        fixes: &[
+            "wait for the other build, or drop `--no-wait` to wait for it",
+            "give each job its own `--target-dir`",
+        ],
+    },
+    Explanation {
+        code: "E0201",
+        name: "rustc-too-old",
+        class: ErrorClass::Environment,
+        description: "The installed Rust is older than the tool supports, or rustc can't be run at all.",
+        causes: &["an outdated toolchain", "rustc missing from PATH"],
+        fixes: &["rustup update", "curl https://sh.rustup.rs -sSf | sh"],
+    },
+    Explanation {
+        code: "E0202",
+        name: "component-missing",
+        class: ErrorClass::Environment,
+        description: "A toolchain component the build needs is missing and wasn't installed.",
+        causes: &[
+            "`--no-install` was given",
+            "the installation prompt was declined",
+        ],
+        fixes: &[
+            "rustup component add rust-src --toolchain <toolchain>",
+            "rustup target add wasm32-unknown-unknown",
+            "iroha_wasm_pack doctor --fix",
+        ],
+    },
+    Explanation {
+        code: "E0203",
+        name: "size-exceeded",
+        class: ErrorClass::Validation,
+        description: "The optimized wasm is larger than the limit Iroha accepts for an executable.",
+        causes: &[
+            "debug info or names left in the module",
+            "dependencies pulling in formatting and panic machinery",
+            "a release profile not tuned for size",
+        ],
+        fixes: &[
+            "build with `--release`",
+            "set `opt-level = \"z\"`, `lto = true`, `codegen-units = 1` and `panic = \"abort\"` in [profile.release]",
+            "iroha_wasm_pack build --release --analyze-size  # to see what takes the space",
+            "raise `max-size` in [package.metadata.iroha_wasm_pack] if the peers allow more",
+        ],
+    },
+    Explanation {
+        code: "E0204",
+        name: "memory-exceeded",
+        class: ErrorClass::Validation,
+        description: "The module asks for more initial memory than Iroha grants a contract.",
+        causes: &["large static buffers", "a linker argument raising the initial memory"],
+        fixes: &["move large buffers to the heap", "remove `--initial-memory` from the rustflags"],
+    },
+    Explanation {
+        code: "E0301",
+        name: "invalid-module",
+        class: ErrorClass::Validation,
+        description: "The wasm file doesn't parse or validate as a wasm module.",
+        causes: &["a truncated or corrupted file", "a file that isn't wasm"],
+        fixes: &["rebuild it with `iroha_wasm_pack build`", "iroha_wasm_pack verify <wasm>  # for the details"],
+    },
+    Explanation {
+        code: "E0302",
+        name: "forbidden-import",
+        class: ErrorClass::Validation,
+        description: "The module imports a function that isn't one of the Iroha host functions, the peer can't instantiate it.",
+        causes: &[
+            "a dependency using std features like time, randomness or files",
+            "a crate built for wasm-bindgen or WASI",
+        ],
+        fixes: &[
+            "iroha_wasm_pack inspect <wasm>  # to see the imports",
+            "disable the default features of the dependency that needs them",
+            "iroha_wasm_pack compat <wasm> --iroha <version>  # to check against a release",
+        ],
+    },
+    Explanation {
+        code: "E0303",
+        name: "entrypoint-missing",
+        class: ErrorClass::Validation,
+        description: "The module exports none of the entrypoints Iroha calls.",
+        causes: &["no function has `#[entrypoint]`", "the entrypoint was removed as dead code"],
+        fixes: &[
+            "add `#[iroha_wasm::entrypoint]` to the main function",
+            "iroha_wasm_pack abi  # to compare the sources with the exports",
+        ],
+    },
+    Explanation {
+        code: "E0304",
+        name: "nondeterministic-feature",
+        class: ErrorClass::Validation,
+        description: "The module uses a wasm feature whose results can differ between peers.",
+        causes: &["threads or relaxed SIMD enabled by `--target-features`"],
+        fixes: &["drop those features from `--target-features`"],
+    },
+    Explanation {
+        code: "E0401",
+        name: "wasm-opt-failed",
+        class: ErrorClass::Build,
+        description: "wasm-opt rejected or crashed on the module.",
+        causes: &["a target feature wasm-opt doesn't know", "a bug in the bundled binaryen"],
+        fixes: &[
+            "pass the same `--target-features` to the build and to wasm-opt",
+            "try another binaryen with `--wasm-opt-path`",
+            "iroha_wasm_pack build --skip-opt  # to build without it",
+        ],
+    },
+    Explanation {
+        code: "E0402",
+        name: "wasm-opt-timeout",
+        class: ErrorClass::Build,
+        description: "wasm-opt didn't finish within `--wasm-opt-timeout`.",
+        causes: &["a huge module, e.g. from heavily monomorphized generics"],
+        fixes: &["raise `--wasm-opt-timeout`", "reduce the code size first, or build with `--skip-opt`"],
+    },
+];
+
+/// The explanation of `code`, case insensitive
+pub fn explanation(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS
+        .iter()
+        .find(|explanation| explanation.code.eq_ignore_ascii_case(code))
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack explain` command.
+#[derive(Debug, StructOpt)]
+pub struct ExplainArgs {
+    /// Code of the error, e.g. `E0203`
+    pub code: String,
+}
+
+impl RunArgs for ExplainArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let explanation = explanation(&self.code).ok_or_else(|| {
+            let codes: Vec<&str> = EXPLANATIONS
+                .iter()
+                .map(|explanation| explanation.code)
+                .collect();
+            Error::usage(format!(
+                "no error code `{}`, known codes are: {}",
+                self.code,
+                codes.join(", ")
+            ))
+        })?;
+        println!("{} {}\n", explanation.code, explanation.name);
+        println!("{}\n", explanation.description);
+        println!(
+            "Class: {:?}, exit code {}\n",
+            explanation.class,
+            explanation.class.exit_code()
+        );
+        println!("Common causes:");
+        for cause in explanation.causes {
+            println!("  - {}", cause);
+        }
+        println!("\nHow to fix it:");
+        for fix in explanation.fixes {
+            println!("  {}", fix);
+        }
+        Ok(CommandReport::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+
+    #[test]
+    fn every_code_in_the_sources_is_explained() {
+        let code = regex::Regex::new(r#""(E\d{4})""#).unwrap();
+        let sources: Vec<String> = fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("src"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "rs"))
+            .map(|path| fs::read_to_string(path).unwrap())
+            .collect();
+        assert!(sources.len() > 2);
+        let used: std::collections::BTreeSet<&str> = sources
+            .iter()
+            .flat_map(|source| code.captures_iter(source))
+            .map(|captures| captures.get(1).unwrap().as_str())
+            .collect();
+        assert!(!used.is_empty());
+        let missing: Vec<&str> = used
+            .into_iter()
+            .filter(|code| explanation(code).is_none())
+            .collect();
+        assert!(missing.is_empty(), "no explanation for {:?}", missing);
+    }
+
+    #[test]
+    fn failed_checks_are_explained() {
+        for check in [
+            "size",
+            "memory",
+            "imports",
+            "entrypoint",
+            "determinism",
+            "other",
+        ] {
+            assert!(explanation(check_code(check)).is_some(), "{}", check);
+        }
+    }
+
+    #[test]
+    fn codes_are_unique_and_in_order() {
+        let codes: Vec<&str> = EXPLANATIONS
+            .iter()
+            .map(|explanation| explanation.code)
+            .collect();
+        let mut sorted = codes.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+        assert_eq!(codes, sorted);
+        assert_eq!(explanation("e0101").map(|found| found.code), Some("E0101"));
+    }
+}
@@ -0,0 +1,127 @@
+//! `--forward-unknown`, forwarding the arguments a command doesn't know to cargo like earlier
+//! releases did.
+//!
+//! Everything from the first unknown flag on is moved behind a `--`, so clap hands it to cargo
+//! instead of failing with a suggestion.
+
+use super::*;
+use std::ffi::OsString;
+use structopt::clap::App;
+
+/// The escape hatch flag
+pub const FORWARD_UNKNOWN: &str = "--forward-unknown";
+
+/// `args` with a `--` before the first flag the subcommand doesn't know, if it was given
+/// `--forward-unknown`
+pub fn forward_unknown(app: &App, mut args: Vec<OsString>) -> Vec<OsString> {
+    let subcommand = match args.get(1).and_then(|arg| arg.to_str()) {
+        Some(subcommand) if args.iter().any(|arg| arg == FORWARD_UNKNOWN) => subcommand.to_owned(),
+        _ => return args,
+    };
+    let options = env_overrides::options(app, &subcommand);
+    let mut value_next = false;
+    for i in 2..args.len() {
+        let arg = args[i].to_string_lossy().into_owned();
+        if arg == "--" {
+            break;
+        }
+        if std::mem::take(&mut value_next) || !arg.starts_with('-') {
+            continue;
+        }
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, _)) => (flag, true),
+            None => (arg.as_str(), false),
+        };
+        let option = options.iter().find(|option| {
+            flag.strip_prefix("--") == Some(option.long.as_str())
+                || option
+                    .short
+                    .as_deref()
+                    .map_or(false, |short| flag.starts_with(short))
+        });
+        match option {
+            // `-pname` carries its value, `-p name` and `--package name` don't.
+            Some(option) => {
+                value_next = option.takes_value
+                    && !inline_value
+                    && (flag.starts_with("--") || flag.len() == 2)
+            }
+            None if matches!(flag, "--help" | "-h") => {}
+            None => {
+                args.insert(i, "--".into());
+                break;
+            }
+        }
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(StructOpt)]
+    enum Cli {
+        #[structopt(name = "build")]
+        Build(build::BuildArgs),
+    }
+
+    fn forwarded(args: &[&str]) -> Vec<String> {
+        let args = std::iter::once("iroha_wasm_pack")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect();
+        forward_unknown(&Cli::clap(), args)
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn unknown_flags_move_behind_the_separator() {
+        assert_eq!(
+            forwarded(&["build", FORWARD_UNKNOWN, "--release", "--features", "debug"]),
+            [
+                "iroha_wasm_pack",
+                "build",
+                FORWARD_UNKNOWN,
+                "--release",
+                "--",
+                "--features",
+                "debug"
+            ]
+        );
+    }
+
+    #[test]
+    fn values_of_known_flags_stay() {
+        assert_eq!(
+            forwarded(&[
+                "build",
+                FORWARD_UNKNOWN,
+                "-p",
+                "--odd-name",
+                "-pname",
+                "--locked"
+            ]),
+            [
+                "iroha_wasm_pack",
+                "build",
+                FORWARD_UNKNOWN,
+                "-p",
+                "--odd-name",
+                "-pname",
+                "--",
+                "--locked"
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_moves_without_the_flag_or_after_a_separator() {
+        let args = ["build", "--features", "debug"];
+        assert_eq!(forwarded(&args)[1..], args);
+        let args = ["build", FORWARD_UNKNOWN, "--", "--features"];
+        assert_eq!(forwarded(&args)[1..], args);
+    }
+}
@@ -0,0 +1,267 @@
+use super::deploy::{artifact, inline_executable, TriggerArgs};
+use super::new::relative_path;
+use super::*;
+use serde_json::{json, Value};
+use std::{env::current_dir, fs, path::PathBuf};
+
+/// Everything required to configure and run the `iroha_wasm_pack genesis` command.
+#[derive(Debug, StructOpt)]
+pub struct GenesisArgs {
+    /// Wasm of the trigger, defaults to the optimized wasm of the current project
+    #[structopt(parse(from_os_str))]
+    pub wasm: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pub trigger: TriggerArgs,
+
+    /// Embed the wasm as base64 instead of referencing its path
+    #[structopt(long = "inline")]
+    pub inline: bool,
+
+    /// Print a whole minimal genesis instead of the instruction
+    #[structopt(long = "full", conflicts_with = "merge-into")]
+    pub full: bool,
+
+    /// Write to a file instead of stdout
+    #[structopt(long = "out", parse(from_os_str))]
+    pub out: Option<PathBuf>,
+
+    /// Add the trigger to this genesis file, replacing one with the same id
+    #[structopt(long = "merge-into", parse(from_os_str), conflicts_with = "out")]
+    pub merge_into: Option<PathBuf>,
+}
+
+/// Decode `instruction` like Iroha reads the genesis
+#[cfg(feature = "iroha")]
+fn check_instruction(instruction: &Value) -> Result<(), Error> {
+    use iroha_data_model::isi::InstructionBox;
+    match serde_json::from_value::<InstructionBox>(instruction.clone()) {
+        Ok(_) => Ok(()),
+        Err(err) => Err(
+            Error::other("the trigger entry doesn't match Iroha's genesis format").caused_by(err),
+        ),
+    }
+}
+
+/// Without the `iroha` feature there is no data model to check the entry against
+#[cfg(not(feature = "iroha"))]
+fn check_instruction(_instruction: &Value) -> Result<(), Error> {
+    Ok(())
+}
+
+impl GenesisArgs {
+    /// Where the genesis is written, the wasm path is relative to its directory
+    fn destination(&self) -> Option<&PathBuf> {
+        self.merge_into.as_ref().or(self.out.as_ref())
+    }
+
+    /// The wasm referenced relative to the genesis file, as Iroha resolves it
+    fn path_executable(&self, wasm: &PathBuf) -> Result<Value, Error> {
+        let wasm = current_dir()?.join(wasm);
+        let base = match self.destination().and_then(|file| file.parent()) {
+            Some(dir) => current_dir()?.join(dir),
+            None => current_dir()?,
+        };
+        let path = relative_path(&base, &wasm);
+        Ok(json!({ "Wasm": path.display().to_string() }))
+    }
+
+    /// Insert `register` into the first transaction, replacing a trigger with the same id
+    fn merge(&self, genesis: &mut Value, register: Value) -> Result<(), Error> {
+        let instructions = genesis
+            .pointer_mut("/transactions/0/isi")
+            .and_then(Value::as_array_mut)
+            .ok_or_else(|| Error::other("the genesis has no `transactions[0].isi` list"))?;
+        let same_trigger = |instruction: &Value| {
+            instruction.pointer("/Register/Trigger/id") == Some(&json!(self.trigger.trigger_id))
+        };
+        match instructions
+            .iter_mut()
+            .find(|instruction| same_trigger(instruction))
+        {
+            Some(existing) => *existing = register,
+            None => instructions.push(register),
+        }
+        Ok(())
+    }
+}
+
+impl RunArgs for GenesisArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let path = artifact(self.wasm.as_ref())?;
+        let module = fs::read(&path)?;
+        let authority = match &self.trigger.authority {
+            Some(authority) => authority.clone(),
+            None => {
+                return Err(Error::other(
+                    "pass the account the trigger runs as with `--authority`",
+                ))
+            }
+        };
+        // Iroha decodes the inline form, a mistake in the entry fails here rather than at peer startup.
+        let inline = self
+            .trigger
+            .register_json(inline_executable(&module), &authority)?;
+        check_instruction(&inline)?;
+        let register = if self.inline {
+            inline
+        } else {
+            self.trigger
+                .register_json(self.path_executable(&path)?, &authority)?
+        };
+        let output = if let Some(existing) = &self.merge_into {
+            let mut genesis: Value = serde_json::from_str(&fs::read_to_string(existing)?)?;
+            self.merge(&mut genesis, register)?;
+            genesis
+        } else if self.full {
+            json!({ "transactions": [{ "isi": [register] }] })
+        } else {
+            register
+        };
+        let json = serde_json::to_string_pretty(&output)? + "\n";
+        match self.destination() {
+            Some(file) => {
+                fs::write(file, json)?;
+                info!(
+                    "Wrote trigger {} to {}",
+                    self.trigger.trigger_id,
+                    file.display()
+                );
+            }
+            None => print!("{}", json),
+        }
+        Ok(CommandReport::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use std::path::Path;
+
+    const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+    /// `genesis` of `dir/target/contract.wasm` with `flags`, the written JSON
+    fn genesis(dir: &Path, flags: &[&str]) -> Result<Value, Error> {
+        let wasm = dir.join("target/contract.wasm");
+        fs::create_dir_all(wasm.parent().unwrap()).unwrap();
+        fs::write(&wasm, MODULE).unwrap();
+        let out = dir.join("config/genesis.json");
+        fs::create_dir_all(out.parent().unwrap()).unwrap();
+        let mut args = vec![
+            "genesis".to_owned(),
+            wasm.display().to_string(),
+            "--trigger-id".to_owned(),
+            "mint_rose".to_owned(),
+        ];
+        args.extend(flags.iter().map(|flag| flag.to_string()));
+        if !flags.contains(&"--merge-into") {
+            args.extend(["--out".to_owned(), out.display().to_string()]);
+        }
+        GenesisArgs::from_iter_safe(args).unwrap().run()?;
+        Ok(serde_json::from_str(&fs::read_to_string(out).unwrap()).unwrap())
+    }
+
+    #[test]
+    fn inline_embeds_the_wasm() {
+        let dir = test_util::temp_dir("genesis-inline");
+        let register = genesis(&dir, &["--authority", "alice@wonderland", "--inline"]).unwrap();
+        let trigger = &register["Register"]["Trigger"];
+        assert_eq!(trigger["id"], "mint_rose");
+        assert_eq!(trigger["action"]["executable"], inline_executable(MODULE));
+        assert_eq!(trigger["action"]["technical_account"], "alice@wonderland");
+        assert_eq!(trigger["action"]["repeats"], "Indefinitely");
+        assert_eq!(trigger["action"]["filter"], json!({ "Time": "PreCommit" }));
+    }
+
+    #[test]
+    fn path_is_relative_to_the_genesis() {
+        let dir = test_util::temp_dir("genesis-path");
+        let register = genesis(&dir, &["--authority", "alice@wonderland"]).unwrap();
+        assert_eq!(
+            register["Register"]["Trigger"]["action"]["executable"],
+            json!({ "Wasm": "../target/contract.wasm" })
+        );
+    }
+
+    #[test]
+    fn filter_and_repeats() {
+        let dir = test_util::temp_dir("genesis-filter");
+        let flags = [
+            "--authority",
+            "alice@wonderland",
+            "--filter",
+            "time:1500:250",
+            "--repeats",
+            "3",
+        ];
+        let action = genesis(&dir, &flags).unwrap()["Register"]["Trigger"]["action"].clone();
+        assert_eq!(action["repeats"], json!({ "Exactly": 3 }));
+        assert_eq!(
+            action["filter"],
+            json!({ "Time": { "Schedule": {
+                "start": { "secs": 1, "nanos": 500_000_000 },
+                "period": { "secs": 0, "nanos": 250_000_000 },
+            } } })
+        );
+        let flags = ["--authority", "alice@wonderland", "--filter", "call"];
+        let action = genesis(&dir, &flags).unwrap()["Register"]["Trigger"]["action"].clone();
+        assert_eq!(
+            action["filter"],
+            json!({ "ExecuteTrigger": {
+                "trigger_id": "mint_rose",
+                "authority": "alice@wonderland",
+            } })
+        );
+    }
+
+    #[test]
+    fn full_wraps_the_instruction() {
+        let dir = test_util::temp_dir("genesis-full");
+        let full = genesis(&dir, &["--authority", "alice@wonderland", "--full"]).unwrap();
+        let isi = full["transactions"][0]["isi"].as_array().unwrap();
+        assert_eq!(isi.len(), 1);
+        assert_eq!(isi[0]["Register"]["Trigger"]["id"], "mint_rose");
+    }
+
+    #[test]
+    fn merge_replaces_the_same_trigger() {
+        let dir = test_util::temp_dir("genesis-merge");
+        let existing = json!({ "transactions": [{ "isi": [
+            { "Register": { "NewDomain": { "id": "wonderland" } } },
+            { "Register": { "Trigger": { "id": "mint_rose", "action": {} } } },
+        ] }] });
+        test_util::write_files(
+            &dir,
+            &[("config/genesis.json", existing.to_string().as_str())],
+        );
+        let path = dir.join("config/genesis.json").display().to_string();
+        let flags = [
+            "--authority",
+            "alice@wonderland",
+            "--merge-into",
+            path.as_str(),
+        ];
+        let merged = genesis(&dir, &flags).unwrap();
+        let isi = merged["transactions"][0]["isi"].as_array().unwrap();
+        assert_eq!(isi.len(), 2);
+        assert_eq!(isi[0], existing["transactions"][0]["isi"][0]);
+        assert_eq!(isi[1]["Register"]["Trigger"]["id"], "mint_rose");
+        assert_eq!(
+            isi[1]["Register"]["Trigger"]["action"]["executable"],
+            json!({ "Wasm": "../target/contract.wasm" })
+        );
+
+        test_util::write_files(&dir, &[("config/genesis.json", "{}")]);
+        let err = genesis(&dir, &flags).err().unwrap();
+        assert!(err.to_string().contains("transactions[0].isi"), "{}", err);
+    }
+
+    #[test]
+    fn authority_is_required() {
+        let dir = test_util::temp_dir("genesis-authority");
+        let err = genesis(&dir, &[]).err().unwrap();
+        assert!(err.to_string().contains("--authority"), "{}", err);
+    }
+}
@@ -0,0 +1,184 @@
+use super::wasm;
+use super::*;
+use std::{fs, io::Read, path::PathBuf, str::FromStr};
+
+/// Everything required to configure and run the `iroha_wasm_pack hash` command.
+#[derive(Debug, StructOpt)]
+pub struct HashArgs {
+    /// Wasm files to hash, `-` reads one from stdin
+    #[structopt(parse(from_os_str), required_unless = "check")]
+    pub paths: Vec<PathBuf>,
+
+    /// Also print this digest, prefixed with its name: sha256 or blake2b
+    #[structopt(long = "algorithm", number_of_values = 1, possible_values = &["sha256", "blake2b"])]
+    pub algorithm: Vec<Algorithm>,
+
+    /// Verify the `hash  filename` lines of a file printed by this command
+    #[structopt(long = "check", parse(from_os_str), conflicts_with = "paths")]
+    pub check: Option<PathBuf>,
+}
+
+/// Digests besides the one Iroha uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha256,
+    Blake2b,
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(Algorithm::Sha256),
+            "blake2b" => Ok(Algorithm::Blake2b),
+            _ => Err(format!(
+                "unknown algorithm `{}`, expected sha256 or blake2b",
+                s
+            )),
+        }
+    }
+}
+
+impl Algorithm {
+    fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Blake2b => "blake2b",
+        }
+    }
+
+    fn digest(self, bytes: &[u8]) -> String {
+        use blake2::{digest::consts::U32, Blake2b, Digest};
+        match self {
+            Algorithm::Sha256 => hex::encode(sha2::Sha256::digest(bytes)),
+            Algorithm::Blake2b => hex::encode(Blake2b::<U32>::digest(bytes)),
+        }
+    }
+}
+
+fn read(path: &PathBuf) -> Result<Vec<u8>, Error> {
+    let mut bytes = Vec::new();
+    let read = if path.as_os_str() == "-" {
+        std::io::stdin().read_to_end(&mut bytes).map(|_| ())
+    } else {
+        fs::read(path).map(|read| bytes = read)
+    };
+    match read {
+        Ok(()) => Ok(bytes),
+        Err(err) => Err(Error::other(format!("read {} failed", path.display())).caused_by(err)),
+    }
+}
+
+/// Verify each `[algorithm:]hash  filename` line, like `sha256sum --check`, returning how
+/// many didn't match
+fn check(list: &PathBuf) -> Result<usize, Error> {
+    let list = String::from_utf8(read(list)?)?;
+    let mut failed = 0;
+    for line in list.lines().filter(|line| !line.trim().is_empty()) {
+        let (expected, path) = match line.split_once("  ") {
+            Some(entry) => entry,
+            None => return Err(Error::other(format!("malformed line `{}`", line))),
+        };
+        let module = read(&PathBuf::from(path))?;
+        let actual = match expected.split_once(':') {
+            Some((algorithm, _)) => format!(
+                "{}:{}",
+                algorithm,
+                algorithm
+                    .parse::<Algorithm>()
+                    .map_err(Error::other)?
+                    .digest(&module)
+            ),
+            None => wasm::iroha_hash(&module),
+        };
+        if actual.eq_ignore_ascii_case(expected) {
+            println!("{}: OK", path);
+        } else {
+            println!("{}: FAILED", path);
+            failed += 1;
+        }
+    }
+    Ok(failed)
+}
+
+impl RunArgs for HashArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        if let Some(list) = &self.check {
+            // A mismatch is an answer rather than an error, like the exit code 1 of `diff`.
+            return match check(list)? {
+                0 => Ok(CommandReport::default()),
+                failed => {
+                    eprintln!("{} computed hash(es) did not match", failed);
+                    Ok(CommandReport {
+                        exit_code: 1,
+                        ..CommandReport::default()
+                    })
+                }
+            };
+        }
+        for path in &self.paths {
+            let module = read(path)?;
+            println!("{}  {}", wasm::iroha_hash(&module), path.display());
+            for algorithm in &self.algorithm {
+                println!(
+                    "{}:{}  {}",
+                    algorithm.name(),
+                    algorithm.digest(&module),
+                    path.display()
+                );
+            }
+        }
+        Ok(CommandReport::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    #[test]
+    fn check_counts_the_mismatches() {
+        let dir = test_util::temp_dir("hash-check");
+        let module = dir.join("contract.wasm");
+        fs::write(&module, b"\0asm\x01\0\0\0").unwrap();
+        let bytes = fs::read(&module).unwrap();
+        let list = dir.join("hashes");
+        fs::write(
+            &list,
+            format!(
+                "{}  {}\nsha256:{}  {}\n",
+                wasm::iroha_hash(&bytes),
+                module.display(),
+                Algorithm::Sha256.digest(&bytes),
+                module.display()
+            ),
+        )
+        .unwrap();
+        assert_eq!(check(&list).unwrap(), 0);
+        fs::write(
+            &list,
+            format!("blake2b:{}  {}\n", "00".repeat(32), module.display()),
+        )
+        .unwrap();
+        assert_eq!(check(&list).unwrap(), 1);
+        fs::write(&list, "no separator\n").unwrap();
+        assert!(check(&list).is_err());
+    }
+
+    #[test]
+    fn mismatches_exit_with_one() {
+        let dir = test_util::temp_dir("hash-check-exit");
+        let module = dir.join("contract.wasm");
+        fs::write(&module, b"\0asm\x01\0\0\0").unwrap();
+        let list = dir.join("hashes");
+        fs::write(
+            &list,
+            format!("sha256:{}  {}\n", "00".repeat(32), module.display()),
+        )
+        .unwrap();
+        let args = HashArgs::from_iter_safe(["hash", "--check", list.to_str().unwrap()]).unwrap();
+        assert_eq!(args.run().unwrap().exit_code, 1);
+    }
+}
@@ -0,0 +1,153 @@
+use super::new::{
+    join_workspace, parse_edition, suggest_name, validate_name, DependencyArgs, Scaffold, Template,
+    WorkspaceMode, DEFAULT_EDITION, TEMPLATE_NAMES,
+};
+use super::*;
+use std::{
+    env::current_dir,
+    path::{Path, PathBuf},
+};
+
+/// Everything required to configure and run the `iroha_wasm_pack init` command.
+#[derive(Debug, StructOpt)]
+pub struct InitArgs {
+    /// Existing directory to turn into a project
+    #[structopt(default_value = ".", parse(from_os_str))]
+    pub path: PathBuf,
+
+    /// Package name, defaults to the directory name
+    #[structopt(long = "name")]
+    pub name: Option<String>,
+
+    /// Kind of project to create: trigger, executor or smartcontract
+    #[structopt(long = "template", default_value = "trigger", possible_values = TEMPLATE_NAMES)]
+    pub template: Template,
+
+    /// Overwrite an existing Cargo.toml and src/lib.rs
+    #[structopt(long = "force")]
+    pub force: bool,
+
+    /// Don't generate .cargo/config.toml
+    #[structopt(long = "no-cargo-config")]
+    pub no_cargo_config: bool,
+
+    /// Rust edition of the project: 2021 or 2024
+    #[structopt(long = "edition", default_value = DEFAULT_EDITION, parse(try_from_str = parse_edition))]
+    pub edition: String,
+
+    #[structopt(flatten)]
+    pub deps: DependencyArgs,
+
+    /// Inside a cargo workspace: add the project to its members, or keep it standalone
+    #[structopt(long = "workspace", default_value = "add", possible_values = &["add", "standalone"])]
+    pub workspace: WorkspaceMode,
+}
+
+impl InitArgs {
+    /// `--name`, or the name of `dir`, checked like the name of `new`
+    fn package_name(&self, dir: &Path) -> Result<String, Error> {
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => match dir.canonicalize()?.file_name() {
+                Some(name) => name.to_string_lossy().into_owned(),
+                None => {
+                    return Err(Error::other(format!(
+                        "can't derive a package name from {}, pass `--name`",
+                        dir.display()
+                    )))
+                }
+            },
+        };
+        if let Err(reason) = validate_name(&name) {
+            return Err(Error::other(format!(
+                "{}, pass `--name {}` or another valid name",
+                reason,
+                suggest_name(&name)
+            )));
+        }
+        Ok(name)
+    }
+}
+
+impl RunArgs for InitArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let dir = current_dir()?.join(&self.path);
+        if !dir.is_dir() {
+            return Err(Error::other(format!(
+                "{} is not a directory",
+                dir.display()
+            )));
+        }
+        let name = self.package_name(&dir)?;
+        let scaffold = Scaffold {
+            dir,
+            name,
+            template: self.template,
+            source: self.deps.source(),
+            edition: self.edition.clone(),
+            size_budget: self.template.default_size_budget(),
+            package_keys: Vec::new(),
+            defines: Vec::new(),
+            sample: None,
+            bare: false,
+            lib_name: None,
+            iroha_rc: self.deps.iroha_rc(),
+        };
+        if !self.force {
+            for file in ["Cargo.toml", "src/lib.rs"] {
+                if scaffold.dir.join(file).exists() {
+                    return Err(Error::other(format!(
+                        "{} already exists, pass `--force` to overwrite it",
+                        scaffold.dir.join(file).display()
+                    )));
+                }
+            }
+        }
+        scaffold.write_cargo_xml()?;
+        scaffold.write_entrypoint()?;
+        scaffold.write_tests()?;
+        if !self.no_cargo_config {
+            scaffold.write_cargo_config()?;
+        }
+        scaffold.merge_gitignore()?;
+        join_workspace(&scaffold.dir, self.workspace)?;
+        self.deps.check_ref_exists();
+        Ok(CommandReport {
+            summary: vec![format!("Pinned Iroha dependencies to {}", scaffold.source)],
+            files: vec![scaffold.dir],
+            ..CommandReport::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    fn init_args(flags: &[&str]) -> InitArgs {
+        InitArgs::from_iter_safe(std::iter::once("init").chain(flags.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn package_name_comes_from_the_directory() {
+        let dir = test_util::temp_dir("init-name").join("my-contract");
+        std::fs::create_dir(&dir).unwrap();
+        assert_eq!(init_args(&[]).package_name(&dir).unwrap(), "my-contract");
+        assert_eq!(
+            init_args(&["--name", "other"]).package_name(&dir).unwrap(),
+            "other"
+        );
+    }
+
+    #[test]
+    fn invalid_package_names_are_rejected() {
+        let dir = test_util::temp_dir("init-invalid").join("1st contract");
+        std::fs::create_dir(&dir).unwrap();
+        let err = init_args(&[]).package_name(&dir).unwrap_err();
+        assert!(err.to_string().contains("invalid character"));
+        assert!(err.to_string().contains("`--name contract_1st_contract`"));
+        let err = init_args(&["--name", "fn"]).package_name(&dir).unwrap_err();
+        assert!(err.to_string().contains("keyword"));
+    }
+}
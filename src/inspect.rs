@@ -0,0 +1,141 @@
+use super::build::{root, target_dir, working_dir, workspace_root};
+use super::wasm::{self, ModuleInfo};
+use super::*;
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+/// Everything required to configure and run the `iroha_wasm_pack inspect` command.
+#[derive(Debug, StructOpt)]
+pub struct InspectArgs {
+    /// Wasm file, defaults to the most recent optimized artifact of the current project
+    #[structopt(parse(from_os_str))]
+    pub path: Option<PathBuf>,
+
+    /// Print the module as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    /// Only show the imports
+    #[structopt(long = "imports-only", conflicts_with = "exports-only")]
+    pub imports_only: bool,
+
+    /// Only show the exports
+    #[structopt(long = "exports-only")]
+    pub exports_only: bool,
+}
+
+/// Most recently written `*_optimized.wasm` of the current project
+pub fn latest_artifact() -> Result<PathBuf, Error> {
+    let root = root(working_dir()?)?;
+    let target_root = workspace_root(&root)?.unwrap_or(root);
+    let wasm_target = target_dir(None, &target_root).join("wasm32-unknown-unknown");
+    let mut latest: Option<(std::time::SystemTime, PathBuf)> = None;
+    for profile in fs::read_dir(&wasm_target).into_iter().flatten().flatten() {
+        for file in fs::read_dir(profile.path()).into_iter().flatten().flatten() {
+            let path = file.path();
+            let is_artifact = path.file_name().map_or(false, |name| {
+                name.to_string_lossy().ends_with("_optimized.wasm")
+            });
+            if !is_artifact {
+                continue;
+            }
+            let modified = file.metadata()?.modified()?;
+            if latest.as_ref().map_or(true, |(time, _)| modified > *time) {
+                latest = Some((modified, path));
+            }
+        }
+    }
+    match latest {
+        Some((_, path)) => Ok(path),
+        None => Err(Error::other(format!(
+            "no optimized wasm in {}, run `iroha_wasm_pack build` or pass a path",
+            wasm_target.display()
+        ))),
+    }
+}
+
+fn print_imports(info: &ModuleInfo) {
+    let mut modules: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for import in &info.imports {
+        modules
+            .entry(import.module.as_str())
+            .or_default()
+            .push(format!("{:<7} {}", import.kind, import.name));
+    }
+    println!("Imports:");
+    for (module, items) in modules {
+        println!("  {}", module);
+        for item in items {
+            println!("    {}", item);
+        }
+    }
+}
+
+fn print_exports(info: &ModuleInfo) {
+    println!("Exports:");
+    for export in &info.exports {
+        println!("  {:<7} {}", export.kind, export.name);
+    }
+}
+
+fn print_limits(title: &str, unit: &str, limits: &[wasm::Limits]) {
+    println!("{}:", title);
+    for (index, limit) in limits.iter().enumerate() {
+        let maximum = limit
+            .maximum
+            .map_or("unbounded".to_owned(), |maximum| maximum.to_string());
+        println!("  {}: {} {}, max {}", index, limit.initial, unit, maximum);
+    }
+}
+
+impl RunArgs for InspectArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => latest_artifact()?,
+        };
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                return Err(Error::other(format!("read {} failed", path.display())).caused_by(err))
+            }
+        };
+        let info = wasm::parse(&bytes)?;
+        if self.json {
+            let json = if self.imports_only {
+                serde_json::json!({ "imports": info.imports })
+            } else if self.exports_only {
+                serde_json::json!({ "exports": info.exports })
+            } else {
+                serde_json::to_value(&info)?
+            };
+            println!("{}", serde_json::to_string_pretty(&json)?);
+            return Ok(CommandReport::default());
+        }
+        if self.imports_only {
+            print_imports(&info);
+            return Ok(CommandReport::default());
+        }
+        if self.exports_only {
+            print_exports(&info);
+            return Ok(CommandReport::default());
+        }
+        println!("{} ({} bytes)", path.display(), bytes.len());
+        print_imports(&info);
+        print_exports(&info);
+        print_limits("Memories", "pages", &info.memories);
+        print_limits("Tables", "elements", &info.tables);
+        println!("Custom sections:");
+        for section in &info.custom_sections {
+            println!("  {:<24} {:>9} B", section.name, section.size);
+        }
+        let mut totals: BTreeMap<&str, usize> = BTreeMap::new();
+        for section in &info.sections {
+            *totals.entry(section.name.as_str()).or_default() += section.size;
+        }
+        println!("Sections:");
+        for (name, size) in totals {
+            println!("  {:<24} {:>9} B", name, size);
+        }
+        Ok(CommandReport::default())
+    }
+}
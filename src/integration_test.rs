@@ -0,0 +1,453 @@
+use super::build::{root, working_dir};
+use super::deploy::{artifact, inline_executable, without_iroha, FilterSpec, TriggerArgs};
+use super::*;
+use duct::cmd;
+use serde_derive::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    fs,
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// What `integration-test` sets up, does and asserts, `tests/integration.toml` by default
+#[derive(Debug, Deserialize)]
+struct Spec {
+    trigger: Option<TriggerSpec>,
+    #[serde(default)]
+    submit: Vec<Submit>,
+    #[serde(default)]
+    expect: Vec<Expectation>,
+}
+
+/// The trigger the contract is registered as in the genesis
+#[derive(Debug, Deserialize)]
+struct TriggerSpec {
+    id: String,
+    authority: Option<String>,
+    /// Same syntax as `--filter` of `deploy`
+    #[serde(default = "pre_commit")]
+    filter: String,
+    repeats: Option<u32>,
+}
+
+fn pre_commit() -> String {
+    "pre-commit".to_owned()
+}
+
+/// A transaction submitted once the peer is up, its instructions as JSON
+#[derive(Debug, Deserialize)]
+#[cfg_attr(not(feature = "iroha"), allow(dead_code))]
+struct Submit {
+    instructions: String,
+}
+
+/// State asserted after the transactions are committed
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+#[cfg_attr(not(feature = "iroha"), allow(dead_code))]
+enum Expectation {
+    Asset {
+        asset: String,
+        quantity: u32,
+    },
+    Metadata {
+        account: String,
+        key: String,
+        /// The value as JSON, e.g. `{"String": "done"}`
+        value: String,
+    },
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack integration-test` command.
+#[derive(Debug, StructOpt)]
+pub struct IntegrationTestArgs {
+    /// Wasm to test, defaults to the optimized wasm of the current project
+    #[structopt(parse(from_os_str))]
+    pub wasm: Option<PathBuf>,
+
+    /// Test spec
+    #[structopt(
+        long = "spec",
+        parse(from_os_str),
+        default_value = "tests/integration.toml"
+    )]
+    pub spec: PathBuf,
+
+    /// Run the peer in a docker container instead of the local `iroha` binary
+    #[structopt(long = "docker")]
+    pub docker: bool,
+
+    /// Image of the peer with `--docker`
+    #[structopt(long = "image", default_value = "hyperledger/iroha2:2.0.0-pre-rc.11")]
+    pub image: String,
+
+    /// Peer binary without `--docker`
+    #[structopt(long = "iroha-bin", default_value = "iroha")]
+    pub iroha_bin: String,
+
+    /// Seconds to wait for the peer to come up
+    #[structopt(long = "timeout", default_value = "60")]
+    pub timeout: u64,
+
+    /// Leave the peer running after the test
+    #[structopt(long = "keep-alive")]
+    pub keep_alive: bool,
+}
+
+/// A running peer
+enum Peer {
+    Process(Child),
+    Container(String),
+}
+
+/// The peer of a test, stopped when dropped unless kept alive.
+///
+/// An interrupt makes the test return early, so the container or the process is removed
+/// by the drop like after a finished test.
+struct PeerGuard {
+    peer: Peer,
+    log: PathBuf,
+    keep_alive: bool,
+}
+
+impl PeerGuard {
+    /// Whether the peer exited on its own
+    fn exited(&mut self) -> bool {
+        match &mut self.peer {
+            Peer::Process(child) => child.try_wait().ok().flatten().is_some(),
+            Peer::Container(name) => cmd!("docker", "inspect", "-f", "{{.State.Running}}", name)
+                .stderr_null()
+                .read()
+                .map_or(true, |running| running.trim() != "true"),
+        }
+    }
+
+    /// Last lines of the peer log
+    fn log_tail(&self) -> String {
+        if let Peer::Container(name) = &self.peer {
+            let _ = cmd!("docker", "logs", name)
+                .stderr_to_stdout()
+                .stdout_path(&self.log)
+                .unchecked()
+                .run();
+        }
+        let log = fs::read_to_string(&self.log).unwrap_or_default();
+        let lines: Vec<&str> = log.lines().collect();
+        lines[lines.len().saturating_sub(40)..].join("\n")
+    }
+}
+
+impl Drop for PeerGuard {
+    fn drop(&mut self) {
+        if self.keep_alive {
+            return;
+        }
+        match &mut self.peer {
+            Peer::Process(child) => {
+                let _ = child.kill();
+                let _ = child.wait();
+            }
+            Peer::Container(name) => {
+                let _ = cmd!("docker", "rm", "-f", name.as_str())
+                    .stdout_null()
+                    .stderr_null()
+                    .run();
+            }
+        }
+    }
+}
+
+/// A port nothing listens on right now
+fn free_port() -> Result<u16, Error> {
+    Ok(TcpListener::bind("127.0.0.1:0")?.local_addr()?.port())
+}
+
+fn read_json(path: &Path) -> Result<Value, Error> {
+    match fs::read_to_string(path) {
+        Ok(json) => Ok(serde_json::from_str(&json)?),
+        Err(err) => Err(Error::other(format!(
+            "read {} failed, error = {}, create the local environment with `iroha_wasm_pack new --with-local-env`",
+            path.display(),
+            err
+        ))),
+    }
+}
+
+impl IntegrationTestArgs {
+    /// Genesis of `local/` with the contract registered as the trigger of the spec
+    fn genesis(&self, local: &Path, spec: &Spec, module: &[u8]) -> Result<Value, Error> {
+        let mut genesis = read_json(&local.join("genesis.json"))?;
+        let trigger = match &spec.trigger {
+            Some(trigger) => trigger,
+            None => return Ok(genesis),
+        };
+        let authority = trigger
+            .authority
+            .clone()
+            .unwrap_or_else(|| "alice@wonderland".to_owned());
+        let args = TriggerArgs {
+            trigger_id: trigger.id.clone(),
+            authority: Some(authority.clone()),
+            filter: trigger.filter.parse::<FilterSpec>().map_err(Error::other)?,
+            filter_file: None,
+            repeats: trigger.repeats,
+        };
+        let register = args.register_json(inline_executable(module), &authority)?;
+        let instructions = genesis
+            .pointer_mut("/transactions/0/isi")
+            .and_then(Value::as_array_mut)
+            .ok_or_else(|| Error::other("local/genesis.json has no `transactions[0].isi` list"))?;
+        // The scaffolded trigger runs the artifact by path, the one under test replaces it.
+        instructions.retain(|instruction| instruction.pointer("/Register/Trigger").is_none());
+        instructions.push(register);
+        Ok(genesis)
+    }
+
+    fn start(&self, dir: &Path, ports: (u16, u16)) -> Result<PeerGuard, Error> {
+        let log = dir.join("peer.log");
+        let peer = if self.docker {
+            let name = format!("iroha_wasm_pack_{}_{}", std::process::id(), ports.0);
+            cmd!(
+                "docker",
+                "run",
+                "-d",
+                "--name",
+                &name,
+                "--network",
+                "host",
+                "-v",
+                format!("{}:/config", dir.display()),
+                "-w",
+                "/config",
+                "-e",
+                "IROHA2_CONFIG_PATH=/config/config.json",
+                "-e",
+                "IROHA2_GENESIS_PATH=/config/genesis.json",
+                &self.image,
+                "--submit-genesis"
+            )
+            .stdout_null()
+            .run()
+            .map_err(|err| {
+                Error::other(format!("docker run {} failed", self.image)).caused_by(err)
+            })?;
+            Peer::Container(name)
+        } else {
+            let output = fs::File::create(&log)?;
+            let child = Command::new(&self.iroha_bin)
+                .arg("--submit-genesis")
+                .current_dir(dir)
+                .env("IROHA2_CONFIG_PATH", dir.join("config.json"))
+                .env("IROHA2_GENESIS_PATH", dir.join("genesis.json"))
+                .stdout(Stdio::from(output.try_clone()?))
+                .stderr(Stdio::from(output))
+                .spawn()
+                .map_err(|err| {
+                    Error::other(format!(
+                        "start {} failed, error = {}, put the peer binary on PATH or pass `--docker`",
+                        self.iroha_bin, err
+                    ))
+                })?;
+            Peer::Process(child)
+        };
+        Ok(PeerGuard {
+            peer,
+            log,
+            keep_alive: self.keep_alive,
+        })
+    }
+
+    /// Wait until Torii answers, failing with the peer log if it doesn't in time
+    fn wait_ready(&self, peer: &mut PeerGuard, api_url: &str) -> Result<(), Error> {
+        let started = Instant::now();
+        let status = format!("{}/status", api_url);
+        while started.elapsed() < Duration::from_secs(self.timeout) {
+            if interrupt::requested() {
+                return Err(interrupt::interrupted());
+            }
+            if peer.exited() {
+                return Err(Error::other(format!(
+                    "the peer exited during startup, its log:\n{}",
+                    peer.log_tail()
+                )));
+            }
+            if ureq::get(&status).call().is_ok() {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(500));
+        }
+        Err(Error::other(format!(
+            "the peer didn't come up in {}s, its log:\n{}",
+            self.timeout,
+            peer.log_tail()
+        )))
+    }
+}
+
+/// Check one expectation against the state of the peer
+#[cfg(feature = "iroha")]
+fn check(
+    client: &iroha_client::client::Client,
+    expectation: &Expectation,
+) -> Result<Option<String>, Error> {
+    use iroha_data_model::prelude::*;
+    let query_err = |err| Error::other("query failed").caused_by(err);
+    Ok(match expectation {
+        Expectation::Asset { asset, quantity } => {
+            let id: AssetId = asset.parse().map_err(|err| {
+                Error::other(format!("invalid asset id `{}`", asset)).caused_by(err.to_string())
+            })?;
+            let actual = client
+                .request(FindAssetQuantityById::new(id))
+                .map_err(query_err)?;
+            let expected = NumericValue::U32(*quantity);
+            (actual != expected)
+                .then(|| format!("{} holds {}, expected {}", asset, actual, expected))
+        }
+        Expectation::Metadata {
+            account,
+            key,
+            value,
+        } => {
+            let id: AccountId = account.parse().map_err(|err| {
+                Error::other(format!("invalid account id `{}`", account)).caused_by(err.to_string())
+            })?;
+            let name: Name = key.parse().map_err(|err| {
+                Error::other(format!("invalid metadata key `{}`", key)).caused_by(err.to_string())
+            })?;
+            let expected: Value = serde_json::from_str(value)?;
+            let actual = client
+                .request(FindAccountKeyValueByIdAndKey::new(id, name))
+                .map_err(query_err)?;
+            let actual = serde_json::to_value(&actual)?;
+            (actual != expected).then(|| {
+                format!(
+                    "{} of {} is {}, expected {}",
+                    key, account, actual, expected
+                )
+            })
+        }
+    })
+}
+
+/// Submit the instructions of `spec` with the client `config`, returns the failed expectations
+#[cfg(feature = "iroha")]
+fn submit_and_check(spec: &Spec, config: Value, peer: &PeerGuard) -> Result<Vec<String>, Error> {
+    use iroha_client::client::Client;
+    use iroha_data_model::isi::InstructionBox;
+    let client = Client::new(&serde_json::from_value(config)?)
+        .map_err(|err| Error::other("create client failed").caused_by(err))?;
+    for (index, submit) in spec.submit.iter().enumerate() {
+        let instructions: Vec<InstructionBox> = serde_json::from_str(&submit.instructions)
+            .map_err(|err| {
+                Error::other(format!("submit[{}] is not a list of instructions", index))
+                    .caused_by(err)
+            })?;
+        if let Err(err) = client.submit_all_blocking(instructions) {
+            return Err(Error::other(format!(
+                "submit[{}] was rejected: {:#}\npeer log:\n{}",
+                index,
+                err,
+                peer.log_tail()
+            )));
+        }
+    }
+    let mut failures = Vec::new();
+    for expectation in &spec.expect {
+        if let Some(failure) = check(&client, expectation)? {
+            failures.push(failure);
+        }
+    }
+    Ok(failures)
+}
+
+#[cfg(not(feature = "iroha"))]
+fn submit_and_check(_spec: &Spec, _config: Value, _peer: &PeerGuard) -> Result<Vec<String>, Error> {
+    Err(without_iroha("`integration-test`"))
+}
+
+impl RunArgs for IntegrationTestArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        if !cfg!(feature = "iroha") {
+            return Err(without_iroha("`integration-test`"));
+        }
+        let root = root(working_dir()?)?;
+        let spec_path = root.join(&self.spec);
+        let spec: Spec = match fs::read_to_string(&spec_path) {
+            Ok(spec) => toml::from_str(&spec).map_err(|err| {
+                Error::other(format!("parse {} failed", spec_path.display())).caused_by(err)
+            })?,
+            Err(err) => {
+                return Err(
+                    Error::other(format!("read {} failed", spec_path.display())).caused_by(err)
+                )
+            }
+        };
+        let path = artifact(self.wasm.as_ref())?;
+        let module = fs::read(&path)?;
+        let local = root.join("local");
+
+        let dir = root
+            .join("target")
+            .join("integration-test")
+            .join(std::process::id().to_string());
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        let (api_port, p2p_port) = (free_port()?, free_port()?);
+        let api_url = format!("http://127.0.0.1:{}", api_port);
+        let mut config = read_json(&local.join("config.json"))?;
+        let p2p_addr = format!("127.0.0.1:{}", p2p_port);
+        config["TORII"]["P2P_ADDR"] = json!(p2p_addr);
+        config["TORII"]["API_URL"] = json!(format!("127.0.0.1:{}", api_port));
+        config["SUMERAGI"]["TRUSTED_PEERS"][0]["address"] = json!(p2p_addr);
+        config["KURA"]["BLOCK_STORE_PATH"] = json!("./storage");
+        fs::write(
+            dir.join("config.json"),
+            serde_json::to_string_pretty(&config)?,
+        )?;
+        let genesis = self.genesis(&local, &spec, &module)?;
+        fs::write(
+            dir.join("genesis.json"),
+            serde_json::to_string_pretty(&genesis)?,
+        )?;
+        let mut client_config = read_json(&local.join("client.json"))?;
+        client_config["TORII_API_URL"] = json!(api_url);
+        fs::write(
+            dir.join("client.json"),
+            serde_json::to_string_pretty(&client_config)?,
+        )?;
+
+        println!("Starting a peer on {} in {}", api_url, dir.display());
+        // Declared first to outlive the peer, a second interrupt during the teardown stops
+        // the process.
+        let _listening = interrupt::listen();
+        let mut peer = self.start(&dir, (api_port, p2p_port))?;
+        self.wait_ready(&mut peer, &api_url)?;
+        let failures = submit_and_check(&spec, client_config, &peer)?;
+        if interrupt::requested() {
+            return Err(interrupt::interrupted());
+        }
+        if self.keep_alive {
+            println!(
+                "The peer keeps running on {}, talk to it with `iroha_client_cli --config {}`",
+                api_url,
+                dir.join("client.json").display()
+            );
+        }
+        if !failures.is_empty() {
+            return Err(Error::other(format!(
+                "{} of {} expectation(s) failed:\n  {}\npeer log:\n{}",
+                failures.len(),
+                spec.expect.len(),
+                failures.join("\n  "),
+                peer.log_tail()
+            )));
+        }
+        println!("{} expectation(s) passed", spec.expect.len());
+        Ok(CommandReport::default())
+    }
+}
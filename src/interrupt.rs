@@ -0,0 +1,60 @@
+//! Ctrl-C as the commands see it.
+//!
+//! The binary owns the signal handler and hands every interrupt to [`request`]. A command that
+//! has to tear down what it started listens while it runs and winds down once [`requested`] is
+//! set, the drop guards of what it started do the rest.
+
+use super::*;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+static LISTENING: AtomicUsize = AtomicUsize::new(0);
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// While it lives an interrupt is left to the command instead of stopping the process
+#[must_use]
+pub struct Listening(());
+
+impl Drop for Listening {
+    fn drop(&mut self) {
+        LISTENING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn listen() -> Listening {
+    LISTENING.fetch_add(1, Ordering::SeqCst);
+    Listening(())
+}
+
+/// Record an interrupt, `false` if the process should stop right away because no command
+/// listens or this is the second one
+pub fn request() -> bool {
+    LISTENING.load(Ordering::SeqCst) > 0 && !REQUESTED.swap(true, Ordering::SeqCst)
+}
+
+/// Whether the command should wind down
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// What a command returns when it wound down after an interrupt
+pub fn interrupted() -> Error {
+    Error::other("interrupted")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_listening_command_gets_the_first_interrupt() {
+        assert!(!request());
+        assert!(!requested());
+        let listening = listen();
+        assert!(request());
+        assert!(requested());
+        // A second Ctrl-C stops a command that hangs while winding down.
+        assert!(!request());
+        drop(listening);
+        assert_eq!(LISTENING.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,141 @@
+//! Build, check and ship Iroha smart contracts compiled to wasm.
+//!
+//! The `iroha_wasm_pack` binary is a thin command line front end over this library, other tools
+//! can drive the same pipeline without shelling out:
+//!
+//! ```no_run
+//! use iroha_wasm_pack::{build, BuildOptions};
+//! use structopt::StructOpt;
+//!
+//! let options = BuildOptions::from_iter_safe(["build", "--release"])?;
+//! for artifact in build(&options)?.artifacts {
+//!     println!("{} {}", artifact.wasm.display(), artifact.hash);
+//! }
+//! # Ok::<(), iroha_wasm_pack::Error>(())
+//! ```
+//!
+//! # Stability
+//!
+//! The library follows semver together with the binary: before 1.0 a minor release may change
+//! the API, a patch release won't. [`BuildReport`], [`BuildContext`] and the JSON documents of
+//! the `metadata` and `abi` commands serialize with serde and carry their own format versions,
+//! [`METADATA_VERSION`] and [`abi::ABI_VERSION`], which only grow with incompatible changes.
+//!
+//! # Forwarding to cargo
+//!
+//! `build` and the commands sharing its options only forward to cargo what follows `--` or is
+//! given with `--cargo-arg`, an unknown flag is a usage error suggesting the nearest known one.
+//! Earlier releases forwarded every argument they didn't know, `--forward-unknown` keeps that
+//! behavior:
+//!
+//! ```sh
+//! iroha_wasm_pack build --release -- --features debug
+//! iroha_wasm_pack build --release --cargo-arg=--locked
+//! iroha_wasm_pack build --forward-unknown --release --features debug
+//! ```
+
+pub use abi::AbiArgs;
+pub use bench::BenchArgs;
+pub use build::{
+    build, Artifact, BuildArgs, BuildArgs as BuildOptions, BuildContext, BuildReport, CheckArgs,
+    CleanArgs, ConfigArgs, ExpandArgs, HistoryArgs, LintArgs, MetadataArgs, OptArgs,
+    METADATA_VERSION,
+};
+pub use cache::CacheArgs;
+pub use compat::CompatArgs;
+pub use decode::DecodeArgs;
+pub use deploy::DeployArgs;
+pub use diff::DiffArgs;
+pub use doctor::DoctorArgs;
+pub use encode::EncodeArgs;
+pub use error::{Diagnostic, Error, ErrorClass};
+pub use explain::ExplainArgs;
+pub use genesis::GenesisArgs;
+pub use hash::HashArgs;
+pub use init::InitArgs;
+pub use inspect::InspectArgs;
+pub use integration_test::IntegrationTestArgs;
+pub use list_templates::ListTemplatesArgs;
+pub use new::NewArgs;
+pub use profile::ProfileArgs;
+pub use registry::{PublishArgs, PullArgs};
+pub use report::CommandReport;
+pub use run::RunContractArgs;
+pub use sign::{SignArgs, VerifySignatureArgs};
+pub use size::SizeArgs;
+pub use snip::SnipArgs;
+pub use strip::StripArgs;
+pub use test::TestArgs;
+pub use text_format::WatArgs;
+pub use upgrade::UpgradeArgs;
+pub use verify::VerifyArgs;
+pub use version::VersionArgs;
+pub use watch::WatchArgs;
+
+use std::result::Result;
+use structopt::StructOpt;
+use tracing::info;
+
+/// Largest wasm binary Iroha accepts by default, overridable by `package.metadata.iroha_wasm_pack.max-size`.
+pub const MAX_WASM_SIZE: u64 = 4194304;
+
+/// Largest initial memory in wasm pages, the 500 MiB Iroha allows contracts by default.
+pub const MAX_MEMORY_PAGES: u64 = 8000;
+
+/// Nightly toolchain used for `-Z build-std` when the project doesn't pin one.
+pub const DEFAULT_TOOLCHAIN: &str = "nightly-2022-12-15";
+/// Runs subcommand
+pub trait RunArgs {
+    /// Runs command
+    ///
+    /// # Errors
+    /// if inner command errors
+    fn run(self) -> Result<CommandReport, Error>;
+}
+
+pub mod abi;
+pub mod bench;
+pub mod build;
+pub mod build_script;
+pub mod cache;
+pub mod compat;
+pub mod decode;
+pub mod deploy;
+pub mod diff;
+pub mod doctor;
+pub mod encode;
+pub mod env_overrides;
+pub mod error;
+pub mod explain;
+pub mod forwarding;
+pub mod genesis;
+pub mod hash;
+pub mod init;
+pub mod inspect;
+pub mod integration_test;
+pub mod interrupt;
+pub mod lints;
+pub mod list_templates;
+pub mod logging;
+pub mod new;
+pub mod profile;
+pub mod progress;
+pub mod project_config;
+pub mod registry;
+pub mod report;
+pub mod run;
+pub mod runner;
+pub mod sign;
+pub mod size;
+pub mod snip;
+pub mod strip;
+pub mod test;
+pub mod text_format;
+pub mod upgrade;
+pub mod verify;
+pub mod version;
+pub mod wasm;
+pub mod watch;
+
+#[cfg(test)]
+mod test_util;
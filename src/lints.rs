@@ -0,0 +1,310 @@
+//! Iroha specific lints over the sources of a contract.
+
+use super::new::walk_files;
+use super::*;
+use serde_derive::Serialize;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+use syn::{spanned::Spanned, visit::Visit};
+
+/// Something a lint found
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub reason: &'static str,
+    pub lint: &'static str,
+    pub path: PathBuf,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "warning[{}]: {}\n  --> {}:{}",
+            self.lint,
+            self.message,
+            self.path.display(),
+            self.line
+        )
+    }
+}
+
+/// Whether a type mentions `f32` or `f64`
+fn mentions_float(ty: &syn::Type) -> bool {
+    struct Floats(bool);
+    impl<'ast> Visit<'ast> for Floats {
+        fn visit_type_path(&mut self, path: &'ast syn::TypePath) {
+            if path.path.is_ident("f32") || path.path.is_ident("f64") {
+                self.0 = true;
+            }
+            syn::visit::visit_type_path(self, path);
+        }
+    }
+    let mut floats = Floats(false);
+    floats.visit_type(ty);
+    floats.0
+}
+
+/// Lints of a single file
+struct FileLints<'a> {
+    path: &'a Path,
+    no_std: bool,
+    entrypoints: usize,
+    findings: Vec<Finding>,
+}
+
+impl FileLints<'_> {
+    fn report(&mut self, lint: &'static str, span: proc_macro2::Span, message: String) {
+        self.findings.push(Finding {
+            reason: "iroha-lint",
+            lint,
+            path: self.path.to_owned(),
+            line: span.start().line,
+            message,
+        });
+    }
+
+    /// Public functions must not take or return floats, their results differ between peers
+    fn check_signature(&mut self, vis: &syn::Visibility, sig: &syn::Signature) {
+        if !matches!(vis, syn::Visibility::Public(_)) {
+            return;
+        }
+        let inputs = sig.inputs.iter().filter_map(|input| match input {
+            syn::FnArg::Typed(typed) => Some(&*typed.ty),
+            syn::FnArg::Receiver(_) => None,
+        });
+        let output = match &sig.output {
+            syn::ReturnType::Type(_, ty) => Some(&**ty),
+            syn::ReturnType::Default => None,
+        };
+        if inputs.chain(output).any(mentions_float) {
+            self.report(
+                "float_in_signature",
+                sig.span(),
+                format!(
+                    "`{}` has a float in its public signature, use fixed point numbers instead",
+                    sig.ident
+                ),
+            );
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for FileLints<'_> {
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        let is_entrypoint = item.attrs.iter().any(|attr| {
+            attr.path()
+                .segments
+                .last()
+                .map_or(false, |segment| segment.ident == "entrypoint")
+        });
+        if is_entrypoint {
+            self.entrypoints += 1;
+        }
+        self.check_signature(&item.vis, &item.sig);
+        syn::visit::visit_item_fn(self, item);
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
+        self.check_signature(&item.vis, &item.sig);
+        syn::visit::visit_impl_item_fn(self, item);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if call.method == "unwrap" || call.method == "expect" {
+            self.report(
+                "unwrap_in_contract",
+                call.method.span(),
+                format!(
+                    "`{}` panics without a message on the host, use `dbg_unwrap` or `dbg_expect`",
+                    call.method
+                ),
+            );
+        }
+        syn::visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if self.no_std
+            && path
+                .segments
+                .first()
+                .map_or(false, |first| first.ident == "std")
+        {
+            self.report(
+                "std_in_no_std",
+                path.span(),
+                "`std` in a `#![no_std]` contract, use `core` or `alloc`".to_owned(),
+            );
+        }
+        syn::visit::visit_path(self, path);
+    }
+
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        if let syn::UseTree::Path(path) = &item.tree {
+            if self.no_std && path.ident == "std" {
+                self.report(
+                    "std_in_no_std",
+                    item.span(),
+                    "`std` in a `#![no_std]` contract, use `core` or `alloc`".to_owned(),
+                );
+            }
+        }
+        syn::visit::visit_item_use(self, item);
+    }
+}
+
+/// Run the lints over `src/` of the crate at `root`, `contract` requires an entrypoint
+pub fn lint_crate(root: &Path, contract: bool) -> Result<Vec<Finding>, Error> {
+    let lib = root.join("src").join("lib.rs");
+    // Files that don't parse are left to the compiler to report.
+    let parse = |path: &Path| {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|source| syn::parse_file(&source).ok())
+    };
+    let no_std = parse(&lib).map_or(false, |file| {
+        file.attrs.iter().any(|attr| attr.path().is_ident("no_std"))
+    });
+    let mut findings = Vec::new();
+    let mut entrypoints = 0;
+    let mut files = walk_files(&root.join("src"))?;
+    files.sort();
+    for path in files
+        .iter()
+        .filter(|path| path.extension().map_or(false, |ext| ext == "rs"))
+    {
+        let file = match parse(path) {
+            Some(file) => file,
+            None => continue,
+        };
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let mut lints = FileLints {
+            path: relative,
+            no_std,
+            entrypoints: 0,
+            findings: Vec::new(),
+        };
+        lints.visit_file(&file);
+        entrypoints += lints.entrypoints;
+        findings.extend(lints.findings);
+    }
+    if contract && entrypoints == 0 && lib.exists() {
+        findings.push(Finding {
+            reason: "iroha-lint",
+            lint: "missing_entrypoint",
+            path: PathBuf::from("src/lib.rs"),
+            line: 1,
+            message: "no function has `#[iroha_wasm::entrypoint]`, Iroha has nothing to call"
+                .to_owned(),
+        });
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    const NONE: [(&str, usize); 0] = [];
+
+    /// `(lint, line)` of the findings in a crate with `files`
+    fn lint(files: &[(&str, &str)], contract: bool) -> Vec<(&'static str, usize)> {
+        let dir = test_util::temp_dir("lints");
+        test_util::write_files(&dir, files);
+        lint_crate(&dir, contract)
+            .unwrap()
+            .iter()
+            .map(|finding| (finding.lint, finding.line))
+            .collect()
+    }
+
+    #[test]
+    fn clean_contract_has_no_findings() {
+        let lib = "#![no_std]\n\n#[iroha_wasm::entrypoint]\nfn main(amount: u32) -> u32 {\n    amount.checked_add(1).dbg_unwrap()\n}\n";
+        assert_eq!(lint(&[("src/lib.rs", lib)], true), NONE);
+    }
+
+    #[test]
+    fn missing_entrypoint() {
+        let lib = "#![no_std]\n\npub fn helper() {}\n";
+        assert_eq!(
+            lint(&[("src/lib.rs", lib)], true),
+            [("missing_entrypoint", 1)]
+        );
+        // Libraries shared by contracts have no entrypoint of their own.
+        assert_eq!(lint(&[("src/lib.rs", lib)], false), NONE);
+    }
+
+    #[test]
+    fn entrypoint_in_a_submodule_counts() {
+        let files = [
+            ("src/lib.rs", "#![no_std]\n\nmod trigger;\n"),
+            ("src/trigger.rs", "#[entrypoint]\nfn main() {}\n"),
+        ];
+        assert_eq!(lint(&files, true), NONE);
+    }
+
+    #[test]
+    fn unwrap_and_expect() {
+        let lib = "#[entrypoint]\nfn main() {\n    let a: Option<u8> = None;\n    a.unwrap();\n    a.expect(\"a\");\n    a.unwrap_or(0);\n}\n";
+        assert_eq!(
+            lint(&[("src/lib.rs", lib)], true),
+            [("unwrap_in_contract", 4), ("unwrap_in_contract", 5)]
+        );
+    }
+
+    #[test]
+    fn std_only_in_no_std_crates() {
+        let lib = "#![no_std]\n\nuse std::vec::Vec;\n\n#[entrypoint]\nfn main() {\n    let _ = std::mem::size_of::<u8>();\n    let _ = core::mem::size_of::<u8>();\n}\n";
+        assert_eq!(
+            lint(&[("src/lib.rs", lib)], true),
+            [("std_in_no_std", 3), ("std_in_no_std", 7)]
+        );
+        let lib = lib.replace("#![no_std]\n", "");
+        assert_eq!(lint(&[("src/lib.rs", lib.as_str())], true), NONE);
+    }
+
+    #[test]
+    fn floats_in_public_signatures() {
+        let lib = "#[entrypoint]\nfn main() {}\n\npub fn price(amount: u32) -> f64 { 0.0 }\n\nfn private(ratio: f32) {}\n\npub struct Pool;\n\nimpl Pool {\n    pub fn share(&self, of: Option<f32>) {}\n}\n";
+        assert_eq!(
+            lint(&[("src/lib.rs", lib)], true),
+            [("float_in_signature", 4), ("float_in_signature", 11)]
+        );
+    }
+
+    #[test]
+    fn unparsable_files_are_left_to_the_compiler() {
+        let files = [
+            ("src/lib.rs", "#[entrypoint]\nfn main() {}\nmod broken;\n"),
+            ("src/broken.rs", "fn (\n"),
+        ];
+        assert_eq!(lint(&files, true), NONE);
+    }
+
+    #[test]
+    fn findings_point_at_the_file() {
+        let dir = test_util::temp_dir("lints-path");
+        test_util::write_files(
+            &dir,
+            &[
+                ("src/lib.rs", "#[entrypoint]\nfn main() {}\nmod helpers;\n"),
+                (
+                    "src/helpers.rs",
+                    "fn helper(a: Option<u8>) -> u8 {\n    a.unwrap()\n}\n",
+                ),
+            ],
+        );
+        let findings = lint_crate(&dir, true).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].to_string(),
+            "warning[unwrap_in_contract]: `unwrap` panics without a message on the host, use `dbg_unwrap` or `dbg_expect`\n  --> src/helpers.rs:2"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use super::new::{Template, DEFAULT_IROHA_VERSION, SAMPLES};
+use super::*;
+use std::{env, fs, path::PathBuf};
+
+/// Everything required to configure and run the `iroha_wasm_pack list-templates` command.
+#[derive(Debug, StructOpt)]
+pub struct ListTemplatesArgs {
+    /// Print the templates as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    /// Only list templates whose name contains this text, fails if none does
+    #[structopt(long = "filter")]
+    pub filter: Option<String>,
+}
+
+/// A template `new` can create a project from.
+struct Entry {
+    name: String,
+    description: String,
+    entrypoint: String,
+    iroha_version: String,
+    source: String,
+}
+
+/// Directory of user templates, `$XDG_CONFIG_HOME/iroha_wasm_pack/templates`
+pub fn registry_dir() -> Option<PathBuf> {
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(config) => PathBuf::from(config),
+        None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config.join("iroha_wasm_pack").join("templates"))
+}
+
+/// Templates of the registry directory, usable with `new --template-path`
+fn registry_entries() -> Vec<Entry> {
+    let dir = match registry_dir() {
+        Some(dir) if dir.is_dir() => dir,
+        _ => return Vec::new(),
+    };
+    let mut entries: Vec<Entry> = fs::read_dir(&dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.join("Cargo.toml").exists())
+        .map(|path| Entry {
+            name: path.file_name().unwrap().to_string_lossy().into_owned(),
+            description: fs::read_to_string(path.join("DESCRIPTION"))
+                .map(|text| text.lines().next().unwrap_or_default().to_owned())
+                .unwrap_or_default(),
+            entrypoint: "custom".to_owned(),
+            iroha_version: "unknown".to_owned(),
+            source: path.display().to_string(),
+        })
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    entries
+}
+
+impl RunArgs for ListTemplatesArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let builtin = Template::ALL.iter().map(|template| Entry {
+            name: template.as_str().to_owned(),
+            description: template.description().to_owned(),
+            entrypoint: template.as_str().to_owned(),
+            iroha_version: template.supported_versions(),
+            source: "built-in".to_owned(),
+        });
+        let samples = SAMPLES.iter().map(|sample| Entry {
+            name: sample.name.to_owned(),
+            description: sample.description.to_owned(),
+            entrypoint: sample.template.as_str().to_owned(),
+            iroha_version: DEFAULT_IROHA_VERSION.to_owned(),
+            source: "example".to_owned(),
+        });
+        let entries: Vec<Entry> = builtin
+            .chain(samples)
+            .chain(registry_entries())
+            .filter(|entry| {
+                self.filter
+                    .as_ref()
+                    .map_or(true, |filter| entry.name.contains(filter.as_str()))
+            })
+            .collect();
+        if entries.is_empty() {
+            return Err(Error::other(format!(
+                "no template matches `{}`",
+                self.filter.unwrap_or_default()
+            )));
+        }
+        if self.json {
+            let entries: Vec<serde_json::Value> = entries
+                .iter()
+                .map(|entry| {
+                    serde_json::json!({
+                        "name": entry.name,
+                        "description": entry.description,
+                        "entrypoint": entry.entrypoint,
+                        "iroha_version": entry.iroha_version,
+                        "source": entry.source,
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&entries)?);
+            return Ok(CommandReport::default());
+        }
+        for entry in entries {
+            println!(
+                "{:<18} {:<14} {:<18} {} [{}]",
+                entry.name, entry.entrypoint, entry.iroha_version, entry.description, entry.source
+            );
+        }
+        Ok(CommandReport::default())
+    }
+}
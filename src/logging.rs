@@ -0,0 +1,172 @@
+//! Logging through `tracing`, the subscriber of the binary and its layers.
+//!
+//! Every step of `build` and `new` runs in a `step` span with the step and package, external
+//! programs emit events when they start and finish. `RUST_LOG` filters what reaches stderr.
+
+use super::*;
+use std::{
+    fs::File,
+    io,
+    path::Path,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Subscriber,
+};
+use tracing_subscriber::{
+    filter::LevelFilter,
+    fmt,
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
+};
+
+/// How events are printed on stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Pretty,
+    /// One JSON object per line
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("unknown log format `{}`", s)),
+        }
+    }
+}
+
+/// Install the global subscriber.
+///
+/// Events go to stderr in `format` as `RUST_LOG` lets them, warnings only without it and
+/// none with `--progress-format json`. With `log_file` every event down to debug is also
+/// written there as JSON lines.
+pub fn init(format: LogFormat, log_file: Option<&Path>) -> Result<(), Error> {
+    let default = if progress::is_json() { "off" } else { "warn" };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default));
+    let stderr = match format {
+        LogFormat::Pretty => fmt::layer()
+            .with_writer(io::stderr)
+            .with_filter(filter)
+            .boxed(),
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_writer(io::stderr)
+            .with_filter(filter)
+            .boxed(),
+    };
+    let mut layers: Vec<Box<dyn Layer<Registry> + Send + Sync>> = vec![stderr, TimingLayer.boxed()];
+    if let Some(path) = log_file {
+        let file = File::create(path)?;
+        layers.push(
+            fmt::layer()
+                .json()
+                .with_writer(Mutex::new(file))
+                .with_filter(LevelFilter::DEBUG)
+                .boxed(),
+        );
+    }
+    tracing_subscriber::registry()
+        .with(layers)
+        .try_init()
+        .map_err(|err| Error::other("installing the logger failed").caused_by(err))
+}
+
+/// Run an external program, `run` starts it and waits for it
+pub fn command<T, E>(program: &str, run: impl FnOnce() -> Result<T, E>) -> Result<T, E> {
+    tracing::debug!(program, "started");
+    let started = Instant::now();
+    let result = run();
+    tracing::info!(
+        program,
+        millis = started.elapsed().as_millis() as u64,
+        success = result.is_ok(),
+        "finished"
+    );
+    result
+}
+
+/// Durations of the closed `step` spans, what `build --timings` prints
+static TIMINGS: Mutex<Vec<build::StepTiming>> = Mutex::new(Vec::new());
+
+/// The steps that ran so far, recorded if `init` installed the subscriber
+pub fn recorded_timings() -> Vec<build::StepTiming> {
+    TIMINGS.lock().unwrap().clone()
+}
+
+/// Records how long every `step` span was open
+struct TimingLayer;
+
+/// Extension of a `step` span
+#[derive(Default)]
+struct StepSpan {
+    package: String,
+    step: String,
+    opened: Option<Instant>,
+}
+
+impl Visit for StepSpan {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "package" => self.package = value.to_owned(),
+            "step" => self.step = value.to_owned(),
+            _ => {}
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for TimingLayer {
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "step" {
+            return;
+        }
+        let mut step = StepSpan {
+            opened: Some(Instant::now()),
+            ..StepSpan::default()
+        };
+        attrs.record(&mut step);
+        progress::emit(progress::Event::StepStarted {
+            package: &step.package,
+            step: &step.step,
+        });
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(step);
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        let span = match ctx.span(&id) {
+            Some(span) => span,
+            None => return,
+        };
+        let extensions = span.extensions();
+        if let Some(step) = extensions.get::<StepSpan>() {
+            let elapsed = step
+                .opened
+                .map_or(Duration::ZERO, |opened| opened.elapsed());
+            progress::emit(progress::Event::StepFinished {
+                package: &step.package,
+                step: &step.step,
+                millis: elapsed.as_millis() as u64,
+            });
+            TIMINGS.lock().unwrap().push(build::StepTiming {
+                package: step.package.clone(),
+                step: step.step.clone(),
+                millis: elapsed.as_millis() as u64,
+            });
+        }
+    }
+}
@@ -1,9 +1,22 @@
-use build::BuildArgs;
-use failure::{err_msg, Error};
-use log::{error, info};
-use new::NewArgs;
-use std::result::Result;
-use structopt::StructOpt;
+use completions::CompletionsArgs;
+use iroha_wasm_pack::{
+    build::{use_wasm_opt_worker, wasm_opt_worker, WASM_OPT_WORKER},
+    forwarding, interrupt,
+    logging::{self, LogFormat},
+    progress::{self, ProgressFormat},
+    project_config,
+};
+use iroha_wasm_pack::{
+    env_overrides, AbiArgs, BenchArgs, BuildArgs, CacheArgs, CheckArgs, CleanArgs, CommandReport,
+    CompatArgs, ConfigArgs, DecodeArgs, DeployArgs, DiffArgs, DoctorArgs, EncodeArgs, Error,
+    ExpandArgs, ExplainArgs, GenesisArgs, HashArgs, HistoryArgs, InitArgs, InspectArgs,
+    IntegrationTestArgs, LintArgs, ListTemplatesArgs, MetadataArgs, NewArgs, OptArgs, ProfileArgs,
+    PublishArgs, PullArgs, RunArgs, RunContractArgs, SignArgs, SizeArgs, SnipArgs, StripArgs,
+    TestArgs, UpgradeArgs, VerifyArgs, VerifySignatureArgs, VersionArgs, WatArgs, WatchArgs,
+};
+use std::{ffi::OsString, path::PathBuf, result::Result, time::Instant};
+use structopt::{clap::ErrorKind, StructOpt};
+use tracing::{error, info};
 
 /// The various kinds of commands that `iroha_wasm_pack` can execute.
 #[derive(Debug, StructOpt)]
@@ -15,25 +28,209 @@ pub enum SubCommand {
     #[structopt(name = "new")]
     /// 🐑 create a new project
     New(NewArgs),
+
+    #[structopt(name = "init")]
+    /// 🌱 create a new project in an existing directory
+    Init(InitArgs),
+
+    #[structopt(name = "check")]
+    /// 🔍 type-check the contract for the wasm target without building it
+    Check(CheckArgs),
+
+    #[structopt(name = "lint")]
+    /// 🔎 run clippy for the wasm target plus Iroha specific lints
+    Lint(LintArgs),
+
+    #[structopt(name = "expand")]
+    /// 🧩 print the crate with its macros expanded, the entrypoint shims highlighted
+    Expand(ExpandArgs),
+
+    #[structopt(name = "clean")]
+    /// 🧹 remove the wasm outputs, keeping the native builds
+    Clean(CleanArgs),
+
+    #[structopt(name = "cache")]
+    /// 🗃️  show, clear or shrink the caches of the tool
+    Cache(CacheArgs),
+
+    #[structopt(name = "watch")]
+    /// 👀 rebuild whenever the sources change
+    Watch(WatchArgs),
+
+    #[structopt(name = "opt")]
+    /// 🗜️  optimize a wasm file like `build` does
+    Opt(OptArgs),
+
+    #[structopt(name = "strip")]
+    /// ✂️  remove debug info and other custom sections from a wasm file
+    Strip(StripArgs),
+
+    #[structopt(name = "snip")]
+    /// 🪓 replace unreachable panic and formatting code of a wasm file by traps
+    Snip(SnipArgs),
+
+    #[structopt(name = "wat")]
+    /// 📝 convert between the binary and text formats of wasm
+    Wat(WatArgs),
+
+    #[structopt(name = "abi")]
+    /// 📜 describe the entrypoints of the contract as a JSON ABI
+    Abi(AbiArgs),
+
+    #[structopt(name = "encode")]
+    /// 🔤 print the wasm as base64, hex or a SCALE encoded `WasmSmartContract`
+    Encode(EncodeArgs),
+
+    #[structopt(name = "decode")]
+    /// 🔓 list the custom sections of a wasm file or show one of them
+    Decode(DecodeArgs),
+
+    #[structopt(name = "compat")]
+    /// 🤝 check the host functions a wasm imports against an Iroha release
+    Compat(CompatArgs),
+
+    #[structopt(name = "inspect")]
+    /// 🔬 show the imports, exports and sections of a wasm module
+    Inspect(InspectArgs),
+
+    #[structopt(name = "verify")]
+    /// ✅ run the post-build checks on any wasm file
+    Verify(VerifyArgs),
+
+    #[structopt(name = "size")]
+    /// 📏 show where the bytes of a wasm module go
+    Size(SizeArgs),
+
+    #[structopt(name = "history")]
+    /// 📈 list the sizes of past builds of the package
+    History(HistoryArgs),
+
+    #[structopt(name = "diff")]
+    /// 🔀 compare the structure of two wasm modules
+    Diff(DiffArgs),
+
+    #[structopt(name = "hash")]
+    /// #️⃣  print the hash Iroha identifies a wasm blob by
+    Hash(HashArgs),
+
+    #[structopt(name = "sign")]
+    /// ✍️  sign the hash of a wasm file with an Iroha private key
+    Sign(SignArgs),
+
+    #[structopt(name = "verify-signature")]
+    /// 🔏 check a signature made by `sign`
+    VerifySignature(VerifySignatureArgs),
+
+    #[structopt(name = "deploy")]
+    /// 🚀 register the contract as a trigger on a node
+    Deploy(DeployArgs),
+
+    #[structopt(name = "genesis")]
+    /// 🌅 emit the genesis entry registering the contract as a trigger
+    Genesis(GenesisArgs),
+
+    #[structopt(name = "run")]
+    /// ▶️  run the entrypoint locally with stub host functions
+    Run(RunContractArgs),
+
+    #[structopt(name = "publish")]
+    /// 📤 push the wasm to an OCI registry
+    Publish(PublishArgs),
+
+    #[structopt(name = "pull")]
+    /// 📥 fetch a wasm published with `publish` and verify it
+    Pull(PullArgs),
+
+    #[structopt(name = "profile")]
+    /// 🔥 count the calls of each function and attribute the fuel of a run to them
+    Profile(ProfileArgs),
+
+    #[structopt(name = "bench")]
+    /// ⏱️  measure the fuel and time the entrypoint takes
+    Bench(BenchArgs),
+
+    #[structopt(name = "doctor")]
+    /// 🩺 diagnose the toolchain and environment the build needs
+    Doctor(DoctorArgs),
+
+    #[structopt(name = "test")]
+    /// 🧪 build the wasm tests and run them in wasmtime
+    Test(TestArgs),
+
+    #[structopt(name = "integration-test")]
+    /// 🧫 run the contract on a disposable local peer and check the resulting state
+    IntegrationTest(IntegrationTestArgs),
+
+    #[structopt(name = "upgrade")]
+    /// ⬆️  upgrade the Iroha dependencies and scaffolding of a project
+    Upgrade(UpgradeArgs),
+
+    #[structopt(name = "config")]
+    /// ⚙️  print the effective configuration of the project and where it comes from
+    Config(ConfigArgs),
+
+    #[structopt(name = "metadata")]
+    /// 🗂️  print the resolved build context as JSON, without building
+    Metadata(MetadataArgs),
+
+    #[structopt(name = "version")]
+    /// 🏷️  print the version, with `--verbose` the build and tooling of the binary
+    Version(VersionArgs),
+
+    #[structopt(name = "explain")]
+    /// 📖 explain an error code of the tool and how to fix it
+    Explain(ExplainArgs),
+
+    #[structopt(name = "completions")]
+    /// 🐚 print the shell completion script
+    Completions(CompletionsArgs),
+
+    #[structopt(name = "list-templates")]
+    /// 📋 list the templates new projects can be created from
+    ListTemplates(ListTemplatesArgs),
 }
 
 /// 📦 ✨  build and release your wasm!
+///
+/// Exit codes: 1 usage, 2 environment, 3 build, 4 validation, 5 other, see `explain E0001`
+/// to `explain E0005`.
 #[derive(Debug, StructOpt)]
 pub struct Args {
     /// The subcommand to run.
     #[structopt(subcommand)] // Note that we mark a field as a subcommand
     pub subcommand: SubCommand,
-}
 
-/// Runs subcommand
-pub trait RunArgs {
-    /// Runs command
-    ///
-    /// # Errors
-    /// if inner command errors
-    fn run(self) -> Result<(), Error>;
-}
+    /// How errors are printed: human, or json objects on stderr for tools wrapping the binary
+    #[structopt(long = "error-format", global = true, default_value = "human", possible_values = &["human", "json"])]
+    pub error_format: String,
 
+    /// How the outcome of a command is printed: human, or a json object on stdout
+    #[structopt(long = "output-format", global = true, default_value = "human", possible_values = &["human", "json"])]
+    pub output_format: String,
+
+    /// How log events are printed on stderr: pretty, or json lines. `RUST_LOG` filters them
+    #[structopt(long = "log-format", global = true, default_value = "pretty", possible_values = &["pretty", "json"])]
+    pub log_format: LogFormat,
+
+    /// How progress is shown on stderr: human, or json events for editors, see the `progress`
+    /// module of the library
+    #[structopt(long = "progress-format", global = true, default_value = "human", possible_values = &["human", "json"])]
+    pub progress_format: ProgressFormat,
+
+    /// Also write every event down to debug as JSON lines to this file
+    #[structopt(long = "log-file", global = true, parse(from_os_str))]
+    pub log_file: Option<PathBuf>,
+
+    /// Configuration file to read instead of the discovered iroha_wasm_pack.toml, a later one
+    /// overrides an earlier one
+    #[structopt(
+        long = "config",
+        global = true,
+        number_of_values = 1,
+        parse(from_os_str)
+    )]
+    pub config: Vec<PathBuf>,
+}
 macro_rules! match_run_all {
     (($self:ident), { $($variants:path),* $(,)?}) => {
         match $self {
@@ -43,377 +240,271 @@ macro_rules! match_run_all {
 }
 
 impl RunArgs for SubCommand {
-    fn run(self) -> Result<(), Error> {
+    fn run(self) -> Result<CommandReport, Error> {
         use SubCommand::*;
-        match_run_all!((self), { Build, New })
+        match_run_all!((self), { Build, Check, Lint, Expand, Clean, Cache, Watch, Opt, Strip, Snip, Wat, Abi, Encode, Decode, Inspect, Compat, Verify, Size, History, Diff, Hash, Sign, VerifySignature, Deploy, Genesis, Publish, Pull, Run, Bench, Profile, Doctor, Test, IntegrationTest, New, Init, ListTemplates, Upgrade, Completions, Explain, Config, Metadata, Version })
     }
 }
 
-fn main() {
-    let args = Args::from_args();
-    if let Err(err) = args.subcommand.run() {
-        error!("{}", err);
-    }
+/// Whether errors go out as JSON, read before parsing so usage errors are reported the same way
+fn json_errors(argv: &[OsString]) -> bool {
+    let argv: Vec<&str> = argv.iter().filter_map(|arg| arg.to_str()).collect();
+    argv.windows(2)
+        .any(|pair| pair == ["--error-format", "json"])
+        || argv.contains(&"--error-format=json")
+        || std::env::var("IROHA_WASM_PACK_ERROR_FORMAT").map_or(false, |format| format == "json")
 }
 
-mod build {
-    use super::*;
-    use serde_derive::Deserialize;
-    use std::{
-        env::current_dir,
-        fs,
-        path::{Path, PathBuf},
-        str::FromStr,
-    };
-    use structopt::clap::AppSettings;
-
-    /// Everything required to configure and run the `iroha_wasm_pack build` command.
-    #[derive(Debug, StructOpt)]
-    #[structopt(
-        // Allows unknown `--option`s to be parsed as positional arguments, so we can forward it to `cargo`.
-        setting = AppSettings::AllowLeadingHyphen,
-
-        // Allows `--` to be parsed as an argument, so we can forward it to `cargo`.
-        setting = AppSettings::TrailingVarArg,
-    )]
-    pub struct BuildArgs {
-        #[structopt(allow_hyphen_values = true)]
-        /// List of extra options to pass to `iroha_wasm_pack build`
-        pub extra_options: Vec<String>,
+/// Print the report of a finished command
+fn render(report: &CommandReport, output_format: &str) {
+    info!(
+        millis = report.millis,
+        artifacts = report.artifacts.len(),
+        warnings = report.warnings.len(),
+        "Finished"
+    );
+    if output_format == "json" {
+        println!("{}", serde_json::to_string(report).expect("serializable"));
+        return;
     }
-
-    pub struct BuildContext {
-        crate_type: String,
-        wasm_in: PathBuf,
-        wasm_out: PathBuf,
+    for line in &report.summary {
+        println!("{}", line);
     }
+}
 
-    // Construct this context to reuse in multi build steps
-    impl BuildContext {
-        fn new(args: &BuildArgs) -> Result<Self, Error> {
-            let root = root(current_dir()?)?;
-            let config = pasre_cargo_config(&root)?;
-            let is_release = args.extra_options.iter().any(|x| x == "--release");
-            let profile = if is_release { "release" } else { "debug" };
-            let wasm_folder = root
-                .join("target")
-                .join("wasm32-unknown-unknown")
-                .join(profile);
-            let wasm_name = &config.package.name;
-            let wasm_in = wasm_folder.join(format!("{}{}", wasm_name, ".wasm"));
-            let wasm_out = wasm_folder.join(format!("{}{}", wasm_name, "_optimized.wasm"));
-            let crate_type = config.lib.crate_type.first().unwrap().to_owned();
-            Ok(BuildContext {
-                crate_type: crate_type,
-                wasm_in: wasm_in,
-                wasm_out: wasm_out,
-            })
-        }
+/// `--version --verbose` as the `version` command, clap's `--version` only prints the semver
+fn verbose_version(argv: Vec<OsString>) -> Vec<OsString> {
+    let flags: Vec<&str> = argv.iter().skip(1).filter_map(|arg| arg.to_str()).collect();
+    let only_version_flags = flags.len() == argv.len().saturating_sub(1)
+        && flags
+            .iter()
+            .all(|flag| matches!(*flag, "--version" | "-V" | "--verbose" | "-v" | "--json"));
+    if !only_version_flags
+        || !flags.iter().any(|flag| matches!(*flag, "--version" | "-V"))
+        || !flags.iter().any(|flag| matches!(*flag, "--verbose" | "-v"))
+    {
+        return argv;
     }
-
-    impl RunArgs for BuildArgs {
-        fn run(self) -> Result<(), Error> {
-            let ctx = BuildContext::new(&self)?;
-            for step in [
-                step_check_rustc_version,
-                step_check_crate_config,
-                step_check_for_wasm_target,
-                step_build_wasm,
-                step_wasm_opt,
-                step_iroha_binary_size_check,
-            ] {
-                step(&self, &ctx)?
-            }
-            Ok(())
-        }
+    let mut rewritten = vec![argv[0].clone(), "version".into(), "--verbose".into()];
+    if flags.contains(&"--json") {
+        rewritten.push("--json".into());
     }
+    rewritten
+}
 
-    /// Find the project root directory.
-    fn root(mut cur: PathBuf) -> Result<PathBuf, Error> {
-        while !cur.join("Cargo.toml").exists() {
-            if !cur.pop() {
-                return Err(err_msg("No Cargo.toml found from current dir or parent, you should init a project by `iroha_wasm_pack new` first"));
-            }
+fn main() {
+    let started = Instant::now();
+    let argv = verbose_version(std::env::args_os().collect());
+    if argv.get(1).map_or(false, |arg| arg == WASM_OPT_WORKER) {
+        let args: Vec<String> = argv[2..]
+            .iter()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect();
+        if let Err(err) = wasm_opt_worker(&args) {
+            eprintln!("{}", err);
+            std::process::exit(err.class().exit_code());
         }
-        Ok(cur)
+        return;
     }
-
-    /// Fetch rustc version by command
-    fn rustc_minor_version() -> Result<u32, Error> {
-        use duct::cmd;
-        let stdout = cmd!("rustc", "--version").read()?;
-        info!("Checked rustc version {}", stdout);
-        let mut pieces = stdout.split('.');
-        if pieces.next() == Some("rustc 1") {
-            if let Some(version) = pieces.next() {
-                return Ok(version.parse()?);
-            }
-        }
-        Err(err_msg("We can't figure out what your Rust version is- which means you might not have Rust installed. Please install Rust version 1.30.0 or higher."))
+    // The bundled wasm-opt runs in a child process, so `--wasm-opt-timeout` can stop it.
+    if let Ok(exe) = std::env::current_exe() {
+        use_wasm_opt_worker(exe);
     }
-
-    pub fn step_check_rustc_version(_: &BuildArgs, _: &BuildContext) -> Result<(), Error> {
-        // Ensure that `rustc` is present and that it is >= 1.30.0
-        let local_minor_version = rustc_minor_version()?;
-        if local_minor_version < 30 {
-            return Err(err_msg(format!("Your version of Rust, '1.{}', is not supported. Please install Rust version 1.30.0 or higher.", local_minor_version.to_string())));
+    let json = json_errors(&argv);
+    let result = env_overrides::apply(&Args::clap(), argv).and_then(|argv| {
+        let argv = forwarding::forward_unknown(&Args::clap(), argv);
+        let args = match Args::from_iter_safe(argv) {
+            Ok(args) => args,
+            // Help, version and the human format keep clap's own output.
+            Err(err)
+                if !json
+                    || matches!(
+                        err.kind,
+                        ErrorKind::HelpDisplayed | ErrorKind::VersionDisplayed
+                    ) =>
+            {
+                err.exit()
+            }
+            Err(err) => {
+                let message = err.message.lines().next().unwrap_or_default();
+                return Err(Error::usage(message.trim_start_matches("error: ")));
+            }
+        };
+        progress::set_format(args.progress_format);
+        logging::init(args.log_format, args.log_file.as_deref())?;
+        project_config::use_files(&args.config)?;
+        // Commands with something to clean up get the first Ctrl-C, the others stop right away.
+        ctrlc::set_handler(|| {
+            if !interrupt::request() {
+                std::process::exit(130);
+            }
+        })?;
+        let mut report = args.subcommand.run()?;
+        report.millis = started.elapsed().as_millis() as u64;
+        progress::emit(progress::Event::Finished {
+            success: true,
+            exit_code: report.exit_code,
+            millis: report.millis,
+            report: Some(&report),
+            error: None,
+        });
+        render(&report, &args.output_format);
+        Ok(report.exit_code)
+    });
+    match result {
+        Ok(0) => {}
+        Ok(code) => std::process::exit(code),
+        // The command wound down after an interrupt, like a shell reports one.
+        Err(_) if interrupt::requested() => std::process::exit(130),
+        Err(err) => {
+            progress::emit(progress::Event::Finished {
+                success: false,
+                exit_code: err.class().exit_code(),
+                millis: started.elapsed().as_millis() as u64,
+                report: None,
+                error: Some(err.diagnostic()),
+            });
+            // The event carries the error, anything else on stderr would break the stream.
+            if progress::is_json() {
+                std::process::exit(err.class().exit_code());
+            }
+            fail(&err, json)
         }
-        Ok(())
     }
+}
 
-    /// Cargo.toml Deserialize
-    #[derive(Deserialize)]
-    struct Package {
-        name: String,
+/// Print `err` and exit with the code of its class
+fn fail(err: &Error, json: bool) -> ! {
+    error!("{}", err);
+    if json {
+        // stdout stays for the output of the command.
+        eprintln!(
+            "{}",
+            serde_json::to_string(&err.diagnostic()).expect("serializable")
+        );
+        std::process::exit(err.class().exit_code());
     }
-
-    #[derive(Deserialize)]
-    struct Lib {
-        #[serde(alias = "crate-type")]
-        crate_type: Vec<String>,
+    match err.code() {
+        Some(code) => eprintln!("Error[{}]: {}", code, err),
+        None => eprintln!("Error: {}", err),
     }
-
-    #[derive(Deserialize)]
-    struct CargoConfig {
-        package: Package,
-        lib: Lib,
+    let mut cause = std::error::Error::source(err.uncoded());
+    while let Some(err) = cause {
+        eprintln!("  caused by: {}", err);
+        cause = err.source();
     }
-
-    /// Parse the cargo toml
-    fn pasre_cargo_config(root: &PathBuf) -> Result<CargoConfig, Error> {
-        let path = root.join("Cargo.toml");
-        let cargo_xml = fs::read_to_string(path.to_str().unwrap()).unwrap();
-        match toml::from_str(&cargo_xml) {
-            Ok(config) => Ok(config),
-            Err(err) => Err(err_msg(format!("parse cargo toml failed, error = {}", err))),
-        }
+    if let Some(code) = err.code() {
+        eprintln!(
+            "\nFor more information about this error, try `iroha_wasm_pack explain {}`.",
+            code
+        );
     }
+    std::process::exit(err.class().exit_code());
+}
 
-    /// Check crate-type
-    pub fn step_check_crate_config(_: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
-        if ctx.crate_type == "cdylib" {
-            Ok(())
-        } else {
-            let msg = format!("crate-type must be cdylib to compile to wasm32-unknown-unknown. Add the following to your \
-                Cargo.toml file:\n\n\
-                [lib]\n\
-                crate-type = [\"cdylib\"]");
-            Err(err_msg(msg))
-        }
-    }
+mod completions {
+    use super::*;
+    use std::io::Write;
+    use structopt::clap::Shell;
 
-    /// Get rustc's sysroot as a PathBuf
-    fn get_rustc_sysroot() -> Result<PathBuf, Error> {
-        use duct::cmd;
-        let result = cmd!("rustc", "--print", "sysroot").read();
-        if result.is_err() {
-            return Err(err_msg(format!(
-                "Getting rustc's sysroot wasn't successful. Got {}",
-                result.unwrap()
-            )));
-        }
-        let stdout = result?;
-        info!("Rustc sysroot: {}", stdout);
-        Ok(PathBuf::from_str(&stdout).unwrap())
+    /// Everything required to configure and run the `iroha_wasm_pack completions` command.
+    #[derive(Debug, StructOpt)]
+    pub struct CompletionsArgs {
+        /// Shell to complete in: bash, zsh, fish, powershell or elvish
+        #[structopt(possible_values = &Shell::variants(), case_insensitive = true)]
+        pub shell: Shell,
     }
 
-    /// Checks if the wasm32-unknown-unknown is present in rustc's sysroot.
-    fn is_wasm32_target_in_sysroot(sysroot: &Path) -> bool {
-        let wasm32_target = "wasm32-unknown-unknown";
-
-        let rustlib_path = sysroot.join("lib/rustlib");
-
-        info!("Looking for {} in {:?}", wasm32_target, rustlib_path);
-
-        if rustlib_path.join(wasm32_target).exists() {
-            info!("Found {} in {:?}", wasm32_target, rustlib_path);
-            true
-        } else {
-            info!("Failed to find {} in {:?}", wasm32_target, rustlib_path);
-            false
-        }
+    /// Completion script of `shell`
+    fn script(shell: Shell) -> Vec<u8> {
+        // Package names for `-p` are only known per project, so they aren't completed.
+        let mut script = Vec::new();
+        Args::clap().gen_completions_to("iroha_wasm_pack", shell, &mut script);
+        script
     }
 
-    /// Add wasm32-unknown-unknown using `rustup`.
-    fn rustup_add_wasm_target() -> Result<(), Error> {
-        use duct::cmd;
-        let result = cmd!("rustup", "target", "add", "wasm32-unknown-unknown").run();
-        if let Err(err) = result {
-            return Err(err_msg(format!(
-                "Adding the wasm32-unknown-unknown target with rustup failed, error = {}",
-                err
-            )));
+    impl RunArgs for CompletionsArgs {
+        fn run(self) -> Result<CommandReport, Error> {
+            std::io::stdout().write_all(&script(self.shell))?;
+            Ok(CommandReport::default())
         }
-        Ok(())
     }
 
-    pub fn step_check_for_wasm_target(_: &BuildArgs, _: &BuildContext) -> Result<(), Error> {
-        let sysroot = get_rustc_sysroot()?;
-
-        // If wasm32-unknown-unknown already exists we're ok.
-        if is_wasm32_target_in_sysroot(&sysroot) {
-            Ok(())
-        // If it doesn't exist, then we need to check if we're using rustup.
-        } else {
-            // If sysroot contains "rustup", then we can assume we're using rustup
-            // and use rustup to add the wasm32-unknown-unknown target.
-            if sysroot.to_string_lossy().contains("rustup") {
-                rustup_add_wasm_target()
-            } else {
-                Ok(())
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn every_shell_completes_the_subcommands() {
+            for shell in Shell::variants() {
+                let shell: Shell = shell.parse().unwrap();
+                let script = String::from_utf8(script(shell)).unwrap();
+                for subcommand in [
+                    "build",
+                    "verify-signature",
+                    "integration-test",
+                    "completions",
+                ] {
+                    assert!(
+                        script.contains(subcommand),
+                        "{} misses {}",
+                        shell,
+                        subcommand
+                    );
+                }
             }
         }
-    }
 
-    pub fn step_build_wasm(args: &BuildArgs, _: &BuildContext) -> Result<(), Error> {
-        use duct::cmd;
-        let extra_args: Vec<&str> = args.extra_options.iter().map(|s| &s[..]).collect();
-        let mut args = vec![
-            "+nightly",
-            "build",
-            "-Z",
-            "build-std",
-            "-Z",
-            "build-std-features=panic_immediate_abort",
-            "--target",
-            "wasm32-unknown-unknown",
-        ];
-        extra_args.iter().for_each(|x| args.push(x));
-        let result = cmd("cargo", args).run();
-        if let Err(err) = result {
-            return Err(err_msg(format!("build wasm failed, error = {}", err)));
+        #[test]
+        fn bash_completes_the_options_of_build() {
+            let script = String::from_utf8(script(Shell::Bash)).unwrap();
+            assert!(script.starts_with("_iroha_wasm_pack()"), "{}", script);
+            for option in ["--release", "--manifest-path", "--target-dir"] {
+                assert!(script.contains(option), "bash misses {}", option);
+            }
         }
-        Ok(())
-    }
-
-    pub fn step_wasm_opt(_: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
-        use wasm_opt::OptimizationOptions;
-        OptimizationOptions::new_optimize_for_size().run(&ctx.wasm_in, &ctx.wasm_out)?;
-        Ok(())
-    }
 
-    pub fn step_iroha_binary_size_check(_: &BuildArgs, ctx: &BuildContext) -> Result<(), Error> {
-        let len = fs::metadata(&ctx.wasm_out)?.len();
-        if len > 4194304 {
-            return Err(err_msg(format!(
-                "Wasm binary too large, max size is 4194304, but got {}",
-                len
-            )));
+        #[test]
+        fn shell_names_are_case_insensitive() {
+            let args = CompletionsArgs::from_iter_safe(["completions", "ZSH"]).unwrap();
+            assert!(matches!(args.shell, Shell::Zsh));
         }
-        Ok(())
     }
 }
 
-mod new {
+#[cfg(test)]
+mod tests {
     use super::*;
-    use std::{env::current_dir, fs, path::Path};
 
-    /// Everything required to configure and run the `iroha_wasm_pack new` command.
-    #[derive(Debug, StructOpt)]
-    pub struct NewArgs {
-        /// Name of the new project
-        pub name: String,
+    fn argv(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
     }
 
-    impl RunArgs for NewArgs {
-        fn run(self) -> Result<(), Error> {
-            for step in [step_cargo_new, step_cargo_xml, step_main_entrypoint] {
-                step(&self)?;
-            }
-            Ok(())
-        }
-    }
-
-    /// Writes a file to disk.
-    pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<(), Error> {
-        let path = path.as_ref();
-        if let Err(err) = fs::write(path, contents.as_ref()) {
-            return Err(err_msg(format!(
-                "write to {} failed, error = {}",
-                path.display(),
-                err
-            )));
-        }
-        Ok(())
-    }
-
-    /// Init project by `cargo new --lib`
-    pub fn step_cargo_new(args: &NewArgs) -> Result<(), Error> {
-        use duct::cmd;
-        if let Err(err) = cmd!("cargo", "new", &args.name, "--lib").run() {
-            return Err(err_msg(format!("init project failed, error = {}", err)));
-        }
-        Ok(())
+    #[test]
+    fn json_errors_is_read_before_parsing() {
+        assert!(json_errors(&argv(&[
+            "iroha_wasm_pack",
+            "build",
+            "--error-format",
+            "json"
+        ])));
+        assert!(json_errors(&argv(&[
+            "iroha_wasm_pack",
+            "--error-format=json",
+            "no-such-command"
+        ])));
+        assert!(!json_errors(&argv(&[
+            "iroha_wasm_pack",
+            "build",
+            "--error-format",
+            "human"
+        ])));
     }
 
-    /// Cargo xml release profile for reducing the size of wasm binary
-    pub fn step_cargo_xml(args: &NewArgs) -> Result<(), Error> {
-        let mut cargo_xml = format!(
-            r#"[package]
-name = "{}"
-version = "0.1.0"
-edition = "2021"
-"#,
-            args.name
+    #[test]
+    fn bad_error_format_is_rejected() {
+        assert!(
+            Args::from_iter_safe(["iroha_wasm_pack", "--error-format", "xml", "build"]).is_err()
         );
-        cargo_xml.push_str(
-            r#"
-[lib]
-# A smart contract should be linked dynamically so that it may link to functions exported
-# from the host environment. The host environment executes a smart contract by
-# calling the function that smart contract exports (entry point of execution)
-crate-type = ['cdylib']
-
-[profile.release]
-strip = "debuginfo" # Remove debugging info from the binary
-panic = "abort"     # Panics are transcribed to Traps when compiling for WASM
-lto = true          # Link-time-optimization produces notable decrease in binary size
-opt-level = "z"     # Optimize for size vs speed with "s"/"z" (removes vectorization)
-codegen-units = 1   # Further reduces binary size but increases compilation time
-
-[dependencies]
-iroha_data_model = { git = "https://github.com/hyperledger/iroha/", branch = "iroha2-dev", default-features = false }
-iroha_wasm = { git = "https://github.com/hyperledger/iroha/", branch = "iroha2-dev" }
-
-[dev-dependencies]
-webassembly-test-runner = { version = "0.1.0" }
-"#);
-        let path = current_dir().unwrap().join(&args.name).join("Cargo.toml");
-        write(path.as_path(), cargo_xml.as_bytes())
-    }
-
-    /// Iroha boilerplate main entrypoint
-    pub fn step_main_entrypoint(args: &NewArgs) -> Result<(), Error> {
-        let entrypoint = r#"//! Smartcontract which creates new nft for every user
-//!
-//! This module isn't included in the build-tree,
-//! but instead it is being built by a `client/build.rs`
-
-#![no_std]
-#![no_main]
-#![allow(clippy::all)]
-
-//! Sample smartcontract which mints 1 rose for it's authority
-
-use core::str::FromStr as _;
-
-use iroha_wasm::{data_model::prelude::*, DebugExpectExt};
-
-/// Mint 1 rose for authority
-#[iroha_wasm::entrypoint(params = "[authority]")]
-fn trigger_entrypoint(authority: <Account as Identifiable>::Id) {
-    let rose_definition_id = <AssetDefinition as Identifiable>::Id::from_str("token#open")
-        .dbg_expect("Failed to parse `token#open` asset definition id");
-    let rose_id = <Asset as Identifiable>::Id::new(rose_definition_id, authority);
-
-    Instruction::Mint(MintBox::new(1_u32, rose_id)).execute();
-}    
-"#;
-        let path = current_dir()
-            .unwrap()
-            .join(&args.name)
-            .join("src")
-            .join("lib.rs");
-        write(path.as_path(), entrypoint.as_bytes())
     }
 }
@@ -0,0 +1,3318 @@
+use super::build::working_dir;
+use super::*;
+use duct::cmd;
+use std::{
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Everything required to configure and run the `iroha_wasm_pack new` command.
+#[derive(Debug, StructOpt)]
+pub struct NewArgs {
+    /// Name of the new project
+    #[structopt(required_unless = "interactive")]
+    pub name: Option<String>,
+
+    /// Ask for every option, showing its default
+    #[structopt(long = "interactive")]
+    pub interactive: bool,
+
+    /// Create the project from a git repository template instead of a built-in one
+    #[structopt(long = "template-git")]
+    pub template_git: Option<String>,
+
+    /// Create the project from a local template directory instead of a built-in one
+    #[structopt(
+        long = "template-path",
+        parse(from_os_str),
+        conflicts_with = "template-git"
+    )]
+    pub template_path: Option<PathBuf>,
+
+    /// Branch, tag or commit of the `--template-git` repository
+    #[structopt(long = "template-rev", requires = "template-git")]
+    pub template_rev: Option<String>,
+
+    /// Package description
+    #[structopt(long = "description")]
+    pub description: Option<String>,
+
+    /// SPDX identifier of the license, MIT or Apache-2.0, also writes a LICENSE file
+    #[structopt(long = "license", parse(try_from_str = parse_license))]
+    pub license: Option<String>,
+
+    /// Path of a custom license file, for licenses not supported by `--license`
+    #[structopt(long = "license-file", conflicts_with = "license")]
+    pub license_file: Option<String>,
+
+    /// Package authors, defaults to git's user.name and user.email
+    #[structopt(long = "authors")]
+    pub authors: Vec<String>,
+
+    /// Start from a bundled Iroha sample contract: multisig, nft_mint or register_account
+    #[structopt(long = "example", parse(try_from_str = parse_sample))]
+    pub example: Option<&'static Sample>,
+
+    /// Extra template variable as `key=value`, can be repeated
+    #[structopt(long = "define", parse(try_from_str = parse_define), number_of_values = 1)]
+    pub defines: Vec<(String, String)>,
+
+    /// Create the project with `cargo new` before writing the templates
+    #[structopt(long = "use-cargo-new")]
+    pub use_cargo_new: bool,
+
+    /// Build the project once it is created to check that it compiles
+    #[structopt(long = "build")]
+    pub build: bool,
+
+    /// Directory of the project, defaults to `./<name>`
+    #[structopt(long = "path", parse(from_os_str))]
+    pub path: Option<PathBuf>,
+
+    /// Inside a cargo workspace: add the project to its members, or keep it standalone
+    #[structopt(long = "workspace", default_value = "add", possible_values = &["add", "standalone"])]
+    pub workspace: WorkspaceMode,
+
+    /// Generate the template even if it doesn't support the Iroha version
+    #[structopt(long = "force-template")]
+    pub force_template: bool,
+
+    /// Name of the library and its wasm artifact, defaults to the package name
+    #[structopt(long = "lib-name", parse(try_from_str = parse_lib_name), conflicts_with = "multi")]
+    pub lib_name: Option<String>,
+
+    /// Generate the minimal skeleton of the template, without sample logic or tests
+    #[structopt(long = "bare", conflicts_with = "example")]
+    pub bare: bool,
+
+    /// Comma separated triggers to generate as a workspace sharing a `common` library
+    #[structopt(
+        long = "multi",
+        use_delimiter = true,
+        conflicts_with_all = &["example", "template-git", "template-path", "with-embedder"]
+    )]
+    pub multi: Vec<String>,
+
+    /// Generate the project without network access, also set by `CARGO_NET_OFFLINE=true`
+    #[structopt(long = "offline", conflicts_with_all = &["use-cargo-new", "template-git"])]
+    pub offline: bool,
+
+    /// Add a `local/` directory with a genesis, configs with development keys and a peer launcher
+    #[structopt(long = "with-local-env")]
+    pub with_local_env: bool,
+
+    /// Add an `integration/` crate running the built wasm in wasmtime with stub host functions
+    #[structopt(long = "with-integration-tests")]
+    pub with_integration_tests: bool,
+
+    /// Host crate to write a build.rs into that builds and embeds the contract
+    #[structopt(long = "with-embedder", parse(from_os_str))]
+    pub with_embedder: Option<PathBuf>,
+
+    /// Don't print the summary and next steps once the project is created
+    #[structopt(short = "q", long = "quiet")]
+    pub quiet: bool,
+
+    /// Largest wasm binary in bytes the build accepts, written to the package metadata
+    #[structopt(long = "size-budget")]
+    pub size_budget: Option<u64>,
+
+    /// Kind of project to create: trigger, executor or smartcontract
+    #[structopt(long = "template", default_value = "trigger", possible_values = TEMPLATE_NAMES)]
+    pub template: Template,
+
+    #[structopt(flatten)]
+    pub deps: DependencyArgs,
+
+    /// Version control to initialize: git or none
+    #[structopt(long = "vcs", default_value = "git")]
+    pub vcs: Vcs,
+
+    /// Make an initial commit of the generated files
+    #[structopt(long = "commit")]
+    pub commit: bool,
+
+    /// Nightly toolchain pinned in the generated rust-toolchain.toml
+    #[structopt(long = "toolchain", default_value = DEFAULT_TOOLCHAIN)]
+    pub toolchain: String,
+
+    /// Don't generate rust-toolchain.toml
+    #[structopt(long = "no-toolchain-file")]
+    pub no_toolchain_file: bool,
+
+    /// Don't generate .cargo/config.toml
+    #[structopt(long = "no-cargo-config")]
+    pub no_cargo_config: bool,
+
+    /// Replace an invalid project name with the suggested one
+    #[structopt(long = "name-fix")]
+    pub name_fix: bool,
+
+    /// Write into an existing non-empty directory, overwriting generated files
+    #[structopt(long = "force")]
+    pub force: bool,
+
+    /// Rust edition of the project: 2021 or 2024
+    #[structopt(long = "edition", default_value = DEFAULT_EDITION, parse(try_from_str = parse_edition))]
+    pub edition: String,
+}
+
+/// Licenses `--license` can write the text of
+const LICENSES: &[(&str, &str)] = &[("MIT", MIT_LICENSE), ("Apache-2.0", APACHE_LICENSE)];
+
+pub fn parse_license(s: &str) -> Result<String, String> {
+    match LICENSES.iter().find(|(id, _)| *id == s) {
+        Some((id, _)) => Ok((*id).to_owned()),
+        None => Err(format!(
+            "unsupported license `{}`, expected MIT or Apache-2.0, use `--license-file` for other licenses",
+            s
+        )),
+    }
+}
+
+/// Quote `value` as a TOML basic string
+pub fn toml_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// `name <email>` from git config, like cargo's default author
+fn git_author() -> Option<String> {
+    let config = |key: &str| {
+        cmd!("git", "config", key)
+            .stderr_null()
+            .read()
+            .ok()
+            .filter(|value| !value.trim().is_empty())
+    };
+    match (config("user.name"), config("user.email")) {
+        (Some(name), Some(email)) => Some(format!("{} <{}>", name.trim(), email.trim())),
+        (Some(name), None) => Some(name.trim().to_owned()),
+        _ => None,
+    }
+}
+
+/// Newest edition supported by `DEFAULT_TOOLCHAIN`
+pub const DEFAULT_EDITION: &str = "2021";
+
+pub fn parse_edition(s: &str) -> Result<String, String> {
+    match s {
+        "2021" | "2024" => Ok(s.to_owned()),
+        _ => Err(format!(
+            "unsupported edition `{}`, expected 2021 or 2024",
+            s
+        )),
+    }
+}
+
+/// Rust keywords, which can't name a crate
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Names that collide with the standard library, test harness or cargo artifacts
+const DENIED_NAMES: &[&str] = &[
+    "alloc",
+    "build",
+    "core",
+    "deps",
+    "examples",
+    "incremental",
+    "proc_macro",
+    "std",
+    "test",
+];
+
+/// Check `name` against cargo's package name rules and the names known to cause trouble
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("the project name must not be empty".to_owned());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        return Err(format!(
+            "invalid character `{}` ({:?}) in project name `{}`, only ASCII letters, digits, `-` and `_` are allowed",
+            c, c, name
+        ));
+    }
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(format!(
+            "project name `{}` must not start with a digit",
+            name
+        ));
+    }
+    let crate_name = name.replace('-', "_");
+    if KEYWORDS.contains(&crate_name.as_str()) {
+        return Err(format!("project name `{}` is a Rust keyword", name));
+    }
+    if DENIED_NAMES.contains(&crate_name.as_str()) {
+        return Err(format!(
+            "project name `{}` collides with a standard crate or cargo directory",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Check that `--lib-name` is a Rust identifier, cargo uses it as the crate name
+pub fn parse_lib_name(s: &str) -> Result<String, String> {
+    let valid = !s.is_empty()
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !s.starts_with(|c: char| c.is_ascii_digit())
+        && !KEYWORDS.contains(&s);
+    if valid {
+        return Ok(s.to_owned());
+    }
+    Err(format!(
+        "lib name `{}` is not a valid Rust identifier, `{}` would be",
+        s,
+        suggest_name(s).replace('-', "_")
+    ))
+}
+
+/// Closest valid name to `name`
+pub fn suggest_name(name: &str) -> String {
+    let mut suggestion: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if suggestion.is_empty() || suggestion.starts_with(|c: char| c.is_ascii_digit()) {
+        suggestion.insert_str(0, "contract_");
+    }
+    if validate_name(&suggestion).is_err() {
+        suggestion.push_str("_contract");
+    }
+    suggestion
+}
+
+impl NewArgs {
+    /// `[package]` keys of the metadata flags
+    fn package_keys(&self) -> Vec<String> {
+        let mut keys = Vec::new();
+        let authors = if self.authors.is_empty() {
+            git_author().into_iter().collect()
+        } else {
+            self.authors.clone()
+        };
+        if !authors.is_empty() {
+            let authors: Vec<String> = authors.iter().map(|x| toml_string(x)).collect();
+            keys.push(format!("authors = [{}]", authors.join(", ")));
+        }
+        if let Some(description) = &self.description {
+            keys.push(format!("description = {}", toml_string(description)));
+        }
+        if let Some(license) = &self.license {
+            keys.push(format!("license = {}", toml_string(license)));
+        }
+        if let Some(license_file) = &self.license_file {
+            keys.push(format!("license-file = {}", toml_string(license_file)));
+        }
+        keys
+    }
+
+    /// Project name, empty until the wizard asked for it
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or_default()
+    }
+
+    /// Directory the project is created in, `--path` or `./<name>`
+    pub fn dir(&self) -> Result<PathBuf, Error> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => PathBuf::from(self.name()),
+        };
+        Ok(working_dir()?.join(path))
+    }
+
+    /// Ask for the options on a TTY, keeping the defaults otherwise
+    fn wizard(&mut self) -> Result<(), Error> {
+        use std::io::{stdin, IsTerminal};
+        if !stdin().is_terminal() {
+            if self.name.is_none() {
+                return Err(Error::other(
+                    "the project name is required when stdin is not a terminal",
+                ));
+            }
+            return Ok(());
+        }
+        loop {
+            let name = ask("Project name", self.name())?;
+            match validate_name(&name) {
+                Ok(()) => {
+                    self.name = Some(name);
+                    break;
+                }
+                Err(reason) => eprintln!("{}", reason),
+            }
+        }
+        loop {
+            let template = ask(
+                "Template (trigger, executor, smartcontract)",
+                self.template.as_str(),
+            )?;
+            match template.parse() {
+                Ok(template) => {
+                    self.template = template;
+                    break;
+                }
+                Err(reason) => eprintln!("{}", reason),
+            }
+        }
+        let default_pin = match (&self.deps.branch, &self.deps.iroha_version) {
+            (Some(branch), _) => format!("branch:{}", branch),
+            (None, Some(version)) => version.clone(),
+            (None, None) => DEFAULT_IROHA_VERSION.to_owned(),
+        };
+        let pin = ask("Iroha tag or revision, or `branch:<name>`", &default_pin)?;
+        match pin.strip_prefix("branch:") {
+            Some(branch) => {
+                self.deps.branch = Some(branch.to_owned());
+                self.deps.iroha_version = None;
+            }
+            None => {
+                self.deps.branch = None;
+                self.deps.iroha_version = Some(pin);
+            }
+        }
+        let default_git = if self.vcs == Vcs::Git { "y" } else { "n" };
+        self.vcs = match ask("Initialize a git repository? (y/n)", default_git)?.as_str() {
+            "y" | "yes" => Vcs::Git,
+            _ => Vcs::None,
+        };
+        loop {
+            let default_budget = self.size_budget.unwrap_or(MAX_WASM_SIZE).to_string();
+            match ask("Size budget of the wasm binary in bytes", &default_budget)?.parse() {
+                Ok(budget) => {
+                    self.size_budget = Some(budget).filter(|budget| *budget != MAX_WASM_SIZE);
+                    break;
+                }
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        eprintln!(
+            "\nThe same project can be created with:\n\n    {}\n",
+            self.command_line()
+        );
+        Ok(())
+    }
+
+    /// Non-interactive command line creating the same project
+    fn command_line(&self) -> String {
+        let mut line = format!(
+            "iroha_wasm_pack new {} --template {}",
+            self.name(),
+            self.template.as_str()
+        );
+        match (&self.deps.branch, &self.deps.iroha_version) {
+            (Some(branch), _) => line.push_str(&format!(" --branch {}", branch)),
+            (None, Some(version)) => line.push_str(&format!(" --iroha-version {}", version)),
+            (None, None) => {}
+        }
+        if self.deps.registry_deps {
+            line.push_str(" --registry-deps");
+        }
+        if self.vcs == Vcs::None {
+            line.push_str(" --vcs none");
+        }
+        if let Some(budget) = self.size_budget {
+            line.push_str(&format!(" --size-budget {}", budget));
+        }
+        if let Some(path) = &self.path {
+            line.push_str(&format!(" --path {}", path.display()));
+        }
+        if self.bare {
+            line.push_str(" --bare");
+        }
+        if self.build {
+            line.push_str(" --build");
+        }
+        line
+    }
+}
+
+/// Print `question` with its default and read the answer, an empty one picks the default
+fn ask(question: &str, default: &str) -> Result<String, Error> {
+    use std::io::{stderr, stdin, Write};
+    eprint!("{} [{}]: ", question, default);
+    stderr().flush()?;
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    Ok(if answer.is_empty() { default } else { answer }.to_owned())
+}
+
+/// Version control systems `new` can initialize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vcs {
+    Git,
+    None,
+}
+
+impl FromStr for Vcs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "git" => Ok(Vcs::Git),
+            "none" => Ok(Vcs::None),
+            _ => Err(format!("unknown vcs `{}`, expected one of: git, none", s)),
+        }
+    }
+}
+
+/// Iroha crates a project depends on
+pub const IROHA_CRATES: &[&str] = &["iroha_data_model", "iroha_wasm", "iroha_validator"];
+
+/// Iroha tag the generated dependencies are pinned to by default
+pub const DEFAULT_IROHA_VERSION: &str = "v2.0.0-pre-rc.11";
+
+/// Git repository of the Iroha crates
+pub const IROHA_GIT: &str = "https://github.com/hyperledger/iroha/";
+
+/// How the generated project depends on the Iroha crates.
+#[derive(Debug, StructOpt)]
+pub struct DependencyArgs {
+    /// Iroha git tag or revision to pin the dependencies to
+    #[structopt(long = "iroha-version", alias = "rev")]
+    pub iroha_version: Option<String>,
+
+    /// Follow an Iroha git branch instead of pinning a tag or revision
+    #[structopt(long = "branch", conflicts_with = "iroha-version")]
+    pub branch: Option<String>,
+
+    /// Depend on the Iroha crates released on crates.io instead of git
+    #[structopt(long = "registry-deps", conflicts_with = "branch")]
+    pub registry_deps: bool,
+}
+
+impl DependencyArgs {
+    /// Source of the Iroha dependencies as manifest key-value pairs,
+    /// like `version = "2.0.0-pre-rc.11"` or `git = "...", tag = "v2.0.0-pre-rc.11"`
+    pub fn source(&self) -> String {
+        let pairs: Vec<String> = self
+            .source_pairs()
+            .into_iter()
+            .map(|(key, value)| format!("{} = {}", key, toml_string(&value)))
+            .collect();
+        pairs.join(", ")
+    }
+
+    /// Release candidate the Iroha dependencies are pinned to, unknown for branches and revisions
+    pub fn iroha_rc(&self) -> Option<u32> {
+        if self.branch.is_some() {
+            return None;
+        }
+        rc_number(
+            self.iroha_version
+                .as_deref()
+                .unwrap_or(DEFAULT_IROHA_VERSION),
+        )
+    }
+
+    /// Keys and values of `source`
+    pub fn source_pairs(&self) -> Vec<(&'static str, String)> {
+        if self.registry_deps {
+            let version = self
+                .iroha_version
+                .as_deref()
+                .unwrap_or(DEFAULT_IROHA_VERSION);
+            return vec![("version", version.trim_start_matches('v').to_owned())];
+        }
+        vec![("git", IROHA_GIT.to_owned()), self.git_ref()]
+    }
+
+    /// Git reference of the Iroha dependencies, like `("tag", "v2.0.0-pre-rc.11")`
+    fn git_ref(&self) -> (&'static str, String) {
+        if let Some(branch) = &self.branch {
+            return ("branch", branch.clone());
+        }
+        let version = self
+            .iroha_version
+            .as_deref()
+            .unwrap_or(DEFAULT_IROHA_VERSION);
+        let is_rev = version.len() >= 7 && version.chars().all(|c| c.is_ascii_hexdigit());
+        if is_rev {
+            ("rev", version.to_owned())
+        } else {
+            ("tag", version.to_owned())
+        }
+    }
+
+    /// Warn if the tag or branch doesn't exist, skipped when git can't reach the remote
+    pub fn check_ref_exists(&self) {
+        if self.registry_deps {
+            return;
+        }
+        let (kind, name) = match (&self.branch, &self.iroha_version) {
+            (Some(branch), _) => ("heads", branch),
+            (None, Some(version)) if self.git_ref().0 == "tag" => ("tags", version),
+            _ => return,
+        };
+        let reference = format!("refs/{}/{}", kind, name);
+        match cmd!("git", "ls-remote", IROHA_GIT, &reference)
+            .stderr_null()
+            .read()
+        {
+            Ok(stdout) if stdout.trim().is_empty() => {
+                eprintln!("warning: {} was not found in {}", reference, IROHA_GIT)
+            }
+            Ok(_) => {}
+            Err(err) => info!("Skipped checking {}, error = {}", reference, err),
+        }
+    }
+}
+
+/// Kinds of Iroha wasm projects `new` can scaffold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    /// Trigger executed by the peer when its filter matches
+    Trigger,
+    /// Executor validating every instruction and query
+    Executor,
+    /// Smart contract submitted as a transaction
+    SmartContract,
+}
+
+/// Names `--template` accepts
+pub const TEMPLATE_NAMES: &[&str] = &["trigger", "executor", "smartcontract"];
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "trigger" => Ok(Template::Trigger),
+            "executor" => Ok(Template::Executor),
+            "smartcontract" => Ok(Template::SmartContract),
+            _ => Err(format!(
+                "unknown template `{}`, expected one of: trigger, executor, smartcontract",
+                s
+            )),
+        }
+    }
+}
+
+impl Template {
+    /// Dependencies added on top of the common `iroha_data_model` and `iroha_wasm`
+    pub fn extra_dependencies(&self, source: &str) -> String {
+        match self {
+            Template::Trigger | Template::SmartContract => String::new(),
+            Template::Executor => format!("iroha_validator = {{ {} }}\n", source),
+        }
+    }
+
+    /// Every built-in template
+    pub const ALL: [Template; 3] = [
+        Template::Trigger,
+        Template::Executor,
+        Template::SmartContract,
+    ];
+
+    /// One line description
+    pub fn description(&self) -> &'static str {
+        match self {
+            Template::Trigger => "Trigger minting a rose for its authority",
+            Template::Executor => {
+                "Executor delegating to the default validation, with marked extension points"
+            }
+            Template::SmartContract => "Smartcontract querying the accounts of the network",
+        }
+    }
+
+    /// Name of the template as accepted by `--template`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Template::Trigger => "trigger",
+            Template::Executor => "executor",
+            Template::SmartContract => "smartcontract",
+        }
+    }
+
+    /// Size budget written to the package metadata when `--size-budget` isn't given
+    pub fn default_size_budget(&self) -> Option<u64> {
+        match self {
+            Template::Trigger | Template::SmartContract => None,
+            // Executors carry the whole default validation and are several times larger.
+            Template::Executor => Some(4 * MAX_WASM_SIZE),
+        }
+    }
+
+    /// Commands printed once a project is created, rendered with the template variables
+    pub fn next_steps(&self) -> &'static [&'static str] {
+        match self {
+            Template::Trigger => &[
+                "cd {{project_dir}}",
+                "iroha_wasm_pack build --release",
+                "cargo test  # runs tests/ with webassembly-test-runner",
+                "# register target/wasm32-unknown-unknown/release/{{lib_name}}_optimized.wasm with a `Register<Trigger>` instruction",
+            ],
+            Template::Executor => &[
+                "cd {{project_dir}}",
+                "iroha_wasm_pack build --release",
+                "cargo test  # runs tests/ with webassembly-test-runner",
+                "# install target/wasm32-unknown-unknown/release/{{lib_name}}_optimized.wasm with an `Upgrade<Executor>` instruction",
+            ],
+            Template::SmartContract => &[
+                "cd {{project_dir}}",
+                "iroha_wasm_pack build --release",
+                "cargo test  # runs tests/ with webassembly-test-runner",
+                "# submit target/wasm32-unknown-unknown/release/{{lib_name}}_optimized.wasm as the instructions of a transaction",
+            ],
+        }
+    }
+
+    /// Variants for the Iroha releases the template supports, newest first
+    pub fn variants(&self) -> &'static [TemplateVariant] {
+        match self {
+            Template::Trigger => &TRIGGER_VARIANTS,
+            Template::Executor => &EXECUTOR_VARIANTS,
+            Template::SmartContract => &SMARTCONTRACT_VARIANTS,
+        }
+    }
+
+    /// Variant for the `v2.0.0-pre-rc.<rc>` release, the newest one if `rc` is unknown
+    pub fn variant(&self, rc: Option<u32>) -> Option<&'static TemplateVariant> {
+        match rc {
+            Some(rc) => self.variants().iter().find(|variant| variant.supports(rc)),
+            None => self.variants().first(),
+        }
+    }
+
+    /// Supported Iroha releases, like `v2.0.0-pre-rc.10+`
+    pub fn supported_versions(&self) -> String {
+        let since = self.variants().iter().map(|variant| variant.since_rc).min();
+        let until = self.variants().iter().map(|variant| variant.until_rc);
+        let until = if until.clone().any(|until| until.is_none()) {
+            None
+        } else {
+            until.flatten().max()
+        };
+        match (since.unwrap_or_default(), until) {
+            (since, Some(until)) => format!("v2.0.0-pre-rc.{}..rc.{}", since, until),
+            (since, None) => format!("v2.0.0-pre-rc.{}+", since),
+        }
+    }
+}
+
+/// Entrypoints of a template for a range of Iroha `v2.0.0-pre-rc.N` releases.
+#[derive(Debug)]
+pub struct TemplateVariant {
+    /// Oldest supported release candidate
+    pub since_rc: u32,
+    /// Newest supported release candidate, `None` while it is current
+    pub until_rc: Option<u32>,
+    /// Content of `src/lib.rs`
+    pub entrypoint: &'static str,
+    /// Content of `src/lib.rs` with `--bare`
+    pub bare_entrypoint: &'static str,
+}
+
+impl TemplateVariant {
+    pub fn supports(&self, rc: u32) -> bool {
+        rc >= self.since_rc && self.until_rc.map_or(true, |until| rc <= until)
+    }
+}
+
+const TRIGGER_VARIANTS: [TemplateVariant; 2] = [
+    TemplateVariant {
+        since_rc: 10,
+        until_rc: None,
+        entrypoint: TRIGGER_ENTRYPOINT,
+        bare_entrypoint: BARE_TRIGGER_ENTRYPOINT,
+    },
+    // The entrypoint macro didn't take the `params` list yet.
+    TemplateVariant {
+        since_rc: 7,
+        until_rc: Some(9),
+        entrypoint: LEGACY_TRIGGER_ENTRYPOINT,
+        bare_entrypoint: LEGACY_BARE_TRIGGER_ENTRYPOINT,
+    },
+];
+
+const EXECUTOR_VARIANTS: [TemplateVariant; 2] = [
+    TemplateVariant {
+        since_rc: 15,
+        until_rc: None,
+        entrypoint: EXECUTOR_ENTRYPOINT,
+        bare_entrypoint: BARE_EXECUTOR_ENTRYPOINT,
+    },
+    // A single `validate` entrypoint returning a `Verdict`, before migrations existed.
+    TemplateVariant {
+        since_rc: 11,
+        until_rc: Some(14),
+        entrypoint: LEGACY_EXECUTOR_ENTRYPOINT,
+        bare_entrypoint: LEGACY_BARE_EXECUTOR_ENTRYPOINT,
+    },
+];
+
+const SMARTCONTRACT_VARIANTS: [TemplateVariant; 1] = [TemplateVariant {
+    since_rc: 9,
+    until_rc: None,
+    entrypoint: SMARTCONTRACT_ENTRYPOINT,
+    bare_entrypoint: BARE_SMARTCONTRACT_ENTRYPOINT,
+}];
+
+/// Release candidate number of an Iroha version like `v2.0.0-pre-rc.11`
+pub fn rc_number(version: &str) -> Option<u32> {
+    let rc = version.split("-pre-rc.").nth(1)?;
+    let digits: String = rc.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+impl RunArgs for NewArgs {
+    fn run(mut self) -> Result<CommandReport, Error> {
+        use std::io::{stdin, IsTerminal};
+        if self.interactive {
+            self.wizard()?;
+        }
+        self.apply_config_defaults()?;
+        let mut report = CommandReport::default();
+        if self.offline() && !self.deps.registry_deps {
+            if registry_mirror_configured() {
+                info!("Using registry dependencies from the configured crates.io replacement");
+                self.deps.registry_deps = true;
+            } else {
+                eprintln!("note: the Iroha dependencies come from git, the first build will need the network to fetch them");
+            }
+        }
+        if let Err(reason) = validate_name(self.name()) {
+            let suggestion = suggest_name(self.name());
+            let accepted = self.name_fix
+                || (stdin().is_terminal() && {
+                    eprint!("{}\nUse `{}` instead? [y/N] ", reason, suggestion);
+                    let mut answer = String::new();
+                    stdin().read_line(&mut answer)?;
+                    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+                });
+            if !accepted {
+                return Err(Error::other(format!(
+                    "{}, `{}` would be valid, pass `--name-fix` to use it",
+                    reason, suggestion
+                )));
+            }
+            self.name = Some(suggestion);
+        }
+        let builtin =
+            self.example.is_none() && self.template_git.is_none() && self.template_path.is_none();
+        if let (true, Some(rc)) = (builtin, self.deps.iroha_rc()) {
+            if self.template.variant(Some(rc)).is_none() {
+                let reason = format!(
+                    "the {} template supports Iroha {}, not v2.0.0-pre-rc.{}",
+                    self.template.as_str(),
+                    self.template.supported_versions(),
+                    rc
+                );
+                if !self.force_template {
+                    return Err(Error::other(format!(
+                        "{}, pass `--force-template` to generate it anyway",
+                        reason
+                    )));
+                }
+                eprintln!("warning: {}, the project may not compile", reason);
+                report
+                    .warnings
+                    .push(format!("{}, the project may not compile", reason));
+            }
+        }
+        for (i, trigger) in self.multi.iter().enumerate() {
+            if let Err(reason) = validate_name(trigger) {
+                return Err(Error::other(format!("--multi: {}", reason)));
+            }
+            if trigger == MULTI_COMMON || self.multi[..i].contains(trigger) {
+                return Err(Error::other(format!(
+                    "--multi: `{}` is used more than once in the workspace",
+                    trigger
+                )));
+            }
+        }
+        let scaffold = Scaffold::for_new(&self)?;
+        let dir = scaffold.dir.clone();
+        let existed = dir.exists();
+        if existed && !self.force && fs::read_dir(&dir)?.next().is_some() {
+            return Err(Error::other(format!(
+                "{} already exists and is not empty, pass `--force` to write into it",
+                dir.display()
+            )));
+        }
+        let steps: &[(&str, fn(&NewArgs, &Scaffold) -> Result<(), Error>)] =
+            if self.template_git.is_some() {
+                &[
+                    ("template-git", step_template_git),
+                    ("toolchain-file", step_toolchain_file),
+                    ("cargo-config", step_cargo_config),
+                    ("workspace", step_workspace),
+                    ("embedder", step_embedder),
+                    ("lockfile", step_lockfile),
+                    ("vcs", step_vcs),
+                ]
+            } else if !self.multi.is_empty() {
+                &[
+                    ("multi", step_multi),
+                    ("license", step_license),
+                    ("toolchain-file", step_toolchain_file),
+                    ("cargo-config", step_cargo_config),
+                    ("lockfile", step_lockfile),
+                    ("vcs", step_vcs),
+                ]
+            } else if self.template_path.is_some() {
+                &[
+                    ("template-path", step_template_path),
+                    ("toolchain-file", step_toolchain_file),
+                    ("cargo-config", step_cargo_config),
+                    ("workspace", step_workspace),
+                    ("embedder", step_embedder),
+                    ("lockfile", step_lockfile),
+                    ("vcs", step_vcs),
+                ]
+            } else {
+                &[
+                    ("project-dir", step_project_dir),
+                    ("cargo-xml", step_cargo_xml),
+                    ("main-entrypoint", step_main_entrypoint),
+                    ("tests", step_tests),
+                    ("integration-tests", step_integration_tests),
+                    ("local-env", step_local_env),
+                    ("license", step_license),
+                    ("toolchain-file", step_toolchain_file),
+                    ("cargo-config", step_cargo_config),
+                    ("workspace", step_workspace),
+                    ("embedder", step_embedder),
+                    ("lockfile", step_lockfile),
+                    ("vcs", step_vcs),
+                ]
+            };
+        for (name, step) in steps {
+            let span = tracing::info_span!("step", step = name, package = self.name());
+            let _entered = span.enter();
+            if let Err(err) = step(&self, &scaffold) {
+                // Remove a half created project so that running `new` again works.
+                if !existed && (!dir.exists() || fs::remove_dir_all(&dir).is_ok()) {
+                    return Err(err);
+                }
+                return Err(Error::other(format!(
+                    "{}\n{} was left with partially generated files",
+                    err,
+                    dir.display()
+                )));
+            }
+        }
+        if !self.offline() {
+            self.deps.check_ref_exists();
+        }
+        if self.build {
+            report.summary.push(self.smoke_build(&dir)?);
+        }
+        if !self.quiet {
+            report.summary.extend(self.next_steps(&scaffold)?);
+        }
+        report.steps = logging::recorded_timings();
+        report.files.push(dir);
+        Ok(report)
+    }
+}
+
+/// File of a custom template with its next steps, removed from the created project
+const NEXT_STEPS_FILE: &str = ".iroha_wasm_pack-next-steps";
+
+/// Whether a cargo config replaces crates.io with a vendored directory or a mirror.
+///
+/// Looks at the configs cargo would read from the current directory and `CARGO_HOME`.
+fn registry_mirror_configured() -> bool {
+    let mut configs = Vec::new();
+    if let Ok(dir) = current_dir() {
+        for ancestor in dir.ancestors() {
+            configs.push(ancestor.join(".cargo"));
+        }
+    }
+    match std::env::var_os("CARGO_HOME") {
+        Some(home) => configs.push(PathBuf::from(home)),
+        None => {
+            if let Some(home) = std::env::var_os("HOME") {
+                configs.push(Path::new(&home).join(".cargo"));
+            }
+        }
+    }
+    replaces_crates_io(&configs)
+}
+
+/// Whether a `config.toml` or `config` in one of the `configs` directories sets
+/// `source.crates-io.replace-with`
+fn replaces_crates_io(configs: &[PathBuf]) -> bool {
+    configs
+        .iter()
+        .flat_map(|dir| [dir.join("config.toml"), dir.join("config")])
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .filter_map(|config| config.parse::<toml::Value>().ok())
+        .any(|config| {
+            config
+                .get("source")
+                .and_then(|source| source.get("crates-io"))
+                .and_then(|crates_io| crates_io.get("replace-with"))
+                .is_some()
+        })
+}
+
+impl NewArgs {
+    /// Whether `--offline` or `CARGO_NET_OFFLINE=true` was given
+    fn offline(&self) -> bool {
+        self.offline
+            || std::env::var("CARGO_NET_OFFLINE").map_or(false, |offline| offline == "true")
+    }
+
+    /// Fill the options left out from `[new]` of the iroha_wasm_pack.toml files above the project
+    fn apply_config_defaults(&mut self) -> Result<(), Error> {
+        use super::project_config::ProjectConfig;
+        let start = match &self.path {
+            Some(path) => path.clone(),
+            None => current_dir()?,
+        };
+        let project = ProjectConfig::discover(&start)?;
+        if self.license.is_none() && self.license_file.is_none() {
+            if let Some((license, _)) = project.get(|file| file.new.license.clone()) {
+                self.license = Some(parse_license(&license).map_err(Error::other)?);
+            }
+        }
+        if self.authors.is_empty() {
+            if let Some((authors, _)) = project.get(|file| file.new.authors.clone()) {
+                self.authors = authors;
+            }
+        }
+        let templated = self.template_git.is_some() || self.template_path.is_some();
+        if !templated && !self.offline() && !self.use_cargo_new {
+            if let Some((url, _)) = project.get(|file| file.new.template_git.clone()) {
+                self.template_git = Some(url);
+            }
+        }
+        if self.size_budget.is_none() {
+            self.size_budget = project
+                .get(|file| file.new.size_budget)
+                .map(|(budget, _)| budget);
+        }
+        Ok(())
+    }
+
+    /// The summary of the created project and what to do next
+    fn next_steps(&self, scaffold: &Scaffold) -> Result<Vec<String>, Error> {
+        let vars = scaffold.vars();
+        let custom = scaffold.dir.join(NEXT_STEPS_FILE);
+        let steps: Vec<String> = if custom.exists() {
+            let steps = fs::read_to_string(&custom)?;
+            fs::remove_file(&custom)?;
+            steps.lines().map(str::to_owned).collect()
+        } else {
+            let steps = if !self.multi.is_empty() {
+                &MULTI_NEXT_STEPS[..]
+            } else if self.template_git.is_some() || self.template_path.is_some() {
+                &scaffold.template.next_steps()[..2]
+            } else {
+                scaffold.template.next_steps()
+            };
+            let mut rendered = Vec::new();
+            // A bare project has no tests to run.
+            for step in steps
+                .iter()
+                .filter(|step| !(self.bare && step.starts_with("cargo test")))
+            {
+                rendered.push(render(step, &vars, "next steps")?);
+            }
+            rendered
+        };
+        let kind = match (&self.template_git, &self.template_path) {
+            (Some(url), _) => url.clone(),
+            (None, Some(path)) => path.display().to_string(),
+            (None, None) => scaffold.template.as_str().to_owned(),
+        };
+        let mut lines = vec![format!(
+            "\nCreated {} `{}` at {}",
+            kind,
+            self.name(),
+            scaffold.dir.display()
+        )];
+        if let Some(lib_name) = &self.lib_name {
+            lines.push(format!(
+                "Library `{}` builds to {}_optimized.wasm",
+                lib_name, lib_name
+            ));
+        }
+        lines.push(format!(
+            "Pinned Iroha dependencies to {}",
+            self.deps.source()
+        ));
+        lines.push("\nNext steps:".to_owned());
+        for step in steps.iter().filter(|step| !step.trim().is_empty()) {
+            lines.push(format!("  {}", step));
+        }
+        Ok(lines)
+    }
+
+    /// Run a debug `iroha_wasm_pack build` without wasm-opt in the created project
+    fn smoke_build(&self, dir: &Path) -> Result<String, Error> {
+        use std::env::set_current_dir;
+        if self.offline() {
+            return Ok("Skipped the build check, dependencies can't be fetched offline".to_owned());
+        }
+        let args =
+            BuildArgs::from_iter_safe(&["build", "--skip-opt", "--toolchain", &self.toolchain])?;
+        let previous = current_dir()?;
+        set_current_dir(dir)?;
+        let result = build(&args);
+        set_current_dir(previous)?;
+        match result {
+            Ok(_) => Ok(format!("Checked that {} builds", dir.display())),
+            Err(err) => Err(Error::other(format!(
+                "{} was created but doesn't build",
+                dir.display()
+            ))
+            .caused_by(err)),
+        }
+    }
+}
+
+/// Writes a file to disk.
+pub fn write<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<(), Error> {
+    let path = path.as_ref();
+    if let Err(err) = fs::write(path, contents.as_ref()) {
+        return Err(Error::other(format!("write to {} failed", path.display())).caused_by(err));
+    }
+    Ok(())
+}
+
+/// Create the project directory, the files are written by the following steps
+pub fn step_project_dir(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    if args.use_cargo_new {
+        return step_cargo_new(args, scaffold);
+    }
+    let src = scaffold.dir.join("src");
+    if let Err(err) = fs::create_dir_all(&src) {
+        return Err(Error::other(format!("create {} failed", src.display())).caused_by(err));
+    }
+    Ok(())
+}
+
+/// Init project by `cargo new --lib`, version control is set up by `step_vcs`.
+///
+/// An existing empty directory is initialized with `cargo init` instead, and an
+/// existing non-empty one (only allowed with `--force`) is left to the other steps.
+pub fn step_cargo_new(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    let dir = &scaffold.dir;
+    let result = if !dir.exists() {
+        if let Some(parent) = dir.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        cmd!(
+            "cargo",
+            "new",
+            "--lib",
+            "--vcs",
+            "none",
+            "--name",
+            args.name(),
+            dir
+        )
+        .run()
+    } else if fs::read_dir(dir)?.next().is_none() {
+        cmd!(
+            "cargo",
+            "init",
+            "--lib",
+            "--vcs",
+            "none",
+            "--name",
+            args.name(),
+            dir
+        )
+        .run()
+    } else {
+        return Ok(());
+    };
+    if let Err(err) = result {
+        return Err(Error::other("init project failed").caused_by(err));
+    }
+    Ok(())
+}
+
+/// Project files shared by `new` and `init`
+pub struct Scaffold {
+    /// Project directory
+    pub dir: PathBuf,
+    /// Package name
+    pub name: String,
+    /// Kind of project
+    pub template: Template,
+    /// Source of the Iroha dependencies, like `version = "2.0.0-pre-rc.11"`
+    pub source: String,
+    /// Rust edition
+    pub edition: String,
+    /// Largest wasm binary the build accepts, if not the default
+    pub size_budget: Option<u64>,
+    /// Extra `[package]` keys, like `description = "..."`
+    pub package_keys: Vec<String>,
+    /// Variables given by `--define`, overriding the built-in ones
+    pub defines: Vec<(String, String)>,
+    /// Sample contract used instead of the template's entrypoint
+    pub sample: Option<&'static Sample>,
+    /// Write the minimal entrypoint and no example tests
+    pub bare: bool,
+    /// `[lib] name`, if not derived from the package name
+    pub lib_name: Option<String>,
+    /// Release candidate of the Iroha pin, picks the variant of the template
+    pub iroha_rc: Option<u32>,
+}
+
+/// Iroha sample contract bundled with the tool.
+#[derive(Debug)]
+pub struct Sample {
+    /// Name accepted by `new --example`
+    pub name: &'static str,
+    /// One line description
+    pub description: &'static str,
+    /// Kind of entrypoint the sample has
+    pub template: Template,
+    /// Content of `src/lib.rs`
+    pub source: &'static str,
+}
+
+/// Samples `new --example` can start from
+pub const SAMPLES: &[Sample] = &[
+    Sample {
+        name: "multisig",
+        description: "Trigger executing an instruction once enough signatories approved it",
+        template: Template::Trigger,
+        source: MULTISIG_SAMPLE,
+    },
+    Sample {
+        name: "nft_mint",
+        description: "Smartcontract registering an NFT for every account",
+        template: Template::SmartContract,
+        source: NFT_MINT_SAMPLE,
+    },
+    Sample {
+        name: "register_account",
+        description: "Smartcontract registering a new account in a domain",
+        template: Template::SmartContract,
+        source: REGISTER_ACCOUNT_SAMPLE,
+    },
+];
+
+pub fn parse_sample(s: &str) -> Result<&'static Sample, String> {
+    match SAMPLES.iter().find(|sample| sample.name == s) {
+        Some(sample) => Ok(sample),
+        None => {
+            let names: Vec<&str> = SAMPLES.iter().map(|sample| sample.name).collect();
+            Err(format!(
+                "unknown example `{}`, available examples are: {}",
+                s,
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+/// Template variables by name
+pub type TemplateVars = std::collections::BTreeMap<String, String>;
+
+/// Replace every `{{name}}` in `template` with its variable, `\{{` stands for a literal `{{`.
+///
+/// Only names made of ASCII letters, digits and `_` are placeholders, so `{{}}` in Rust
+/// format strings is left alone. `file` names the template in errors.
+pub fn render(template: &str, vars: &TemplateVars, file: &str) -> Result<String, Error> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            rendered.push_str(&rest[..start - 1]);
+            rendered.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let name = match after.find("}}") {
+            Some(end) => &after[..end],
+            None => {
+                rendered.push_str("{{");
+                rest = after;
+                continue;
+            }
+        };
+        let is_placeholder =
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_placeholder {
+            rendered.push_str("{{");
+            rest = after;
+            continue;
+        }
+        match vars.get(name) {
+            Some(value) => rendered.push_str(value),
+            None => {
+                return Err(Error::other(format!(
+                    "unknown template variable `{}` in {}, define it with `--define {}=<value>`",
+                    name, file, name
+                )))
+            }
+        }
+        rest = &after[name.len() + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(rendered)
+}
+
+/// Parse a `--define key=value` pair
+pub fn parse_define(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value))
+            if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+        {
+            Ok((key.to_owned(), value.to_owned()))
+        }
+        _ => Err(format!(
+            "invalid define `{}`, expected `key=value` with a key of letters, digits and `_`",
+            s
+        )),
+    }
+}
+
+impl Scaffold {
+    /// Scaffold of the project created by `new`
+    pub fn for_new(args: &NewArgs) -> Result<Self, Error> {
+        let template = args.example.map_or(args.template, |sample| sample.template);
+        Ok(Scaffold {
+            dir: args.dir()?,
+            name: args.name().to_owned(),
+            template,
+            source: args.deps.source(),
+            edition: args.edition.clone(),
+            size_budget: args.size_budget.or(template.default_size_budget()),
+            package_keys: args.package_keys(),
+            defines: args.defines.clone(),
+            sample: args.example,
+            bare: args.bare,
+            lib_name: args.lib_name.clone(),
+            iroha_rc: args.deps.iroha_rc(),
+        })
+    }
+
+    /// Variables available to the templates
+    pub fn vars(&self) -> TemplateVars {
+        let mut vars = TemplateVars::new();
+        let mut set = |key: &str, value: String| {
+            vars.insert(key.to_owned(), value);
+        };
+        set("project_name", self.name.clone());
+        set(
+            "project_dir",
+            match current_dir() {
+                Ok(cwd) => self.dir.strip_prefix(cwd).unwrap_or(&self.dir),
+                Err(_) => &self.dir,
+            }
+            .display()
+            .to_string(),
+        );
+        set("crate_name", self.name.replace('-', "_"));
+        set(
+            "lib_name",
+            self.lib_name
+                .clone()
+                .unwrap_or_else(|| self.name.replace('-', "_")),
+        );
+        set(
+            "lib_keys",
+            self.lib_name
+                .as_ref()
+                .map(|name| format!("name = {}\n", toml_string(name)))
+                .unwrap_or_default(),
+        );
+        set("edition", self.edition.clone());
+        set("template", self.template.as_str().to_owned());
+        set("iroha_source", self.source.clone());
+        set(
+            "package_keys",
+            self.package_keys
+                .iter()
+                .map(|key| format!("{}\n", key))
+                .collect(),
+        );
+        set(
+            "extra_dependencies",
+            self.template.extra_dependencies(&self.source),
+        );
+        set(
+            "package_metadata",
+            match self.size_budget {
+                Some(budget) => format!(
+                    "\n[package.metadata.iroha_wasm_pack]\nmax-size = {}\n",
+                    budget
+                ),
+                None => String::new(),
+            },
+        );
+        for (key, value) in &self.defines {
+            vars.insert(key.clone(), value.clone());
+        }
+        vars
+    }
+
+    /// Cargo xml release profile for reducing the size of wasm binary
+    pub fn cargo_xml(&self) -> Result<String, Error> {
+        render(CARGO_TEMPLATE, &self.vars(), "Cargo.toml")
+    }
+
+    /// Write `Cargo.toml`
+    pub fn write_cargo_xml(&self) -> Result<(), Error> {
+        write(self.dir.join("Cargo.toml"), self.cargo_xml()?.as_bytes())
+    }
+
+    /// Write `src/lib.rs`
+    pub fn write_entrypoint(&self) -> Result<(), Error> {
+        let src = self.dir.join("src");
+        fs::create_dir_all(&src)?;
+        let variant = self
+            .template
+            .variant(self.iroha_rc)
+            .unwrap_or(&self.template.variants()[0]);
+        let source = match self.sample {
+            Some(sample) => sample.source,
+            None if self.bare => variant.bare_entrypoint,
+            None => variant.entrypoint,
+        };
+        let entrypoint = render(source, &self.vars(), "src/lib.rs")?;
+        write(src.join("lib.rs"), entrypoint.as_bytes())
+    }
+
+    /// Write the example test run by `webassembly-test-runner`, existing tests are left alone
+    pub fn write_tests(&self) -> Result<(), Error> {
+        let tests = self.dir.join("tests");
+        if self.bare || tests.exists() {
+            return Ok(());
+        }
+        fs::create_dir_all(&tests)?;
+        let smoke = render(TESTS_TEMPLATE, &self.vars(), "tests/smoke.rs")?;
+        write(tests.join("smoke.rs"), smoke.as_bytes())
+    }
+
+    /// Write `.cargo/config.toml`, an existing one is left alone
+    pub fn write_cargo_config(&self) -> Result<(), Error> {
+        let dir = self.dir.join(".cargo");
+        let path = dir.join("config.toml");
+        if path.exists() || dir.join("config").exists() {
+            eprintln!(
+                "warning: {} already has a cargo config, add `build.target = \"wasm32-unknown-unknown\"` to it yourself",
+                self.dir.display()
+            );
+            return Ok(());
+        }
+        fs::create_dir_all(&dir)?;
+        write(path, CARGO_CONFIG.as_bytes())
+    }
+
+    /// Add the entries the project needs to `.gitignore`, keeping existing ones
+    pub fn merge_gitignore(&self) -> Result<(), Error> {
+        let path = self.dir.join(".gitignore");
+        let mut gitignore = fs::read_to_string(&path).unwrap_or_default();
+        // Cargo.lock is kept under version control so builds resolve the same Iroha commit.
+        for entry in ["/target", "*_optimized.wasm", "*.debug.wasm"] {
+            if !gitignore.lines().any(|line| line.trim() == entry) {
+                if !gitignore.is_empty() && !gitignore.ends_with('\n') {
+                    gitignore.push('\n');
+                }
+                gitignore.push_str(entry);
+                gitignore.push('\n');
+            }
+        }
+        write(path, gitignore.as_bytes())
+    }
+}
+
+/// Write `.gitignore` and init a git repository unless the project is inside one
+pub fn step_vcs(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    if args.vcs == Vcs::None {
+        return Ok(());
+    }
+    scaffold.merge_gitignore()?;
+    let inside_repo = cmd!("git", "rev-parse", "--is-inside-work-tree")
+        .dir(&scaffold.dir)
+        .stdout_null()
+        .stderr_null()
+        .run()
+        .is_ok();
+    if inside_repo {
+        if args.commit {
+            eprintln!("warning: the project is inside an existing git repository, skipped the initial commit");
+        }
+        return Ok(());
+    }
+    let git = |git_args: &[&str]| -> Result<(), Error> {
+        if let Err(err) = cmd("git", git_args).dir(&scaffold.dir).run() {
+            return Err(Error::other(format!("git {} failed", git_args.join(" "))).caused_by(err));
+        }
+        Ok(())
+    };
+    git(&["init", "--quiet"])?;
+    if args.commit {
+        git(&["add", "--all"])?;
+        git(&["commit", "--quiet", "--message", "Initial commit"])?;
+    }
+    Ok(())
+}
+
+/// Write the text of the `--license` to `LICENSE`
+pub fn step_license(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    let text = match LICENSES
+        .iter()
+        .find(|(id, _)| Some(*id) == args.license.as_deref())
+    {
+        Some((_, text)) => text,
+        None => return Ok(()),
+    };
+    write(scaffold.dir.join("LICENSE"), text.as_bytes())
+}
+
+/// Pin the toolchain the build needs in `rust-toolchain.toml`, unless a template already did
+pub fn step_toolchain_file(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    let dir = &scaffold.dir;
+    if args.no_toolchain_file
+        || dir.join("rust-toolchain.toml").exists()
+        || dir.join("rust-toolchain").exists()
+    {
+        return Ok(());
+    }
+    let toolchain_file = format!(
+        r#"[toolchain]
+channel = "{}"
+components = ["rust-src"]
+targets = ["wasm32-unknown-unknown"]
+"#,
+        args.toolchain
+    );
+    write(
+        scaffold.dir.join("rust-toolchain.toml"),
+        toolchain_file.as_bytes(),
+    )
+}
+
+/// Make plain cargo and rust-analyzer target wasm by default
+pub fn step_cargo_config(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    if args.no_cargo_config {
+        return Ok(());
+    }
+    scaffold.write_cargo_config()
+}
+
+/// Host-side crate loading the optimized wasm into wasmtime, with `--with-integration-tests`
+pub fn step_integration_tests(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    if !args.with_integration_tests {
+        return Ok(());
+    }
+    let host_target = match cmd!("rustc", "-vV").read() {
+        Ok(version) => version
+            .lines()
+            .find_map(|line| line.strip_prefix("host: "))
+            .map(str::to_owned),
+        Err(_) => None,
+    };
+    let host_target = match host_target {
+        Some(host_target) => host_target,
+        None => {
+            return Err(Error::toolchain(
+                "can't find the host target with `rustc -vV`",
+            ))
+        }
+    };
+    let mut vars = scaffold.vars();
+    vars.insert("host_target".to_owned(), host_target);
+    let dir = scaffold.dir.join("integration");
+    for (file, template) in [
+        ("Cargo.toml", INTEGRATION_CARGO_TEMPLATE),
+        (".cargo/config.toml", INTEGRATION_CARGO_CONFIG),
+        (".gitignore", "/target\nCargo.lock\n"),
+        ("src/lib.rs", INTEGRATION_SUPPORT),
+        ("tests/integration.rs", INTEGRATION_TESTS),
+    ] {
+        let path = dir.join(file);
+        fs::create_dir_all(path.parent().unwrap())?;
+        write(
+            &path,
+            render(template, &vars, &format!("integration/{}", file))?,
+        )?;
+    }
+    Ok(())
+}
+
+/// Account the local peer's genesis creates, the authority of the template code
+const LOCAL_ACCOUNT: &str = "alice@wonderland";
+
+/// Freshly generated development key pair, as the public key and the private key JSON
+fn dev_key_pair() -> Result<(String, serde_json::Value), Error> {
+    let key_pair = match iroha_crypto::KeyPair::generate() {
+        Ok(key_pair) => key_pair,
+        Err(err) => return Err(Error::other("generate keys failed").caused_by(err.to_string())),
+    };
+    Ok((
+        key_pair.public_key().to_string(),
+        serde_json::to_value(key_pair.private_key())?,
+    ))
+}
+
+/// Genesis, peer and client configs with development keys to run the contract on a local peer
+pub fn step_local_env(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    use serde_json::json;
+    if !args.with_local_env {
+        return Ok(());
+    }
+    let vars = scaffold.vars();
+    let (peer_public_key, peer_private_key) = dev_key_pair()?;
+    let (genesis_public_key, genesis_private_key) = dev_key_pair()?;
+    let (account_public_key, account_private_key) = dev_key_pair()?;
+    let wasm = format!(
+        "../target/wasm32-unknown-unknown/release/{}_optimized.wasm",
+        vars["lib_name"]
+    );
+    let mut instructions = vec![
+        json!({ "Register": { "NewDomain": { "id": "wonderland", "logo": null, "metadata": {} } } }),
+        json!({ "Register": { "NewDomain": { "id": "open", "logo": null, "metadata": {} } } }),
+        json!({ "Register": { "NewAccount": {
+            "id": LOCAL_ACCOUNT,
+            "signatories": [account_public_key],
+            "metadata": {}
+        } } }),
+        json!({ "Register": { "NewAssetDefinition": {
+            "id": "token#open",
+            "value_type": "Quantity",
+            "mintable": "Infinitely",
+            "metadata": {}
+        } } }),
+    ];
+    // Executors and smartcontracts are submitted by the client once the peer runs.
+    if scaffold.template == Template::Trigger {
+        instructions.push(json!({ "Register": { "Trigger": {
+            "id": format!("{}_trigger", vars["lib_name"]),
+            "action": {
+                "executable": { "Wasm": wasm },
+                "repeats": "Indefinitely",
+                "technical_account": LOCAL_ACCOUNT,
+                "filter": { "Time": "PreCommit" },
+                "metadata": {}
+            }
+        } } }));
+    }
+    let genesis = json!({ "transactions": [{ "isi": instructions }] });
+    let peer = json!({
+        "PUBLIC_KEY": peer_public_key,
+        "PRIVATE_KEY": peer_private_key,
+        "TORII": { "P2P_ADDR": "127.0.0.1:1337", "API_URL": "127.0.0.1:8080" },
+        "SUMERAGI": {
+            "TRUSTED_PEERS": [{ "address": "127.0.0.1:1337", "public_key": peer_public_key }]
+        },
+        "GENESIS": {
+            "ACCOUNT_PUBLIC_KEY": genesis_public_key,
+            "ACCOUNT_PRIVATE_KEY": genesis_private_key
+        },
+        "KURA": { "BLOCK_STORE_PATH": "./storage" }
+    });
+    let client = json!({
+        "PUBLIC_KEY": account_public_key,
+        "PRIVATE_KEY": account_private_key,
+        "ACCOUNT_ID": LOCAL_ACCOUNT,
+        "BASIC_AUTH": { "web_login": "mad_hatter", "password": "ilovetea" },
+        "TORII_API_URL": "http://127.0.0.1:8080",
+        "TORII_TELEMETRY_URL": "http://127.0.0.1:8180"
+    });
+    let dir = scaffold.dir.join("local");
+    fs::create_dir_all(&dir)?;
+    write(
+        dir.join("genesis.json"),
+        serde_json::to_string_pretty(&genesis)?,
+    )?;
+    write(
+        dir.join("config.json"),
+        serde_json::to_string_pretty(&peer)?,
+    )?;
+    write(
+        dir.join("client.json"),
+        serde_json::to_string_pretty(&client)?,
+    )?;
+    write(dir.join(".gitignore"), "/storage\n")?;
+    let script = dir.join("run-peer.sh");
+    write(
+        &script,
+        render(LOCAL_PEER_SCRIPT, &vars, "local/run-peer.sh")?,
+    )?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+/// Directory and package name of the library shared by the `--multi` triggers
+const MULTI_COMMON: &str = "common";
+
+/// Next steps of a `--multi` workspace
+const MULTI_NEXT_STEPS: [&str; 3] = [
+    "cd {{project_dir}}",
+    "iroha_wasm_pack build --release --workspace",
+    "# shared code goes to common/, each trigger has its own crate",
+];
+
+/// Workspace of the `--multi` triggers and their `common` library
+pub fn step_multi(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    let mut vars = scaffold.vars();
+    let members: String = args
+        .multi
+        .iter()
+        .map(|trigger| format!("    {},\n", toml_string(trigger)))
+        .collect();
+    vars.insert("members".to_owned(), members);
+    let mut files = vec![
+        (PathBuf::from("Cargo.toml"), MULTI_WORKSPACE_TEMPLATE),
+        (
+            Path::new(MULTI_COMMON).join("Cargo.toml"),
+            MULTI_COMMON_CARGO_TEMPLATE,
+        ),
+        (
+            Path::new(MULTI_COMMON).join("src").join("lib.rs"),
+            MULTI_COMMON_ENTRYPOINT,
+        ),
+    ];
+    for trigger in &args.multi {
+        files.push((
+            Path::new(trigger).join("Cargo.toml"),
+            MULTI_TRIGGER_CARGO_TEMPLATE,
+        ));
+        files.push((
+            Path::new(trigger).join("src").join("lib.rs"),
+            MULTI_TRIGGER_ENTRYPOINT,
+        ));
+    }
+    for (file, template) in files {
+        let path = scaffold.dir.join(&file);
+        fs::create_dir_all(path.parent().unwrap())?;
+        let member = file
+            .components()
+            .next()
+            .unwrap()
+            .as_os_str()
+            .to_string_lossy()
+            .into_owned();
+        vars.insert("member_crate".to_owned(), member.replace('-', "_"));
+        vars.insert("member".to_owned(), member);
+        write(&path, render(template, &vars, &file.display().to_string())?)?;
+    }
+    scaffold.merge_gitignore()
+}
+
+/// Resolve the dependencies into a Cargo.lock committed with the project
+pub fn step_lockfile(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    if args.offline() {
+        eprintln!(
+            "note: no Cargo.lock was generated offline, the first build resolves the dependencies"
+        );
+        return Ok(());
+    }
+    let dir = &scaffold.dir;
+    if let Err(err) = cmd!("cargo", "generate-lockfile").dir(dir).run() {
+        eprintln!(
+            "warning: generate Cargo.lock failed, the first build resolves the dependencies, error = {}",
+            err
+        );
+    }
+    Ok(())
+}
+
+/// Join or stay out of an enclosing cargo workspace
+pub fn step_workspace(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    join_workspace(&scaffold.dir, args.workspace)
+}
+
+/// Write a build.rs into the `--with-embedder` host crate that builds the contract
+/// and points an env variable at its optimized wasm
+pub fn step_embedder(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    let host = match &args.with_embedder {
+        Some(host) => current_dir()?.join(host),
+        None => return Ok(()),
+    };
+    if !host.join("Cargo.toml").exists() {
+        return Err(Error::other(format!(
+            "{} is not a crate, `--with-embedder` needs the directory of a Cargo.toml",
+            host.display()
+        )));
+    }
+    let build_rs = host.join("build.rs");
+    if build_rs.exists() {
+        return Err(Error::other(format!(
+            "{} already exists, call the contract build from it by hand",
+            build_rs.display()
+        )));
+    }
+    let mut vars = scaffold.vars();
+    let env_name = format!("{}_WASM", vars["crate_name"].to_uppercase());
+    let contract_dir = relative_path(&fs::canonicalize(&host)?, &fs::canonicalize(&scaffold.dir)?);
+    vars.insert(
+        "contract_dir".to_owned(),
+        contract_dir.display().to_string().replace('\\', "/"),
+    );
+    vars.insert("env_name".to_owned(), env_name.clone());
+    write(&build_rs, render(EMBEDDER_TEMPLATE, &vars, "build.rs")?)?;
+    println!(
+        "Wrote {}, embed the contract with `include_bytes!(env!(\"{}\"))`",
+        build_rs.display(),
+        env_name
+    );
+    Ok(())
+}
+
+/// Path of `to` relative to `from`, both absolute
+pub fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from: Vec<_> = from.components().collect();
+    let to: Vec<_> = to.components().collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    let mut path = PathBuf::new();
+    for _ in common..from.len() {
+        path.push("..");
+    }
+    for component in &to[common..] {
+        path.push(component);
+    }
+    path
+}
+
+/// How `new` and `init` treat an enclosing cargo workspace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkspaceMode {
+    Add,
+    Standalone,
+}
+
+impl FromStr for WorkspaceMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "add" => Ok(WorkspaceMode::Add),
+            "standalone" => Ok(WorkspaceMode::Standalone),
+            _ => Err(format!(
+                "unknown workspace mode `{}`, expected one of: add, standalone",
+                s
+            )),
+        }
+    }
+}
+
+/// Manifest of the workspace enclosing `dir`.
+///
+/// The search stops at the root of the git repository and at the filesystem `dir` is on,
+/// so an unrelated workspace higher up is never picked.
+fn enclosing_workspace(dir: &Path) -> Option<PathBuf> {
+    #[cfg(unix)]
+    fn device(path: &Path) -> Option<u64> {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(path).ok().map(|metadata| metadata.dev())
+    }
+    #[cfg(not(unix))]
+    fn device(_: &Path) -> Option<u64> {
+        Some(0)
+    }
+    let start = device(dir);
+    let mut cur = dir.parent();
+    while let Some(parent) = cur {
+        if device(parent) != start {
+            return None;
+        }
+        let manifest = parent.join("Cargo.toml");
+        if let Ok(cargo_xml) = fs::read_to_string(&manifest) {
+            if cargo_xml
+                .parse::<toml_edit::Document>()
+                .map_or(false, |doc| doc.contains_key("workspace"))
+            {
+                return Some(manifest);
+            }
+        }
+        if parent.join(".git").exists() {
+            return None;
+        }
+        cur = parent.parent();
+    }
+    None
+}
+
+/// Add the project at `dir` to the members of the enclosing workspace, or declare it
+/// a workspace of its own, so cargo doesn't refuse to build it.
+pub fn join_workspace(dir: &Path, mode: WorkspaceMode) -> Result<(), Error> {
+    use toml_edit::{value, Array, Document, Item};
+    let dir = fs::canonicalize(dir)?;
+    let manifest = match enclosing_workspace(&dir) {
+        Some(manifest) => manifest,
+        None => return Ok(()),
+    };
+    let root = manifest.parent().unwrap();
+    let member = dir
+        .strip_prefix(root)
+        .map_err(|_| {
+            Error::other(format!(
+                "{} is not below the workspace at {}",
+                dir.display(),
+                root.display()
+            ))
+        })?
+        .to_string_lossy()
+        .replace('\\', "/");
+    if mode == WorkspaceMode::Standalone {
+        let own = dir.join("Cargo.toml");
+        let mut cargo_xml = fs::read_to_string(&own)?;
+        if !cargo_xml.parse::<Document>()?.contains_key("workspace") {
+            cargo_xml.push_str("\n[workspace]\n");
+            write(&own, cargo_xml)?;
+            println!(
+                "Added an empty [workspace] to {} to keep it out of {}",
+                own.display(),
+                manifest.display()
+            );
+        }
+        return Ok(());
+    }
+    let mut doc: Document = fs::read_to_string(&manifest)?.parse()?;
+    let listed = |doc: &Document, key: &str| {
+        doc["workspace"]
+            .get(key)
+            .and_then(Item::as_array)
+            .map_or(false, |patterns| {
+                patterns.iter().filter_map(|x| x.as_str()).any(|pattern| {
+                    glob::Pattern::new(pattern.trim_start_matches("./"))
+                        .map_or(false, |pattern| pattern.matches(&member))
+                })
+            })
+    };
+    if listed(&doc, "exclude") {
+        eprintln!(
+            "warning: {} is excluded from the workspace {}",
+            member,
+            manifest.display()
+        );
+        return Ok(());
+    }
+    if listed(&doc, "members") {
+        return Ok(());
+    }
+    let members = match doc["workspace"]["members"]
+        .or_insert(value(Array::new()))
+        .as_array_mut()
+    {
+        Some(members) => members,
+        None => {
+            return Err(Error::other(format!(
+                "workspace.members of {} is not an array",
+                manifest.display()
+            )))
+        }
+    };
+    members.push(member.as_str());
+    write(&manifest, doc.to_string())?;
+    println!("Added {} to the members of {}", member, manifest.display());
+    Ok(())
+}
+
+/// Clone the `--template-git` repository as the project, authenticating like plain git does
+pub fn step_template_git(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    let url = match &args.template_git {
+        Some(url) => url,
+        None => return Ok(()),
+    };
+    let dir = &scaffold.dir;
+    if dir.exists() && fs::read_dir(dir)?.next().is_some() {
+        return Err(Error::other(format!(
+            "{} is not empty, a git template can only be cloned into a new directory",
+            dir.display()
+        )));
+    }
+    if let Some(parent) = dir.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if let Err(err) = cmd!("git", "clone", "--quiet", "--", url, dir).run() {
+        return Err(Error::other(format!("clone template {} failed", url)).caused_by(err));
+    }
+    if let Some(rev) = &args.template_rev {
+        if let Err(err) = cmd!("git", "checkout", "--quiet", rev).dir(dir).run() {
+            return Err(
+                Error::other(format!("checkout {} of template {} failed", rev, url)).caused_by(err),
+            );
+        }
+    }
+    fs::remove_dir_all(dir.join(".git"))?;
+    apply_template(dir, &scaffold.vars())
+}
+
+/// Copy the `--template-path` directory as the project
+pub fn step_template_path(args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    let template = match &args.template_path {
+        Some(template) => template,
+        None => return Ok(()),
+    };
+    if !template.is_dir() {
+        return Err(Error::other(format!(
+            "template {} is not a directory",
+            template.display()
+        )));
+    }
+    let dir = &scaffold.dir;
+    copy_template(template, dir)?;
+    apply_template(dir, &scaffold.vars())
+}
+
+/// Copy a template tree, skipping VCS metadata and build output.
+///
+/// Symlinked files are copied as their targets, symlinked directories are rejected
+/// so a template can't pull in arbitrary trees or loop.
+fn copy_template(from: &Path, to: &Path) -> Result<(), Error> {
+    const SKIPPED: &[&str] = &[".git", ".hg", ".svn", "target"];
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if SKIPPED.iter().any(|skipped| name == *skipped) {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_symlink() && path.is_dir() {
+            return Err(Error::other(format!(
+                "template contains the symlinked directory {}, which is not supported",
+                path.display()
+            )));
+        }
+        if file_type.is_dir() {
+            copy_template(&path, &to.join(&name))?;
+        } else if let Err(err) = fs::copy(&path, to.join(&name)) {
+            return Err(
+                Error::other(format!("copy template file {} failed", path.display()))
+                    .caused_by(err),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Files below `dir`, recursively
+pub fn walk_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(walk_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Turn a copied template into the project.
+///
+/// Renders the placeholders of every text file and sets the package name of the manifest.
+pub fn apply_template(dir: &Path, vars: &TemplateVars) -> Result<(), Error> {
+    let name = &vars["project_name"];
+    let manifest = dir.join("Cargo.toml");
+    if !manifest.exists() {
+        return Err(Error::other(format!(
+            "template has no Cargo.toml at its root, {} is not a project template",
+            dir.display()
+        )));
+    }
+    for path in walk_files(dir)? {
+        if let Ok(text) = fs::read_to_string(&path) {
+            if text.contains("{{") {
+                let file = path
+                    .strip_prefix(dir)
+                    .unwrap_or(&path)
+                    .display()
+                    .to_string();
+                write(&path, render(&text, vars, &file)?)?;
+            }
+        }
+    }
+    let mut in_package = false;
+    let mut cargo_xml = String::new();
+    for line in fs::read_to_string(&manifest)?.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_package = trimmed == "[package]";
+        }
+        if in_package && trimmed.starts_with("name") && trimmed.contains('=') {
+            cargo_xml.push_str(&format!("name = \"{}\"\n", name));
+        } else {
+            cargo_xml.push_str(line);
+            cargo_xml.push('\n');
+        }
+    }
+    write(manifest, cargo_xml)
+}
+
+/// Cargo xml release profile for reducing the size of wasm binary
+pub fn step_cargo_xml(_args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    scaffold.write_cargo_xml()
+}
+
+/// Iroha boilerplate main entrypoint
+pub fn step_main_entrypoint(_args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    scaffold.write_entrypoint()
+}
+
+/// Example test run inside a wasm runtime
+pub fn step_tests(_args: &NewArgs, scaffold: &Scaffold) -> Result<(), Error> {
+    scaffold.write_tests()
+}
+
+const MULTISIG_SAMPLE: &str = r#"//! Multisignature trigger
+//!
+//! Every call stores the approval of the calling account, once all signatories
+//! of the `multisig#wonderland` account approved, the stored instruction is executed.
+
+#![no_std]
+#![no_main]
+#![allow(clippy::all)]
+
+extern crate alloc;
+
+use alloc::{format, vec::Vec};
+use core::str::FromStr as _;
+
+use iroha_wasm::{data_model::prelude::*, DebugExpectExt};
+
+/// Number of approvals required to execute the stored instruction
+const QUORUM: u32 = 2;
+
+/// Record the approval of `authority` and execute the instruction on quorum
+#[iroha_wasm::entrypoint(params = "[authority]")]
+fn trigger_entrypoint(authority: <Account as Identifiable>::Id) {
+    let multisig = <Account as Identifiable>::Id::from_str("multisig@wonderland")
+        .dbg_expect("Failed to parse `multisig@wonderland` account id");
+    let approval = Name::from_str(&format!("approval_{}", authority.name))
+        .dbg_expect("Failed to build the approval key");
+    Instruction::SetKeyValue(SetKeyValueBox::new(multisig.clone(), approval, true)).execute();
+
+    let approvals: Vec<Name> = QueryBox::from(FindAccountKeyValueByIdAndKey::new(
+        multisig.clone(),
+        Name::from_str("approvals").dbg_expect("Failed to build the approvals key"),
+    ))
+    .execute()
+    .try_into()
+    .unwrap_or_default();
+    if approvals.len() as u32 + 1 >= QUORUM {
+        let rose_definition_id = <AssetDefinition as Identifiable>::Id::from_str("rose#wonderland")
+            .dbg_expect("Failed to parse `rose#wonderland` asset definition id");
+        let rose_id = <Asset as Identifiable>::Id::new(rose_definition_id, multisig);
+        Instruction::Mint(MintBox::new(1_u32, rose_id)).execute();
+    }
+}
+"#;
+
+const NFT_MINT_SAMPLE: &str = r#"//! Smartcontract which creates new nft for every user
+
+#![no_std]
+#![no_main]
+#![allow(clippy::all)]
+
+extern crate alloc;
+
+use alloc::format;
+use core::str::FromStr as _;
+
+use iroha_wasm::{data_model::prelude::*, DebugExpectExt};
+
+/// Register an NFT for every account of the network
+#[iroha_wasm::entrypoint]
+fn smartcontract_entrypoint(_authority: <Account as Identifiable>::Id) {
+    let accounts: alloc::vec::Vec<Account> = QueryBox::from(FindAllAccounts::new())
+        .execute()
+        .try_into()
+        .dbg_expect("Failed to query accounts");
+    for account in accounts {
+        let id = account.id();
+        let nft_definition_id = <AssetDefinition as Identifiable>::Id::from_str(&format!(
+            "nft_for_{}_in_{}#{}",
+            id.name, id.domain_id, id.domain_id
+        ))
+        .dbg_expect("Failed to build the NFT definition id");
+        let nft_definition = AssetDefinition::store(nft_definition_id).build();
+        Instruction::Register(RegisterBox::new(nft_definition)).execute();
+    }
+}
+"#;
+
+const REGISTER_ACCOUNT_SAMPLE: &str = r#"//! Smartcontract which registers a new account
+
+#![no_std]
+#![no_main]
+#![allow(clippy::all)]
+
+use core::str::FromStr as _;
+
+use iroha_wasm::{data_model::prelude::*, DebugExpectExt};
+
+/// Register `mad_hatter@wonderland` without signatories
+#[iroha_wasm::entrypoint]
+fn smartcontract_entrypoint(_authority: <Account as Identifiable>::Id) {
+    let account_id = <Account as Identifiable>::Id::from_str("mad_hatter@wonderland")
+        .dbg_expect("Failed to parse `mad_hatter@wonderland` account id");
+    Instruction::Register(RegisterBox::new(Account::new(account_id, []))).execute();
+}
+"#;
+
+const CARGO_TEMPLATE: &str = r#"[package]
+name = "{{project_name}}"
+version = "0.1.0"
+edition = "{{edition}}"
+{{package_keys}}
+[lib]
+{{lib_keys}}# A smart contract should be linked dynamically so that it may link to functions exported
+# from the host environment. The host environment executes a smart contract by
+# calling the function that smart contract exports (entry point of execution)
+crate-type = ['cdylib']
+
+[profile.release]
+strip = "debuginfo" # Remove debugging info from the binary
+panic = "abort"     # Panics are transcribed to Traps when compiling for WASM
+lto = true          # Link-time-optimization produces notable decrease in binary size
+opt-level = "z"     # Optimize for size vs speed with "s"/"z" (removes vectorization)
+codegen-units = 1   # Further reduces binary size but increases compilation time
+
+[dependencies]
+iroha_data_model = { {{iroha_source}}, default-features = false }
+iroha_wasm = { {{iroha_source}} }
+{{extra_dependencies}}
+[dev-dependencies]
+webassembly-test = { version = "0.1.0" }
+webassembly-test-runner = { version = "0.1.0" }
+{{package_metadata}}"#;
+
+const MIT_LICENSE: &str = r#"MIT License
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+"#;
+
+const APACHE_LICENSE: &str = r#"Licensed under the Apache License, Version 2.0 (the "License");
+you may not use this file except in compliance with the License.
+You may obtain a copy of the License at
+
+    http://www.apache.org/licenses/LICENSE-2.0
+
+Unless required by applicable law or agreed to in writing, software
+distributed under the License is distributed on an "AS IS" BASIS,
+WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+See the License for the specific language governing permissions and
+limitations under the License.
+"#;
+
+const CARGO_CONFIG: &str = r#"[build]
+# `cargo check`, `cargo clippy` and rust-analyzer see the same target as `iroha_wasm_pack build`
+target = "wasm32-unknown-unknown"
+
+[alias]
+iroha-build = "build --release -Z build-std -Z build-std-features=panic_immediate_abort"
+
+[target.wasm32-unknown-unknown]
+# `cargo test` runs the wasm test binaries with it, see `tests/smoke.rs`
+runner = "webassembly-test-runner"
+"#;
+
+const EMBEDDER_TEMPLATE: &str = r#"//! Builds the `{{project_name}}` contract with `iroha_wasm_pack` and points
+//! `{{env_name}}` at its optimized wasm, embed it with:
+//!
+//! ```ignore
+//! const CONTRACT: &[u8] = include_bytes!(env!("{{env_name}}"));
+//! ```
+
+use std::{env, path::Path, process::Command};
+
+/// Contract project, relative to this crate
+const CONTRACT_DIR: &str = "{{contract_dir}}";
+
+fn main() {
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").expect("set by cargo");
+    let contract = Path::new(&manifest_dir).join(CONTRACT_DIR);
+    println!("cargo:rerun-if-changed={}", contract.join("src").display());
+    println!("cargo:rerun-if-changed={}", contract.join("Cargo.toml").display());
+    println!("cargo:rerun-if-env-changed=IROHA_WASM_PACK");
+
+    let tool = env::var("IROHA_WASM_PACK").unwrap_or_else(|_| "iroha_wasm_pack".to_owned());
+    let status = Command::new(&tool)
+        .args(["build", "--release", "--ci"])
+        .current_dir(&contract)
+        // The flags and target directory of this crate don't apply to the contract.
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .env_remove("CARGO_TARGET_DIR")
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => panic!(
+            "building the contract at {} failed ({}), run `iroha_wasm_pack build --release` there for details",
+            contract.display(),
+            status
+        ),
+        Err(err) => panic!(
+            "can't run `{}`: {}, install it or point IROHA_WASM_PACK at the binary",
+            tool, err
+        ),
+    }
+
+    let wasm = contract.join("target/wasm32-unknown-unknown/release/{{lib_name}}_optimized.wasm");
+    println!("cargo:rustc-env={{env_name}}={}", wasm.display());
+}
+"#;
+
+const TESTS_TEMPLATE: &str = r#"//! Tests of {{project_name}}, run inside a wasm runtime.
+//!
+//! The test binaries are built for wasm32-unknown-unknown and executed by
+//! `webassembly-test-runner`, the cargo runner set in `.cargo/config.toml`:
+//!
+//! ```sh
+//! cargo install webassembly-test-runner
+//! cargo test
+//! ```
+//!
+//! `iroha_wasm_pack build --tests` only builds the test binaries.
+
+use core::str::FromStr as _;
+
+use iroha_data_model::prelude::*;
+use webassembly_test::webassembly_test;
+
+#[webassembly_test]
+fn account_id_roundtrip() {
+    let account_id = AccountId::from_str("alice@wonderland").expect("valid account id");
+    assert_eq!(account_id.to_string(), "alice@wonderland");
+}
+"#;
+
+const TRIGGER_ENTRYPOINT: &str = r#"//! Smartcontract which creates new nft for every user
+//!
+//! This module isn't included in the build-tree,
+//! but instead it is being built by a `client/build.rs`
+
+#![no_std]
+#![no_main]
+#![allow(clippy::all)]
+
+//! Sample smartcontract which mints 1 rose for it's authority
+
+use core::str::FromStr as _;
+
+use iroha_wasm::{data_model::prelude::*, DebugExpectExt};
+
+/// Mint 1 rose for authority
+#[iroha_wasm::entrypoint(params = "[authority]")]
+fn trigger_entrypoint(authority: <Account as Identifiable>::Id) {
+    let rose_definition_id = <AssetDefinition as Identifiable>::Id::from_str("token#open")
+        .dbg_expect("Failed to parse `token#open` asset definition id");
+    let rose_id = <Asset as Identifiable>::Id::new(rose_definition_id, authority);
+
+    Instruction::Mint(MintBox::new(1_u32, rose_id)).execute();
+}    
+"#;
+
+const EXECUTOR_ENTRYPOINT: &str = r#"//! Executor which decides whether an operation is allowed to run
+//!
+//! The peer calls the `validate_*` entrypoints for every transaction, instruction
+//! and query submitted by an account, and only executes the operation if it passes.
+//! Everything is delegated to the default validation of `iroha_validator`, look for
+//! `EXTENSION POINT` to add your own rules.
+
+#![no_std]
+#![no_main]
+#![allow(clippy::all)]
+
+extern crate alloc;
+
+use iroha_validator::{default, prelude::*, smart_contract};
+
+/// State of the executor while it visits an operation
+struct Executor {
+    verdict: Result,
+    block_height: u64,
+    host: smart_contract::Host,
+}
+
+impl Executor {
+    fn new(block_height: u64) -> Self {
+        Self {
+            verdict: Ok(()),
+            block_height,
+            host: smart_contract::Host,
+        }
+    }
+}
+
+impl Visit for Executor {
+    // EXTENSION POINT: override `visit_*` methods here. Reject an operation with
+    // `deny!(self, "reason")` and call `default::visit_*` to keep the built-in checks.
+}
+
+impl Validate for Executor {
+    fn verdict(&self) -> &Result {
+        &self.verdict
+    }
+
+    fn block_height(&self) -> u64 {
+        self.block_height
+    }
+
+    fn deny(&mut self, reason: ValidationFail) {
+        self.verdict = Err(reason);
+    }
+}
+
+/// Migrate the state of the previous executor when the network upgrades to this one
+#[entrypoint]
+pub fn migrate(_block_height: u64) -> MigrationResult {
+    // EXTENSION POINT: register custom permission tokens or convert the existing ones.
+    default::permissions::register_default_permissions()?;
+    Ok(())
+}
+
+/// Validate a whole transaction before its instructions are executed
+#[entrypoint]
+pub fn validate_transaction(
+    authority: AccountId,
+    transaction: VersionedSignedTransaction,
+    block_height: u64,
+) -> Result {
+    let mut executor = Executor::new(block_height);
+    executor.visit_transaction(&authority, &transaction);
+    executor.verdict
+}
+
+/// Validate an instruction executed by a trigger or a smartcontract
+#[entrypoint]
+pub fn validate_instruction(
+    authority: AccountId,
+    instruction: InstructionBox,
+    block_height: u64,
+) -> Result {
+    let mut executor = Executor::new(block_height);
+    executor.visit_instruction(&authority, &instruction);
+    executor.verdict
+}
+
+/// Validate a query before it is answered
+#[entrypoint]
+pub fn validate_query(authority: AccountId, query: QueryBox, block_height: u64) -> Result {
+    let mut executor = Executor::new(block_height);
+    executor.visit_query(&authority, &query);
+    executor.verdict
+}
+"#;
+
+const SMARTCONTRACT_ENTRYPOINT: &str = r#"//! Smartcontract which queries the accounts of the network
+//!
+//! A smartcontract is submitted inside a transaction and executed once
+//! with the authority of the account that signed it.
+
+#![no_std]
+#![no_main]
+#![allow(clippy::all)]
+
+use iroha_wasm::{data_model::prelude::*, DebugExpectExt};
+
+/// Query all accounts on behalf of the transaction authority
+#[iroha_wasm::entrypoint]
+fn smartcontract_entrypoint(_authority: <Account as Identifiable>::Id) {
+    let _accounts = QueryBox::from(FindAllAccounts::new())
+        .execute()
+        .dbg_expect("Failed to query accounts");
+}
+"#;
+
+const BARE_TRIGGER_ENTRYPOINT: &str = r#"#![no_std]
+#![no_main]
+
+use iroha_wasm::data_model::prelude::*;
+
+#[iroha_wasm::entrypoint(params = "[authority]")]
+fn trigger_entrypoint(_authority: <Account as Identifiable>::Id) {
+    // TODO
+}
+"#;
+
+const BARE_EXECUTOR_ENTRYPOINT: &str = r#"#![no_std]
+#![no_main]
+
+use iroha_validator::prelude::*;
+
+#[entrypoint]
+pub fn migrate(_block_height: u64) -> MigrationResult {
+    // TODO
+    Ok(())
+}
+
+#[entrypoint]
+pub fn validate_transaction(
+    _authority: AccountId,
+    _transaction: VersionedSignedTransaction,
+    _block_height: u64,
+) -> Result {
+    // TODO
+    Ok(())
+}
+
+#[entrypoint]
+pub fn validate_instruction(
+    _authority: AccountId,
+    _instruction: InstructionBox,
+    _block_height: u64,
+) -> Result {
+    // TODO
+    Ok(())
+}
+
+#[entrypoint]
+pub fn validate_query(_authority: AccountId, _query: QueryBox, _block_height: u64) -> Result {
+    // TODO
+    Ok(())
+}
+"#;
+
+const BARE_SMARTCONTRACT_ENTRYPOINT: &str = r#"#![no_std]
+#![no_main]
+
+use iroha_wasm::data_model::prelude::*;
+
+#[iroha_wasm::entrypoint]
+fn smartcontract_entrypoint(_authority: <Account as Identifiable>::Id) {
+    // TODO
+}
+"#;
+
+const MULTI_WORKSPACE_TEMPLATE: &str = r#"[workspace]
+resolver = "2"
+members = [
+    "common",
+{{members}}]
+
+[workspace.package]
+version = "0.1.0"
+edition = "{{edition}}"
+
+[workspace.dependencies]
+common = { path = "common" }
+iroha_data_model = { {{iroha_source}}, default-features = false }
+iroha_wasm = { {{iroha_source}} }
+
+# Profiles are shared by every trigger of the workspace
+[profile.release]
+strip = "debuginfo" # Remove debugging info from the binary
+panic = "abort"     # Panics are transcribed to Traps when compiling for WASM
+lto = true          # Link-time-optimization produces notable decrease in binary size
+opt-level = "z"     # Optimize for size vs speed with "s"/"z" (removes vectorization)
+codegen-units = 1   # Further reduces binary size but increases compilation time
+"#;
+
+const MULTI_COMMON_CARGO_TEMPLATE: &str = r#"[package]
+name = "common"
+version = { workspace = true }
+edition = { workspace = true }
+{{package_keys}}
+# A library shared by the triggers, `build --workspace` doesn't build it on its own
+[package.metadata.iroha_wasm_pack]
+skip = true
+
+[dependencies]
+iroha_data_model = { workspace = true }
+iroha_wasm = { workspace = true }
+"#;
+
+const MULTI_COMMON_ENTRYPOINT: &str = r#"//! Code shared by the triggers of {{project_name}}
+
+#![no_std]
+
+use core::str::FromStr as _;
+
+use iroha_wasm::{data_model::prelude::*, DebugExpectExt};
+
+/// Mint 1 rose for `authority`
+pub fn mint_rose(authority: <Account as Identifiable>::Id) {
+    let rose_definition_id = <AssetDefinition as Identifiable>::Id::from_str("rose#wonderland")
+        .dbg_expect("Failed to parse `rose#wonderland` asset definition id");
+    let rose_id = <Asset as Identifiable>::Id::new(rose_definition_id, authority);
+
+    Instruction::Mint(MintBox::new(1_u32, rose_id)).execute();
+}
+"#;
+
+const MULTI_TRIGGER_CARGO_TEMPLATE: &str = r#"[package]
+name = "{{member}}"
+version = { workspace = true }
+edition = { workspace = true }
+{{package_keys}}
+[lib]
+crate-type = ['cdylib']
+
+[dependencies]
+common = { workspace = true }
+iroha_data_model = { workspace = true }
+iroha_wasm = { workspace = true }
+{{package_metadata}}"#;
+
+const MULTI_TRIGGER_ENTRYPOINT: &str = r#"//! The `{{member}}` trigger of {{project_name}}
+
+#![no_std]
+#![no_main]
+
+use iroha_wasm::data_model::prelude::*;
+
+#[iroha_wasm::entrypoint(params = "[authority]")]
+fn trigger_entrypoint(authority: <Account as Identifiable>::Id) {
+    // TODO: replace with the logic of `{{member_crate}}`
+    common::mint_rose(authority);
+}
+"#;
+
+const INTEGRATION_CARGO_TEMPLATE: &str = r#"[package]
+name = "{{project_name}}-integration"
+version = "0.1.0"
+edition = "{{edition}}"
+publish = false
+
+# Not part of the contract, tests run on the host against its built wasm
+[workspace]
+
+[dependencies]
+anyhow = "1.0"
+wasmtime = "6.0"
+"#;
+
+const INTEGRATION_CARGO_CONFIG: &str = r#"[build]
+# The contract builds for wasm32-unknown-unknown, its host-side tests run natively.
+# Generated for this machine, change it to your host target if it differs.
+target = "{{host_target}}"
+"#;
+
+const INTEGRATION_SUPPORT: &str = r#"//! Host-side harness of `{{project_name}}`: loads the optimized wasm built by
+//! `iroha_wasm_pack build --release` into wasmtime with stub Iroha host functions.
+//!
+//! Unlike `iroha_wasm_pack build --tests`, which compiles tests to wasm and runs them
+//! inside a wasm runtime, these tests run natively and drive the contract from the
+//! outside like a peer would. Extend `Host` and `linker` with the host functions your
+//! contract imports rather than rewriting them in every test.
+
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use wasmtime::{Caller, Engine, Instance, Linker, Module, Store};
+
+/// Module the Iroha host functions are imported from
+pub const IROHA_MODULE: &str = "iroha";
+
+/// Optimized wasm of the contract, `CONTRACT_WASM` overrides the default output path
+pub fn wasm_path() -> PathBuf {
+    match std::env::var_os("CONTRACT_WASM") {
+        Some(path) => path.into(),
+        None => PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("../target/wasm32-unknown-unknown/release/{{lib_name}}_optimized.wasm"),
+    }
+}
+
+/// What the contract asked the host to do
+#[derive(Debug, Default)]
+pub struct Host {
+    /// Encoded instructions passed to `execute_instruction`
+    pub instructions: Vec<Vec<u8>>,
+    /// Messages passed to `dbg`
+    pub logs: Vec<String>,
+}
+
+/// Copy `len` bytes at `offset` out of the contract's memory
+fn read(caller: &mut Caller<'_, Host>, offset: i32, len: i32) -> Result<Vec<u8>> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .context("the contract exports no memory")?;
+    let mut bytes = vec![0; len as usize];
+    memory.read(&caller, offset as usize, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Stub host functions, imports without a stub trap when called
+pub fn linker(engine: &Engine, module: &Module) -> Result<Linker<Host>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap(
+        IROHA_MODULE,
+        "execute_instruction",
+        |mut caller: Caller<'_, Host>, offset: i32, len: i32| -> Result<()> {
+            let instruction = read(&mut caller, offset, len)?;
+            caller.data_mut().instructions.push(instruction);
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        IROHA_MODULE,
+        "dbg",
+        |mut caller: Caller<'_, Host>, offset: i32, len: i32| -> Result<()> {
+            let message = read(&mut caller, offset, len)?;
+            caller
+                .data_mut()
+                .logs
+                .push(String::from_utf8_lossy(&message).into_owned());
+            Ok(())
+        },
+    )?;
+    linker.define_unknown_imports_as_traps(module)?;
+    Ok(linker)
+}
+
+/// Instantiated contract with the host state it acted on
+pub struct Contract {
+    pub store: Store<Host>,
+    pub instance: Instance,
+}
+
+impl Contract {
+    /// Instantiate the wasm at `wasm_path()`
+    pub fn load() -> Result<Self> {
+        let path = wasm_path();
+        let engine = Engine::default();
+        let module = Module::from_file(&engine, &path).with_context(|| {
+            format!(
+                "load {}, run `iroha_wasm_pack build --release` in the contract first",
+                path.display()
+            )
+        })?;
+        let mut store = Store::new(&engine, Host::default());
+        let instance = linker(&engine, &module)?.instantiate(&mut store, &module)?;
+        Ok(Contract { store, instance })
+    }
+
+    /// Names of the functions the contract exports, its entrypoints among them
+    pub fn exported_functions(&mut self) -> Vec<String> {
+        self.instance
+            .exports(&mut self.store)
+            .filter_map(|export| {
+                let name = export.name().to_owned();
+                export.into_func().map(|_| name)
+            })
+            .collect()
+    }
+}
+"#;
+
+const INTEGRATION_TESTS: &str = r#"//! Build the contract first, then run the tests from `integration/`:
+//!
+//! ```sh
+//! iroha_wasm_pack build --release
+//! cd integration && cargo test
+//! ```
+
+use {{crate_name}}_integration::Contract;
+
+#[test]
+fn contract_exports_an_entrypoint() {
+    let mut contract = Contract::load().expect("load the contract");
+    assert!(!contract.exported_functions().is_empty());
+    // Call an entrypoint with `contract.instance.get_typed_func` and an encoded payload
+    // written to its memory, then assert on `contract.store.data().instructions`.
+}
+"#;
+
+const LEGACY_TRIGGER_ENTRYPOINT: &str = r#"//! Sample trigger which mints 1 rose for its authority
+
+#![no_std]
+#![no_main]
+#![allow(clippy::all)]
+
+use core::str::FromStr as _;
+
+use iroha_wasm::{data_model::prelude::*, DebugExpectExt};
+
+/// Mint 1 rose for authority
+#[iroha_wasm::entrypoint]
+fn trigger_entrypoint(authority: <Account as Identifiable>::Id) {
+    let rose_definition_id = <AssetDefinition as Identifiable>::Id::from_str("rose#wonderland")
+        .dbg_expect("Failed to parse `rose#wonderland` asset definition id");
+    let rose_id = <Asset as Identifiable>::Id::new(rose_definition_id, authority);
+
+    Instruction::Mint(MintBox::new(1_u32, rose_id)).execute();
+}
+"#;
+
+const LEGACY_BARE_TRIGGER_ENTRYPOINT: &str = r#"#![no_std]
+#![no_main]
+
+use iroha_wasm::data_model::prelude::*;
+
+#[iroha_wasm::entrypoint]
+fn trigger_entrypoint(_authority: <Account as Identifiable>::Id) {
+    // TODO
+}
+"#;
+
+const LEGACY_EXECUTOR_ENTRYPOINT: &str = r#"//! Executor which decides whether an operation is allowed to run
+//!
+//! The peer calls the entrypoint for every instruction, query and expression
+//! submitted by an account, and only executes the operation if it is allowed.
+
+#![no_std]
+#![no_main]
+#![allow(clippy::all)]
+
+use iroha_validator::prelude::*;
+
+/// Allow every operation, replace with your own permission checks
+#[entrypoint(params = "[authority, operation]")]
+pub fn validate(authority: <Account as Identifiable>::Id, operation: NeedsPermissionBox) -> Verdict {
+    let _ = (authority, operation);
+    Verdict::Pass
+}
+"#;
+
+const LEGACY_BARE_EXECUTOR_ENTRYPOINT: &str = r#"#![no_std]
+#![no_main]
+
+use iroha_validator::prelude::*;
+
+#[entrypoint(params = "[authority, operation]")]
+pub fn validate(_authority: <Account as Identifiable>::Id, _operation: NeedsPermissionBox) -> Verdict {
+    // TODO
+    Verdict::Pass
+}
+"#;
+
+const LOCAL_PEER_SCRIPT: &str = r#"#!/bin/sh
+# Builds {{project_name}} and starts a single local peer submitting genesis.json.
+#
+# Needs the `iroha` peer binary of the pinned release on PATH. The keys in this
+# directory are for development only, never reuse them on a real network.
+# Talk to the peer with `iroha_client_cli --config client.json`.
+set -e
+cd "$(dirname "$0")"
+(cd .. && iroha_wasm_pack build --release)
+IROHA2_CONFIG_PATH=config.json IROHA2_GENESIS_PATH=genesis.json exec iroha --submit-genesis
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    /// Scaffold of a `contract` project of `template` in a new directory
+    fn scaffold(name: &str, template: Template) -> Scaffold {
+        Scaffold {
+            dir: test_util::temp_dir(name).join("contract"),
+            name: "contract".to_owned(),
+            template,
+            source: "version = \"2.0.0-pre-rc.11\"".to_owned(),
+            edition: DEFAULT_EDITION.to_owned(),
+            size_budget: template.default_size_budget(),
+            package_keys: Vec::new(),
+            defines: Vec::new(),
+            sample: None,
+            bare: false,
+            lib_name: None,
+            iroha_rc: Some(11),
+        }
+    }
+
+    #[test]
+    fn every_template_variant_renders_to_rust() {
+        for template in Template::ALL {
+            let vars = scaffold("variants", template).vars();
+            for variant in template.variants() {
+                for source in [variant.entrypoint, variant.bare_entrypoint] {
+                    let rendered = render(source, &vars, "src/lib.rs").unwrap();
+                    if let Err(err) = syn::parse_file(&rendered) {
+                        panic!(
+                            "{} since rc.{} is not Rust: {}",
+                            template.as_str(),
+                            variant.since_rc,
+                            err
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cargo_toml_has_the_dependencies_of_the_template() {
+        for template in Template::ALL {
+            let manifest: toml::Value = scaffold("cargo-toml", template)
+                .cargo_xml()
+                .unwrap()
+                .parse()
+                .unwrap();
+            let dependencies = manifest["dependencies"].as_table().unwrap();
+            assert!(dependencies.contains_key("iroha_wasm"));
+            assert_eq!(
+                dependencies.contains_key("iroha_validator"),
+                template == Template::Executor
+            );
+            assert_eq!(manifest["lib"]["crate-type"][0].as_str(), Some("cdylib"));
+            let budget = manifest
+                .get("package")
+                .and_then(|package| package.get("metadata"))
+                .and_then(|metadata| metadata["iroha_wasm_pack"]["max-size"].as_integer());
+            assert_eq!(
+                budget.map(|budget| budget as u64),
+                template.default_size_budget()
+            );
+        }
+    }
+
+    #[test]
+    fn every_template_writes_its_entrypoint() {
+        for (template, marker) in [
+            (Template::Trigger, "params = \"[authority]\""),
+            (Template::Executor, "#[entrypoint("),
+            (Template::SmartContract, "#[iroha_wasm::entrypoint]"),
+        ] {
+            let scaffold = scaffold(template.as_str(), template);
+            scaffold.write_entrypoint().unwrap();
+            let lib = fs::read_to_string(scaffold.dir.join("src/lib.rs")).unwrap();
+            assert!(lib.contains(marker), "{}:\n{}", template.as_str(), lib);
+        }
+    }
+
+    #[test]
+    fn valid_names_pass() {
+        for name in ["contract", "my-contract", "my_contract", "rose2", "Rose"] {
+            assert_eq!(validate_name(name), Ok(()), "{}", name);
+        }
+    }
+
+    #[test]
+    fn invalid_names_are_explained() {
+        for (name, reason) in [
+            ("", "must not be empty"),
+            ("fn", "is a Rust keyword"),
+            ("Self", "is a Rust keyword"),
+            ("1contract", "must not start with a digit"),
+            ("my contract", "invalid character ` `"),
+            ("my.contract", "invalid character `.`"),
+            ("rosé", "invalid character `é`"),
+            ("std", "collides with a standard crate"),
+            ("proc-macro", "collides with a standard crate"),
+            ("incremental", "cargo directory"),
+        ] {
+            match validate_name(name) {
+                Ok(()) => panic!("`{}` was accepted", name),
+                Err(err) => assert!(err.contains(reason), "`{}`: {}", name, err),
+            }
+        }
+    }
+
+    #[test]
+    fn suggested_names_are_valid() {
+        for name in ["1contract", "my contract", "fn", "std", ""] {
+            assert_eq!(validate_name(&suggest_name(name)), Ok(()), "{}", name);
+        }
+    }
+
+    /// `new contract` into `dir` without the network, git or a parent workspace
+    fn new_args(dir: &Path, flags: &[&str]) -> NewArgs {
+        let dir = dir.to_str().unwrap();
+        let args = [
+            "new",
+            "contract",
+            "--path",
+            dir,
+            "--offline",
+            "--vcs",
+            "none",
+            "--workspace",
+            "standalone",
+            "--quiet",
+        ];
+        NewArgs::from_iter_safe(args.iter().chain(flags)).unwrap()
+    }
+
+    #[test]
+    fn refuses_to_write_into_a_non_empty_directory() {
+        let dir = test_util::temp_dir("clobber").join("contract");
+        test_util::write_files(&dir, &[("notes.txt", "mine")]);
+        let err = new_args(&dir, &[]).run().err().unwrap();
+        assert!(err.to_string().contains("pass `--force`"), "{}", err);
+        assert!(!dir.join("Cargo.toml").exists());
+        assert_eq!(fs::read_to_string(dir.join("notes.txt")).unwrap(), "mine");
+    }
+
+    #[test]
+    fn writes_into_an_empty_directory() {
+        let dir = test_util::temp_dir("empty").join("contract");
+        fs::create_dir_all(&dir).unwrap();
+        new_args(&dir, &[]).run().unwrap();
+        assert!(dir.join("Cargo.toml").is_file());
+        assert!(dir.join("src/lib.rs").is_file());
+    }
+
+    #[test]
+    fn force_writes_into_a_non_empty_directory() {
+        let dir = test_util::temp_dir("force").join("contract");
+        test_util::write_files(&dir, &[("notes.txt", "mine")]);
+        new_args(&dir, &["--force"]).run().unwrap();
+        assert!(dir.join("Cargo.toml").is_file());
+        assert!(dir.join("src/lib.rs").is_file());
+        assert_eq!(fs::read_to_string(dir.join("notes.txt")).unwrap(), "mine");
+    }
+
+    #[test]
+    fn copy_template_skips_vcs_and_target_dirs() {
+        let root = test_util::temp_dir("copy-template");
+        let template = root.join("template");
+        test_util::write_files(
+            &template,
+            &[
+                ("Cargo.toml", "[package]\nname = \"{{project_name}}\"\n"),
+                ("src/lib.rs", "// {{project_name}}\n"),
+                (".git/HEAD", "ref: refs/heads/main\n"),
+                (".hg/store", ""),
+                (".svn/entries", ""),
+                ("target/debug/contract.wasm", ""),
+                (".gitignore", "/target\n"),
+            ],
+        );
+        let project = root.join("project");
+        copy_template(&template, &project).unwrap();
+        let mut files: Vec<_> = walk_files(&project)
+            .unwrap()
+            .iter()
+            .map(|file| file.strip_prefix(&project).unwrap().to_owned())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            [".gitignore", "Cargo.toml", "src/lib.rs"]
+                .iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_template_rejects_symlinked_dirs() {
+        let root = test_util::temp_dir("copy-template-symlink");
+        let template = root.join("template");
+        test_util::write_files(&template, &[("Cargo.toml", ""), ("shared/mod.rs", "")]);
+        std::os::unix::fs::symlink(template.join("shared"), template.join("linked")).unwrap();
+        let err = copy_template(&template, &root.join("project"))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("symlinked directory"), "{}", err);
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> TemplateVars {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn render_replaces_variables() {
+        let vars = vars(&[("project_name", "rose"), ("authority", "alice")]);
+        assert_eq!(
+            render(
+                "{{project_name}} by {{authority}}, {{project_name}}",
+                &vars,
+                "f"
+            )
+            .unwrap(),
+            "rose by alice, rose"
+        );
+    }
+
+    #[test]
+    fn render_leaves_non_placeholders_alone() {
+        let vars = vars(&[("name", "rose")]);
+        for text in [
+            "println!(\"{{}}\", x);",
+            "{{ name }}",
+            "{{name-1}}",
+            "{{name",
+            "name}}",
+            "no braces",
+        ] {
+            assert_eq!(render(text, &vars, "f").unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn render_unescapes_braces() {
+        let vars = vars(&[("name", "rose")]);
+        assert_eq!(
+            render("\\{{name}} is {{name}}", &vars, "f").unwrap(),
+            "{{name}} is rose"
+        );
+    }
+
+    #[test]
+    fn render_rejects_unknown_variables() {
+        let err = render(
+            "{{name}} {{missing}}",
+            &vars(&[("name", "rose")]),
+            "src/lib.rs",
+        )
+        .err()
+        .unwrap()
+        .to_string();
+        assert!(err.contains("`missing`"), "{}", err);
+        assert!(err.contains("src/lib.rs"), "{}", err);
+        assert!(err.contains("--define missing=<value>"), "{}", err);
+    }
+
+    #[test]
+    fn defines_are_key_value_pairs() {
+        assert_eq!(
+            parse_define("owner=alice=bob"),
+            Ok(("owner".to_owned(), "alice=bob".to_owned()))
+        );
+        assert_eq!(
+            parse_define("empty="),
+            Ok(("empty".to_owned(), String::new()))
+        );
+        for invalid in ["owner", "=alice", "the owner=alice"] {
+            assert!(parse_define(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn every_sample_renders_to_rust() {
+        for sample in SAMPLES {
+            let vars = scaffold(sample.name, sample.template).vars();
+            let rendered = render(sample.source, &vars, "src/lib.rs").unwrap();
+            if let Err(err) = syn::parse_file(&rendered) {
+                panic!("sample {} is not Rust: {}", sample.name, err);
+            }
+        }
+    }
+
+    #[test]
+    fn example_writes_the_sample_with_its_template() {
+        let dir = test_util::temp_dir("example").join("contract");
+        let flags = ["--example", "nft_mint", "--template", "trigger"];
+        let scaffold = Scaffold::for_new(&new_args(&dir, &flags)).unwrap();
+        assert_eq!(scaffold.template, Template::SmartContract);
+        new_args(&dir, &flags).run().unwrap();
+        let sample = parse_sample("nft_mint").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("src/lib.rs")).unwrap(),
+            render(sample.source, &scaffold.vars(), "src/lib.rs").unwrap()
+        );
+    }
+
+    #[test]
+    fn unknown_examples_list_the_samples() {
+        let err = parse_sample("escrow").unwrap_err();
+        for sample in SAMPLES {
+            assert!(err.contains(sample.name), "{}", err);
+        }
+    }
+
+    #[test]
+    fn example_conflicts_with_bare() {
+        let args = ["new", "contract", "--example", "multisig", "--bare"];
+        assert!(NewArgs::from_iter_safe(&args).is_err());
+    }
+
+    #[test]
+    fn generated_tests_run_in_wasm() {
+        let scaffold = scaffold("tests", Template::Trigger);
+        scaffold.write_tests().unwrap();
+        let smoke = fs::read_to_string(scaffold.dir.join("tests/smoke.rs")).unwrap();
+        let file = syn::parse_file(&smoke).unwrap();
+        let tests = file
+            .items
+            .iter()
+            .filter(|item| match item {
+                syn::Item::Fn(function) => function
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("webassembly_test")),
+                _ => false,
+            })
+            .count();
+        assert_eq!(tests, 1);
+
+        let manifest: toml::Value = scaffold.cargo_xml().unwrap().parse().unwrap();
+        let dev_dependencies = manifest["dev-dependencies"].as_table().unwrap();
+        assert!(dev_dependencies.contains_key("webassembly-test"));
+        scaffold.write_cargo_config().unwrap();
+        let config: toml::Value = fs::read_to_string(scaffold.dir.join(".cargo/config.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            config["target"]["wasm32-unknown-unknown"]["runner"].as_str(),
+            Some("webassembly-test-runner")
+        );
+    }
+
+    #[test]
+    fn generated_tests_leave_existing_tests_alone() {
+        let scaffold = scaffold("existing-tests", Template::Trigger);
+        test_util::write_files(&scaffold.dir, &[("tests/mine.rs", "// mine\n")]);
+        scaffold.write_tests().unwrap();
+        assert!(!scaffold.dir.join("tests/smoke.rs").exists());
+        assert_eq!(
+            fs::read_to_string(scaffold.dir.join("tests/mine.rs")).unwrap(),
+            "// mine\n"
+        );
+    }
+
+    #[test]
+    fn bare_projects_have_no_tests() {
+        let scaffold = Scaffold {
+            bare: true,
+            ..scaffold("bare-tests", Template::Trigger)
+        };
+        scaffold.write_tests().unwrap();
+        assert!(!scaffold.dir.join("tests").exists());
+    }
+
+    #[test]
+    fn multi_lays_out_a_workspace_of_triggers() {
+        let dir = test_util::temp_dir("multi").join("contract");
+        new_args(&dir, &["--multi", "mint,burn-all"]).run().unwrap();
+        let manifest = |path: &str| -> toml::Value {
+            fs::read_to_string(dir.join(path)).unwrap().parse().unwrap()
+        };
+        let members: Vec<_> = manifest("Cargo.toml")["workspace"]["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|member| member.as_str().unwrap().to_owned())
+            .collect();
+        assert_eq!(members, ["common", "mint", "burn-all"]);
+        let common = manifest("common/Cargo.toml");
+        assert_eq!(common["package"]["name"].as_str(), Some("common"));
+        assert_eq!(
+            common["package"]["metadata"]["iroha_wasm_pack"]["skip"].as_bool(),
+            Some(true)
+        );
+        for member in ["mint", "burn-all"] {
+            let trigger = manifest(&format!("{}/Cargo.toml", member));
+            assert_eq!(trigger["package"]["name"].as_str(), Some(member));
+            assert_eq!(trigger["lib"]["crate-type"][0].as_str(), Some("cdylib"));
+            assert!(trigger["dependencies"].get("common").is_some());
+        }
+        for lib in [
+            "common/src/lib.rs",
+            "mint/src/lib.rs",
+            "burn-all/src/lib.rs",
+        ] {
+            let source = fs::read_to_string(dir.join(lib)).unwrap();
+            assert!(syn::parse_file(&source).is_ok(), "{}:\n{}", lib, source);
+        }
+        let entrypoint = fs::read_to_string(dir.join("burn-all/src/lib.rs")).unwrap();
+        assert!(entrypoint.contains("logic of `burn_all`"), "{}", entrypoint);
+        assert!(!dir.join("src").exists());
+    }
+
+    #[test]
+    fn multi_rejects_repeated_members() {
+        for multi in ["mint,mint", "common,mint"] {
+            let dir = test_util::temp_dir("multi-repeated").join("contract");
+            let err = new_args(&dir, &["--multi", multi]).run().err().unwrap();
+            assert!(err.to_string().contains("more than once"), "{}", err);
+            assert!(!dir.exists());
+        }
+    }
+
+    #[test]
+    fn offline_uses_a_replaced_crates_io() {
+        let home = test_util::temp_dir("cargo-home");
+        let configs = [home.join("project/.cargo"), home.join(".cargo")];
+        assert!(!replaces_crates_io(&configs));
+        test_util::write_files(
+            &home,
+            &[("project/.cargo/config.toml", "[net]\noffline = true\n")],
+        );
+        assert!(!replaces_crates_io(&configs));
+        // `config` without the extension is still read by cargo
+        test_util::write_files(
+            &home,
+            &[(
+                ".cargo/config",
+                "[source.crates-io]\nreplace-with = \"vendored\"\n\n[source.vendored]\ndirectory = \"vendor\"\n",
+            )],
+        );
+        assert!(replaces_crates_io(&configs));
+    }
+
+    #[test]
+    fn offline_skips_the_lockfile_and_the_build() {
+        let dir = test_util::temp_dir("offline").join("contract");
+        new_args(&dir, &["--build"]).run().unwrap();
+        assert!(dir.join("Cargo.toml").is_file());
+        assert!(!dir.join("Cargo.lock").exists());
+        assert!(!dir.join("target").exists());
+    }
+
+    #[test]
+    fn offline_ignores_the_configured_template_git() {
+        let root = test_util::temp_dir("offline-config");
+        test_util::write_files(
+            &root,
+            &[(
+                crate::project_config::CONFIG_FILE_NAME,
+                "[new]\ntemplate-git = \"https://example.invalid/template.git\"\n",
+            )],
+        );
+        let dir = root.join("contract");
+        new_args(&dir, &[]).run().unwrap();
+        assert!(dir.join("src/lib.rs").is_file());
+    }
+
+    #[test]
+    fn offline_conflicts_with_the_network_options() {
+        for flag in [
+            "--use-cargo-new",
+            "--template-git=https://example.invalid/t.git",
+        ] {
+            let args = ["new", "contract", "--offline", flag];
+            assert!(NewArgs::from_iter_safe(&args).is_err(), "{}", flag);
+        }
+    }
+}
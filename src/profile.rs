@@ -0,0 +1,318 @@
+use super::deploy::artifact;
+use super::run::{Harness, HostArgs};
+use super::wasm::{self, read_leb128, write_leb128};
+use super::*;
+use serde_derive::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+use wasmparser::{Parser, Payload};
+use wasmtime::{Config, WasmBacktrace};
+
+/// Prefix of the exported call counters `instrument` adds
+const COUNTER_PREFIX: &str = "__iroha_wasm_pack_calls_";
+
+/// Everything required to configure and run the `iroha_wasm_pack profile` command.
+#[derive(Debug, StructOpt)]
+pub struct ProfileArgs {
+    /// Wasm to profile, defaults to the optimized wasm of the current project
+    #[structopt(parse(from_os_str))]
+    pub wasm: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pub host: HostArgs,
+
+    /// Number of points in the run the executing function is sampled at
+    #[structopt(long = "samples", default_value = "1000")]
+    pub samples: u64,
+
+    /// Hide functions with a smaller share of the samples
+    #[structopt(long = "min-percent", default_value = "0")]
+    pub min_percent: f64,
+
+    /// Print the profile as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+}
+
+/// Calls and cost share of one function
+#[derive(Debug, Serialize)]
+pub struct FunctionProfile {
+    pub index: u32,
+    pub name: String,
+    pub calls: u64,
+    pub samples: u64,
+    pub percent: f64,
+}
+
+fn section(id: u8, content: &[u8], out: &mut Vec<u8>) {
+    out.push(id);
+    write_leb128(content.len(), out);
+    out.extend_from_slice(content);
+}
+
+/// Position of a section id in the order wasm requires
+fn rank(id: u8) -> usize {
+    [1, 2, 3, 4, 5, 13, 6, 7, 8, 9, 12, 10, 11]
+        .iter()
+        .position(|known| *known == id)
+        .unwrap_or(0)
+}
+
+/// Copy of `module` counting the calls of every defined function in an exported global.
+///
+/// Globals are appended, so no existing index shifts and only the global, export and code
+/// sections change.
+fn instrument(module: &[u8]) -> Result<Vec<u8>, Error> {
+    let info = wasm::parse(module)?;
+    let imported_globals = info
+        .imports
+        .iter()
+        .filter(|import| import.kind == "global")
+        .count();
+    let mut defined_globals = 0;
+    let mut bodies = Vec::new();
+    for payload in Parser::new(0).parse_all(module) {
+        match payload? {
+            Payload::GlobalSection(reader) => defined_globals = reader.count() as usize,
+            Payload::CodeSectionEntry(body) => {
+                let mut locals = body.get_locals_reader()?;
+                for _ in 0..locals.get_count() {
+                    locals.read()?;
+                }
+                bodies.push((body.range(), locals.original_position()));
+            }
+            _ => {}
+        }
+    }
+    let first_counter = imported_globals + defined_globals;
+    let counters = bodies.len();
+    let mut globals = Vec::new();
+    let mut exports = Vec::new();
+    for defined in 0..counters {
+        // (global (mut i64) (i64.const 0))
+        globals.extend_from_slice(&[0x7e, 0x01, 0x42, 0x00, 0x0b]);
+        let name = format!("{}{}", COUNTER_PREFIX, defined);
+        write_leb128(name.len(), &mut exports);
+        exports.extend_from_slice(name.as_bytes());
+        exports.push(0x03);
+        write_leb128(first_counter + defined, &mut exports);
+    }
+    let mut code = Vec::new();
+    write_leb128(counters, &mut code);
+    for (defined, (range, operators)) in bodies.iter().enumerate() {
+        let mut counter = Vec::new();
+        write_leb128(first_counter + defined, &mut counter);
+        let mut body = module[range.start..*operators].to_vec();
+        // global.get, i64.const 1, i64.add, global.set
+        body.push(0x23);
+        body.extend_from_slice(&counter);
+        body.extend_from_slice(&[0x42, 0x01, 0x7c, 0x24]);
+        body.extend_from_slice(&counter);
+        body.extend_from_slice(&module[*operators..range.end]);
+        write_leb128(body.len(), &mut code);
+        code.extend_from_slice(&body);
+    }
+
+    let mut instrumented = module[..8].to_vec();
+    let (mut globals_done, mut exports_done) = (false, false);
+    let mut offset = 8;
+    while offset < module.len() {
+        let id = module[offset];
+        offset += 1;
+        let size = read_leb128(module, &mut offset)?;
+        let content = &module[offset..offset + size];
+        offset += size;
+        if id != 0 && !globals_done && rank(id) > rank(6) {
+            let mut content = Vec::new();
+            write_leb128(counters, &mut content);
+            content.extend_from_slice(&globals);
+            section(6, &content, &mut instrumented);
+            globals_done = true;
+        }
+        if id != 0 && !exports_done && rank(id) > rank(7) {
+            let mut content = Vec::new();
+            write_leb128(counters, &mut content);
+            content.extend_from_slice(&exports);
+            section(7, &content, &mut instrumented);
+            exports_done = true;
+        }
+        let (extra, entries) = match id {
+            6 if !globals_done => {
+                globals_done = true;
+                (counters, &globals)
+            }
+            7 if !exports_done => {
+                exports_done = true;
+                (counters, &exports)
+            }
+            10 => {
+                section(10, &code, &mut instrumented);
+                continue;
+            }
+            _ => {
+                section(id, content, &mut instrumented);
+                continue;
+            }
+        };
+        let mut position = 0;
+        let count = read_leb128(content, &mut position)?;
+        let mut merged = Vec::new();
+        write_leb128(count + extra, &mut merged);
+        merged.extend_from_slice(&content[position..]);
+        merged.extend_from_slice(entries);
+        section(id, &merged, &mut instrumented);
+    }
+    Ok(instrumented)
+}
+
+impl ProfileArgs {
+    /// Calls of each function by its index, from a run of the instrumented module
+    fn calls(
+        &self,
+        path: &Path,
+        module: &[u8],
+        imported: u32,
+    ) -> Result<BTreeMap<u32, u64>, Error> {
+        let instrumented = instrument(module)?;
+        let harness = Harness::with_module(&Config::new(), path, &instrumented, &self.host)?;
+        let mut store = harness.store()?;
+        let (instance, main) = harness
+            .instantiate(&mut store)
+            .map_err(|err| Error::wasm(path.display().to_string()).caused_by(err))?;
+        if let Err(err) = main.call(&mut store, ()) {
+            eprintln!(
+                "warning: `{}` trapped, the counts are up to the trap: {:#}",
+                harness.entrypoint, err
+            );
+        }
+        let mut calls = BTreeMap::new();
+        for export in instance
+            .exports(&mut store)
+            .map(|export| export.name().to_owned())
+            .collect::<Vec<_>>()
+        {
+            let defined: u32 = match export
+                .strip_prefix(COUNTER_PREFIX)
+                .and_then(|index| index.parse().ok())
+            {
+                Some(defined) => defined,
+                None => continue,
+            };
+            if let Some(global) = instance.get_global(&mut store, &export) {
+                calls.insert(
+                    imported + defined,
+                    global.get(&mut store).i64().unwrap_or_default() as u64,
+                );
+            }
+        }
+        Ok(calls)
+    }
+
+    /// Samples of each function by its index.
+    ///
+    /// The run is repeated with fuel for an increasing part of it, the function on top of
+    /// the backtrace of the out of fuel trap is where that much fuel was spent.
+    fn fuel_samples(&self, path: &Path, module: &[u8]) -> Result<BTreeMap<u32, u64>, Error> {
+        let wasmtime_err =
+            |err: anyhow::Error| Error::wasm(path.display().to_string()).caused_by(err);
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let harness = Harness::with_module(&config, path, module, &self.host)?;
+        let trapped = |err: anyhow::Error| {
+            Error::other(format!("`{}` trapped", harness.entrypoint)).caused_by(err)
+        };
+        // Instantiation runs the start function, its fuel is given on top of the budget.
+        let mut store = harness.store()?;
+        store.add_fuel(u64::MAX / 2).map_err(wasmtime_err)?;
+        let (_, main) = harness.instantiate(&mut store).map_err(wasmtime_err)?;
+        let instantiation = store.fuel_consumed().unwrap_or_default();
+        main.call(&mut store, ()).map_err(trapped)?;
+        let total = store.fuel_consumed().unwrap_or_default() - instantiation;
+        let points = self.samples.max(1).min(total.max(1));
+        let mut samples = BTreeMap::new();
+        for point in 1..=points {
+            let mut store = harness.store()?;
+            store
+                .add_fuel(instantiation + total * point / (points + 1))
+                .map_err(wasmtime_err)?;
+            let (_, main) = harness.instantiate(&mut store).map_err(wasmtime_err)?;
+            let top = main.call(&mut store, ()).err().and_then(|err| {
+                err.downcast_ref::<WasmBacktrace>()
+                    .and_then(|trace| trace.frames().first().map(|frame| frame.func_index()))
+            });
+            if let Some(index) = top {
+                *samples.entry(index).or_insert(0) += 1;
+            }
+        }
+        Ok(samples)
+    }
+}
+
+impl RunArgs for ProfileArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let path = artifact(self.wasm.as_ref())?;
+        let module = fs::read(&path).map_err(|err| {
+            Error::other(format!("read {} failed", path.display())).caused_by(err)
+        })?;
+        let imported = wasm::parse(&module)?
+            .imports
+            .iter()
+            .filter(|import| import.kind == "func")
+            .count() as u32;
+        let names = wasm::function_names(&module)?;
+        let calls = self.calls(&path, &module, imported)?;
+        let samples = self.fuel_samples(&path, &module)?;
+        let sampled: u64 = samples.values().sum();
+        let mut profile: Vec<FunctionProfile> = calls
+            .keys()
+            .chain(samples.keys())
+            .copied()
+            .collect::<std::collections::BTreeSet<u32>>()
+            .into_iter()
+            .map(|index| {
+                let samples = samples.get(&index).copied().unwrap_or_default();
+                FunctionProfile {
+                    index,
+                    name: names
+                        .get(&index)
+                        .cloned()
+                        .unwrap_or_else(|| format!("func[{}]", index)),
+                    calls: calls.get(&index).copied().unwrap_or_default(),
+                    samples,
+                    percent: samples as f64 * 100.0 / sampled.max(1) as f64,
+                }
+            })
+            .filter(|function| function.calls > 0 || function.samples > 0)
+            .filter(|function| function.percent >= self.min_percent)
+            .collect();
+        profile.sort_by(|a, b| {
+            b.samples
+                .cmp(&a.samples)
+                .then(b.calls.cmp(&a.calls))
+                .then(a.index.cmp(&b.index))
+        });
+        if self.json {
+            let json = serde_json::json!({
+                "wasm": path,
+                "samples": sampled,
+                "functions": profile,
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+            return Ok(CommandReport::default());
+        }
+        println!("{:>7}  {:>10}  {}", "cost", "calls", "function");
+        for function in &profile {
+            println!(
+                "{:>6.2}%  {:>10}  {}",
+                function.percent, function.calls, function.name
+            );
+        }
+        if names.is_empty() {
+            println!("no name section, build with `--keep-names` to see function names");
+        }
+        Ok(CommandReport::default())
+    }
+}
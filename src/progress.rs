@@ -0,0 +1,252 @@
+//! `--progress-format json`, events for editors and other tools following a command as it runs.
+//!
+//! Every event is a JSON object on its own line of stderr, carrying the schema `version` and the
+//! `event` kind. Within a version events and fields are only added, never removed or renamed, so
+//! consumers should ignore what they don't know. `examples/progress/consumer.js` reads them.
+
+use super::*;
+use error::Diagnostic;
+use serde_derive::Serialize;
+use std::{
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Version of the event schema
+pub const PROGRESS_VERSION: u32 = 1;
+
+/// How progress is shown on stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressFormat {
+    /// Log lines, rendered cargo diagnostics and warnings as they come
+    Human,
+    /// One event per line, the human output is left out
+    Json,
+}
+
+impl FromStr for ProgressFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(ProgressFormat::Human),
+            "json" => Ok(ProgressFormat::Json),
+            _ => Err(format!("unknown progress format `{}`", s)),
+        }
+    }
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Pick the format for the rest of the process
+pub fn set_format(format: ProgressFormat) {
+    JSON.store(format == ProgressFormat::Json, Ordering::Relaxed);
+}
+
+/// Whether events are emitted instead of the human output
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Something that happened while a command ran
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event<'a> {
+    StepStarted {
+        package: &'a str,
+        step: &'a str,
+    },
+    StepFinished {
+        package: &'a str,
+        step: &'a str,
+        millis: u64,
+    },
+    /// A message of rustc or clippy, passed through from cargo's JSON
+    Diagnostic {
+        level: &'a str,
+        message: &'a str,
+        code: Option<&'a str>,
+        /// Primary span, relative to the workspace root like cargo gives it
+        file: Option<&'a str>,
+        line: Option<u64>,
+        column: Option<u64>,
+    },
+    Warning {
+        message: &'a str,
+    },
+    /// Last event of every run, `report` on success and `error` on failure
+    Finished {
+        success: bool,
+        exit_code: i32,
+        millis: u64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        report: Option<&'a CommandReport>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<Diagnostic>,
+    },
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    version: u32,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+/// The line printed for `event`
+fn line(event: &Event<'_>) -> String {
+    let line = Line {
+        version: PROGRESS_VERSION,
+        event,
+    };
+    serde_json::to_string(&line).expect("serializable")
+}
+
+/// Print `event` if the JSON format is on
+pub fn emit(event: Event<'_>) {
+    if is_json() {
+        eprintln!("{}", line(&event));
+    }
+}
+
+/// The `diagnostic` event of a `compiler-message` of cargo's JSON output
+fn compiler_message(message: &serde_json::Value) -> Event<'_> {
+    let diagnostic = &message["message"];
+    let primary = diagnostic["spans"]
+        .as_array()
+        .and_then(|spans| spans.iter().find(|span| span["is_primary"] == true));
+    Event::Diagnostic {
+        level: diagnostic["level"].as_str().unwrap_or_default(),
+        message: diagnostic["message"].as_str().unwrap_or_default(),
+        code: diagnostic["code"]["code"].as_str(),
+        file: primary.and_then(|span| span["file_name"].as_str()),
+        line: primary.and_then(|span| span["line_start"].as_u64()),
+        column: primary.and_then(|span| span["column_start"].as_u64()),
+    }
+}
+
+/// Emit a `compiler-message` of cargo's JSON output as a `diagnostic` event
+pub fn emit_compiler_message(message: &serde_json::Value) {
+    emit(compiler_message(message));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn parsed(event: &Event<'_>) -> Value {
+        serde_json::from_str(&line(event)).unwrap()
+    }
+
+    #[test]
+    fn events_are_tagged_and_versioned() {
+        assert_eq!(
+            parsed(&Event::StepStarted {
+                package: "contract",
+                step: "wasm-opt",
+            }),
+            json!({
+                "version": PROGRESS_VERSION,
+                "event": "step-started",
+                "package": "contract",
+                "step": "wasm-opt",
+            })
+        );
+        assert_eq!(
+            parsed(&Event::StepFinished {
+                package: "contract",
+                step: "wasm-opt",
+                millis: 12,
+            }),
+            json!({
+                "version": PROGRESS_VERSION,
+                "event": "step-finished",
+                "package": "contract",
+                "step": "wasm-opt",
+                "millis": 12,
+            })
+        );
+        assert_eq!(
+            parsed(&Event::Warning { message: "unused" }),
+            json!({ "version": PROGRESS_VERSION, "event": "warning", "message": "unused" })
+        );
+    }
+
+    #[test]
+    fn every_event_is_one_line() {
+        let event = Event::Warning {
+            message: "first\nsecond",
+        };
+        assert_eq!(line(&event).lines().count(), 1);
+    }
+
+    #[test]
+    fn finished_carries_the_report_or_the_error() {
+        let report = CommandReport::summary(["done"]);
+        let success = parsed(&Event::Finished {
+            success: true,
+            exit_code: 0,
+            millis: 5,
+            report: Some(&report),
+            error: None,
+        });
+        assert_eq!(success["event"], "finished");
+        assert_eq!(success["report"]["summary"], json!(["done"]));
+        assert!(success.get("error").is_none());
+        let failure = parsed(&Event::Finished {
+            success: false,
+            exit_code: 1,
+            millis: 5,
+            report: None,
+            error: Some(Error::usage("bad flag").diagnostic()),
+        });
+        assert_eq!(failure["success"], false);
+        assert_eq!(failure["error"]["code"], "E0001");
+        assert_eq!(failure["error"]["message"], "bad flag");
+        assert!(failure.get("report").is_none());
+    }
+
+    #[test]
+    fn compiler_messages_become_diagnostics() {
+        let message = json!({
+            "reason": "compiler-message",
+            "message": {
+                "level": "warning",
+                "message": "unused variable: `x`",
+                "code": { "code": "unused_variables" },
+                "spans": [
+                    { "is_primary": false, "file_name": "src/other.rs", "line_start": 1, "column_start": 1 },
+                    { "is_primary": true, "file_name": "src/lib.rs", "line_start": 7, "column_start": 9 },
+                ],
+            },
+        });
+        assert_eq!(
+            parsed(&compiler_message(&message)),
+            json!({
+                "version": PROGRESS_VERSION,
+                "event": "diagnostic",
+                "level": "warning",
+                "message": "unused variable: `x`",
+                "code": "unused_variables",
+                "file": "src/lib.rs",
+                "line": 7,
+                "column": 9,
+            })
+        );
+        let bare = json!({ "message": { "level": "error", "message": "aborting" } });
+        assert_eq!(
+            parsed(&compiler_message(&bare)),
+            json!({
+                "version": PROGRESS_VERSION,
+                "event": "diagnostic",
+                "level": "error",
+                "message": "aborting",
+                "code": null,
+                "file": null,
+                "line": null,
+                "column": null,
+            })
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! `iroha_wasm_pack.toml`, the configuration file of a project or workspace.
+//!
+//! ```toml
+//! version = 1
+//!
+//! [build]
+//! max-size = 1048576
+//!
+//! [packages.my_trigger]
+//! max-size = 262144
+//!
+//! [validation]
+//! allowed-imports = ["env::abort"]
+//!
+//! [deploy.dev]
+//! node-url = "http://127.0.0.1:8080"
+//! client-config = "local/client.json"
+//!
+//! [new]
+//! license = "Apache-2.0"
+//!
+//! [hooks]
+//! pre_build = ["./scripts/gen.sh"]
+//! post_verify_module = [{ command = ["./scripts/upload.sh", "--bucket", "wasm"], allow-failure = true }]
+//! on_failure = ["./scripts/notify.sh"]
+//! ```
+//!
+//! Hooks run without a shell in the directory of the file listing them, the commands of a key in
+//! order. They get the `IROHA_WASM_PACK_HOOK_` variables `NAME` (the key), `STEP`, `PACKAGE`,
+//! `PROFILE`, `CRATE_ROOT`, `TARGET_DIR`, `WASM` and, for `on_failure`, `ERROR`. Only `build`
+//! runs them, `--no-hooks` turns them off.
+
+use super::*;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Name of the configuration file
+pub const CONFIG_FILE_NAME: &str = "iroha_wasm_pack.toml";
+
+/// Newest `version` of the configuration file this release reads
+pub const CONFIG_VERSION: u32 = 1;
+
+/// Schema of `iroha_wasm_pack.toml`, every section is optional
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    /// Version of the schema, bumped on incompatible changes
+    pub version: u32,
+    #[serde(default)]
+    pub build: BuildDefaults,
+    /// Size budgets of the members of a workspace, by package name
+    #[serde(default)]
+    pub packages: BTreeMap<String, PackageBudget>,
+    #[serde(default)]
+    pub validation: Validation,
+    /// Nodes `deploy --env <name>` deploys to
+    #[serde(default)]
+    pub deploy: BTreeMap<String, DeployTarget>,
+    #[serde(default)]
+    pub new: NewDefaults,
+    /// Commands `build` runs around its steps, by `pre_build`, `post_build`, `on_failure`,
+    /// `pre_<step>` or `post_<step>` with the dashes of the step name as underscores
+    #[serde(default)]
+    pub hooks: BTreeMap<String, Vec<Hook>>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BuildDefaults {
+    pub max_size: Option<u64>,
+    pub fail_on_warnings: Option<bool>,
+    pub toolchain: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PackageBudget {
+    pub max_size: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Validation {
+    /// Imports allowed besides the host functions, as `module` or `module::name`
+    pub allowed_imports: Option<Vec<String>>,
+    pub max_memory_pages: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DeployTarget {
+    pub node_url: Option<String>,
+    /// Relative to the directory of the configuration file
+    pub client_config: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct NewDefaults {
+    pub license: Option<String>,
+    pub authors: Option<Vec<String>>,
+    pub template_git: Option<String>,
+    pub size_budget: Option<u64>,
+}
+
+/// A command of `[hooks]`, run without a shell so it works the same on Windows
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Hook {
+    /// The program and its arguments separated by spaces
+    Command(String),
+    Detailed(DetailedHook),
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct DetailedHook {
+    /// The program and its arguments, which may contain spaces
+    pub command: Vec<String>,
+    /// Only warn if the command fails instead of failing the build
+    #[serde(default)]
+    pub allow_failure: bool,
+}
+
+impl Hook {
+    pub fn argv(&self) -> Vec<String> {
+        match self {
+            Hook::Command(command) => command.split_whitespace().map(str::to_owned).collect(),
+            Hook::Detailed(hook) => hook.command.clone(),
+        }
+    }
+
+    pub fn allow_failure(&self) -> bool {
+        match self {
+            Hook::Command(_) => false,
+            Hook::Detailed(hook) => hook.allow_failure,
+        }
+    }
+}
+
+/// Whether `[hooks]` knows `key`
+fn is_hook_key(key: &str) -> bool {
+    let step = key
+        .strip_prefix("pre_")
+        .or_else(|| key.strip_prefix("post_"));
+    matches!(key, "pre_build" | "post_build" | "on_failure")
+        || step.map_or(false, |step| {
+            build::registered_step(&step.replace('_', "-")).is_ok()
+        })
+}
+
+/// Files `--config` selected, the last given first
+static EXPLICIT: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Read these files instead of discovering them, a later one overrides an earlier one.
+///
+/// They are checked right away, a missing or invalid file is an error and not skipped.
+pub fn use_files(paths: &[PathBuf]) -> Result<(), Error> {
+    let mut files = Vec::new();
+    for path in paths {
+        let path = current_dir()?.join(path);
+        if !path.is_file() {
+            return Err(
+                Error::manifest(format!("config file {} doesn't exist", path.display()))
+                    .with_code("E0105"),
+            );
+        }
+        load(&path)?;
+        files.insert(0, path);
+    }
+    *EXPLICIT.lock().unwrap() = files;
+    Ok(())
+}
+
+/// The configuration files found from a directory up, the nearest first.
+///
+/// A value of a nearer file wins, so a member's file overrides the workspace's.
+#[derive(Debug, Default)]
+pub struct ProjectConfig {
+    files: Vec<(PathBuf, ConfigFile)>,
+    explicit: bool,
+}
+
+impl ProjectConfig {
+    /// Load every `iroha_wasm_pack.toml` in `start` and its ancestors, or the `--config` files
+    pub fn discover(start: &Path) -> Result<Self, Error> {
+        let explicit = EXPLICIT.lock().unwrap().clone();
+        if !explicit.is_empty() {
+            let mut files = Vec::new();
+            for path in explicit {
+                let file = load(&path)?;
+                files.push((path, file));
+            }
+            return Ok(ProjectConfig {
+                files,
+                explicit: true,
+            });
+        }
+        let mut files = Vec::new();
+        for dir in start.ancestors() {
+            let path = dir.join(CONFIG_FILE_NAME);
+            if path.is_file() {
+                files.push((path.clone(), load(&path)?));
+            }
+        }
+        Ok(ProjectConfig {
+            files,
+            explicit: false,
+        })
+    }
+
+    /// Whether the files were given with `--config` instead of discovered
+    pub fn is_explicit(&self) -> bool {
+        self.explicit
+    }
+
+    /// Paths of the loaded files, the nearest first
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// The value `pick` finds in the nearest file that has one, with that file
+    pub fn get<T>(&self, pick: impl Fn(&ConfigFile) -> Option<T>) -> Option<(T, &Path)> {
+        self.files
+            .iter()
+            .find_map(|(path, file)| pick(file).map(|value| (value, path.as_path())))
+    }
+}
+
+/// Parse and check one configuration file
+pub fn load(path: &Path) -> Result<ConfigFile, Error> {
+    let text = fs::read_to_string(path)?;
+    // toml's errors name the key and the line, the path says which file.
+    let file: ConfigFile = toml::from_str(&text).map_err(|err| {
+        Error::manifest(format!("{}: {}", path.display(), err)).with_code("E0105")
+    })?;
+    if file.version == 0 || file.version > CONFIG_VERSION {
+        return Err(Error::manifest(format!(
+            "{}: version {} is not supported, this iroha_wasm_pack reads versions 1 to {}",
+            path.display(),
+            file.version,
+            CONFIG_VERSION
+        ))
+        .with_code("E0105"));
+    }
+    if let Some(key) = file.hooks.keys().find(|key| !is_hook_key(key)) {
+        return Err(Error::manifest(format!(
+            "{}: unknown hook `{}`, hooks are pre_build, post_build, on_failure and \
+            pre_<step> or post_<step> for the steps `build --dry-run` lists",
+            path.display(),
+            key
+        ))
+        .with_code("E0105"));
+    }
+    Ok(file)
+}
@@ -0,0 +1,251 @@
+use super::deploy::artifact;
+use super::wasm;
+use super::*;
+use oci_distribution::{
+    client::{ClientConfig, Config, ImageLayer},
+    errors::OciDistributionError,
+    manifest::{OciImageManifest, WASM_CONFIG_MEDIA_TYPE, WASM_LAYER_MEDIA_TYPE},
+    secrets::RegistryAuth,
+    Client, Reference,
+};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, env, fs, future::Future, path::PathBuf};
+
+/// Media type of the JSON describing the wasm layer
+const METADATA_MEDIA_TYPE: &str = "application/vnd.iroha.wasm.metadata.v1+json";
+
+/// Annotation with the hash Iroha identifies the wasm by
+const HASH_ANNOTATION: &str = "org.hyperledger.iroha.wasm.hash";
+
+/// Registry and credentials, shared by `publish` and `pull`
+#[derive(Debug, StructOpt)]
+pub struct RegistryArgs {
+    /// Artifact reference, e.g. `oci://registry.example.com/contracts/escrow:1.2.0`
+    #[structopt(long = "registry")]
+    pub registry: String,
+
+    /// User to log in as, the password is read from `--token-env`
+    #[structopt(long = "username")]
+    pub username: Option<String>,
+
+    /// Environment variable with the password or token of `--username`
+    #[structopt(long = "token-env", default_value = "OCI_TOKEN")]
+    pub token_env: String,
+}
+
+impl RegistryArgs {
+    fn reference(&self) -> Result<Reference, Error> {
+        let reference = self
+            .registry
+            .strip_prefix("oci://")
+            .unwrap_or(&self.registry);
+        reference.parse().map_err(|err| {
+            Error::other(format!("invalid registry reference `{}`", reference)).caused_by(err)
+        })
+    }
+
+    /// `--username`, else the docker credential helpers, else anonymous
+    fn auth(&self, reference: &Reference) -> Result<RegistryAuth, Error> {
+        use docker_credential::DockerCredential;
+        if let Some(username) = &self.username {
+            let token = env::var(&self.token_env).map_err(|_| {
+                Error::other(format!(
+                    "`--username` needs the token in ${}",
+                    self.token_env
+                ))
+            })?;
+            return Ok(RegistryAuth::Basic(username.clone(), token));
+        }
+        Ok(
+            match docker_credential::get_credential(reference.registry()) {
+                Ok(DockerCredential::UsernamePassword(username, password)) => {
+                    RegistryAuth::Basic(username, password)
+                }
+                Ok(DockerCredential::IdentityToken(_)) | Err(_) => RegistryAuth::Anonymous,
+            },
+        )
+    }
+}
+
+/// Tell authentication, network and registry failures apart
+fn registry_err(reference: &Reference, err: OciDistributionError) -> Error {
+    match err {
+        OciDistributionError::AuthenticationFailure(_)
+        | OciDistributionError::UnauthorizedError { .. } => Error::other(format!(
+            "authentication to {} failed, log in with `docker login` or pass `--username`",
+            reference.registry()
+        ))
+        .caused_by(err),
+        OciDistributionError::RequestError(_) => {
+            Error::other(format!("network error talking to {}", reference.registry()))
+                .caused_by(err)
+        }
+        err => Error::other(format!("registry {} refused", reference.registry())).caused_by(err),
+    }
+}
+
+fn block_on<F: Future>(future: F) -> Result<F::Output, Error> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    Ok(runtime.block_on(future))
+}
+
+fn sha256_digest(data: &[u8]) -> String {
+    format!("sha256:{}", hex::encode(Sha256::digest(data)))
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack publish` command.
+#[derive(Debug, StructOpt)]
+pub struct PublishArgs {
+    /// Wasm to publish, defaults to the optimized wasm of the current project
+    #[structopt(parse(from_os_str))]
+    pub wasm: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pub registry: RegistryArgs,
+
+    /// Print the manifest instead of pushing
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+}
+
+impl RunArgs for PublishArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let reference = self.registry.reference()?;
+        let path = artifact(self.wasm.as_ref())?;
+        let module = fs::read(&path)?;
+        let hash = wasm::iroha_hash(&module);
+        let annotations: HashMap<String, String> = [
+            (HASH_ANNOTATION.to_owned(), hash.clone()),
+            (
+                "org.opencontainers.image.title".to_owned(),
+                path.file_name()
+                    .map_or(String::new(), |name| name.to_string_lossy().into_owned()),
+            ),
+        ]
+        .into_iter()
+        .collect();
+        let metadata = serde_json::json!({
+            "hash": hash,
+            "size": module.len(),
+            "custom_sections": wasm::parse(&module)?.custom_sections,
+        });
+        let layers = vec![
+            ImageLayer::new(
+                module,
+                WASM_LAYER_MEDIA_TYPE.to_owned(),
+                Some(annotations.clone()),
+            ),
+            ImageLayer::new(
+                serde_json::to_vec_pretty(&metadata)?,
+                METADATA_MEDIA_TYPE.to_owned(),
+                None,
+            ),
+        ];
+        let config = Config::new(b"{}".to_vec(), WASM_CONFIG_MEDIA_TYPE.to_owned(), None);
+        let manifest = OciImageManifest::build(&layers, &config, Some(annotations));
+        if self.dry_run {
+            println!("{}", serde_json::to_string_pretty(&manifest)?);
+            return Ok(CommandReport::default());
+        }
+        let auth = self.registry.auth(&reference)?;
+        let mut client = Client::new(ClientConfig::default());
+        block_on(async {
+            if client
+                .fetch_manifest_digest(&reference, &auth)
+                .await
+                .is_ok()
+            {
+                return Err(Error::other(format!(
+                    "{} already exists, publish under a new tag",
+                    reference
+                )));
+            }
+            client
+                .push(&reference, &layers, config, &auth, Some(manifest))
+                .await
+                .map_err(|err| registry_err(&reference, err))?;
+            let digest = client
+                .fetch_manifest_digest(&reference, &auth)
+                .await
+                .map_err(|err| registry_err(&reference, err))?;
+            Ok(CommandReport::summary([format!(
+                "Published {}@{}",
+                reference, digest
+            )]))
+        })?
+    }
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack pull` command.
+#[derive(Debug, StructOpt)]
+pub struct PullArgs {
+    #[structopt(flatten)]
+    pub registry: RegistryArgs,
+
+    /// Where to write the wasm
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: PathBuf,
+}
+
+impl RunArgs for PullArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let reference = self.registry.reference()?;
+        let auth = self.registry.auth(&reference)?;
+        let mut client = Client::new(ClientConfig::default());
+        let (manifest, image) = block_on(async {
+            let (manifest, _) = client
+                .pull_image_manifest(&reference, &auth)
+                .await
+                .map_err(|err| registry_err(&reference, err))?;
+            let image = client
+                .pull(
+                    &reference,
+                    &auth,
+                    vec![WASM_LAYER_MEDIA_TYPE, METADATA_MEDIA_TYPE],
+                )
+                .await
+                .map_err(|err| registry_err(&reference, err))?;
+            Ok::<_, Error>((manifest, image))
+        })??;
+        let descriptor = manifest
+            .layers
+            .iter()
+            .find(|layer| layer.media_type == WASM_LAYER_MEDIA_TYPE)
+            .ok_or_else(|| Error::other(format!("{} has no wasm layer", reference)))?;
+        let layer = image
+            .layers
+            .iter()
+            .find(|layer| layer.media_type == WASM_LAYER_MEDIA_TYPE)
+            .ok_or_else(|| Error::other(format!("{} has no wasm layer", reference)))?;
+        let digest = sha256_digest(&layer.data);
+        if digest != descriptor.digest {
+            return Err(Error::other(format!(
+                "the wasm of {} is corrupt, its digest is {} but the manifest says {}",
+                reference, digest, descriptor.digest
+            )));
+        }
+        let hash = wasm::iroha_hash(&layer.data);
+        let annotated = descriptor
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(HASH_ANNOTATION));
+        if annotated.map_or(false, |annotated| *annotated != hash) {
+            return Err(Error::other(format!(
+                "the wasm of {} hashes to {}, not to the annotated {}",
+                reference,
+                hash,
+                annotated.map_or("", String::as_str)
+            )));
+        }
+        fs::write(&self.output, &layer.data)?;
+        println!(
+            "Pulled {} into {} (hash {})",
+            reference,
+            self.output.display(),
+            hash
+        );
+        Ok(CommandReport::default())
+    }
+}
@@ -0,0 +1,53 @@
+//! What a command did, returned by [`RunArgs::run`](crate::RunArgs::run).
+//!
+//! Commands fill the report instead of printing their outcome, the binary renders it as text
+//! or JSON according to `--output-format` and picks the exit code.
+
+use super::*;
+use build::{Artifact, BuildReport, StepTiming};
+use serde_derive::Serialize;
+use std::path::PathBuf;
+
+/// Outcome of a command that didn't fail
+#[derive(Debug, Default, Serialize)]
+pub struct CommandReport {
+    /// Lines for people, the human format prints them on stdout
+    pub summary: Vec<String>,
+    /// Every step that ran, in order
+    pub steps: Vec<StepTiming>,
+    /// Optimized wasm written by the command
+    pub artifacts: Vec<Artifact>,
+    /// Other files and directories the command created
+    pub files: Vec<PathBuf>,
+    /// Warnings, already printed when they were emitted
+    pub warnings: Vec<String>,
+    /// What the command found or resolved for tools, e.g. the settings of `config`, the
+    /// summary has it for people
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    /// How long the whole command took, set by the binary
+    pub millis: u64,
+    /// Non-zero for results that aren't errors but scripts check, like `diff` finding one
+    pub exit_code: i32,
+}
+
+impl CommandReport {
+    /// A report made of summary lines only
+    pub fn summary<S: Into<String>>(lines: impl IntoIterator<Item = S>) -> Self {
+        CommandReport {
+            summary: lines.into_iter().map(Into::into).collect(),
+            ..CommandReport::default()
+        }
+    }
+}
+
+impl From<BuildReport> for CommandReport {
+    fn from(report: BuildReport) -> Self {
+        CommandReport {
+            steps: report.steps,
+            artifacts: report.artifacts,
+            warnings: report.warnings,
+            ..CommandReport::default()
+        }
+    }
+}
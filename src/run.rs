@@ -0,0 +1,308 @@
+use super::deploy::artifact;
+use super::wasm;
+use super::*;
+use serde_derive::Serialize;
+use serde_json::{json, Value};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use wasmtime::{
+    Caller, Config, Engine, Instance, Linker, Module, Store, TypedFunc, WasmBacktraceDetails,
+};
+
+/// How the entrypoint is called, shared by `run`, `bench` and `profile`
+#[derive(Debug, StructOpt)]
+pub struct HostArgs {
+    /// Export to call, defaults to the Iroha entrypoint the module has
+    #[structopt(long = "entrypoint")]
+    pub entrypoint: Option<String>,
+
+    /// Account the contract runs as
+    #[structopt(long = "authority", default_value = "alice@wonderland")]
+    pub authority: String,
+
+    /// File with the SCALE encoded payload handed to the contract after the authority
+    #[structopt(long = "payload", parse(from_os_str))]
+    pub payload: Option<PathBuf>,
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack run` command.
+#[derive(Debug, StructOpt)]
+pub struct RunContractArgs {
+    /// Wasm to run, defaults to the optimized wasm of the current project
+    #[structopt(parse(from_os_str))]
+    pub wasm: Option<PathBuf>,
+
+    #[structopt(flatten)]
+    pub host: HostArgs,
+
+    /// Print the trace of host calls as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+}
+
+/// A call of the contract to a host function
+#[derive(Debug, Serialize)]
+pub struct HostCall {
+    pub function: String,
+    pub args: Value,
+}
+
+/// What the stub host hands out and records
+pub struct Host {
+    payload: Vec<u8>,
+    pub calls: Vec<HostCall>,
+}
+
+/// Copy `len` bytes at `offset` out of the contract's memory
+fn read(caller: &mut Caller<'_, Host>, offset: i32, len: i32) -> anyhow::Result<Vec<u8>> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| anyhow::anyhow!("the contract exports no memory"))?;
+    let mut bytes = vec![0; len as usize];
+    memory.read(&caller, offset as usize, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Decoded instruction, or its bytes if it doesn't decode
+#[cfg(feature = "iroha")]
+fn decode_instruction(bytes: &[u8]) -> Value {
+    use iroha_data_model::isi::InstructionBox;
+    use parity_scale_codec::Decode;
+    match InstructionBox::decode(&mut &bytes[..]) {
+        Ok(instruction) => serde_json::to_value(&instruction)
+            .unwrap_or_else(|_| json!(format!("{:?}", instruction))),
+        Err(_) => json!({ "undecoded": hex::encode(bytes) }),
+    }
+}
+
+/// The bytes of the instruction, there is no data model to decode it with
+#[cfg(not(feature = "iroha"))]
+fn decode_instruction(bytes: &[u8]) -> Value {
+    json!({ "undecoded": hex::encode(bytes) })
+}
+
+/// Hand the payload to the contract in memory it allocates, prefixed by the total length
+fn write_payload(caller: &mut Caller<'_, Host>) -> anyhow::Result<i32> {
+    let payload = caller.data().payload.clone();
+    let len = (payload.len() + 4) as u32;
+    let alloc = caller
+        .get_export("_iroha_alloc")
+        .and_then(|export| export.into_func())
+        .ok_or_else(|| anyhow::anyhow!("the contract exports no `_iroha_alloc`"))?
+        .typed::<i32, i32>(&caller)?;
+    let offset = alloc.call(&mut *caller, len as i32)?;
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| anyhow::anyhow!("the contract exports no memory"))?;
+    memory.write(&mut *caller, offset as usize, &len.to_le_bytes())?;
+    memory.write(&mut *caller, offset as usize + 4, &payload)?;
+    Ok(offset)
+}
+
+/// Stubs recording every host call, other imports trap when called
+pub fn linker(engine: &Engine, module: &Module) -> anyhow::Result<Linker<Host>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap(
+        wasm::HOST_MODULE,
+        "execute_instruction",
+        |mut caller: Caller<'_, Host>, offset: i32, len: i32| -> anyhow::Result<()> {
+            let instruction = decode_instruction(&read(&mut caller, offset, len)?);
+            caller.data_mut().calls.push(HostCall {
+                function: "execute_instruction".to_owned(),
+                args: instruction,
+            });
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        wasm::HOST_MODULE,
+        "execute_query",
+        |mut caller: Caller<'_, Host>, offset: i32, len: i32| -> anyhow::Result<i32> {
+            let query = hex::encode(read(&mut caller, offset, len)?);
+            caller.data_mut().calls.push(HostCall {
+                function: "execute_query".to_owned(),
+                args: json!({ "undecoded": query }),
+            });
+            anyhow::bail!("queries are not emulated by `iroha_wasm_pack run`")
+        },
+    )?;
+    linker.func_wrap(
+        wasm::HOST_MODULE,
+        "dbg",
+        |mut caller: Caller<'_, Host>, offset: i32, len: i32| -> anyhow::Result<()> {
+            let message = String::from_utf8_lossy(&read(&mut caller, offset, len)?).into_owned();
+            caller.data_mut().calls.push(HostCall {
+                function: "dbg".to_owned(),
+                args: json!(message),
+            });
+            Ok(())
+        },
+    )?;
+    for getter in ["get_trigger_payload", "get_smart_contract_payload"] {
+        linker.func_wrap(
+            wasm::HOST_MODULE,
+            getter,
+            move |mut caller: Caller<'_, Host>| -> anyhow::Result<i32> {
+                caller.data_mut().calls.push(HostCall {
+                    function: getter.to_owned(),
+                    args: json!([]),
+                });
+                write_payload(&mut caller)
+            },
+        )?;
+    }
+    linker.define_unknown_imports_as_traps(module)?;
+    Ok(linker)
+}
+
+/// A loaded module with the stub host, the one execution path of `run`, `bench` and `profile`
+pub struct Harness<'a> {
+    pub engine: Engine,
+    pub module: Module,
+    pub entrypoint: String,
+    linker: Linker<Host>,
+    host: &'a HostArgs,
+}
+
+impl<'a> Harness<'a> {
+    pub fn new(config: &Config, path: &Path, host: &'a HostArgs) -> Result<Self, Error> {
+        let bytes = fs::read(path).map_err(|err| {
+            Error::other(format!("read {} failed", path.display())).caused_by(err)
+        })?;
+        Harness::with_module(config, path, &bytes, host)
+    }
+
+    /// Harness for `bytes`, a possibly rewritten version of the wasm at `path`
+    pub fn with_module(
+        config: &Config,
+        path: &Path,
+        bytes: &[u8],
+        host: &'a HostArgs,
+    ) -> Result<Self, Error> {
+        let engine = Engine::new(config)
+            .map_err(|err| Error::wasm("create wasm engine failed").caused_by(err))?;
+        let module = Module::new(&engine, bytes)
+            .map_err(|err| Error::wasm(format!("load {} failed", path.display())).caused_by(err))?;
+        let entrypoint = host.entrypoint(&module)?;
+        let linker = linker(&engine, &module)
+            .map_err(|err| Error::wasm(path.display().to_string()).caused_by(err))?;
+        Ok(Harness {
+            engine,
+            module,
+            entrypoint,
+            linker,
+            host,
+        })
+    }
+
+    /// Fresh store with the stub host
+    pub fn store(&self) -> Result<Store<Host>, Error> {
+        Ok(Store::new(&self.engine, self.host.host()?))
+    }
+
+    /// Instantiate the module in `store`, instantiation excludes the entrypoint
+    pub fn instantiate(
+        &self,
+        store: &mut Store<Host>,
+    ) -> anyhow::Result<(Instance, TypedFunc<(), ()>)> {
+        let instance = self.linker.instantiate(&mut *store, &self.module)?;
+        let main = instance.get_typed_func::<(), ()>(&mut *store, &self.entrypoint)?;
+        Ok((instance, main))
+    }
+}
+
+/// SCALE encoding of the account id `authority`
+#[cfg(feature = "iroha")]
+fn encode_authority(authority: &str) -> Result<Vec<u8>, Error> {
+    use iroha_data_model::account::AccountId;
+    use parity_scale_codec::Encode;
+    match authority.parse::<AccountId>() {
+        Ok(authority) => Ok(authority.encode()),
+        Err(err) => Err(Error::other("invalid `--authority`").caused_by(err.to_string())),
+    }
+}
+
+#[cfg(not(feature = "iroha"))]
+fn encode_authority(_authority: &str) -> Result<Vec<u8>, Error> {
+    Err(super::deploy::without_iroha("encoding `--authority`"))
+}
+
+impl HostArgs {
+    /// The authority, then the payload file
+    fn payload(&self) -> Result<Vec<u8>, Error> {
+        let mut payload = encode_authority(&self.authority)?;
+        if let Some(path) = &self.payload {
+            payload.extend(fs::read(path)?);
+        }
+        Ok(payload)
+    }
+
+    /// Stub host handing out the payload
+    pub fn host(&self) -> Result<Host, Error> {
+        Ok(Host {
+            payload: self.payload()?,
+            calls: Vec::new(),
+        })
+    }
+
+    /// `--entrypoint`, or the Iroha entrypoint `module` exports
+    pub fn entrypoint(&self, module: &Module) -> Result<String, Error> {
+        match &self.entrypoint {
+            Some(entrypoint) => Ok(entrypoint.clone()),
+            None => module
+                .exports()
+                .map(|export| export.name())
+                .find(|name| wasm::ENTRYPOINT_EXPORTS.contains(name))
+                .map(str::to_owned)
+                .ok_or_else(|| Error::wasm("no Iroha entrypoint exported, pass `--entrypoint`")),
+        }
+    }
+}
+
+impl RunContractArgs {
+    fn print(&self, calls: &[HostCall], outcome: &Result<(), String>) -> Result<(), Error> {
+        if self.json {
+            let trace = json!({
+                "calls": calls,
+                "trap": outcome.as_ref().err(),
+            });
+            println!("{}", serde_json::to_string_pretty(&trace)?);
+            return Ok(());
+        }
+        for call in calls {
+            println!("{}: {}", call.function, call.args);
+        }
+        let instructions = calls
+            .iter()
+            .filter(|call| call.function == "execute_instruction")
+            .count();
+        println!("{} instruction(s) executed", instructions);
+        Ok(())
+    }
+}
+
+impl RunArgs for RunContractArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let path = artifact(self.wasm.as_ref())?;
+        let mut config = Config::new();
+        // Debug builds carry DWARF, so traps point at source lines.
+        config.wasm_backtrace_details(WasmBacktraceDetails::Enable);
+        let harness = Harness::new(&config, &path, &self.host)?;
+        let entrypoint = &harness.entrypoint;
+        let mut store = harness.store()?;
+        let outcome = harness
+            .instantiate(&mut store)
+            .and_then(|(_, main)| main.call(&mut store, ()))
+            // The debug form includes the wasm backtrace with function names and offsets.
+            .map_err(|err| format!("{:?}", err));
+        self.print(&store.data().calls, &outcome)?;
+        match outcome {
+            Ok(()) => Ok(CommandReport::default()),
+            Err(trap) => Err(Error::other(format!("`{}` trapped", entrypoint)).caused_by(trap)),
+        }
+    }
+}
@@ -0,0 +1,325 @@
+//! Running external programs behind a trait, so the logic around them can run on canned output.
+
+use super::*;
+use std::{
+    collections::VecDeque,
+    path::PathBuf,
+    process::ExitStatus,
+    sync::{Arc, Mutex},
+};
+
+/// A program with its arguments, extra environment and working directory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Invocation {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: Vec<(String, String)>,
+    pub dir: Option<PathBuf>,
+    /// Capture stderr instead of letting it through to the terminal
+    pub capture_stderr: bool,
+    /// What the program reads on stdin
+    pub stdin: Option<Vec<u8>>,
+}
+
+impl Invocation {
+    pub fn new<S: AsRef<str>>(program: &str, args: impl IntoIterator<Item = S>) -> Self {
+        Invocation {
+            program: program.to_owned(),
+            args: args
+                .into_iter()
+                .map(|arg| arg.as_ref().to_owned())
+                .collect(),
+            ..Invocation::default()
+        }
+    }
+
+    pub fn env(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.env.push((key.to_owned(), value.into()));
+        self
+    }
+
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.dir = Some(dir.into());
+        self
+    }
+
+    pub fn capture_stderr(mut self) -> Self {
+        self.capture_stderr = true;
+        self
+    }
+
+    pub fn stdin(mut self, input: impl Into<Vec<u8>>) -> Self {
+        self.stdin = Some(input.into());
+        self
+    }
+
+    fn arg_refs(&self) -> Vec<&str> {
+        self.args.iter().map(String::as_str).collect()
+    }
+
+    /// The error of the invocation failing with `output`
+    pub fn failed(&self, output: &CommandOutput) -> Error {
+        Error::command(
+            &self.program,
+            &self.arg_refs(),
+            output.status,
+            &output.stderr,
+        )
+    }
+}
+
+/// What a program printed and how it exited
+#[derive(Debug, Clone, Default)]
+pub struct CommandOutput {
+    /// `None` if it was killed by a signal
+    pub code: Option<i32>,
+    /// The real status, scripted outputs have none
+    pub status: Option<ExitStatus>,
+    pub stdout: String,
+    /// Empty unless the invocation captures stderr
+    pub stderr: String,
+}
+
+impl CommandOutput {
+    /// Exit code 0 with `stdout`
+    pub fn success(stdout: &str) -> Self {
+        CommandOutput {
+            code: Some(0),
+            stdout: stdout.to_owned(),
+            ..CommandOutput::default()
+        }
+    }
+
+    /// Exit `code` with `stderr`
+    pub fn failure(code: i32, stderr: &str) -> Self {
+        CommandOutput {
+            code: Some(code),
+            stderr: stderr.to_owned(),
+            ..CommandOutput::default()
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// A program started by `CommandRunner::spawn`
+pub trait RunningCommand: Send {
+    /// What the program printed and how it exited, `None` while it still runs
+    fn try_wait(&mut self) -> Result<Option<CommandOutput>, Error>;
+
+    /// Stop the program
+    fn kill(&mut self) -> Result<(), Error>;
+}
+
+/// Runs the external programs of the tool
+pub trait CommandRunner: Send + Sync {
+    /// Run to completion capturing stdout, whatever the exit code
+    fn output(&self, invocation: &Invocation) -> Result<CommandOutput, Error>;
+
+    /// Run with the terminal as stdout and stderr, failing on a non-zero exit code
+    fn run(&self, invocation: &Invocation) -> Result<(), Error>;
+
+    /// Start in the background capturing stdout, so the caller can stop it
+    fn spawn(&self, invocation: &Invocation) -> Result<Box<dyn RunningCommand>, Error>;
+
+    /// The trimmed stdout, failing on a non-zero exit code
+    fn read(&self, invocation: &Invocation) -> Result<String, Error> {
+        let output = self.output(invocation)?;
+        if !output.is_success() {
+            return Err(invocation.failed(&output));
+        }
+        Ok(output.stdout.trim_end().to_owned())
+    }
+}
+
+/// Runs the programs for real with duct
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DuctRunner;
+
+impl DuctRunner {
+    fn expression(invocation: &Invocation) -> duct::Expression {
+        let mut expression = duct::cmd(&invocation.program, &invocation.args);
+        for (key, value) in &invocation.env {
+            expression = expression.env(key, value);
+        }
+        if let Some(dir) = &invocation.dir {
+            expression = expression.dir(dir);
+        }
+        if let Some(input) = &invocation.stdin {
+            expression = expression.stdin_bytes(input.clone());
+        }
+        expression.unchecked()
+    }
+
+    fn spawn_error(invocation: &Invocation, err: std::io::Error) -> Error {
+        Error::command(
+            &invocation.program,
+            &invocation.arg_refs(),
+            None,
+            &err.to_string(),
+        )
+    }
+}
+
+/// A program started by `DuctRunner`
+struct DuctHandle {
+    handle: duct::Handle,
+    invocation: Invocation,
+}
+
+impl RunningCommand for DuctHandle {
+    fn try_wait(&mut self) -> Result<Option<CommandOutput>, Error> {
+        let output = self
+            .handle
+            .try_wait()
+            .map_err(|err| DuctRunner::spawn_error(&self.invocation, err))?;
+        Ok(output.map(|output| CommandOutput {
+            code: output.status.code(),
+            status: Some(output.status),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }))
+    }
+
+    fn kill(&mut self) -> Result<(), Error> {
+        self.handle
+            .kill()
+            .map_err(|err| DuctRunner::spawn_error(&self.invocation, err))
+    }
+}
+
+impl CommandRunner for DuctRunner {
+    fn output(&self, invocation: &Invocation) -> Result<CommandOutput, Error> {
+        let mut expression = Self::expression(invocation).stdout_capture();
+        if invocation.capture_stderr {
+            expression = expression.stderr_capture();
+        }
+        let output = logging::command(&invocation.program, || expression.run()).map_err(|err| {
+            Error::command(
+                &invocation.program,
+                &invocation.arg_refs(),
+                None,
+                &err.to_string(),
+            )
+        })?;
+        Ok(CommandOutput {
+            code: output.status.code(),
+            status: Some(output.status),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+
+    fn run(&self, invocation: &Invocation) -> Result<(), Error> {
+        let output = logging::command(&invocation.program, || Self::expression(invocation).run())
+            .map_err(|err| {
+            Error::command(
+                &invocation.program,
+                &invocation.arg_refs(),
+                None,
+                &err.to_string(),
+            )
+        })?;
+        if !output.status.success() {
+            return Err(Error::command(
+                &invocation.program,
+                &invocation.arg_refs(),
+                Some(output.status),
+                "",
+            ));
+        }
+        Ok(())
+    }
+
+    fn spawn(&self, invocation: &Invocation) -> Result<Box<dyn RunningCommand>, Error> {
+        let mut expression = Self::expression(invocation).stdout_capture();
+        if invocation.capture_stderr {
+            expression = expression.stderr_capture();
+        }
+        let handle = expression
+            .start()
+            .map_err(|err| Self::spawn_error(invocation, err))?;
+        Ok(Box::new(DuctHandle {
+            handle,
+            invocation: invocation.clone(),
+        }))
+    }
+}
+
+/// Answers every invocation with the next canned output and records the invocations.
+///
+/// An invocation of another program than the next answer expects is an error, so a script
+/// also checks the order of the calls.
+#[derive(Debug, Default)]
+pub struct ScriptedRunner {
+    script: Mutex<VecDeque<(String, CommandOutput)>>,
+    invocations: Mutex<Vec<Invocation>>,
+}
+
+impl ScriptedRunner {
+    /// Answer the next invocation, which has to be of `program`, with `output`
+    pub fn respond(self, program: &str, output: CommandOutput) -> Self {
+        self.script
+            .lock()
+            .unwrap()
+            .push_back((program.to_owned(), output));
+        self
+    }
+
+    pub fn into_shared(self) -> Arc<dyn CommandRunner> {
+        Arc::new(self)
+    }
+
+    /// Every invocation so far, in order
+    pub fn invocations(&self) -> Vec<Invocation> {
+        self.invocations.lock().unwrap().clone()
+    }
+
+    fn next(&self, invocation: &Invocation) -> Result<CommandOutput, Error> {
+        self.invocations.lock().unwrap().push(invocation.clone());
+        match self.script.lock().unwrap().pop_front() {
+            Some((program, output)) if program == invocation.program => Ok(output),
+            Some((program, _)) => Err(Error::other(format!(
+                "scripted `{}` but `{}` was run",
+                program, invocation.program
+            ))),
+            None => Err(Error::other(format!(
+                "`{}` was run after the end of the script",
+                invocation.program
+            ))),
+        }
+    }
+}
+
+/// A scripted program, which has already exited when it is started
+struct Exited(CommandOutput);
+
+impl RunningCommand for Exited {
+    fn try_wait(&mut self) -> Result<Option<CommandOutput>, Error> {
+        Ok(Some(self.0.clone()))
+    }
+
+    fn kill(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl CommandRunner for ScriptedRunner {
+    fn output(&self, invocation: &Invocation) -> Result<CommandOutput, Error> {
+        self.next(invocation)
+    }
+
+    fn run(&self, invocation: &Invocation) -> Result<(), Error> {
+        let output = self.next(invocation)?;
+        if !output.is_success() {
+            return Err(invocation.failed(&output));
+        }
+        Ok(())
+    }
+
+    fn spawn(&self, invocation: &Invocation) -> Result<Box<dyn RunningCommand>, Error> {
+        Ok(Box::new(Exited(self.next(invocation)?)))
+    }
+}
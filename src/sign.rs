@@ -0,0 +1,235 @@
+use super::wasm;
+use super::*;
+use iroha_crypto::{KeyGenConfiguration, KeyPair, PrivateKey, PublicKey, Signature};
+use std::{env, fs, path::PathBuf};
+
+/// Everything required to configure and run the `iroha_wasm_pack sign` command.
+#[derive(Debug, StructOpt)]
+pub struct SignArgs {
+    /// Wasm file to sign
+    #[structopt(parse(from_os_str))]
+    pub wasm: PathBuf,
+
+    /// File with the private key JSON of Iroha, e.g. `{"digest_function": "ed25519", "payload": "..."}`
+    #[structopt(long = "key", parse(from_os_str), required_unless = "key-env")]
+    pub key: Option<PathBuf>,
+
+    /// Environment variable holding the private key JSON, for CI secrets
+    #[structopt(long = "key-env", conflicts_with = "key")]
+    pub key_env: Option<String>,
+
+    /// Signature file, defaults to `<wasm>.sig`
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+}
+
+/// Everything required to configure and run the `iroha_wasm_pack verify-signature` command.
+#[derive(Debug, StructOpt)]
+pub struct VerifySignatureArgs {
+    /// Signed wasm file
+    #[structopt(parse(from_os_str))]
+    pub wasm: PathBuf,
+
+    /// Multihash of the public key the signature must be made with, e.g. `ed0120...`
+    #[structopt(long = "public-key")]
+    pub public_key: String,
+
+    /// Signature file, defaults to `<wasm>.sig`
+    #[structopt(long = "signature", parse(from_os_str))]
+    pub signature: Option<PathBuf>,
+}
+
+/// `<wasm>.sig` next to the wasm
+fn signature_path(wasm: &PathBuf) -> PathBuf {
+    let mut path = wasm.clone().into_os_string();
+    path.push(".sig");
+    path.into()
+}
+
+/// Hash of the wasm Iroha uses, the payload that is signed
+fn signed_payload(wasm: &PathBuf) -> Result<Vec<u8>, Error> {
+    match fs::read(wasm) {
+        Ok(module) => Ok(wasm::iroha_hash_bytes(&module)),
+        Err(err) => Err(Error::other(format!("read {} failed", wasm.display())).caused_by(err)),
+    }
+}
+
+impl SignArgs {
+    /// Key pair of the private key, errors never include the key material
+    fn key_pair(&self) -> Result<KeyPair, Error> {
+        let (json, origin) = match (&self.key, &self.key_env) {
+            (Some(path), _) => (
+                fs::read_to_string(path).map_err(|err| {
+                    Error::other(format!("read {} failed", path.display())).caused_by(err)
+                })?,
+                path.display().to_string(),
+            ),
+            (None, Some(var)) => (
+                env::var(var).map_err(|_| Error::other(format!("${} is not set", var)))?,
+                format!("${}", var),
+            ),
+            (None, None) => {
+                return Err(Error::other(
+                    "pass the private key with `--key` or `--key-env`",
+                ))
+            }
+        };
+        let private_key: PrivateKey = serde_json::from_str(&json).map_err(|_| {
+            Error::other(format!(
+                "{} is not a private key JSON with `digest_function` and `payload`",
+                origin
+            ))
+        })?;
+        let config = KeyGenConfiguration::default().use_private_key(private_key);
+        KeyPair::generate_with_configuration(config)
+            .map_err(|_| Error::other(format!("{} holds an invalid private key", origin)))
+    }
+}
+
+impl RunArgs for SignArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let payload = signed_payload(&self.wasm)?;
+        let key_pair = self.key_pair()?;
+        let signature = match Signature::new(key_pair, &payload) {
+            Ok(signature) => signature,
+            Err(err) => return Err(Error::other("signing failed").caused_by(err.to_string())),
+        };
+        let output = self
+            .output
+            .clone()
+            .unwrap_or_else(|| signature_path(&self.wasm));
+        fs::write(&output, serde_json::to_string_pretty(&signature)?)?;
+        println!(
+            "Signed {} by {} into {}",
+            self.wasm.display(),
+            signature.public_key(),
+            output.display()
+        );
+        Ok(CommandReport::default())
+    }
+}
+
+impl RunArgs for VerifySignatureArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let public_key: PublicKey = match self.public_key.parse() {
+            Ok(public_key) => public_key,
+            Err(err) => return Err(Error::other("invalid public key").caused_by(err.to_string())),
+        };
+        let path = self
+            .signature
+            .clone()
+            .unwrap_or_else(|| signature_path(&self.wasm));
+        let signature: Signature = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(err) => {
+                return Err(Error::other(format!("read {} failed", path.display())).caused_by(err))
+            }
+        };
+        if signature.public_key() != &public_key {
+            return Err(Error::other(format!(
+                "{} is signed by {}, not by {}",
+                path.display(),
+                signature.public_key(),
+                public_key
+            )));
+        }
+        if let Err(err) = signature.verify(&signed_payload(&self.wasm)?) {
+            return Err(
+                Error::other(format!("invalid signature of {}", self.wasm.display()))
+                    .caused_by(err.to_string()),
+            );
+        }
+        println!("{}: signature OK", self.wasm.display());
+        Ok(CommandReport::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+    use parity_scale_codec::Encode;
+    use std::path::Path;
+
+    /// A module and the private key JSON of a new key pair in a new directory
+    fn fixture(name: &str) -> (PathBuf, KeyPair) {
+        let dir = test_util::temp_dir(name);
+        let key_pair = KeyPair::generate().unwrap();
+        let key = serde_json::to_string(key_pair.private_key()).unwrap();
+        test_util::write_files(&dir, &[("key.json", key.as_str())]);
+        fs::write(dir.join("contract.wasm"), b"\0asm\x01\0\0\0").unwrap();
+        (dir, key_pair)
+    }
+
+    fn sign(dir: &Path) -> Result<CommandReport, Error> {
+        SignArgs::from_iter_safe([
+            "sign".to_owned(),
+            dir.join("contract.wasm").display().to_string(),
+            "--key".to_owned(),
+            dir.join("key.json").display().to_string(),
+        ])
+        .unwrap()
+        .run()
+    }
+
+    fn verify(dir: &Path, public_key: &PublicKey) -> Result<CommandReport, Error> {
+        VerifySignatureArgs::from_iter_safe([
+            "verify-signature".to_owned(),
+            dir.join("contract.wasm").display().to_string(),
+            "--public-key".to_owned(),
+            public_key.to_string(),
+        ])
+        .unwrap()
+        .run()
+    }
+
+    #[test]
+    fn signed_payload_is_the_iroha_hash() {
+        let module = b"\0asm\x01\0\0\0".to_vec();
+        let hash = iroha_crypto::Hash::new(&module.encode());
+        let hash: &[u8] = hash.as_ref();
+        assert_eq!(wasm::iroha_hash_bytes(&module), hash);
+    }
+
+    #[test]
+    fn signature_verifies_with_iroha_crypto() {
+        let (dir, key_pair) = fixture("sign");
+        sign(&dir).unwrap();
+        let json = fs::read_to_string(dir.join("contract.wasm.sig")).unwrap();
+        let signature: Signature = serde_json::from_str(&json).unwrap();
+        assert_eq!(signature.public_key(), key_pair.public_key());
+        let module = fs::read(dir.join("contract.wasm")).unwrap();
+        let hash = iroha_crypto::Hash::new(&module.encode());
+        let hash: &[u8] = hash.as_ref();
+        assert!(signature.verify(hash).is_ok());
+        verify(&dir, key_pair.public_key()).unwrap();
+    }
+
+    #[test]
+    fn tampered_module_fails_verification() {
+        let (dir, key_pair) = fixture("tampered");
+        sign(&dir).unwrap();
+        fs::write(dir.join("contract.wasm"), b"\0asm\x01\0\0\0\0").unwrap();
+        let err = verify(&dir, key_pair.public_key()).err().unwrap();
+        assert!(err.to_string().contains("invalid signature"), "{}", err);
+    }
+
+    #[test]
+    fn other_key_fails_verification() {
+        let (dir, _) = fixture("other-key");
+        sign(&dir).unwrap();
+        let other = KeyPair::generate().unwrap();
+        let err = verify(&dir, other.public_key()).err().unwrap();
+        assert!(err.to_string().contains("is signed by"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_key_errors_hide_the_key() {
+        let dir = test_util::temp_dir("invalid-key");
+        test_util::write_files(&dir, &[("key.json", "{\"secret\": \"0123456789abcdef\"}")]);
+        fs::write(dir.join("contract.wasm"), b"\0asm\x01\0\0\0").unwrap();
+        let err = sign(&dir).err().unwrap().to_string();
+        assert!(err.contains("is not a private key JSON"), "{}", err);
+        assert!(!err.contains("0123456789abcdef"), "{}", err);
+    }
+}
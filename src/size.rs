@@ -0,0 +1,117 @@
+use super::inspect::latest_artifact;
+use super::wasm::{self, SizeReport};
+use super::*;
+use std::{collections::BTreeMap, fs, path::PathBuf};
+
+/// Everything required to configure and run the `iroha_wasm_pack size` command.
+#[derive(Debug, StructOpt)]
+pub struct SizeArgs {
+    /// Wasm file, defaults to the most recent optimized artifact of the current project
+    #[structopt(parse(from_os_str))]
+    pub path: Option<PathBuf>,
+
+    /// Number of functions and data segments to list
+    #[structopt(long = "limit", default_value = "10")]
+    pub limit: usize,
+
+    /// Print the report as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+
+    /// Show the size changes from this other wasm file to the analyzed one
+    #[structopt(long = "diff", parse(from_os_str))]
+    pub diff: Option<PathBuf>,
+}
+
+fn analyze(path: &PathBuf) -> Result<SizeReport, Error> {
+    match fs::read(path) {
+        Ok(module) => wasm::analyze_size(&module),
+        Err(err) => Err(Error::other(format!("read {} failed", path.display())).caused_by(err)),
+    }
+}
+
+/// Nonzero differences of `new - old` by key, largest change first
+pub fn deltas<'a>(
+    old: impl Iterator<Item = (&'a str, usize)>,
+    new: impl Iterator<Item = (&'a str, usize)>,
+) -> Vec<(&'a str, i64)> {
+    let mut sizes: BTreeMap<&str, i64> = BTreeMap::new();
+    for (name, size) in old {
+        *sizes.entry(name).or_default() -= size as i64;
+    }
+    for (name, size) in new {
+        *sizes.entry(name).or_default() += size as i64;
+    }
+    let mut deltas: Vec<(&str, i64)> = sizes.into_iter().filter(|(_, delta)| *delta != 0).collect();
+    deltas.sort_by_key(|(_, delta)| -delta.abs());
+    deltas
+}
+
+impl RunArgs for SizeArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => latest_artifact()?,
+        };
+        let report = analyze(&path)?;
+        let old = match &self.diff {
+            Some(other) => analyze(other)?,
+            None => {
+                let report = report.truncated(self.limit);
+                if self.json {
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                } else {
+                    report.print();
+                }
+                return Ok(CommandReport::default());
+            }
+        };
+        let sections = deltas(
+            old.sections
+                .iter()
+                .map(|section| (section.name.as_str(), section.size)),
+            report
+                .sections
+                .iter()
+                .map(|section| (section.name.as_str(), section.size)),
+        );
+        let mut functions = deltas(
+            old.functions
+                .iter()
+                .map(|function| (function.name.as_str(), function.size)),
+            report
+                .functions
+                .iter()
+                .map(|function| (function.name.as_str(), function.size)),
+        );
+        functions.truncate(self.limit);
+        let total = report.total as i64 - old.total as i64;
+        let gzip = report.gzip as i64 - old.gzip as i64;
+        if self.json {
+            let json = serde_json::json!({
+                "total": total,
+                "gzip": gzip,
+                "sections": sections.iter().collect::<BTreeMap<_, _>>(),
+                "functions": functions.iter().collect::<BTreeMap<_, _>>(),
+            });
+            println!("{}", serde_json::to_string_pretty(&json)?);
+            return Ok(CommandReport::default());
+        }
+        println!(
+            "Total: {} -> {} bytes ({:+}), gzip {:+}",
+            old.total, report.total, total, gzip
+        );
+        if !(old.has_names && report.has_names) {
+            println!("No name section in both modules, functions are compared by index");
+        }
+        println!("Sections:");
+        for (name, delta) in &sections {
+            println!("  {:<24} {:>+9} B", name, delta);
+        }
+        println!("Functions:");
+        for (name, delta) in &functions {
+            println!("  {:>+9} B  {}", delta, name);
+        }
+        Ok(CommandReport::default())
+    }
+}
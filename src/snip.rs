@@ -0,0 +1,122 @@
+use super::wasm;
+use super::*;
+use regex::RegexSet;
+use std::{fs, path::PathBuf};
+
+/// Symbols of the panic and formatting machinery a contract can't legitimately reach
+const PANIC_FMT: &[&str] = &[
+    r"^core::fmt::",
+    r"^core::panicking::",
+    r"^core::result::unwrap_failed",
+    r"^core::option::expect_failed",
+    r"^core::slice::index::.*_fail",
+    r"^std::panicking::",
+    r"^rust_begin_unwind$",
+    r"^<.* as core::fmt::(Debug|Display)>::fmt",
+];
+
+/// Body of a function that traps right away: no locals, `unreachable`, `end`
+const UNREACHABLE_BODY: [u8; 3] = [0x00, 0x00, 0x0b];
+
+/// Everything required to configure and run the `iroha_wasm_pack snip` command.
+#[derive(Debug, StructOpt)]
+pub struct SnipArgs {
+    /// Wasm file to snip, it needs a name section
+    #[structopt(parse(from_os_str))]
+    pub input: PathBuf,
+
+    /// Output file, defaults to overwriting the input
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    /// Regex of the function names whose bodies are replaced by `unreachable`
+    #[structopt(long = "pattern", number_of_values = 1, required_unless = "preset")]
+    pub patterns: Vec<String>,
+
+    /// Patterns for common symbols: panic-fmt
+    #[structopt(long = "preset", possible_values = &["panic-fmt"])]
+    pub preset: Option<String>,
+}
+
+impl RunArgs for SnipArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let module = fs::read(&self.input).map_err(|err| {
+            Error::wasm(format!("read {} failed", self.input.display())).caused_by(err)
+        })?;
+        let mut patterns = self.patterns.clone();
+        if self.preset.is_some() {
+            patterns.extend(PANIC_FMT.iter().map(|pattern| pattern.to_string()));
+        }
+        let patterns = RegexSet::new(&patterns)?;
+        let info = wasm::parse(&module)?;
+        let imported = info
+            .imports
+            .iter()
+            .filter(|import| import.kind == "func")
+            .count() as u32;
+        let names = wasm::function_names(&module)?;
+        if names.is_empty() {
+            return Err(Error::wasm(format!(
+                "{} has no name section, snip the output of `build --keep-names`",
+                self.input.display()
+            )));
+        }
+        let exported: Vec<u32> = wasm::exported_functions(&module)?;
+        let mut snipped = Vec::new();
+        for (index, name) in &names {
+            if *index < imported || !patterns.is_match(name) {
+                continue;
+            }
+            if exported.contains(index) {
+                eprintln!("warning: not snipping `{}`, it is exported", name);
+                continue;
+            }
+            snipped.push(*index);
+        }
+        if snipped.is_empty() {
+            println!("No function matches, {} is unchanged", self.input.display());
+            return Ok(CommandReport::default());
+        }
+        let replaced = wasm::replace_function_bodies(&module, |defined| {
+            snipped
+                .contains(&(imported + defined))
+                .then(|| UNREACHABLE_BODY.to_vec())
+        })?;
+        let output = self.output.as_ref().unwrap_or(&self.input);
+        // Written next to the output, so the rename below doesn't cross file systems.
+        let partial = output.with_extension("wasm.partial");
+        fs::write(&partial, &replaced)?;
+        // Dead code elimination drops what only the snipped functions called.
+        let dce = wasm_opt::OptimizationOptions::new_opt_level_0()
+            .add_pass(wasm_opt::Pass::RemoveUnusedModuleElements)
+            .debug_info(true)
+            .run(&partial, &partial);
+        if let Err(err) = dce {
+            let _ = fs::remove_file(&partial);
+            return Err(Error::wasm("wasm-opt failed").caused_by(err));
+        }
+        let result = fs::read(&partial)?;
+        if let Err(err) = wasmparser::validate(&result) {
+            let _ = fs::remove_file(&partial);
+            return Err(Error::wasm("the snipped module is invalid").caused_by(err));
+        }
+        fs::rename(&partial, output)?;
+        for index in &snipped {
+            println!("snipped {}", names[index]);
+        }
+        println!(
+            "{} -> {} bytes ({} saved) in {}",
+            module.len(),
+            result.len(),
+            module.len() as i64 - result.len() as i64,
+            output.display()
+        );
+        println!(
+            "WARNING: the {} snipped function(s) now trap when called. If the contract can \
+            reach any of them, it aborts on the peer instead of reporting an error, test it \
+            before deploying.",
+            snipped.len()
+        );
+        Ok(CommandReport::default())
+    }
+}
@@ -0,0 +1,81 @@
+use super::wasm;
+use super::*;
+use std::{fs, path::PathBuf};
+
+/// Everything required to configure and run the `iroha_wasm_pack strip` command.
+#[derive(Debug, StructOpt)]
+pub struct StripArgs {
+    /// Wasm file to strip
+    #[structopt(parse(from_os_str))]
+    pub input: PathBuf,
+
+    /// Output file, defaults to overwriting the input
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    /// Also remove this custom section
+    #[structopt(long = "remove", number_of_values = 1)]
+    pub remove: Vec<String>,
+
+    /// Never remove this custom section, even the name section or DWARF
+    #[structopt(long = "keep", number_of_values = 1)]
+    pub keep: Vec<String>,
+
+    /// Only list the sections that would be removed
+    #[structopt(long = "list")]
+    pub list: bool,
+}
+
+impl StripArgs {
+    /// Name, producers and DWARF go, besides `--remove`, unless protected by `--keep`
+    fn removes(&self, name: &str) -> bool {
+        if self.keep.iter().any(|keep| keep == name) {
+            return false;
+        }
+        name == "name"
+            || name == "producers"
+            || name.starts_with(".debug_")
+            || self.remove.iter().any(|remove| remove == name)
+    }
+}
+
+impl RunArgs for StripArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let module = match fs::read(&self.input) {
+            Ok(module) => module,
+            Err(err) => {
+                return Err(
+                    Error::wasm(format!("read {} failed", self.input.display())).caused_by(err)
+                )
+            }
+        };
+        if self.list {
+            for section in wasm::parse(&module)?.custom_sections {
+                if self.removes(&section.name) {
+                    println!("{:<24} {:>9} B", section.name, section.size);
+                }
+            }
+            return Ok(CommandReport::default());
+        }
+        let stripped = wasm::retain_custom_sections(&module, |name| !self.removes(name))?;
+        let check = wasm::check_structure(&stripped);
+        if check.outcome == wasm::Outcome::Fail {
+            return Err(Error::wasm(format!(
+                "stripping left an invalid module, error = {}",
+                check.details.join(", ")
+            )));
+        }
+        let output = self.output.as_ref().unwrap_or(&self.input);
+        // Written next to the output and renamed, so readers never see a partial file.
+        let partial = output.with_extension("wasm.partial");
+        fs::write(&partial, &stripped)?;
+        fs::rename(&partial, output)?;
+        println!(
+            "Stripped {} bytes, {} -> {} bytes",
+            module.len() - stripped.len(),
+            module.len(),
+            stripped.len()
+        );
+        Ok(CommandReport::default())
+    }
+}
@@ -0,0 +1,188 @@
+use super::build::build_tests;
+use super::*;
+use std::path::Path;
+use wasmtime::{Engine, Linker, Module, Store};
+
+/// Everything required to configure and run the `iroha_wasm_pack test` command.
+#[derive(Debug, StructOpt)]
+pub struct TestArgs {
+    /// Only run the tests whose name contains this text
+    pub filter: Option<String>,
+
+    /// Build the tests with the release profile
+    #[structopt(long = "release")]
+    pub release: bool,
+
+    /// Print what the tests sent to the host as they run, instead of only for failures
+    #[structopt(long = "nocapture")]
+    pub nocapture: bool,
+
+    /// Package of the workspace to test
+    #[structopt(short = "p", long = "package")]
+    pub package: Option<String>,
+
+    /// Test every member of the workspace
+    #[structopt(long = "workspace", conflicts_with = "package")]
+    pub workspace: bool,
+
+    /// Nightly toolchain to build with, overriding the toolchain file of the project
+    #[structopt(long = "toolchain")]
+    pub toolchain: Option<String>,
+
+    /// Build with the precompiled std of the wasm32 target instead of `-Z build-std`
+    #[structopt(long = "no-build-std")]
+    pub no_build_std: bool,
+
+    /// Install missing toolchain components without asking
+    #[structopt(short = "y", long = "yes")]
+    pub yes: bool,
+}
+
+/// Export prefixes of the functions `#[webassembly_test]` generates
+const TEST_PREFIX: &str = "$webassembly-test$";
+const IGNORED_PREFIX: &str = "$webassembly-test-ignore$";
+
+/// Outcome of a single test
+enum Outcome {
+    Passed,
+    Failed(String),
+    Ignored,
+}
+
+impl TestArgs {
+    /// `build --tests` with the same toolchain settings as `build`
+    fn build_args(&self) -> Result<BuildArgs, Error> {
+        let mut args = vec!["build".to_owned(), "--tests".to_owned()];
+        if let Some(package) = &self.package {
+            args.extend(["--package".to_owned(), package.clone()]);
+        }
+        if let Some(toolchain) = &self.toolchain {
+            args.extend(["--toolchain".to_owned(), toolchain.clone()]);
+        }
+        for (set, flag) in [
+            (self.workspace, "--workspace"),
+            (self.no_build_std, "--no-build-std"),
+            (self.yes, "--yes"),
+            (self.release, "--release"),
+        ] {
+            if set {
+                args.push(flag.to_owned());
+            }
+        }
+        Ok(BuildArgs::from_iter_safe(&args)?)
+    }
+}
+
+/// Run the export `name` of a fresh instance, imports of the contract trap if called
+fn run_test(engine: &Engine, module: &Module, name: &str) -> anyhow::Result<()> {
+    let mut linker: Linker<()> = Linker::new(engine);
+    let mut store = Store::new(engine, ());
+    linker.define_unknown_imports_as_traps(module)?;
+    let instance = linker.instantiate(&mut store, module)?;
+    let test = instance.get_typed_func::<(), ()>(&mut store, name)?;
+    test.call(&mut store, ())
+}
+
+/// Run the tests of one harness, printing a line per test
+fn run_harness(
+    args: &TestArgs,
+    path: &Path,
+    failures: &mut Vec<(String, String)>,
+) -> Result<(usize, usize, usize, usize), Error> {
+    let engine = Engine::default();
+    let module = match Module::from_file(&engine, path) {
+        Ok(module) => module,
+        Err(err) => {
+            return Err(Error::other(format!("load {} failed", path.display())).caused_by(err))
+        }
+    };
+    let mut tests: Vec<(String, String, bool)> = module
+        .exports()
+        .filter_map(|export| {
+            let name = export.name();
+            if let Some(test) = name.strip_prefix(TEST_PREFIX) {
+                Some((test.to_owned(), name.to_owned(), false))
+            } else {
+                name.strip_prefix(IGNORED_PREFIX)
+                    .map(|test| (test.to_owned(), name.to_owned(), true))
+            }
+        })
+        .collect();
+    tests.sort();
+    let (mut passed, mut failed, mut ignored, mut filtered) = (0, 0, 0, 0);
+    println!(
+        "\n     Running {}\n\nrunning {} tests",
+        path.display(),
+        tests.len()
+    );
+    for (test, export, is_ignored) in tests {
+        if args
+            .filter
+            .as_ref()
+            .map_or(false, |filter| !test.contains(filter.as_str()))
+        {
+            filtered += 1;
+            continue;
+        }
+        let outcome = if is_ignored {
+            Outcome::Ignored
+        } else {
+            match run_test(&engine, &module, &export) {
+                Ok(()) => Outcome::Passed,
+                Err(err) => Outcome::Failed(format!("{:#}", err)),
+            }
+        };
+        match outcome {
+            Outcome::Passed => {
+                passed += 1;
+                println!("test {} ... ok", test);
+            }
+            Outcome::Ignored => {
+                ignored += 1;
+                println!("test {} ... ignored", test);
+            }
+            Outcome::Failed(err) => {
+                failed += 1;
+                println!("test {} ... FAILED", test);
+                if args.nocapture {
+                    println!("{}", err);
+                }
+                failures.push((test, err));
+            }
+        }
+    }
+    Ok((passed, failed, ignored, filtered))
+}
+
+impl RunArgs for TestArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let harnesses = build_tests(&self.build_args()?)?;
+        let mut failures = Vec::new();
+        let (mut passed, mut failed, mut ignored, mut filtered) = (0, 0, 0, 0);
+        for harness in &harnesses {
+            let counts = run_harness(&self, harness, &mut failures)?;
+            passed += counts.0;
+            failed += counts.1;
+            ignored += counts.2;
+            filtered += counts.3;
+        }
+        if !failures.is_empty() && !self.nocapture {
+            println!("\nfailures:\n");
+            for (test, err) in &failures {
+                println!("---- {} ----\n{}\n", test, err);
+            }
+        }
+        println!(
+            "\ntest result: {}. {} passed; {} failed; {} ignored; 0 measured; {} filtered out",
+            if failed == 0 { "ok" } else { "FAILED" },
+            passed,
+            failed,
+            ignored,
+            filtered
+        );
+        if failed > 0 {
+            return Err(Error::other(format!("{} test(s) failed", failed)));
+        }
+        Ok(CommandReport::default())
+    }
+}
@@ -0,0 +1,46 @@
+//! Fixtures shared by the tests of the modules
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A new empty directory under the system's temporary directory
+pub fn temp_dir(name: &str) -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "iroha_wasm_pack-{}-{}-{}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::SeqCst),
+        name
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Write `files` of `(path, contents)` under `dir`, creating the parent directories
+pub fn write_files(dir: &Path, files: &[(&str, &str)]) {
+    for (path, contents) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+}
+
+/// A `cdylib` package named `contract` in a new directory
+pub fn contract(name: &str) -> PathBuf {
+    let dir = temp_dir(name);
+    write_files(
+        &dir,
+        &[
+            (
+                "Cargo.toml",
+                "[package]\nname = \"contract\"\nversion = \"0.1.0\"\n\n[lib]\ncrate-type = [\"cdylib\"]\n",
+            ),
+            ("src/lib.rs", ""),
+        ],
+    );
+    dir
+}
@@ -0,0 +1,137 @@
+use super::*;
+use std::{fs, io::Write, path::PathBuf};
+
+/// Everything required to configure and run the `iroha_wasm_pack wat` command.
+#[derive(Debug, StructOpt)]
+pub struct WatArgs {
+    /// Wasm binary to disassemble, or text to assemble with `--to-wasm`
+    #[structopt(parse(from_os_str))]
+    pub input: PathBuf,
+
+    /// Write to a file instead of stdout
+    #[structopt(short = "o", long = "output", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+
+    /// Assemble the text format into a binary module
+    #[structopt(long = "to-wasm")]
+    pub to_wasm: bool,
+}
+
+impl WatArgs {
+    /// Disassemble the binary input
+    fn to_text(&self) -> Result<Vec<u8>, Error> {
+        let module = fs::read(&self.input)?;
+        // wasmprinter has no streaming writer, the text is built in memory once.
+        match wasmprinter::print_bytes(&module) {
+            Ok(text) => Ok(text.into_bytes()),
+            Err(err) => Err(
+                Error::wasm(format!("disassemble {} failed", self.input.display())).caused_by(err),
+            ),
+        }
+    }
+
+    /// Assemble the text input, validated like the output of `build`
+    fn to_binary(&self) -> Result<Vec<u8>, Error> {
+        // The error of `parse_file` points at the line and column of the input.
+        let module = wat::parse_file(&self.input).map_err(|err| Error::wasm(err.to_string()))?;
+        let check = wasm::check_structure(&module);
+        if check.outcome == wasm::Outcome::Fail {
+            return Err(Error::wasm(format!(
+                "{} is not a valid module, error = {}",
+                self.input.display(),
+                check.details.join(", ")
+            )));
+        }
+        Ok(module)
+    }
+}
+
+impl RunArgs for WatArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let converted = if self.to_wasm {
+            self.to_binary()?
+        } else {
+            self.to_text()?
+        };
+        match &self.output {
+            Some(output) => fs::write(output, converted)?,
+            None if self.to_wasm => {
+                return Err(Error::wasm(
+                    "refusing to write a binary module to stdout, pass `-o`",
+                ))
+            }
+            None => std::io::stdout().lock().write_all(&converted)?,
+        }
+        Ok(CommandReport::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    const MODULE: &str = r#"(module
+  (import "iroha" "execute_instruction" (func $execute (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 16) "rose#wonderland")
+  (func (export "_iroha_trigger_main")
+    i32.const 16
+    i32.const 15
+    call $execute)
+)
+"#;
+
+    fn wat(flags: &[&str]) -> Result<CommandReport, Error> {
+        WatArgs::from_iter_safe(["wat"].iter().chain(flags))
+            .unwrap()
+            .run()
+    }
+
+    #[test]
+    fn text_and_binary_round_trip() {
+        let dir = test_util::temp_dir("wat");
+        test_util::write_files(&dir, &[("module.wat", MODULE)]);
+        let path = |name: &str| dir.join(name).display().to_string();
+        wat(&["--to-wasm", &path("module.wat"), "-o", &path("first.wasm")]).unwrap();
+        wat(&[&path("first.wasm"), "-o", &path("printed.wat")]).unwrap();
+        wat(&[
+            "--to-wasm",
+            &path("printed.wat"),
+            "-o",
+            &path("second.wasm"),
+        ])
+        .unwrap();
+        let first = fs::read(dir.join("first.wasm")).unwrap();
+        assert_eq!(first, fs::read(dir.join("second.wasm")).unwrap());
+        assert_eq!(first, ::wat::parse_str(MODULE).unwrap());
+        let printed = fs::read_to_string(dir.join("printed.wat")).unwrap();
+        assert!(printed.contains("\"execute_instruction\""), "{}", printed);
+    }
+
+    #[test]
+    fn invalid_text_points_at_the_line() {
+        let dir = test_util::temp_dir("wat-invalid");
+        test_util::write_files(
+            &dir,
+            &[(
+                "module.wat",
+                "(module\n  (func (result i32)\n    i32.bogus))\n",
+            )],
+        );
+        let input = dir.join("module.wat").display().to_string();
+        let output = dir.join("module.wasm").display().to_string();
+        let err = wat(&["--to-wasm", &input, "-o", &output]).err().unwrap();
+        assert!(err.to_string().contains(":3:"), "{}", err);
+        assert!(!dir.join("module.wasm").exists());
+    }
+
+    #[test]
+    fn binary_is_not_written_to_stdout() {
+        let dir = test_util::temp_dir("wat-stdout");
+        test_util::write_files(&dir, &[("module.wat", MODULE)]);
+        let input = dir.join("module.wat").display().to_string();
+        let err = wat(&["--to-wasm", &input]).err().unwrap();
+        assert!(err.to_string().contains("pass `-o`"), "{}", err);
+    }
+}
@@ -0,0 +1,408 @@
+use super::new::{rc_number, walk_files, DependencyArgs, IROHA_CRATES};
+use super::*;
+use duct::cmd;
+use std::{
+    env::current_dir,
+    fs,
+    path::{Path, PathBuf},
+};
+use toml_edit::{value, Document, Item};
+
+/// Everything required to configure and run the `iroha_wasm_pack upgrade` command.
+#[derive(Debug, StructOpt)]
+pub struct UpgradeArgs {
+    #[structopt(flatten)]
+    pub deps: DependencyArgs,
+
+    /// Nightly toolchain to pin in rust-toolchain.toml
+    #[structopt(long = "toolchain", default_value = DEFAULT_TOOLCHAIN)]
+    pub toolchain: String,
+
+    /// Only print the changes that would be made
+    #[structopt(long = "dry-run")]
+    pub dry_run: bool,
+
+    /// Also rewrite the contract code for the API of the new Iroha version, printing a diff
+    #[structopt(long = "codemod")]
+    pub codemod: bool,
+
+    /// Write the changes of `--codemod` instead of only printing them
+    #[structopt(long = "apply", requires = "codemod")]
+    pub apply: bool,
+
+    /// Upgrade even if the git working tree has uncommitted changes
+    #[structopt(long = "allow-dirty")]
+    pub allow_dirty: bool,
+}
+
+/// Release profile keys recommended for contracts
+const RECOMMENDED_PROFILE: &[(&str, &str)] = &[
+    ("strip", "\"debuginfo\""),
+    ("panic", "\"abort\""),
+    ("lto", "true"),
+    ("opt-level", "\"z\""),
+    ("codegen-units", "1"),
+];
+
+/// Mechanical rewrite of the contract code between Iroha releases.
+struct Rewrite {
+    /// Release candidate that needs the rewrite, applied when upgrading across it
+    rc: u32,
+    pattern: &'static str,
+    replacement: &'static str,
+}
+
+const REWRITES: &[Rewrite] = &[
+    // Only the trigger entrypoint takes its authority, smartcontracts keep the bare attribute.
+    Rewrite {
+        rc: 10,
+        pattern: r"#\[iroha_wasm::entrypoint\](\s*(?:pub\s+)?fn\s+trigger_entrypoint\s*\(\s*authority\b)",
+        replacement: r#"#[iroha_wasm::entrypoint(params = "[authority]")]$1"#,
+    },
+    Rewrite {
+        rc: 12,
+        pattern: r"<(Account|Asset|AssetDefinition|Domain|Peer|Role|Trigger)\s+as\s+Identifiable>::Id\b",
+        replacement: "${1}Id",
+    },
+];
+
+/// Code the codemod can't rewrite safely, reported for a manual migration.
+struct Manual {
+    /// Release candidate that changed the API
+    rc: u32,
+    pattern: &'static str,
+    hint: &'static str,
+}
+
+const MANUAL: &[Manual] = &[
+    Manual {
+        rc: 10,
+        pattern: r"#\[iroha_wasm::entrypoint\]",
+        hint: "a trigger entrypoint needs `params = \"[authority]\"`, a smartcontract one doesn't",
+    },
+    Manual {
+        rc: 15,
+        pattern: r"\bNeedsPermissionBox\b|\bVerdict::",
+        hint: "executors have `migrate` and `validate_*` entrypoints, compare with `iroha_wasm_pack new --template executor`",
+    },
+    Manual {
+        rc: 15,
+        pattern: r"\bInstruction::\w+\(",
+        hint: "instructions are executed without the `Instruction` wrapper, like `MintBox::new(..).execute()`",
+    },
+];
+
+/// Release candidate the manifest pins the Iroha crates to
+fn pinned_rc(cargo_xml: &str) -> Option<u32> {
+    let doc: Document = cargo_xml.parse().ok()?;
+    IROHA_CRATES.iter().find_map(|krate| {
+        let dependency = doc.get("dependencies")?.get(krate)?;
+        if let Some(version) = dependency.as_str() {
+            return rc_number(version);
+        }
+        let table = dependency.as_table_like()?;
+        ["tag", "version"]
+            .iter()
+            .find_map(|key| table.get(key).and_then(Item::as_str).and_then(rc_number))
+    })
+}
+
+/// Code left for a manual migration, at a 1-based line
+#[derive(Debug, PartialEq)]
+struct Skipped {
+    line: usize,
+    code: String,
+    hint: &'static str,
+}
+
+/// `source` rewritten for an upgrade from `from` to `to`, with the code needing a human
+fn rewrite(source: &str, from: u32, to: u32) -> Result<(String, Vec<Skipped>), Error> {
+    use regex::Regex;
+    let crossed = |rc: u32| from < rc && rc <= to;
+    let mut new = source.to_owned();
+    for rewrite in REWRITES.iter().filter(|rewrite| crossed(rewrite.rc)) {
+        new = Regex::new(rewrite.pattern)?
+            .replace_all(&new, rewrite.replacement)
+            .into_owned();
+    }
+    let mut skipped = Vec::new();
+    for item in MANUAL.iter().filter(|item| crossed(item.rc)) {
+        for found in Regex::new(item.pattern)?.find_iter(&new) {
+            skipped.push(Skipped {
+                line: new[..found.start()].matches('\n').count() + 1,
+                code: found.as_str().to_owned(),
+                hint: item.hint,
+            });
+        }
+    }
+    Ok((new, skipped))
+}
+
+/// Unified diff of a file
+fn print_diff(root: &Path, path: &Path, old: &str, new: &str) {
+    let name = path
+        .strip_prefix(root)
+        .unwrap_or(path)
+        .display()
+        .to_string();
+    print!(
+        "{}",
+        similar::TextDiff::from_lines(old, new)
+            .unified_diff()
+            .header(&format!("a/{}", name), &format!("b/{}", name))
+    );
+}
+
+impl UpgradeArgs {
+    /// Rewrite the sources for an upgrade from `from` to `to`, reporting what needs a human
+    fn codemod(&self, root: &Path, from: u32, to: u32) -> Result<(), Error> {
+        let mut files: Vec<PathBuf> = Vec::new();
+        for dir in ["src", "examples", "tests", "benches"] {
+            if root.join(dir).is_dir() {
+                files.extend(walk_files(&root.join(dir))?);
+            }
+        }
+        files.retain(|file| file.extension().map_or(false, |ext| ext == "rs"));
+        files.sort();
+        let mut manual = 0;
+        for file in files {
+            let old = fs::read_to_string(&file)?;
+            let (new, skipped) = rewrite(&old, from, to)?;
+            for item in &skipped {
+                eprintln!(
+                    "{}:{}: skipped `{}`, {}",
+                    file.strip_prefix(root).unwrap_or(&file).display(),
+                    item.line,
+                    item.code,
+                    item.hint
+                );
+            }
+            manual += skipped.len();
+            if new != old {
+                print_diff(root, &file, &old, &new);
+                if self.apply && !self.dry_run {
+                    fs::write(&file, new)?;
+                }
+            }
+        }
+        if manual > 0 {
+            eprintln!("{} place(s) need a manual migration", manual);
+        }
+        if !self.apply {
+            println!("Pass `--apply` to write the code changes");
+        }
+        Ok(())
+    }
+
+    /// Manifest with the new dependency pins and the missing profile keys
+    fn upgrade_manifest(&self, cargo_xml: &str) -> Result<String, Error> {
+        let mut doc: Document = cargo_xml.parse()?;
+        for krate in IROHA_CRATES {
+            let dependency = match doc["dependencies"].get_mut(krate) {
+                Some(dependency) => dependency,
+                None => continue,
+            };
+            let table = match dependency.as_table_like_mut() {
+                Some(table) => table,
+                // A plain version string.
+                None => {
+                    if let Some((_, version)) = self
+                        .deps
+                        .source_pairs()
+                        .into_iter()
+                        .find(|(key, _)| *key == "version")
+                    {
+                        *dependency = value(version);
+                    }
+                    continue;
+                }
+            };
+            for key in ["git", "branch", "tag", "rev", "version"] {
+                table.remove(key);
+            }
+            for (key, pair_value) in self.deps.source_pairs() {
+                table.insert(key, value(pair_value));
+            }
+        }
+        let release = &mut doc["profile"]["release"];
+        for (key, recommended) in RECOMMENDED_PROFILE {
+            if release.get(key).is_none() {
+                let recommended: toml_edit::Value = recommended.parse()?;
+                release[key] = Item::Value(recommended);
+            }
+        }
+        Ok(doc.to_string())
+    }
+
+    /// `rust-toolchain.toml` with the new pin
+    fn upgrade_toolchain(&self, toolchain_file: Option<&str>) -> Result<String, Error> {
+        let mut doc: Document = toolchain_file.unwrap_or_default().parse()?;
+        doc["toolchain"]["channel"] = value(self.toolchain.as_str());
+        if doc["toolchain"].get("components").is_none() {
+            doc["toolchain"]["components"] = value(toml_edit::Array::from_iter(["rust-src"]));
+        }
+        if doc["toolchain"].get("targets").is_none() {
+            doc["toolchain"]["targets"] =
+                value(toml_edit::Array::from_iter(["wasm32-unknown-unknown"]));
+        }
+        Ok(doc.to_string())
+    }
+}
+
+impl RunArgs for UpgradeArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let root = current_dir()?;
+        let manifest = root.join("Cargo.toml");
+        if !manifest.exists() {
+            return Err(Error::other(
+                "no Cargo.toml in the current directory, run `upgrade` in the project root",
+            ));
+        }
+        if !self.dry_run && !self.allow_dirty {
+            let status = cmd!("git", "status", "--porcelain")
+                .dir(&root)
+                .stderr_null()
+                .read()
+                .unwrap_or_default();
+            if !status.trim().is_empty() {
+                return Err(Error::other("the git working tree has uncommitted changes, commit them or pass `--allow-dirty`"));
+            }
+        }
+        let toolchain_path = root.join("rust-toolchain.toml");
+        let old_manifest = fs::read_to_string(&manifest)?;
+        let versions = (pinned_rc(&old_manifest), self.deps.iroha_rc());
+        let old_toolchain = fs::read_to_string(&toolchain_path).ok();
+        let changes = [
+            (
+                manifest,
+                old_manifest.clone(),
+                self.upgrade_manifest(&old_manifest)?,
+            ),
+            (
+                toolchain_path,
+                old_toolchain.clone().unwrap_or_default(),
+                self.upgrade_toolchain(old_toolchain.as_deref())?,
+            ),
+        ];
+        let mut changed = false;
+        for (path, old, new) in &changes {
+            if old == new {
+                continue;
+            }
+            changed = true;
+            print_diff(&root, path, old, new);
+            if !self.dry_run {
+                fs::write(path, new)?;
+            }
+        }
+        if !changed {
+            println!("Already up to date");
+        }
+        if self.codemod {
+            match versions {
+                (Some(from), Some(to)) => self.codemod(&root, from, to)?,
+                _ => eprintln!("warning: skipped `--codemod`, it needs tag or crates.io pins to know the Iroha versions"),
+            }
+        }
+        Ok(CommandReport::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util;
+
+    const TRIGGER_RC9: &str = r#"#![no_std]
+#![no_main]
+
+use iroha_wasm::data_model::prelude::*;
+
+#[iroha_wasm::entrypoint]
+fn trigger_entrypoint(authority: <Account as Identifiable>::Id) {
+    let rose: <AssetDefinition as Identifiable>::Id = "rose#wonderland".parse().unwrap();
+    Instruction::Mint(MintBox::new(1_u32, AssetId::new(rose, authority))).execute();
+}
+"#;
+
+    const TRIGGER_RC12: &str = r#"#![no_std]
+#![no_main]
+
+use iroha_wasm::data_model::prelude::*;
+
+#[iroha_wasm::entrypoint(params = "[authority]")]
+fn trigger_entrypoint(authority: AccountId) {
+    let rose: AssetDefinitionId = "rose#wonderland".parse().unwrap();
+    Instruction::Mint(MintBox::new(1_u32, AssetId::new(rose, authority))).execute();
+}
+"#;
+
+    const SMARTCONTRACT_RC9: &str = r#"#![no_std]
+#![no_main]
+
+#[iroha_wasm::entrypoint]
+fn main(_owner: iroha_wasm::data_model::prelude::AccountId) {}
+"#;
+
+    #[test]
+    fn codemod_rewrites_a_trigger() {
+        let (new, skipped) = rewrite(TRIGGER_RC9, 9, 12).unwrap();
+        assert_eq!(new, TRIGGER_RC12);
+        assert!(skipped.is_empty(), "{:?}", skipped);
+    }
+
+    #[test]
+    fn codemod_only_applies_the_crossed_releases() {
+        let (new, _) = rewrite(TRIGGER_RC9, 10, 11).unwrap();
+        assert_eq!(new, TRIGGER_RC9);
+        let (new, _) = rewrite(TRIGGER_RC9, 10, 12).unwrap();
+        assert!(new.contains("#[iroha_wasm::entrypoint]\n"), "{}", new);
+        assert!(new.contains("(authority: AccountId)"), "{}", new);
+    }
+
+    #[test]
+    fn codemod_reports_what_it_cant_rewrite() {
+        let (new, skipped) = rewrite(SMARTCONTRACT_RC9, 9, 12).unwrap();
+        assert_eq!(new, SMARTCONTRACT_RC9);
+        assert_eq!(
+            skipped,
+            [Skipped {
+                line: 4,
+                code: "#[iroha_wasm::entrypoint]".to_owned(),
+                hint: MANUAL[0].hint,
+            }]
+        );
+        let (_, skipped) = rewrite(TRIGGER_RC12, 14, 15).unwrap();
+        let found: Vec<_> = skipped
+            .iter()
+            .map(|item| (item.line, item.code.as_str()))
+            .collect();
+        assert_eq!(found, [(9, "Instruction::Mint(")]);
+    }
+
+    #[test]
+    fn codemod_writes_only_with_apply() {
+        let root = test_util::temp_dir("codemod");
+        test_util::write_files(
+            &root,
+            &[("src/lib.rs", TRIGGER_RC9), ("src/notes.md", TRIGGER_RC9)],
+        );
+        let args = |flags: &[&str]| {
+            UpgradeArgs::from_iter_safe(["upgrade", "--codemod"].iter().chain(flags)).unwrap()
+        };
+        args(&[]).codemod(&root, 9, 12).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("src/lib.rs")).unwrap(),
+            TRIGGER_RC9
+        );
+        args(&["--apply"]).codemod(&root, 9, 12).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("src/lib.rs")).unwrap(),
+            TRIGGER_RC12
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("src/notes.md")).unwrap(),
+            TRIGGER_RC9
+        );
+    }
+}
@@ -0,0 +1,85 @@
+use super::wasm::{self, CheckPolicy, Outcome};
+use super::*;
+use std::{fs, io::Read, path::PathBuf};
+
+/// Everything required to configure and run the `iroha_wasm_pack verify` command.
+#[derive(Debug, StructOpt)]
+pub struct VerifyArgs {
+    /// Wasm file to check, `-` reads it from stdin
+    #[structopt(parse(from_os_str))]
+    pub path: PathBuf,
+
+    /// Largest accepted module in bytes, 4 MiB by default
+    #[structopt(long = "max-size")]
+    pub max_size: Option<u64>,
+
+    /// Largest accepted initial memory in wasm pages, 8000 (500 MiB) by default
+    #[structopt(long = "max-memory-pages")]
+    pub max_memory_pages: Option<u64>,
+
+    /// Also accept imports of a `module` or a `module::name`
+    #[structopt(long = "allow-import", number_of_values = 1)]
+    pub allow_import: Vec<String>,
+
+    /// Print the checks as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+}
+
+impl VerifyArgs {
+    fn read_module(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        let read = if self.path.as_os_str() == "-" {
+            std::io::stdin().read_to_end(&mut bytes).map(|_| ())
+        } else {
+            fs::read(&self.path).map(|read| bytes = read)
+        };
+        match read {
+            Ok(()) => Ok(bytes),
+            Err(err) => {
+                Err(Error::wasm(format!("read {} failed", self.path.display())).caused_by(err))
+            }
+        }
+    }
+}
+
+impl RunArgs for VerifyArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let module = self.read_module()?;
+        let policy = CheckPolicy {
+            max_size: self.max_size.unwrap_or(MAX_WASM_SIZE),
+            max_memory_pages: self.max_memory_pages.unwrap_or(MAX_MEMORY_PAGES),
+            allowed_imports: self.allow_import.clone(),
+            contract: true,
+        };
+        let checks = wasm::run_checks(&module, &policy);
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&checks)?);
+        } else {
+            for check in &checks {
+                let outcome = match check.outcome {
+                    Outcome::Pass => "pass",
+                    Outcome::Warn => "warn",
+                    Outcome::Fail => "FAIL",
+                };
+                println!("{:<4} {}", outcome, check.name);
+                for detail in &check.details {
+                    println!("       {}", detail);
+                }
+            }
+        }
+        let failed = checks
+            .iter()
+            .filter(|check| check.outcome == Outcome::Fail)
+            .count();
+        if failed > 0 {
+            return Err(Error::wasm(format!(
+                "{} of {} checks failed for {}",
+                failed,
+                checks.len(),
+                self.path.display()
+            )));
+        }
+        Ok(CommandReport::default())
+    }
+}
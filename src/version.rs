@@ -0,0 +1,84 @@
+//! `iroha_wasm_pack version`, what exactly this binary is, for bug reports.
+//!
+//! The commit, build date, wasm-opt version and host are embedded by `build.rs`, `doctor` looks
+//! at the machine instead.
+
+use super::*;
+use serde_derive::Serialize;
+
+/// Everything required to configure and run the `iroha_wasm_pack version` command.
+#[derive(Debug, StructOpt)]
+pub struct VersionArgs {
+    /// Also print the build and the tooling the binary comes with
+    #[structopt(short = "v", long = "verbose")]
+    pub verbose: bool,
+
+    /// Print everything as JSON
+    #[structopt(long = "json")]
+    pub json: bool,
+}
+
+/// The binary and what went into it, `unknown` where the build couldn't tell
+#[derive(Debug, Serialize)]
+pub struct VersionInfo {
+    pub version: &'static str,
+    pub commit: &'static str,
+    pub build_date: &'static str,
+    /// Version of the `wasm-opt` crate
+    pub wasm_opt: &'static str,
+    /// Binaryen release the `wasm-opt` crate bundles, its minor version
+    pub binaryen: String,
+    /// Toolchain used when the project doesn't pin one
+    pub default_toolchain: &'static str,
+    /// Iroha tag `new` pins the dependencies to
+    pub iroha: &'static str,
+    pub iroha_git: &'static str,
+    /// Target triple the binary was built for
+    pub host: &'static str,
+}
+
+impl VersionInfo {
+    pub fn current() -> Self {
+        let wasm_opt = env!("IROHA_WASM_PACK_WASM_OPT_VERSION");
+        let binaryen = match wasm_opt.split('.').nth(1) {
+            Some(minor) => format!("version_{}", minor),
+            None => "unknown".to_owned(),
+        };
+        VersionInfo {
+            version: env!("CARGO_PKG_VERSION"),
+            commit: env!("IROHA_WASM_PACK_GIT_COMMIT"),
+            build_date: env!("IROHA_WASM_PACK_BUILD_DATE"),
+            wasm_opt,
+            binaryen,
+            default_toolchain: DEFAULT_TOOLCHAIN,
+            iroha: new::DEFAULT_IROHA_VERSION,
+            iroha_git: new::IROHA_GIT,
+            host: env!("IROHA_WASM_PACK_HOST"),
+        }
+    }
+}
+
+impl RunArgs for VersionArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let info = VersionInfo::current();
+        if self.json {
+            println!("{}", serde_json::to_string_pretty(&info)?);
+            return Ok(CommandReport::default());
+        }
+        let mut summary = vec![format!("iroha_wasm_pack {}", info.version)];
+        if self.verbose {
+            summary.extend([
+                format!("{:<19}{}", "commit:", info.commit),
+                format!("{:<19}{}", "build date:", info.build_date),
+                format!(
+                    "{:<19}{} (binaryen {})",
+                    "wasm-opt:", info.wasm_opt, info.binaryen
+                ),
+                format!("{:<19}{}", "default toolchain:", info.default_toolchain),
+                format!("{:<19}{} ({})", "iroha:", info.iroha, info.iroha_git),
+                format!("{:<19}{}", "host:", info.host),
+            ]);
+        }
+        Ok(CommandReport::summary(summary))
+    }
+}
@@ -0,0 +1,787 @@
+//! Parsing of wasm modules shared by the build checks and the commands reading built contracts.
+
+use super::*;
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+use wasmparser::{ExternalKind, Parser, Payload, TypeRef, Validator, WasmFeatures};
+
+/// An imported item
+#[derive(Debug, Serialize)]
+pub struct Import {
+    pub module: String,
+    pub name: String,
+    pub kind: &'static str,
+}
+
+/// An exported item
+#[derive(Debug, Serialize)]
+pub struct Export {
+    pub name: String,
+    pub kind: &'static str,
+}
+
+/// Initial and maximum size of a memory in pages, or of a table in elements
+#[derive(Debug, Serialize)]
+pub struct Limits {
+    pub initial: u64,
+    pub maximum: Option<u64>,
+}
+
+/// A section and its size in bytes, custom sections by their name
+#[derive(Debug, Serialize)]
+pub struct Section {
+    pub name: String,
+    pub size: usize,
+}
+
+/// What a wasm module declares.
+#[derive(Debug, Default, Serialize)]
+pub struct ModuleInfo {
+    pub imports: Vec<Import>,
+    pub exports: Vec<Export>,
+    pub memories: Vec<Limits>,
+    pub tables: Vec<Limits>,
+    pub custom_sections: Vec<Section>,
+    pub sections: Vec<Section>,
+}
+
+/// Name of a known section id
+fn section_name(id: u8) -> &'static str {
+    match id {
+        0 => "custom",
+        1 => "type",
+        2 => "import",
+        3 => "function",
+        4 => "table",
+        5 => "memory",
+        6 => "global",
+        7 => "export",
+        8 => "start",
+        9 => "element",
+        10 => "code",
+        11 => "data",
+        12 => "datacount",
+        13 => "tag",
+        _ => "unknown",
+    }
+}
+
+fn type_ref_kind(ty: &TypeRef) -> &'static str {
+    match ty {
+        TypeRef::Func(_) => "func",
+        TypeRef::Table(_) => "table",
+        TypeRef::Memory(_) => "memory",
+        TypeRef::Global(_) => "global",
+        TypeRef::Tag(_) => "tag",
+    }
+}
+
+fn external_kind(kind: ExternalKind) -> &'static str {
+    match kind {
+        ExternalKind::Func => "func",
+        ExternalKind::Table => "table",
+        ExternalKind::Memory => "memory",
+        ExternalKind::Global => "global",
+        ExternalKind::Tag => "tag",
+    }
+}
+
+/// A function and the size of its body
+#[derive(Debug, Serialize)]
+pub struct FunctionSize {
+    pub name: String,
+    pub size: usize,
+}
+
+/// A data segment with the printable start of its bytes
+#[derive(Debug, Serialize)]
+pub struct DataSize {
+    pub index: usize,
+    pub size: usize,
+    pub preview: String,
+}
+
+/// Where the bytes of a module go, largest items first
+#[derive(Debug, Serialize)]
+pub struct SizeReport {
+    pub total: usize,
+    pub gzip: usize,
+    /// Whether functions are named by the name section rather than by index
+    pub has_names: bool,
+    pub sections: Vec<Section>,
+    pub custom_sections: Vec<Section>,
+    pub functions: Vec<FunctionSize>,
+    pub data_segments: Vec<DataSize>,
+}
+
+/// Break down the size of a module by section, function and data segment
+pub fn analyze_size(bytes: &[u8]) -> Result<SizeReport, Error> {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+    let info = parse(bytes)?;
+    let imported = info
+        .imports
+        .iter()
+        .filter(|import| import.kind == "func")
+        .count();
+    let mut bodies = Vec::new();
+    let mut data_segments = Vec::new();
+    let names = function_names(bytes)?;
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload? {
+            Payload::CodeSectionEntry(body) => bodies.push(body.range().len()),
+            Payload::DataSection(reader) => {
+                for (index, data) in reader.into_iter().enumerate() {
+                    let data = data?;
+                    let preview = data
+                        .data
+                        .iter()
+                        .take(32)
+                        .map(|byte| match *byte {
+                            b' '..=b'~' => *byte as char,
+                            _ => '.',
+                        })
+                        .collect();
+                    data_segments.push(DataSize {
+                        index,
+                        size: data.range.len(),
+                        preview,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut functions: Vec<FunctionSize> = bodies
+        .into_iter()
+        .enumerate()
+        .map(|(offset, size)| {
+            let index = (imported + offset) as u32;
+            let name = names
+                .get(&index)
+                .cloned()
+                .unwrap_or_else(|| format!("func[{}]", index));
+            FunctionSize { name, size }
+        })
+        .collect();
+    functions.sort_by(|a, b| b.size.cmp(&a.size));
+    data_segments.sort_by(|a, b| b.size.cmp(&a.size));
+    let mut sections: BTreeMap<String, usize> = BTreeMap::new();
+    for section in info.sections {
+        *sections.entry(section.name).or_default() += section.size;
+    }
+    let mut sections: Vec<Section> = sections
+        .into_iter()
+        .map(|(name, size)| Section { name, size })
+        .collect();
+    sections.sort_by(|a, b| b.size.cmp(&a.size));
+    let mut custom_sections = info.custom_sections;
+    custom_sections.sort_by(|a, b| b.size.cmp(&a.size));
+    let mut gzip = GzEncoder::new(Vec::new(), Compression::best());
+    gzip.write_all(bytes)?;
+    Ok(SizeReport {
+        total: bytes.len(),
+        gzip: gzip.finish()?.len(),
+        has_names: !names.is_empty(),
+        sections,
+        custom_sections,
+        functions,
+        data_segments,
+    })
+}
+
+impl SizeReport {
+    /// Only the `limit` largest functions and data segments
+    pub fn truncated(mut self, limit: usize) -> Self {
+        self.functions.truncate(limit);
+        self.data_segments.truncate(limit);
+        self
+    }
+
+    pub fn print(&self) {
+        println!("Total: {} bytes, {} gzipped", self.total, self.gzip);
+        println!("Sections:");
+        for section in &self.sections {
+            println!("  {:<24} {:>9} B", section.name, section.size);
+        }
+        println!("Custom sections:");
+        for section in &self.custom_sections {
+            println!("  {:<24} {:>9} B", section.name, section.size);
+        }
+        if !self.has_names {
+            println!("No name section, functions are shown by index (build with `--keep-names`)");
+        }
+        println!("Largest functions:");
+        for function in &self.functions {
+            println!("  {:>9} B  {}", function.size, function.name);
+        }
+        println!("Largest data segments:");
+        for data in &self.data_segments {
+            println!(
+                "  {:>9} B  data[{}] {:?}",
+                data.size, data.index, data.preview
+            );
+        }
+    }
+}
+
+/// Hex of the hash Iroha stores for a `WasmSmartContract` with this blob.
+///
+/// That's `iroha_crypto::Hash` of the SCALE encoding: a blake2b-256 digest of the
+/// compact length prefixed bytes, with the lowest bit of the last byte set.
+pub fn iroha_hash(bytes: &[u8]) -> String {
+    hex::encode(iroha_hash_bytes(bytes))
+}
+
+/// The hash of `iroha_hash` as bytes
+pub fn iroha_hash_bytes(bytes: &[u8]) -> Vec<u8> {
+    use blake2::{digest::consts::U32, Blake2b, Digest};
+    let mut encoded = compact_len(bytes.len() as u64);
+    encoded.extend_from_slice(bytes);
+    let mut hash = Blake2b::<U32>::digest(&encoded);
+    hash[31] |= 1;
+    hash.to_vec()
+}
+
+/// SCALE compact encoding of a length
+fn compact_len(len: u64) -> Vec<u8> {
+    match len {
+        0..=0x3f => vec![(len << 2) as u8],
+        0x40..=0x3fff => ((len << 2) as u16 | 0b01).to_le_bytes().to_vec(),
+        0x4000..=0x3fff_ffff => ((len << 2) as u32 | 0b10).to_le_bytes().to_vec(),
+        _ => {
+            let bytes: Vec<u8> = len
+                .to_le_bytes()
+                .into_iter()
+                .rev()
+                .skip_while(|byte| *byte == 0)
+                .collect();
+            let mut prefix = vec![(((bytes.len() - 4) << 2) | 0b11) as u8];
+            prefix.extend(bytes.into_iter().rev());
+            prefix
+        }
+    }
+}
+
+/// Contents of the data segments in module order
+pub fn data_segments(bytes: &[u8]) -> Result<Vec<&[u8]>, Error> {
+    let mut segments = Vec::new();
+    for payload in Parser::new(0).parse_all(bytes) {
+        if let Payload::DataSection(reader) = payload? {
+            for data in reader {
+                segments.push(data?.data);
+            }
+        }
+    }
+    Ok(segments)
+}
+
+/// Contents of the custom section `name`, the first one if there are several
+pub fn custom_section<'a>(bytes: &'a [u8], name: &str) -> Result<Option<&'a [u8]>, Error> {
+    for payload in Parser::new(0).parse_all(bytes) {
+        if let Payload::CustomSection(reader) = payload? {
+            if reader.name() == name {
+                return Ok(Some(reader.data()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Copy of `module` without the custom sections not named in `keep`
+pub fn strip_custom_sections(module: &[u8], keep: &[&str]) -> Result<Vec<u8>, Error> {
+    retain_custom_sections(module, |name| keep.contains(&name))
+}
+
+/// Unsigned LEB128 number at `offset` of `module`, `offset` is moved past it
+pub fn read_leb128(module: &[u8], offset: &mut usize) -> Result<usize, Error> {
+    let mut value = 0;
+    for shift in (0..35).step_by(7) {
+        let byte = *module
+            .get(*offset)
+            .ok_or_else(|| Error::wasm("unexpected end of the wasm module"))?;
+        *offset += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(Error::wasm("invalid LEB128 number in the wasm module"))
+}
+
+/// Append `value` to `out` as unsigned LEB128
+pub fn write_leb128(mut value: usize, out: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Function names of the name section by function index
+pub fn function_names(bytes: &[u8]) -> Result<BTreeMap<u32, String>, Error> {
+    use wasmparser::{Name, NameSectionReader};
+    let mut names = BTreeMap::new();
+    for payload in Parser::new(0).parse_all(bytes) {
+        if let Payload::CustomSection(reader) = payload? {
+            if reader.name() != "name" {
+                continue;
+            }
+            let reader = NameSectionReader::new(reader.data(), reader.data_offset());
+            for name in reader {
+                if let Name::Function(map) = name? {
+                    for naming in map {
+                        let naming = naming?;
+                        names.insert(naming.index, naming.name.to_owned());
+                    }
+                }
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Indices of the exported functions
+pub fn exported_functions(bytes: &[u8]) -> Result<Vec<u32>, Error> {
+    let mut exported = Vec::new();
+    for payload in Parser::new(0).parse_all(bytes) {
+        if let Payload::ExportSection(reader) = payload? {
+            for export in reader {
+                let export = export?;
+                if export.kind == ExternalKind::Func {
+                    exported.push(export.index);
+                }
+            }
+        }
+    }
+    Ok(exported)
+}
+
+/// Copy of `module` with the bodies `replace` returns for the defined functions by index
+pub fn replace_function_bodies(
+    module: &[u8],
+    replace: impl Fn(u32) -> Option<Vec<u8>>,
+) -> Result<Vec<u8>, Error> {
+    if module.len() < 8 || &module[..4] != b"\0asm" {
+        return Err(Error::wasm("not a wasm module"));
+    }
+    let mut replaced = module[..8].to_vec();
+    let mut offset = 8;
+    while offset < module.len() {
+        let start = offset;
+        let id = module[offset];
+        offset += 1;
+        let size = read_leb128(module, &mut offset)?;
+        let end = offset + size;
+        if end > module.len() {
+            return Err(Error::wasm(
+                "a section runs past the end of the wasm module",
+            ));
+        }
+        if id != 10 {
+            replaced.extend_from_slice(&module[start..end]);
+            offset = end;
+            continue;
+        }
+        let count = read_leb128(module, &mut offset)?;
+        let mut code = Vec::new();
+        write_leb128(count, &mut code);
+        for defined in 0..count {
+            let size = read_leb128(module, &mut offset)?;
+            let body = match replace(defined as u32) {
+                Some(body) => body,
+                None => module[offset..offset + size].to_vec(),
+            };
+            write_leb128(body.len(), &mut code);
+            code.extend_from_slice(&body);
+            offset += size;
+        }
+        replaced.push(10);
+        write_leb128(code.len(), &mut replaced);
+        replaced.extend_from_slice(&code);
+        offset = end;
+    }
+    Ok(replaced)
+}
+
+/// Copy of `module` with only the custom sections whose name `keep` accepts
+pub fn retain_custom_sections(
+    module: &[u8],
+    keep: impl Fn(&str) -> bool,
+) -> Result<Vec<u8>, Error> {
+    if module.len() < 8 || &module[..4] != b"\0asm" {
+        return Err(Error::wasm("not a wasm module"));
+    }
+    let mut stripped = module[..8].to_vec();
+    let mut offset = 8;
+    while offset < module.len() {
+        let start = offset;
+        let id = module[offset];
+        offset += 1;
+        let size = read_leb128(module, &mut offset)?;
+        let end = offset + size;
+        if end > module.len() {
+            return Err(Error::wasm(
+                "a section runs past the end of the wasm module",
+            ));
+        }
+        let kept = id != 0 || {
+            let len = read_leb128(module, &mut offset)?;
+            module
+                .get(offset..offset + len)
+                .map_or(false, |name| keep(&String::from_utf8_lossy(name)))
+        };
+        if kept {
+            stripped.extend_from_slice(&module[start..end]);
+        }
+        offset = end;
+    }
+    Ok(stripped)
+}
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// A named check with the reasons for its outcome
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+    pub details: Vec<String>,
+}
+
+impl Check {
+    fn new(name: &'static str, outcome: Outcome, details: Vec<String>) -> Self {
+        Check {
+            name,
+            outcome,
+            details,
+        }
+    }
+}
+
+/// Exports of the entrypoints Iroha calls, one of them has to be present
+pub const ENTRYPOINT_EXPORTS: &[&str] = &[
+    "_iroha_smart_contract_main",
+    "_iroha_trigger_main",
+    "_iroha_executor_migrate",
+    "_iroha_executor_validate_transaction",
+    "_iroha_executor_validate_instruction",
+    "_iroha_executor_validate_query",
+    "_iroha_validator_main",
+];
+
+/// Module the Iroha host functions are imported from
+pub const HOST_MODULE: &str = "iroha";
+
+/// Limits a module is checked against
+#[derive(Debug)]
+pub struct CheckPolicy {
+    pub max_size: u64,
+    pub max_memory_pages: u64,
+    /// Extra imports as `module` or `module::name`, besides the host functions
+    pub allowed_imports: Vec<String>,
+    /// Whether to require the entrypoints, memory and imports of a contract
+    pub contract: bool,
+}
+
+impl Default for CheckPolicy {
+    fn default() -> Self {
+        CheckPolicy {
+            max_size: MAX_WASM_SIZE,
+            max_memory_pages: MAX_MEMORY_PAGES,
+            allowed_imports: Vec::new(),
+            contract: true,
+        }
+    }
+}
+
+/// The module is valid wasm
+pub fn check_structure(bytes: &[u8]) -> Check {
+    match Validator::new().validate_all(bytes) {
+        Ok(_) => Check::new("structure", Outcome::Pass, Vec::new()),
+        Err(err) => Check::new("structure", Outcome::Fail, vec![err.to_string()]),
+    }
+}
+
+/// The module fits in `max_size`, with a warning past 90%
+pub fn check_size(bytes: &[u8], max_size: u64) -> Check {
+    let len = bytes.len() as u64;
+    if len > max_size {
+        let details = format!(
+            "Wasm binary too large, max size is {}, but got {}",
+            max_size, len
+        );
+        return Check::new("size", Outcome::Fail, vec![details]);
+    }
+    if len > max_size / 10 * 9 {
+        let details = format!(
+            "Wasm binary is {} bytes, close to the max size of {}",
+            len, max_size
+        );
+        return Check::new("size", Outcome::Warn, vec![details]);
+    }
+    Check::new("size", Outcome::Pass, Vec::new())
+}
+
+/// Only functions of the Iroha host or of `allowed` are imported
+pub fn check_imports(info: &ModuleInfo, allowed: &[String]) -> Check {
+    let details: Vec<String> = info
+        .imports
+        .iter()
+        .filter(|import| !(import.module == HOST_MODULE && import.kind == "func"))
+        .filter(|import| {
+            let qualified = format!("{}::{}", import.module, import.name);
+            !allowed
+                .iter()
+                .any(|allowed| *allowed == import.module || *allowed == qualified)
+        })
+        .map(|import| {
+            format!(
+                "{} `{}::{}` is not provided by the Iroha host",
+                import.kind, import.module, import.name
+            )
+        })
+        .collect();
+    let outcome = if details.is_empty() {
+        Outcome::Pass
+    } else {
+        Outcome::Fail
+    };
+    Check::new("imports", outcome, details)
+}
+
+/// An Iroha entrypoint is exported as a function
+pub fn check_entrypoints(info: &ModuleInfo) -> Check {
+    let found: Vec<String> = info
+        .exports
+        .iter()
+        .filter(|export| {
+            export.kind == "func" && ENTRYPOINT_EXPORTS.contains(&export.name.as_str())
+        })
+        .map(|export| export.name.clone())
+        .collect();
+    if found.is_empty() {
+        let details = format!(
+            "none of the entrypoints {} is exported",
+            ENTRYPOINT_EXPORTS.join(", ")
+        );
+        return Check::new("entrypoint", Outcome::Fail, vec![details]);
+    }
+    Check::new("entrypoint", Outcome::Pass, found)
+}
+
+/// No threads or relaxed SIMD, with a warning for floats whose NaN bits differ between peers
+pub fn check_determinism(bytes: &[u8]) -> Check {
+    let mut features = WasmFeatures {
+        threads: false,
+        relaxed_simd: false,
+        ..WasmFeatures::default()
+    };
+    if let Err(err) = Validator::new_with_features(features).validate_all(bytes) {
+        return Check::new("determinism", Outcome::Fail, vec![err.to_string()]);
+    }
+    features.floats = false;
+    if let Err(err) = Validator::new_with_features(features).validate_all(bytes) {
+        let details = format!("floating point results may differ between peers: {}", err);
+        return Check::new("determinism", Outcome::Warn, vec![details]);
+    }
+    Check::new("determinism", Outcome::Pass, Vec::new())
+}
+
+/// A single memory, exported as `memory` and starting below `max_pages`
+pub fn check_memory(info: &ModuleInfo, max_pages: u64) -> Check {
+    let mut details = Vec::new();
+    if info.memories.len() != 1 {
+        details.push(format!(
+            "expected a single memory, but got {}",
+            info.memories.len()
+        ));
+    }
+    if !info
+        .exports
+        .iter()
+        .any(|export| export.kind == "memory" && export.name == "memory")
+    {
+        details.push("no memory is exported as `memory`".to_owned());
+    }
+    for memory in info
+        .memories
+        .iter()
+        .filter(|memory| memory.initial > max_pages)
+    {
+        details.push(format!(
+            "memory starts at {} pages, more than the limit of {}",
+            memory.initial, max_pages
+        ));
+    }
+    let outcome = if details.is_empty() {
+        Outcome::Pass
+    } else {
+        Outcome::Fail
+    };
+    Check::new("memory", outcome, details)
+}
+
+/// Every check of `policy`, those reading the module are skipped if it doesn't parse
+pub fn run_checks(bytes: &[u8], policy: &CheckPolicy) -> Vec<Check> {
+    let mut checks = vec![check_structure(bytes), check_size(bytes, policy.max_size)];
+    if checks[0].outcome == Outcome::Fail {
+        return checks;
+    }
+    let info = match parse(bytes) {
+        Ok(info) => info,
+        Err(err) => {
+            checks.push(Check::new("parse", Outcome::Fail, vec![err.to_string()]));
+            return checks;
+        }
+    };
+    checks.push(check_determinism(bytes));
+    if policy.contract {
+        checks.push(check_imports(&info, &policy.allowed_imports));
+        checks.push(check_entrypoints(&info));
+        checks.push(check_memory(&info, policy.max_memory_pages));
+    }
+    checks
+}
+
+/// Parse the declarations of a module, without validating its code
+pub fn parse(bytes: &[u8]) -> Result<ModuleInfo, Error> {
+    let mut info = ModuleInfo::default();
+    for payload in Parser::new(0).parse_all(bytes) {
+        let payload = payload?;
+        if let Some((id, range)) = payload.as_section() {
+            if id != 0 {
+                info.sections.push(Section {
+                    name: section_name(id).to_owned(),
+                    size: range.len(),
+                });
+            }
+        }
+        match payload {
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    info.imports.push(Import {
+                        module: import.module.to_owned(),
+                        name: import.name.to_owned(),
+                        kind: type_ref_kind(&import.ty),
+                    });
+                    if let TypeRef::Memory(memory) = import.ty {
+                        info.memories.push(Limits {
+                            initial: memory.initial,
+                            maximum: memory.maximum,
+                        });
+                    }
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    info.exports.push(Export {
+                        name: export.name.to_owned(),
+                        kind: external_kind(export.kind),
+                    });
+                }
+            }
+            Payload::MemorySection(reader) => {
+                for memory in reader {
+                    let memory = memory?;
+                    info.memories.push(Limits {
+                        initial: memory.initial,
+                        maximum: memory.maximum,
+                    });
+                }
+            }
+            Payload::TableSection(reader) => {
+                for table in reader {
+                    let table = table?;
+                    info.tables.push(Limits {
+                        initial: u64::from(table.ty.initial),
+                        maximum: table.ty.maximum.map(u64::from),
+                    });
+                }
+            }
+            Payload::CustomSection(reader) => {
+                info.custom_sections.push(Section {
+                    name: reader.name().to_owned(),
+                    size: reader.data().len(),
+                });
+                info.sections.push(Section {
+                    name: "custom".to_owned(),
+                    size: reader.range().len(),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compact_lengths_match_scale() {
+        use parity_scale_codec::{Compact, Encode};
+        for len in [
+            0,
+            1,
+            0x3f,
+            0x40,
+            0x3fff,
+            0x4000,
+            0x3fff_ffff,
+            0x4000_0000,
+            0xffff_ffff,
+            0x1_0000_0000,
+            u64::MAX,
+        ] {
+            assert_eq!(compact_len(len), Compact(len).encode(), "{:#x}", len);
+        }
+    }
+
+    #[test]
+    fn iroha_hash_of_fixed_inputs() {
+        for (input, hash) in [
+            (
+                Vec::new(),
+                "03170a2e7597b7b7e3d84c05391d139a62b157e78786d8c082f29dcf4c111315",
+            ),
+            (
+                b"\0asm\x01\0\0\0".to_vec(),
+                "d08976ff7520673698627bf984e40aed1e3a413462a366ad192d7566525a779b",
+            ),
+            (
+                vec![b'a'; 0x3f],
+                "a101052146c123fe0f31128cf11ae7fd9c46efb7ce41731e990f752d35cdefe3",
+            ),
+            (
+                vec![b'a'; 0x40],
+                "7490fc8e93029b795c2c8797c48815e54ae8d119384704d4e6ca790187723afb",
+            ),
+            (
+                vec![b'a'; 0x3fff],
+                "f6ea474a8fb027734124b9595667266d1d3355b52be6eda90e75a090ce8426fb",
+            ),
+            (
+                vec![b'a'; 0x4000],
+                "5bf83f3c8c859b073266b73330131a9357f85f0c8c1f13a2f95abdeddae91fc5",
+            ),
+        ] {
+            assert_eq!(iroha_hash(&input), hash, "{} bytes", input.len());
+            assert_eq!(iroha_hash_bytes(&input).last().unwrap() & 1, 1);
+        }
+    }
+}
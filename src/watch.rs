@@ -0,0 +1,185 @@
+use super::*;
+use duct::cmd;
+use std::{
+    env::current_dir,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+use structopt::clap::AppSettings;
+
+/// Everything required to configure and run the `iroha_wasm_pack watch` command.
+#[derive(Debug, StructOpt)]
+#[structopt(
+    setting = AppSettings::TrailingVarArg,
+)]
+pub struct WatchArgs {
+    /// Shell command to run after each successful build
+    #[structopt(long = "exec")]
+    pub exec: Option<String>,
+
+    /// Run wasm-opt, which `watch` skips by default to rebuild faster
+    #[structopt(long = "opt")]
+    pub opt: bool,
+
+    #[structopt(allow_hyphen_values = true)]
+    /// Options passed to `iroha_wasm_pack build`
+    pub build_options: Vec<String>,
+}
+
+/// Something the watch loop reacts to
+enum Signal {
+    Changed,
+    Interrupted,
+}
+
+/// Changes arriving this soon after the first one are folded into the same rebuild
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Sources of the project whose changes trigger a rebuild
+fn watched_paths(root: &Path) -> Vec<PathBuf> {
+    [
+        "src",
+        "examples",
+        "tests",
+        "build.rs",
+        "Cargo.toml",
+        ".cargo",
+        "rust-toolchain.toml",
+    ]
+    .iter()
+    .map(|path| root.join(path))
+    .filter(|path| path.exists())
+    .collect()
+}
+
+/// How often the loops look for an interrupt
+const POLL: Duration = Duration::from_millis(100);
+
+/// Block until a change or an interrupt, folding the changes of the debounce window
+fn next_signal(rx: &Receiver<Signal>) -> Signal {
+    loop {
+        if interrupt::requested() {
+            return Signal::Interrupted;
+        }
+        match rx.recv_timeout(POLL) {
+            Ok(Signal::Changed) => break,
+            Ok(Signal::Interrupted) | Err(RecvTimeoutError::Disconnected) => {
+                return Signal::Interrupted
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+    }
+    let deadline = Instant::now() + DEBOUNCE;
+    loop {
+        if interrupt::requested() {
+            return Signal::Interrupted;
+        }
+        match rx.recv_timeout(deadline.saturating_duration_since(Instant::now()).min(POLL)) {
+            Ok(Signal::Changed) => continue,
+            Ok(Signal::Interrupted) | Err(RecvTimeoutError::Disconnected) => {
+                return Signal::Interrupted
+            }
+            Err(RecvTimeoutError::Timeout) if Instant::now() >= deadline => return Signal::Changed,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+    }
+}
+
+impl WatchArgs {
+    /// `iroha_wasm_pack build` with the forwarded options
+    fn build_command(&self) -> Result<duct::Expression, Error> {
+        let mut args = vec!["build".to_owned()];
+        if !self.opt {
+            args.push("--skip-opt".to_owned());
+        }
+        args.extend(self.build_options.iter().cloned());
+        Ok(cmd(std::env::current_exe()?, args).unchecked())
+    }
+
+    /// Run `--exec` after a successful build
+    fn run_exec(&self) {
+        let exec = match &self.exec {
+            Some(exec) => exec,
+            None => return,
+        };
+        #[cfg(windows)]
+        let command = cmd!("cmd", "/C", exec);
+        #[cfg(not(windows))]
+        let command = cmd!("sh", "-c", exec);
+        match command.unchecked().run() {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => eprintln!("[watch] `{}` failed with {}", exec, output.status),
+            Err(err) => eprintln!("[watch] run `{}` failed, error = {}", exec, err),
+        }
+    }
+}
+
+impl RunArgs for WatchArgs {
+    fn run(self) -> Result<CommandReport, Error> {
+        let root = current_dir()?;
+        let (changes, rx) = channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    if !event.kind.is_access() {
+                        let _ = changes.send(Signal::Changed);
+                    }
+                }
+            })?;
+        {
+            use notify::{RecursiveMode, Watcher};
+            for path in watched_paths(&root) {
+                watcher.watch(&path, RecursiveMode::Recursive)?;
+            }
+        }
+        let _listening = interrupt::listen();
+        loop {
+            print!("\x1b[2J\x1b[H");
+            println!("[watch] building {}", root.display());
+            let started = Instant::now();
+            let build = self.build_command()?.start()?;
+            // Wait for the build, restarting it if the sources change meanwhile.
+            let restarted = loop {
+                if interrupt::requested() {
+                    // Killing cargo mid-build leaves only its own resumable state behind.
+                    build.kill()?;
+                    return Ok(CommandReport::default());
+                }
+                if let Ok(Signal::Changed) = rx.recv_timeout(POLL) {
+                    build.kill()?;
+                    break true;
+                }
+                if let Some(output) = build.try_wait()? {
+                    let elapsed = started.elapsed().as_secs_f64();
+                    if output.status.success() {
+                        println!("[watch] built in {:.1}s, waiting for changes", elapsed);
+                        self.run_exec();
+                    } else {
+                        println!(
+                            "[watch] build failed after {:.1}s, waiting for changes",
+                            elapsed
+                        );
+                    }
+                    break false;
+                }
+            };
+            if restarted {
+                // Fold the rest of the burst of changes into this restart.
+                loop {
+                    if interrupt::requested() {
+                        return Ok(CommandReport::default());
+                    }
+                    match rx.recv_timeout(DEBOUNCE) {
+                        Ok(Signal::Changed) => continue,
+                        _ => break,
+                    }
+                }
+                continue;
+            }
+            if let Signal::Interrupted = next_signal(&rx) {
+                return Ok(CommandReport::default());
+            }
+        }
+    }
+}
@@ -0,0 +1,264 @@
+//! End to end tests of the `iroha_wasm_pack` binary.
+//!
+//! The tests building the fixture contract are skipped when the precompiled
+//! wasm32-unknown-unknown target isn't installed.
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A new directory holding `files` of `(path, contents)`
+fn fixture(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "iroha_wasm_pack-cli-{}-{}-{}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::SeqCst),
+        name
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    for (path, contents) in files {
+        let path = dir.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+/// A `cdylib` contract named `contract` exporting a trigger entrypoint
+fn contract(name: &str) -> PathBuf {
+    fixture(
+        name,
+        &[
+            (
+                "Cargo.toml",
+                "[package]\nname = \"contract\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n\
+                [lib]\ncrate-type = [\"cdylib\"]\n",
+            ),
+            (
+                "src/lib.rs",
+                "#![no_std]\n\n\
+                #[panic_handler]\n\
+                fn panic(_: &core::panic::PanicInfo) -> ! {\n    loop {}\n}\n\n\
+                #[no_mangle]\n\
+                pub extern \"C\" fn _iroha_trigger_main(_context: *const u8, _len: usize) {}\n",
+            ),
+        ],
+    )
+}
+
+fn iroha_wasm_pack() -> Command {
+    let mut command = Command::cargo_bin("iroha_wasm_pack").unwrap();
+    command.env_remove("RUST_LOG");
+    command
+}
+
+fn manifest(dir: &Path) -> String {
+    dir.join("Cargo.toml").display().to_string()
+}
+
+/// Whether the precompiled wasm32 target the fixture builds with is installed
+fn wasm_target_installed() -> bool {
+    let output = std::process::Command::new("rustc")
+        .args([
+            "--print",
+            "target-libdir",
+            "--target",
+            "wasm32-unknown-unknown",
+        ])
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            Path::new(String::from_utf8_lossy(&output.stdout).trim()).is_dir()
+        }
+        _ => false,
+    }
+}
+
+macro_rules! require_wasm_target {
+    () => {
+        if !wasm_target_installed() {
+            eprintln!("skipped, the wasm32-unknown-unknown target isn't installed");
+            return;
+        }
+    };
+}
+
+/// `build` of the fixture with the stable toolchain, no `-Z build-std` and nothing installed
+fn build(dir: &Path, flags: &[&str]) -> Command {
+    let mut command = iroha_wasm_pack();
+    command
+        .args(flags)
+        .args(["build", "--no-build-std", "--no-install", "--manifest-path"])
+        .arg(manifest(dir));
+    command
+}
+
+/// Every line of `output` parsed as JSON
+fn json_lines(output: &[u8]) -> Vec<Value> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|err| panic!("{}: {}", err, line)))
+        .collect()
+}
+
+#[test]
+fn dry_run_lists_the_package_and_the_steps() {
+    let dir = contract("dry-run");
+    iroha_wasm_pack()
+        .args(["build", "--dry-run", "--manifest-path", &manifest(&dir)])
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::starts_with("contract\n").and(predicate::str::contains("build-wasm")),
+        );
+}
+
+#[test]
+fn manifest_errors_name_their_code() {
+    let dir = fixture("no-package", &[("Cargo.toml", "[dependencies]\n")]);
+    iroha_wasm_pack()
+        .args(["build", "--dry-run", "--manifest-path", &manifest(&dir)])
+        .assert()
+        .code(2)
+        .stdout("")
+        .stderr(
+            predicate::str::contains("Error[E0102]: missing [package] section in Cargo.toml")
+                .and(predicate::str::contains("iroha_wasm_pack explain E0102")),
+        );
+}
+
+#[test]
+fn unknown_commands_are_usage_errors() {
+    iroha_wasm_pack().arg("no-such-command").assert().code(1);
+}
+
+#[test]
+fn explain_prints_the_code() {
+    iroha_wasm_pack()
+        .args(["explain", "e0102"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("E0102 "));
+}
+
+#[test]
+fn json_errors_are_diagnostics_on_stderr() {
+    let dir = fixture("json-error", &[("Cargo.toml", "[dependencies]\n")]);
+    let output = iroha_wasm_pack()
+        .args([
+            "--error-format",
+            "json",
+            "build",
+            "--dry-run",
+            "--manifest-path",
+        ])
+        .arg(manifest(&dir))
+        .assert()
+        .code(2)
+        .stdout("")
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    let diagnostic: Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(diagnostic["code"], "E0102");
+    assert_eq!(diagnostic["exit_code"], 2);
+    assert_eq!(
+        diagnostic["message"],
+        "missing [package] section in Cargo.toml"
+    );
+}
+
+#[test]
+fn json_usage_errors_are_diagnostics_too() {
+    let output = iroha_wasm_pack()
+        .args(["--error-format", "json", "build", "--no-such-flag"])
+        .assert()
+        .code(1)
+        .get_output()
+        .stderr
+        .clone();
+    let stderr = String::from_utf8(output).unwrap();
+    let diagnostic: Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert_eq!(diagnostic["code"], "E0001");
+    assert_eq!(diagnostic["exit_code"], 1);
+}
+
+#[test]
+fn failed_progress_stream_ends_with_the_error() {
+    let dir = fixture("progress-error", &[("Cargo.toml", "[dependencies]\n")]);
+    let output = iroha_wasm_pack()
+        .args([
+            "--progress-format",
+            "json",
+            "build",
+            "--dry-run",
+            "--manifest-path",
+        ])
+        .arg(manifest(&dir))
+        .assert()
+        .code(2)
+        .get_output()
+        .stderr
+        .clone();
+    let events = json_lines(&output);
+    assert_eq!(events.len(), 1, "{:?}", events);
+    assert_eq!(events[0]["event"], "finished");
+    assert_eq!(events[0]["success"], false);
+    assert_eq!(events[0]["error"]["code"], "E0102");
+}
+
+#[test]
+fn build_writes_the_optimized_wasm() {
+    require_wasm_target!();
+    let dir = contract("build");
+    let output = build(&dir, &["--output-format", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let report: Value = serde_json::from_slice(&output).unwrap();
+    let artifacts = report["artifacts"].as_array().unwrap();
+    assert_eq!(artifacts.len(), 1, "{}", report);
+    assert_eq!(artifacts[0]["package"], "contract");
+    let wasm = fs::read(artifacts[0]["wasm"].as_str().unwrap()).unwrap();
+    assert!(wasm.starts_with(b"\0asm"));
+    assert_eq!(artifacts[0]["size"], wasm.len() as u64);
+    assert!(!artifacts[0]["hash"].as_str().unwrap().is_empty());
+    let steps: Vec<&str> = report["steps"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|step| step["step"].as_str())
+        .collect();
+    assert!(steps.contains(&"build-wasm"), "{:?}", steps);
+    assert!(steps.contains(&"verify-module"), "{:?}", steps);
+}
+
+#[test]
+fn progress_stream_is_json_lines() {
+    require_wasm_target!();
+    let dir = contract("progress");
+    let output = build(&dir, &["--progress-format", "json"])
+        .assert()
+        .success()
+        .get_output()
+        .stderr
+        .clone();
+    let events = json_lines(&output);
+    assert!(events.iter().all(|event| event["version"].is_u64()));
+    assert!(events
+        .iter()
+        .any(|event| { event["event"] == "step-finished" && event["step"] == "build-wasm" }));
+    let finished = events.last().unwrap();
+    assert_eq!(finished["event"], "finished", "{:?}", events);
+    assert_eq!(finished["success"], true);
+    assert_eq!(finished["report"]["artifacts"][0]["package"], "contract");
+}